@@ -2,7 +2,17 @@ use warp::Filter;
 use serde::{Deserialize, Serialize};
 use crate::blockchain::transaction::Transaction;
 use crate::blockchain::block::Block;
+use crate::blockchain::ledger::Ledger;
+use crate::blockchain::multisig::Approval;
+use crate::consensus::dispute::{DisputeError, PendingMint};
+use crate::consensus::registry::{RegistryError, VerifierStatus};
+use crate::crypto::address::Address;
+use crate::mempool::mempool::{Mempool, MempoolError};
+use crate::network::message::Message;
 use crate::network::p2p::P2PNetwork;
+use crate::task::marketplace::{Task, TaskError, TaskFilter};
+use crate::token::supply::SupplyStats;
+use std::sync::{Arc, Mutex};
 
 /// Struct to represent an RPC response.
 #[derive(Serialize, Deserialize)]
@@ -18,12 +28,18 @@ pub struct RpcError {
     pub error: String,
 }
 
-/// Starts the RPC server.
-pub async fn start_rpc_server(network: P2PNetwork) {
+/// Starts the RPC server, serving blocks from the given ledger and accepting transactions
+/// into the given mempool, which are relayed to peers over the given network.
+pub async fn start_rpc_server(
+    network: P2PNetwork,
+    ledger: Arc<Mutex<Ledger>>,
+    mempool: Arc<Mutex<Mempool>>,
+) {
     // Route to get the latest block.
+    let block_ledger = ledger.clone();
     let get_block = warp::path!("block" / "latest")
         .map(move || {
-            let latest_block = get_latest_block();
+            let latest_block = get_latest_block(&block_ledger);
             match latest_block {
                 Some(block) => warp::reply::json(&RpcResponse {
                     status: "success".to_string(),
@@ -36,39 +52,305 @@ pub async fn start_rpc_server(network: P2PNetwork) {
             }
         });
 
+    // Route to list the pending transactions, most urgent first.
+    let pending_mempool = mempool.clone();
+    let get_mempool = warp::path!("mempool").map(move || {
+        warp::reply::json(&RpcResponse {
+            status: "success".to_string(),
+            result: Some(get_pending_transactions(&pending_mempool)),
+        })
+    });
+
+    // Route to get the nonce an account's next transaction should carry.
+    let nonce_ledger = ledger.clone();
+    let nonce_mempool = mempool.clone();
+    let get_nonce = warp::path!("account" / String / "nonce").map(move |account: String| {
+        match account.parse::<Address>() {
+            Ok(address) => warp::reply::json(&RpcResponse {
+                status: "success".to_string(),
+                result: Some(get_next_nonce(&address, &nonce_ledger, &nonce_mempool)),
+            }),
+            Err(err) => warp::reply::json(&RpcError {
+                status: "error".to_string(),
+                error: err.to_string(),
+            }),
+        }
+    });
+
+    // Route to propose a transaction from a multisig account for its members to approve.
+    let propose_ledger = ledger.clone();
+    let propose_mempool = mempool.clone();
+    let propose_tx = warp::path!("multisig" / "propose")
+        .and(warp::body::json())
+        .map(move |tx: Transaction| {
+            let result = propose_transaction(tx, &propose_ledger, &propose_mempool);
+            approvals_needed_reply(result)
+        });
+
+    // Route to add a member's approval to a proposed transaction.
+    let approve_ledger = ledger.clone();
+    let approve_mempool = mempool.clone();
+    let approve_tx = warp::path!("multisig" / String / "approve")
+        .and(warp::body::json())
+        .map(move |id: String, approval: Approval| {
+            let result = approve_transaction(&id, approval, &approve_ledger, &approve_mempool);
+            approvals_needed_reply(result)
+        });
+
+    // Route to get a proposed transaction and the approvals it has collected.
+    let proposal_mempool = mempool.clone();
+    let get_proposal = warp::path!("multisig" / String).map(move |id: String| {
+        match get_proposal(&id, &proposal_mempool) {
+            Some(tx) => warp::reply::json(&RpcResponse {
+                status: "success".to_string(),
+                result: Some(tx),
+            }),
+            None => warp::reply::json(&RpcError {
+                status: "error".to_string(),
+                error: MempoolError::UnknownProposal(id).to_string(),
+            }),
+        }
+    });
+
+    // Route to list the tasks on the marketplace, filtered by status, category, client,
+    // worker or minimum reward.
+    let tasks_ledger = ledger.clone();
+    let get_tasks = warp::path!("tasks")
+        .and(warp::query::<TaskFilter>())
+        .map(move |filter: TaskFilter| {
+            warp::reply::json(&RpcResponse {
+                status: "success".to_string(),
+                result: Some(get_tasks(&filter, &tasks_ledger)),
+            })
+        });
+
+    // Route to get a task on the marketplace by the ID of the transaction that posted it.
+    let task_ledger = ledger.clone();
+    let get_task = warp::path!("task" / String).map(move |id: String| {
+        match get_task(&id, &task_ledger) {
+            Some(task) => warp::reply::json(&RpcResponse {
+                status: "success".to_string(),
+                result: Some(task),
+            }),
+            None => warp::reply::json(&RpcError {
+                status: "error".to_string(),
+                error: TaskError::UnknownTask(id).to_string(),
+            }),
+        }
+    });
+
+    // Route to list the verifiers in the registry, with their bonds and reputation.
+    let verifiers_ledger = ledger.clone();
+    let get_verifiers = warp::path!("verifiers").map(move || {
+        warp::reply::json(&RpcResponse {
+            status: "success".to_string(),
+            result: Some(get_verifiers(&verifiers_ledger)),
+        })
+    });
+
+    // Route to get a verifier's standing by address (hex-encoded public key).
+    let verifier_ledger = ledger.clone();
+    let get_verifier = warp::path!("verifier" / String).map(move |verifier: String| {
+        match get_verifier(&verifier, &verifier_ledger) {
+            Some(status) => warp::reply::json(&RpcResponse {
+                status: "success".to_string(),
+                result: Some(status),
+            }),
+            None => warp::reply::json(&RpcError {
+                status: "error".to_string(),
+                error: RegistryError::UnknownVerifier(verifier).to_string(),
+            }),
+        }
+    });
+
+    // Route to get a work mint that can still be disputed or is under dispute.
+    let mint_ledger = ledger.clone();
+    let get_mint = warp::path!("mint" / String).map(move |mint_id: String| {
+        match get_pending_mint(&mint_id, &mint_ledger) {
+            Some(mint) => warp::reply::json(&RpcResponse {
+                status: "success".to_string(),
+                result: Some(mint),
+            }),
+            None => warp::reply::json(&RpcError {
+                status: "error".to_string(),
+                error: DisputeError::UnknownMint(mint_id).to_string(),
+            }),
+        }
+    });
+
+    // Route to get the token supply, with this epoch's issuance and caps.
+    let supply_ledger = ledger.clone();
+    let get_supply = warp::path!("supply").map(move || {
+        warp::reply::json(&RpcResponse {
+            status: "success".to_string(),
+            result: Some(get_supply_stats(&supply_ledger)),
+        })
+    });
+
     // Route to submit a transaction.
     let submit_tx = warp::path!("transaction" / "submit")
         .and(warp::body::json())
         .map(move |tx: Transaction| {
-            let result = submit_transaction(tx);
+            let result = submit_transaction(tx, &ledger, &mempool, &network);
             match result {
-                true => warp::reply::json(&RpcResponse {
+                Ok(()) => warp::reply::json(&RpcResponse {
                     status: "success".to_string(),
                     result: Some("Transaction submitted".to_string()),
                 }),
-                false => warp::reply::json(&RpcError {
+                // Tell the client exactly why the transaction was refused
+                Err(err) => warp::reply::json(&RpcError {
                     status: "error".to_string(),
-                    error: "Transaction failed".to_string(),
+                    error: err.to_string(),
                 }),
             }
         });
 
     // Combine the routes.
-    let routes = get_block.or(submit_tx);
+    let routes = get_block
+        .or(get_mempool)
+        .or(get_nonce)
+        .or(submit_tx)
+        .or(propose_tx)
+        .or(approve_tx)
+        .or(get_proposal)
+        .or(get_tasks)
+        .or(get_task)
+        .or(get_verifiers)
+        .or(get_verifier)
+        .or(get_mint)
+        .or(get_supply);
 
     // Start the server on port 3030.
     warp::serve(routes).run(([127, 0, 0, 1], 3030)).await;
 }
 
-/// Example function to get the latest block.
-fn get_latest_block() -> Option<Block> {
-    // Replace this with real logic to fetch the latest block.
-    Some(Block::new(1, "prev_hash".to_string(), vec![], 0))
+/// Gets the latest block in the ledger.
+fn get_latest_block(ledger: &Mutex<Ledger>) -> Option<Block> {
+    let ledger = ledger.lock().ok()?;
+    Some(ledger.get_latest_block().clone())
+}
+
+/// Gets the pending transactions in the mempool, most urgent first.
+fn get_pending_transactions(mempool: &Mutex<Mempool>) -> Vec<Transaction> {
+    let mempool = mempool.lock().expect("Mempool lock poisoned");
+    mempool
+        .transactions_by_priority()
+        .into_iter()
+        .cloned()
+        .collect()
+}
+
+/// Gets the tasks matching the filter, with their status as of the latest block.
+fn get_tasks(filter: &TaskFilter, ledger: &Mutex<Ledger>) -> Vec<Task> {
+    let ledger = ledger.lock().expect("Ledger lock poisoned");
+    let timestamp = ledger.get_latest_block().header.timestamp;
+    ledger
+        .get_tasks(filter)
+        .into_iter()
+        .map(|task| Task {
+            status: task.status_at(timestamp),
+            ..task.clone()
+        })
+        .collect()
+}
+
+/// Gets a task by its ID, with its status as of the latest block.
+fn get_task(id: &str, ledger: &Mutex<Ledger>) -> Option<Task> {
+    let ledger = ledger.lock().expect("Ledger lock poisoned");
+    let timestamp = ledger.get_latest_block().header.timestamp;
+    ledger.get_task(id).map(|task| Task {
+        status: task.status_at(timestamp),
+        ..task.clone()
+    })
+}
+
+/// Gets the standing of every verifier in the registry.
+fn get_verifiers(ledger: &Mutex<Ledger>) -> Vec<VerifierStatus> {
+    let ledger = ledger.lock().expect("Ledger lock poisoned");
+    ledger.get_verifiers()
+}
+
+/// Gets a verifier's standing in the registry.
+fn get_verifier(verifier: &str, ledger: &Mutex<Ledger>) -> Option<VerifierStatus> {
+    let ledger = ledger.lock().expect("Ledger lock poisoned");
+    ledger.get_verifier(verifier)
+}
+
+/// Gets a work mint that can still be disputed or is under dispute.
+fn get_pending_mint(mint_id: &str, ledger: &Mutex<Ledger>) -> Option<PendingMint> {
+    let ledger = ledger.lock().expect("Ledger lock poisoned");
+    ledger.get_pending_mint(mint_id).cloned()
+}
+
+/// Gets the token supply as of the latest block.
+fn get_supply_stats(ledger: &Mutex<Ledger>) -> SupplyStats {
+    let ledger = ledger.lock().expect("Ledger lock poisoned");
+    ledger.get_supply_stats()
+}
+
+/// Gets the nonce an account's next transaction should carry, counting its pending transactions.
+fn get_next_nonce(account: &Address, ledger: &Mutex<Ledger>, mempool: &Mutex<Mempool>) -> u64 {
+    let ledger = ledger.lock().expect("Ledger lock poisoned");
+    let mempool = mempool.lock().expect("Mempool lock poisoned");
+    mempool.next_nonce(&account.to_string(), &ledger)
+}
+
+/// Validates a transaction against the ledger and the pending transactions, adds it to the
+/// mempool and relays it to peers.
+fn submit_transaction(
+    tx: Transaction,
+    ledger: &Mutex<Ledger>,
+    mempool: &Mutex<Mempool>,
+    network: &P2PNetwork,
+) -> Result<(), MempoolError> {
+    {
+        let ledger = ledger.lock().expect("Ledger lock poisoned");
+        let mut mempool = mempool.lock().expect("Mempool lock poisoned");
+        mempool.add(tx.clone(), &ledger)?;
+    }
+    network.broadcast(&Message::transaction(&tx));
+    Ok(())
+}
+
+/// Holds a transaction from a multisig account until enough of its members approve it.
+fn propose_transaction(
+    tx: Transaction,
+    ledger: &Mutex<Ledger>,
+    mempool: &Mutex<Mempool>,
+) -> Result<usize, MempoolError> {
+    let ledger = ledger.lock().expect("Ledger lock poisoned");
+    let mut mempool = mempool.lock().expect("Mempool lock poisoned");
+    mempool.propose(tx, &ledger)
+}
+
+/// Adds a member's approval to a proposed transaction, submitting it once it is approved.
+fn approve_transaction(
+    id: &str,
+    approval: Approval,
+    ledger: &Mutex<Ledger>,
+    mempool: &Mutex<Mempool>,
+) -> Result<usize, MempoolError> {
+    let ledger = ledger.lock().expect("Ledger lock poisoned");
+    let mut mempool = mempool.lock().expect("Mempool lock poisoned");
+    mempool.approve(id, approval, &ledger)
+}
+
+/// Gets a transaction that is waiting for approvals.
+fn get_proposal(id: &str, mempool: &Mutex<Mempool>) -> Option<Transaction> {
+    let mempool = mempool.lock().expect("Mempool lock poisoned");
+    mempool.get_proposal(id).cloned()
 }
 
-/// Example function to submit a transaction.
-fn submit_transaction(tx: Transaction) -> bool {
-    // Add logic to validate and submit the transaction to the P2P network.
-    println!("Submitting transaction: {:?}", tx);
-    true
+/// Replies with the number of approvals a proposed transaction still needs, or why it was refused.
+fn approvals_needed_reply(result: Result<usize, MempoolError>) -> warp::reply::Json {
+    match result {
+        Ok(needed) => warp::reply::json(&RpcResponse {
+            status: "success".to_string(),
+            result: Some(needed),
+        }),
+        Err(err) => warp::reply::json(&RpcError {
+            status: "error".to_string(),
+            error: err.to_string(),
+        }),
+    }
 }
@@ -1,73 +1,108 @@
-use crate::blockchain::merkle_tree::MerkleTree;
+use crate::blockchain::header::{BlockHeader, BLOCK_VERSION, MAX_TARGET};
+use crate::blockchain::merkle_tree::{MerkleTree, ProofStep};
 use crate::blockchain::transaction::Transaction;
-use crate::crypto::hash::calculate_hash;
+use ed25519_dalek::PublicKey;
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Represents a block in the blockchain.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Block {
-    /// Block index (height in the blockchain).
-    pub index: u64,
-    /// Timestamp of when the block was created.
-    pub timestamp: u64,
-    /// Hash of the previous block in the blockchain.
-    pub previous_hash: String,
-    /// The Merkle root, which is a hash of all the transactions in this block.
-    pub merkle_root: String,
-    /// The hash of this block (calculated based on its contents).
+    /// The header, which everything about the block is committed to through.
+    pub header: BlockHeader,
+    /// The hash of this block (the hash of its header).
     pub hash: String,
     /// The list of transactions included in this block.
     pub transactions: Vec<Transaction>,
-    /// A nonce used for the consensus algorithm (e.g., Proof-of-Work).
-    pub nonce: u64,
+    /// The producer's signature over the block hash, for engines that seal blocks by signing
+    /// them rather than by proof of work.
+    #[serde(default)]
+    pub seal: Option<Seal>,
+}
+
+/// A block producer's signature over a block's hash.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Seal {
+    /// Public key of the block producer.
+    pub signer: PublicKey,
+    /// Signature over the block hash.
+    pub signature: Vec<u8>,
 }
 
 impl Block {
     /// Creates a new block.
+    ///
+    /// The state root is left empty; block producers fill it in with
+    /// `Ledger::commit_state_root` before sealing the block.
     pub fn new(
         index: u64,
         previous_hash: String,
         transactions: Vec<Transaction>,
         nonce: u64,
     ) -> Self {
-        let timestamp = get_current_timestamp();
-        let merkle_tree =
-            MerkleTree::new(transactions.iter().map(|tx| tx.calculate_hash()).collect());
-        let merkle_root = merkle_tree.root.clone().unwrap_or_default();
+        let header = BlockHeader {
+            version: BLOCK_VERSION,
+            height: index,
+            previous_hash,
+            merkle_root: Block::calculate_merkle_root(&transactions),
+            timestamp: get_current_timestamp(),
+            target: MAX_TARGET,
+            nonce,
+            extra_nonce: 0,
+            state_root: String::new(),
+            vote: None,
+            beneficiary: None,
+        };
 
         let mut block = Block {
-            index,
-            timestamp,
-            previous_hash: previous_hash.clone(),
-            merkle_root,
+            header,
             hash: String::new(),
             transactions,
-            nonce,
+            seal: None,
         };
 
-        // Calculate the block hash based on its contents
+        // Calculate the block hash based on its header
         block.hash = block.calculate_hash();
         block
     }
 
-    /// Calculates the hash of the block based on its contents.
-    pub fn calculate_hash(&self) -> String {
-        let block_contents = format!(
-            "{}{}{}{}{}{}",
-            self.index,
-            self.timestamp,
-            self.previous_hash,
-            self.merkle_root,
-            self.nonce,
-            self.transactions.len()
+    /// Calculates the merkle root of the given transactions, which is empty for a block with
+    /// no transactions. Each leaf is the hash of a whole transaction, signature and approvals
+    /// included, so the block commits to who authorised each transaction as well as what it
+    /// does.
+    pub fn calculate_merkle_root(transactions: &[Transaction]) -> String {
+        let merkle_tree = MerkleTree::new(
+            transactions
+                .iter()
+                .map(|tx| tx.calculate_signed_hash())
+                .collect(),
         );
-        calculate_hash(&block_contents)
+        merkle_tree.root.unwrap_or_default()
+    }
+
+    /// Generates a proof that the transaction at `tx_index` is included in this block, which
+    /// can be checked against the header's merkle root with `MerkleTree::verify_proof` and the
+    /// transaction's signed hash.
+    pub fn merkle_proof(&self, tx_index: usize) -> Option<Vec<ProofStep>> {
+        MerkleTree::new(
+            self.transactions
+                .iter()
+                .map(|tx| tx.calculate_signed_hash())
+                .collect(),
+        )
+        .generate_proof(tx_index)
+    }
+
+    /// Calculates the hash of the block, which is the hash of its header.
+    pub fn calculate_hash(&self) -> String {
+        self.header.calculate_hash()
     }
 
-    /// Validates the block by comparing its calculated hash with its stored hash.
+    /// Validates the block by checking that its stored hash matches its header and that the
+    /// header's merkle root matches the transactions in the body.
     pub fn validate(&self) -> bool {
         self.hash == self.calculate_hash()
+            && self.header.merkle_root == Block::calculate_merkle_root(&self.transactions)
     }
 }
 
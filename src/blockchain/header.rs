@@ -0,0 +1,95 @@
+use crate::crypto::address::Address;
+use crate::crypto::hash::hash_bytes;
+use crate::utils::encoding::CanonicalEncoder;
+use serde::{Deserialize, Serialize};
+
+/// Version of the block header format produced by this node.
+pub const BLOCK_VERSION: u32 = 1;
+
+/// The easiest possible proof-of-work target, which every hash meets.
+pub const MAX_TARGET: [u8; 32] = [0xff; 32];
+
+/// The fixed-size summary of a block that its hash commits to.
+///
+/// The header commits to the block's transactions through the merkle root and to the ledger
+/// state after the block through the state root, so light clients can follow the chain and
+/// check inclusion proofs without downloading block bodies.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockHeader {
+    /// Version of the header format.
+    pub version: u32,
+    /// Height of the block in the chain.
+    pub height: u64,
+    /// Hash of the previous block in the chain.
+    pub previous_hash: String,
+    /// Merkle root of the block's transaction IDs.
+    pub merkle_root: String,
+    /// Timestamp of when the block was created.
+    pub timestamp: u64,
+    /// Proof-of-work target: the block hash, read as a big-endian 256-bit number, must not
+    /// exceed it.
+    pub target: [u8; 32],
+    /// A nonce used for the consensus algorithm (e.g., Proof-of-Work).
+    pub nonce: u64,
+    /// Extra search space for miners once every `nonce` has been tried.
+    #[serde(default)]
+    pub extra_nonce: u64,
+    /// Hash of the ledger state after applying the block.
+    pub state_root: String,
+    /// Under Proof-of-Authority, the block producer's vote to change the authority set.
+    #[serde(default)]
+    pub vote: Option<AuthorityVote>,
+    /// Address that receives the fees paid by the block's transactions, when the fee policy
+    /// pays them to the block producer.
+    #[serde(default)]
+    pub beneficiary: Option<Address>,
+}
+
+/// A vote to add an authority to, or remove one from, the set allowed to seal blocks under
+/// Proof-of-Authority. Authorities vote by sealing a block whose header carries the vote.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AuthorityVote {
+    /// Address (hex-encoded public key) of the authority being voted on.
+    pub authority: String,
+    /// `true` to add the authority to the set, `false` to remove it.
+    pub add: bool,
+}
+
+impl BlockHeader {
+    /// Builds the canonical byte encoding of the header.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut encoder = CanonicalEncoder::new("work-tokens/block-header");
+        encoder
+            .u32(self.version)
+            .u64(self.height)
+            .str(&self.previous_hash)
+            .str(&self.merkle_root)
+            .u64(self.timestamp)
+            .bytes(&self.target)
+            .u64(self.nonce)
+            .u64(self.extra_nonce)
+            .str(&self.state_root);
+        match &self.vote {
+            None => {
+                encoder.u8(0);
+            }
+            Some(vote) => {
+                encoder.u8(1).str(&vote.authority).u8(vote.add as u8);
+            }
+        }
+        match &self.beneficiary {
+            None => {
+                encoder.u8(0);
+            }
+            Some(beneficiary) => {
+                encoder.u8(1).str(&beneficiary.to_string());
+            }
+        }
+        encoder.finish()
+    }
+
+    /// Calculates the hash of the header, which is the hash of the block.
+    pub fn calculate_hash(&self) -> String {
+        hash_bytes(&self.canonical_bytes())
+    }
+}
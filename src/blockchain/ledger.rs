@@ -1,26 +1,113 @@
 use crate::blockchain::block::Block;
+use crate::blockchain::state::LedgerState;
 use crate::blockchain::transaction::Transaction;
-use crate::crypto::hash::calculate_hash;
+use crate::config::TokenSettings;
+use crate::consensus::dispute::PendingMint;
+use crate::consensus::engine::ConsensusEngine;
+use crate::consensus::proof_of_work::ProofOfWork;
+use crate::consensus::registry::{verifier_statuses, VerifierStatus};
+use crate::consensus::validator::{ValidationError, Validator};
+use crate::task::marketplace::{Task, TaskFilter};
+use crate::token::expiration::Token;
+use crate::token::fees::FEE_POOL_ACCOUNT;
+use crate::token::issuance::Issuance;
+use crate::token::lot::TokenLot;
+use crate::token::policy::ExpiryPolicy;
+use crate::token::supply::SupplyStats;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How far into the future (in seconds) a block's timestamp may be before it is rejected,
+/// unless the consensus engine sets a tighter bound.
+pub const MAX_FUTURE_BLOCK_TIME: u64 = 2 * 60 * 60;
+
+/// Most blocks kept on side branches. Once full, the lowest side blocks are dropped to make
+/// room for new ones.
+pub const MAX_SIDE_BLOCKS: usize = 1024;
 
 /// Represents the blockchain ledger, which consists of a chain of blocks.
 pub struct Ledger {
     /// The list of blocks, representing the entire blockchain.
     pub chain: Vec<Block>,
+    /// Every token issuance minted by an accepted work claim, in chain order.
+    pub issuances: Vec<Issuance>,
+    /// Spendable token lots derived from the blocks in the chain, along with the expiry
+    /// policy used to derive them.
+    pub state: LedgerState,
+    /// Consensus rules deciding who may produce each block.
+    pub consensus: Arc<dyn ConsensusEngine>,
+    /// Height of the latest block the finality gadget has finalized. Blocks up to this height
+    /// are never rolled back.
+    pub finalized_height: u64,
+    /// Properly sealed blocks that are not on the main chain, keyed by hash, at most
+    /// `MAX_SIDE_BLOCKS` of them. Their branches replace the main chain if they come to have
+    /// more cumulative work or contain a block the finality gadget finalizes.
+    pub side_blocks: HashMap<String, Block>,
+    /// Transactions dropped from the chain by reorgs, waiting to go back to the mempool.
+    orphaned_transactions: Vec<Transaction>,
 }
 
 impl Ledger {
     /// Creates a new ledger with the genesis block (the first block in the blockchain).
     pub fn new() -> Self {
-        let genesis_block = Ledger::create_genesis_block();
+        Self::with_state(LedgerState::new())
+    }
+
+    /// Creates a new ledger using the token expiry policy, holding cap, fee policy and verifier
+    /// rules from the given settings.
+    pub fn with_settings(settings: &TokenSettings) -> Self {
+        Self::with_state(LedgerState::with_settings(settings))
+    }
+
+    /// Creates a new ledger that applies a custom token expiry policy.
+    pub fn with_expiry_policy(expiry_policy: Arc<dyn ExpiryPolicy>) -> Self {
+        Self::with_state(LedgerState::with_expiry_policy(expiry_policy))
+    }
+
+    /// Sets the consensus engine the ledger enforces. The genesis block is rebuilt to carry
+    /// the engine's initial target, so this can only be used on a ledger with no other blocks.
+    pub fn with_consensus(mut self, consensus: Arc<dyn ConsensusEngine>) -> Self {
+        assert_eq!(self.chain.len(), 1, "Ledger already has blocks");
+        self.chain = vec![Ledger::create_genesis_block(
+            &self.state,
+            consensus.as_ref(),
+        )];
+        self.consensus = consensus;
+        self
+    }
+
+    /// Sets the ID of the network the ledger belongs to. Transactions signed for any other
+    /// network are rejected.
+    pub fn with_chain_id(mut self, chain_id: u32) -> Self {
+        self.state.chain_id = chain_id;
+        self
+    }
+
+    fn with_state(state: LedgerState) -> Self {
+        let consensus: Arc<dyn ConsensusEngine> = Arc::new(ProofOfWork::default());
+        let genesis_block = Ledger::create_genesis_block(&state, consensus.as_ref());
         Ledger {
             chain: vec![genesis_block],
+            issuances: Vec::new(),
+            state,
+            consensus,
+            finalized_height: 0,
+            side_blocks: HashMap::new(),
+            orphaned_transactions: Vec::new(),
         }
     }
 
     /// Creates the genesis block, which is the first block in the blockchain.
-    fn create_genesis_block() -> Block {
+    /// Its timestamp is fixed so that every node starts from an identical genesis block.
+    fn create_genesis_block(state: &LedgerState, consensus: &dyn ConsensusEngine) -> Block {
         let genesis_transactions = vec![];
-        Block::new(0, String::from("0"), genesis_transactions, 0)
+        let mut genesis_block = Block::new(0, String::from("0"), genesis_transactions, 0);
+        genesis_block.header.timestamp = 0;
+        genesis_block.header.target = consensus.initial_target();
+        genesis_block.header.state_root = state.state_root();
+        genesis_block.hash = genesis_block.calculate_hash();
+        genesis_block
     }
 
     /// Returns the latest block in the blockchain.
@@ -28,28 +115,627 @@ impl Ledger {
         self.chain.last().expect("Blockchain is empty")
     }
 
+    /// Returns the Proof-of-Work target the next block must carry.
+    pub fn next_target(&self) -> [u8; 32] {
+        self.consensus.next_target(&self.chain)
+    }
+
+    /// Fills in a block's state root with the state that applying it to the current tip
+    /// would produce, and updates its hash to match. Block producers call this before
+    /// sealing a block.
+    ///
+    /// # Arguments
+    /// * `block` - The block to commit the state root of.
+    ///
+    /// # Returns
+    /// * `Result<(), ValidationError>` - Why the block cannot be applied, if it cannot.
+    pub fn commit_state_root(&self, block: &mut Block) -> Result<(), ValidationError> {
+        let mut next_state = self.state.clone();
+        next_state.apply_block(block)?;
+        block.header.state_root = next_state.state_root();
+        block.hash = block.calculate_hash();
+        Ok(())
+    }
+
     /// Adds a new block to the ledger after validating it.
-    pub fn add_block(&mut self, mut new_block: Block) -> bool {
+    ///
+    /// A block that extends the tip is validated and appended. A block that builds on any
+    /// other known block at or after the finalized block is stored on a side branch once its
+    /// target and seal check out for that branch, and the ledger reorganizes onto the branch
+    /// once it has more cumulative work than the main chain. Transactions in blocks a reorg
+    /// drops that are not in the new branch are kept, to be taken with
+    /// `take_orphaned_transactions`.
+    ///
+    /// # Arguments
+    /// * `new_block` - The block received.
+    ///
+    /// # Returns
+    /// * `bool` - Returns `true` if the block was appended to the chain or stored on a side
+    ///   branch.
+    pub fn add_block(&mut self, new_block: Block) -> bool {
+        if new_block.header.previous_hash == self.get_latest_block().hash {
+            return self.append_block(new_block);
+        }
+
+        if self.get_block_by_hash(&new_block.hash).is_some()
+            || self.side_blocks.contains_key(&new_block.hash)
+        {
+            println!("Error: Block {} is already known.", new_block.hash);
+            return false;
+        }
+
+        let Some(ancestry) = self.branch_ancestry(&new_block.header.previous_hash) else {
+            println!("Error: New block's parent is unknown.");
+            return false;
+        };
+        if !self.check_side_block(&new_block, &ancestry) {
+            return false;
+        }
+
+        let hash = new_block.hash.clone();
+        self.side_blocks.insert(hash.clone(), new_block);
+        self.prune_side_blocks();
+        self.reorganize_to(&hash)
+    }
+
+    /// Returns the blocks from genesis up to and including the block `hash`, following side
+    /// branches back to the main chain, or `None` if the block is unknown or its branch no
+    /// longer joins the main chain.
+    fn branch_ancestry(&self, hash: &str) -> Option<Vec<Block>> {
+        let mut branch = Vec::new();
+        let mut hash = hash.to_string();
+        while let Some(block) = self.side_blocks.get(&hash) {
+            hash = block.header.previous_hash.clone();
+            branch.push(block.clone());
+        }
+        let fork_height = self.get_block_by_hash(&hash)?.header.height as usize;
+        let mut ancestry = self.chain[..=fork_height].to_vec();
+        ancestry.extend(branch.into_iter().rev());
+        Some(ancestry)
+    }
+
+    /// Checks everything about a block on a side branch that does not depend on the state,
+    /// before it is stored or its work is counted: it must follow its parent, branch off at
+    /// or after the finalized block, carry the target the retarget rule sets for its branch
+    /// and be sealed by whoever the consensus engine entitles to produce it there.
+    fn check_side_block(&self, block: &Block, ancestry: &[Block]) -> bool {
+        let parent = ancestry.last().expect("Ancestry includes genesis");
+        if block.header.height != parent.header.height + 1 {
+            println!("Error: New block's index does not follow its parent's index.");
+            return false;
+        }
+        let fork_height = ancestry
+            .iter()
+            .take_while(|ancestor| self.get_block_by_hash(&ancestor.hash).is_some())
+            .count() as u64
+            - 1;
+        if fork_height < self.finalized_height {
+            println!("Error: New block's branch conflicts with the finalized block.");
+            return false;
+        }
+        if block.header.timestamp < parent.header.timestamp
+            || block.header.timestamp
+                > get_current_timestamp().saturating_add(self.consensus.max_future_time())
+        {
+            println!("Error: New block's timestamp is out of range.");
+            return false;
+        }
+        if !block.validate() {
+            println!("Error: New block's hash or merkle root does not match its contents.");
+            return false;
+        }
+        if block.header.target != self.consensus.next_target(ancestry) {
+            println!("Error: New block's target does not match the retarget rule.");
+            return false;
+        }
+        if let Err(err) = self.consensus.verify_seal(ancestry, block) {
+            println!("Error: New block is not properly sealed: {}.", err);
+            return false;
+        }
+        true
+    }
+
+    /// Drops side blocks that can no longer become part of the main chain because they are
+    /// at or below the finalized block, then the lowest side blocks while there are more than
+    /// `MAX_SIDE_BLOCKS`.
+    fn prune_side_blocks(&mut self) {
+        let finalized_height = self.finalized_height;
+        self.side_blocks
+            .retain(|_, block| block.header.height > finalized_height);
+        if self.side_blocks.len() <= MAX_SIDE_BLOCKS {
+            return;
+        }
+        let mut heights: Vec<(u64, String)> = self
+            .side_blocks
+            .values()
+            .map(|block| (block.header.height, block.hash.clone()))
+            .collect();
+        heights.sort();
+        let excess = self.side_blocks.len() - MAX_SIDE_BLOCKS;
+        for (_, hash) in heights.into_iter().take(excess) {
+            self.side_blocks.remove(&hash);
+        }
+    }
+
+    /// Switches the main chain to the branch ending at the side block `tip_hash` if that
+    /// branch has more cumulative work. Returns `false` if switching was attempted but the
+    /// branch turned out to be invalid.
+    fn reorganize_to(&mut self, tip_hash: &str) -> bool {
+        // A branch cut off from the chain by an invalid block can never be switched to
+        let Some((fork_height, branch)) = self.side_branch(tip_hash) else {
+            return true;
+        };
+
+        let branch_work = self.cumulative_work(&branch);
+        let main_work = self.cumulative_work(&self.chain[fork_height as usize + 1..]);
+        if branch_work <= main_work {
+            return true;
+        }
+        if fork_height < self.finalized_height {
+            println!("Error: Heavier branch conflicts with the finalized block.");
+            return true;
+        }
+        self.switch_to_branch(fork_height, branch)
+    }
+
+    /// Walks back from the side block `tip_hash` to where its branch leaves the main chain.
+    ///
+    /// # Returns
+    /// * `Option<(u64, Vec<Block>)>` - The height of the main chain block the branch builds on
+    ///   and the branch's side blocks in chain order, or `None` if the branch no longer joins
+    ///   the main chain.
+    fn side_branch(&self, tip_hash: &str) -> Option<(u64, Vec<Block>)> {
+        let mut branch = Vec::new();
+        let mut hash = tip_hash.to_string();
+        while let Some(block) = self.side_blocks.get(&hash) {
+            hash = block.header.previous_hash.clone();
+            branch.push(block.clone());
+        }
+        branch.reverse();
+        let fork_height = self.get_block_by_hash(&hash)?.header.height;
+        Some((fork_height, branch))
+    }
+
+    /// Replaces the main chain after `fork_height` with the given branch, keeping the blocks
+    /// it drops on side branches. If a block in the branch does not apply, the chain is put
+    /// back as it was and `false` is returned.
+    fn switch_to_branch(&mut self, fork_height: u64, branch: Vec<Block>) -> bool {
+        let dropped = self.chain[fork_height as usize + 1..].to_vec();
+        self.rollback_to(fork_height);
+        for (index, block) in branch.iter().enumerate() {
+            self.side_blocks.remove(&block.hash);
+            if !self.append_block(block.clone()) {
+                // The branch is invalid from this block on, so go back to the chain as it was
+                // and keep only the valid part of the branch
+                println!(
+                    "Error: Branch block {} is invalid; keeping the current chain.",
+                    block.hash
+                );
+                for block in &branch[index + 1..] {
+                    self.side_blocks.remove(&block.hash);
+                }
+                for block in &branch[..index] {
+                    self.side_blocks.insert(block.hash.clone(), block.clone());
+                }
+                self.rollback_to(fork_height);
+                self.restore_blocks(dropped);
+                return false;
+            }
+        }
+
+        let kept: HashSet<&str> = branch
+            .iter()
+            .flat_map(|block| block.transactions.iter())
+            .map(|tx| tx.id.as_str())
+            .collect();
+        for block in dropped {
+            self.orphaned_transactions.extend(
+                block
+                    .transactions
+                    .iter()
+                    .filter(|tx| !kept.contains(tx.id.as_str()))
+                    .cloned(),
+            );
+            self.side_blocks.insert(block.hash.clone(), block);
+        }
+        true
+    }
+
+    /// Appends blocks that a failed reorg rolled back. A block that no longer appends, such as
+    /// one whose timestamp the local clock has fallen behind, is kept on a side branch with
+    /// every block after it, and its transactions go back to the mempool.
+    fn restore_blocks(&mut self, blocks: Vec<Block>) {
+        let mut blocks = blocks.into_iter();
+        for block in blocks.by_ref() {
+            let hash = block.hash.clone();
+            if self.append_block(block.clone()) {
+                continue;
+            }
+            println!("Error: Dropped block {} can no longer be restored.", hash);
+            for block in std::iter::once(block).chain(blocks) {
+                self.orphaned_transactions
+                    .extend(block.transactions.iter().cloned());
+                self.side_blocks.insert(block.hash.clone(), block);
+            }
+            return;
+        }
+    }
+
+    /// Sums the work the consensus engine credits to each of the given blocks.
+    fn cumulative_work(&self, blocks: &[Block]) -> u128 {
+        blocks.iter().fold(0, |work, block| {
+            work.saturating_add(self.consensus.block_work(block))
+        })
+    }
+
+    /// Retrieves a block by its hash, from the main chain or a side branch.
+    pub fn find_block(&self, hash: &str) -> Option<&Block> {
+        self.get_block_by_hash(hash)
+            .or_else(|| self.side_blocks.get(hash))
+    }
+
+    /// Takes the transactions that were dropped from the chain by reorgs, so they can be
+    /// returned to the mempool.
+    pub fn take_orphaned_transactions(&mut self) -> Vec<Transaction> {
+        std::mem::take(&mut self.orphaned_transactions)
+    }
+
+    /// Validates a block that extends the tip and appends it to the chain.
+    fn append_block(&mut self, new_block: Block) -> bool {
         // Validate the new block before adding it to the ledger
         let latest_block = self.get_latest_block();
 
-        if new_block.previous_hash != latest_block.hash {
+        if new_block.header.previous_hash != latest_block.hash {
             println!("Error: New block's previous hash does not match the latest block's hash.");
             return false;
         }
 
-        new_block.hash = new_block.calculate_hash();
+        if new_block.header.height != latest_block.header.height + 1 {
+            println!("Error: New block's index does not follow the latest block's index.");
+            return false;
+        }
+
+        // Expiry is judged against block timestamps, so they must never go backwards
+        if new_block.header.timestamp < latest_block.header.timestamp {
+            println!("Error: New block's timestamp is earlier than the latest block's.");
+            return false;
+        }
 
-        // Check that the new block is valid
+        if new_block.header.timestamp
+            > get_current_timestamp().saturating_add(self.consensus.max_future_time())
+        {
+            println!("Error: New block's timestamp is too far in the future.");
+            return false;
+        }
+
+        // Check that the stored hash matches the header and the header matches the body
         if !new_block.validate() {
+            println!("Error: New block's hash or merkle root does not match its contents.");
+            return false;
+        }
+
+        // The target must follow the retarget rule, and the block must be sealed by
+        // whoever the consensus engine entitles to produce it
+        if new_block.header.target != self.next_target() {
+            println!("Error: New block's target does not match the retarget rule.");
+            return false;
+        }
+
+        if let Err(err) = self.consensus.verify_seal(&self.chain, &new_block) {
+            println!("Error: New block is not properly sealed: {}.", err);
+            return false;
+        }
+
+        if !Validator::validate_block(&new_block) {
             println!("Error: New block is invalid.");
             return false;
         }
 
+        // Each piece of work can only ever be rewarded once
+        for transaction in &new_block.transactions {
+            if let Some(claim) = transaction.work_claim() {
+                if self.is_work_claimed(&claim.work_hash) {
+                    println!("Error: Work {} has already been claimed.", claim.work_hash);
+                    return false;
+                }
+            }
+        }
+
+        // Spend and create lots; the block is rejected unless every transaction applies cleanly
+        let mut next_state = self.state.clone();
+        if let Err(err) = next_state.apply_block(&new_block) {
+            println!("Error: New block cannot be applied: {}.", err);
+            return false;
+        }
+        if next_state.state_root() != new_block.header.state_root {
+            println!("Error: New block's state root does not match the resulting state.");
+            return false;
+        }
+        self.state = next_state;
+
+        self.record_issuances(&new_block);
         self.chain.push(new_block);
         true
     }
 
+    /// Records the tokens minted by every accepted work claim in a block.
+    fn record_issuances(&mut self, block: &Block) {
+        for transaction in &block.transactions {
+            if let Some(issuance) = Issuance::from_work_claim(
+                transaction,
+                block.header.timestamp,
+                self.state.expiry_policy.as_ref(),
+            ) {
+                self.issuances.push(issuance);
+            }
+        }
+    }
+
+    /// Marks a block as final, so that it and every block before it can never be rolled
+    /// back. Called once the finality gadget reports that the validator set has finalized the
+    /// block.
+    ///
+    /// A block on a side branch is final all the same: the ledger reorganizes onto its branch
+    /// even if the branch has less work than the main chain. From then on, any branch that
+    /// does not contain the finalized block is dropped and never switched to.
+    ///
+    /// # Arguments
+    /// * `hash` - The hash of the finalized block.
+    ///
+    /// # Returns
+    /// * `bool` - Returns `true` if the block is now final on the main chain, or `false` if it
+    ///   is unknown, conflicts with the latest finalized block, or its branch is invalid.
+    pub fn finalize_block(&mut self, hash: &str) -> bool {
+        if self.get_block_by_hash(hash).is_none() {
+            let Some((fork_height, branch)) = self.side_branch(hash) else {
+                println!("Error: Finalized block {} is unknown.", hash);
+                return false;
+            };
+            if fork_height < self.finalized_height {
+                println!(
+                    "Error: Block {} conflicts with the latest finalized block.",
+                    hash
+                );
+                return false;
+            }
+            if !self.switch_to_branch(fork_height, branch) {
+                return false;
+            }
+        }
+
+        let height = self
+            .get_block_by_hash(hash)
+            .expect("Finalized block is on the main chain")
+            .header
+            .height;
+        if height < self.finalized_height {
+            println!(
+                "Error: Block {} is behind the latest finalized block.",
+                hash
+            );
+            return false;
+        }
+        self.finalized_height = height;
+
+        // Branches that leave the main chain below the finalized block can never be switched to
+        let conflicting: Vec<String> = self
+            .side_blocks
+            .keys()
+            .filter(|hash| {
+                self.side_branch(hash)
+                    .is_some_and(|(fork_height, _)| fork_height < height)
+            })
+            .cloned()
+            .collect();
+        for hash in conflicting {
+            self.side_blocks.remove(&hash);
+        }
+        self.prune_side_blocks();
+        true
+    }
+
+    /// Checks whether the block at the given height is final.
+    pub fn is_finalized(&self, height: u64) -> bool {
+        height <= self.finalized_height && height < self.chain.len() as u64
+    }
+
+    /// Rolls the chain back so that the block at `height` is the latest, rebuilding the state
+    /// from the blocks that remain. Finalized blocks are never rolled back.
+    ///
+    /// # Arguments
+    /// * `height` - The height of the block to roll back to.
+    ///
+    /// # Returns
+    /// * `bool` - Returns `true` if the chain was rolled back.
+    pub fn rollback_to(&mut self, height: u64) -> bool {
+        if height < self.finalized_height {
+            println!("Error: Cannot roll back past the finalized block.");
+            return false;
+        }
+        if height >= self.get_latest_block().header.height {
+            return false;
+        }
+
+        self.chain.truncate(height as usize + 1);
+        self.state = LedgerState {
+            holding_cap: self.state.holding_cap.clone(),
+            chain_id: self.state.chain_id,
+            fee_policy: self.state.fee_policy,
+            verifier_rules: self.state.verifier_rules.clone(),
+            issuance_policy: self.state.issuance_policy.clone(),
+            ..LedgerState::with_expiry_policy(self.state.expiry_policy.clone())
+        };
+        self.issuances.clear();
+        let chain = std::mem::take(&mut self.chain);
+        for block in &chain[1..] {
+            self.state
+                .apply_block(block)
+                .expect("Blocks in the chain apply cleanly");
+            self.record_issuances(block);
+        }
+        self.chain = chain;
+        true
+    }
+
+    /// Validates a transaction that has not been included in a block yet, as if it were
+    /// included in the next block.
+    ///
+    /// # Arguments
+    /// * `transaction` - The transaction to be validated.
+    ///
+    /// # Returns
+    /// * `Result<(), ValidationError>` - Why the transaction would be rejected, if it would.
+    pub fn validate_pending_transaction(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<(), ValidationError> {
+        self.apply_pending_transaction(transaction, &mut self.state.clone(), self.next_block_time())
+    }
+
+    /// Applies a transaction that has not been included in a block yet to a state built on
+    /// top of the ledger's, such as one with other pending transactions applied, as if it
+    /// were included in the next block.
+    ///
+    /// Returns an error if the transaction would be rejected. The state may then be partially
+    /// updated, so callers should apply transactions to a copy and only keep it on success.
+    ///
+    /// # Arguments
+    /// * `transaction` - The transaction to be applied.
+    /// * `state` - The state to apply the transaction to.
+    /// * `timestamp` - Timestamp of the block the transaction would be included in.
+    ///
+    /// # Returns
+    /// * `Result<(), ValidationError>` - Why the transaction would be rejected, if it would.
+    pub fn apply_pending_transaction(
+        &self,
+        transaction: &Transaction,
+        state: &mut LedgerState,
+        timestamp: u64,
+    ) -> Result<(), ValidationError> {
+        if !Validator::validate_transaction(transaction) {
+            return Err(ValidationError::InvalidTransaction(transaction.id.clone()));
+        }
+        if let Some(claim) = transaction.work_claim() {
+            if self.is_work_claimed(&claim.work_hash) {
+                return Err(ValidationError::WorkAlreadyClaimed(claim.work_hash.clone()));
+            }
+        }
+
+        let height = self.get_latest_block().header.height + 1;
+        state.apply_transaction(transaction, height, timestamp)
+    }
+
+    /// Returns the timestamp a block produced now on top of the tip would carry: the current
+    /// time, or the tip's timestamp if the clock is behind it.
+    pub fn next_block_time(&self) -> u64 {
+        get_current_timestamp().max(self.get_latest_block().header.timestamp)
+    }
+
+    /// Checks whether a work claim with the given work hash has already been accepted.
+    pub fn is_work_claimed(&self, work_hash: &str) -> bool {
+        self.state.claimed_work.contains(work_hash)
+    }
+
+    /// Gets the nonce an account's next transaction must carry.
+    pub fn get_next_nonce(&self, account: &str) -> u64 {
+        self.state.next_nonce(account)
+    }
+
+    /// Gets an account's balance of lots that are still spendable as of the latest block,
+    /// valued under the ledger's expiry policy.
+    pub fn get_balance(&self, account: &str) -> u64 {
+        let latest_block = self.get_latest_block();
+        self.state.get_balance(
+            account,
+            latest_block.header.height,
+            latest_block.header.timestamp,
+        )
+    }
+
+    /// Gets the total number of tokens that have expired unspent since genesis.
+    pub fn get_expired_supply(&self) -> u64 {
+        self.state.expired_supply
+    }
+
+    /// Reports the token supply as of the latest block.
+    pub fn get_supply_stats(&self) -> SupplyStats {
+        let latest_block = self.get_latest_block();
+        self.state
+            .supply_stats(latest_block.header.height, latest_block.header.timestamp)
+    }
+
+    /// Gets the total number of tokens paid as fees and burned since genesis.
+    pub fn get_burned_fees(&self) -> u64 {
+        self.state.burned_fees
+    }
+
+    /// Gets the balance of the fee pool: fees collected and not yet paid out.
+    pub fn get_fee_pool(&self) -> u64 {
+        self.get_balance(FEE_POOL_ACCOUNT)
+    }
+
+    /// Gets the number of tokens that expired in the block at the given height.
+    pub fn get_expired_supply_in_block(&self, height: u64) -> u64 {
+        self.state.get_expired_in_block(height)
+    }
+
+    /// Gets the number of tokens that expired while held by an account.
+    pub fn get_expired_supply_for_account(&self, account: &str) -> u64 {
+        self.state.get_expired_for_account(account)
+    }
+
+    /// Retrieves the unspent lots owned by an account.
+    pub fn get_lots(&self, account: &str) -> Vec<&TokenLot> {
+        self.state.get_lots(account)
+    }
+
+    /// Retrieves a task posted to the marketplace by the ID of the transaction that posted it.
+    pub fn get_task(&self, task_id: &str) -> Option<&Task> {
+        self.state.get_task(task_id)
+    }
+
+    /// Retrieves every task matching the filter as of the latest block.
+    pub fn get_tasks(&self, filter: &TaskFilter) -> Vec<&Task> {
+        self.state
+            .get_tasks(filter, self.get_latest_block().header.timestamp)
+    }
+
+    /// Retrieves the standing of every verifier in the registry as of the latest block, in
+    /// address order.
+    pub fn get_verifiers(&self) -> Vec<VerifierStatus> {
+        let header = &self.get_latest_block().header;
+        verifier_statuses(
+            &self.state.verifier_rules,
+            &self.state.verifiers,
+            |verifier| {
+                self.state
+                    .get_bond(verifier, header.height, header.timestamp)
+            },
+        )
+    }
+
+    /// Retrieves the standing of a verifier by address (hex-encoded public key).
+    pub fn get_verifier(&self, verifier: &str) -> Option<VerifierStatus> {
+        self.get_verifiers()
+            .into_iter()
+            .find(|status| status.verifier == verifier)
+    }
+
+    /// Retrieves a work mint that can still be disputed or is under dispute, by the ID of the
+    /// transaction that made it.
+    pub fn get_pending_mint(&self, mint_id: &str) -> Option<&PendingMint> {
+        self.state.pending_mints.get(mint_id)
+    }
+
+    /// Retrieves every token minted to an account through work claims.
+    pub fn get_issued_tokens(&self, account: &str) -> Vec<Token> {
+        self.issuances
+            .iter()
+            .filter(|issuance| issuance.issuer == account)
+            .flat_map(|issuance| issuance.issued_tokens.iter().cloned())
+            .collect()
+    }
+
     /// Validates the integrity of the entire blockchain.
     /// This ensures that each block links properly to the previous block and that all hashes are valid.
     pub fn validate_chain(&self) -> bool {
@@ -58,14 +744,31 @@ impl Ledger {
             let previous_block = &self.chain[i - 1];
 
             // Check if the current block's previous hash matches the previous block's hash
-            if current_block.previous_hash != previous_block.hash {
-                println!("Error: Block {}'s previous hash does not match the hash of the previous block.", current_block.index);
+            if current_block.header.previous_hash != previous_block.hash {
+                println!("Error: Block {}'s previous hash does not match the hash of the previous block.", current_block.header.height);
+                return false;
+            }
+
+            // Check if the current block's hash and merkle root are valid
+            if !current_block.validate() {
+                println!(
+                    "Error: Block {}'s hash is invalid.",
+                    current_block.header.height
+                );
                 return false;
             }
 
-            // Check if the current block's hash is valid
-            if current_block.hash != current_block.calculate_hash() {
-                println!("Error: Block {}'s hash is invalid.", current_block.index);
+            // Check that the block followed the retarget rule and was properly sealed
+            if current_block.header.target != self.consensus.next_target(&self.chain[..i])
+                || self
+                    .consensus
+                    .verify_seal(&self.chain[..i], current_block)
+                    .is_err()
+            {
+                println!(
+                    "Error: Block {}'s seal is invalid.",
+                    current_block.header.height
+                );
                 return false;
             }
         }
@@ -74,7 +777,9 @@ impl Ledger {
 
     /// Retrieves a block by its index in the blockchain.
     pub fn get_block_by_index(&self, index: u64) -> Option<&Block> {
-        self.chain.iter().find(|&block| block.index == index)
+        self.chain
+            .iter()
+            .find(|&block| block.header.height == index)
     }
 
     /// Retrieves a block by its hash.
@@ -90,3 +795,17 @@ impl Ledger {
             .collect()
     }
 }
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Helper function to get the current timestamp in seconds since the UNIX epoch.
+fn get_current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Time went backwards")
+        .as_secs()
+}
@@ -1,67 +1,136 @@
-use crate::crypto::hash::calculate_hash;
+use crate::crypto::hash::hash_bytes;
 use serde::{Deserialize, Serialize};
 
+/// Prefix for hashing a leaf, so a leaf can never be mistaken for an inner node.
+const LEAF_PREFIX: u8 = 0x00;
+/// Prefix for hashing an inner node from its two children.
+const NODE_PREFIX: u8 = 0x01;
+
 /// Represents a Merkle Tree in the blockchain, which ensures transaction integrity.
+///
+/// Leaves and inner nodes are hashed with different prefixes, and a node without a sibling is
+/// carried up to the next level unchanged rather than paired with a copy of itself, so two
+/// different transaction lists can never share a root.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MerkleTree {
     pub root: Option<String>,
     pub transactions: Vec<String>, // Stores the transaction hashes
+    /// Every level of the tree, from the leaf hashes up to the root.
+    pub levels: Vec<Vec<String>>,
+}
+
+/// Which side of the path being proven a sibling hash sits on.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// One step of an inclusion proof: the sibling to combine with, and which side it is on.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProofStep {
+    pub hash: String,
+    pub side: Side,
 }
 
 impl MerkleTree {
     /// Constructs a new Merkle Tree from a list of transactions.
     pub fn new(transaction_hashes: Vec<String>) -> Self {
-        let root = if transaction_hashes.is_empty() {
-            None
+        let levels = if transaction_hashes.is_empty() {
+            Vec::new()
         } else {
-            Some(build_merkle_root(transaction_hashes.clone()))
+            build_levels(&transaction_hashes)
         };
+        let root = levels.last().map(|level| level[0].clone());
 
         MerkleTree {
             root,
             transactions: transaction_hashes,
+            levels,
         }
     }
 
-    /// Verifies if a transaction hash is part of the Merkle Tree by comparing it to the root.
-    pub fn verify_proof(&self, tx_hash: &String, proof: &Vec<String>, root: &String) -> bool {
-        let mut current_hash = tx_hash.clone();
+    /// Generates a proof that the transaction at `tx_index` is included in the tree: the
+    /// sibling hashes on the path from its leaf up to the root, lowest first.
+    ///
+    /// Returns `None` if there is no transaction at that index.
+    pub fn generate_proof(&self, tx_index: usize) -> Option<Vec<ProofStep>> {
+        if tx_index >= self.transactions.len() {
+            return None;
+        }
 
-        for sibling_hash in proof {
-            current_hash = combine_and_hash(&current_hash, sibling_hash);
+        let mut proof = Vec::new();
+        let mut index = tx_index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = if index.is_multiple_of(2) {
+                level.get(index + 1).map(|hash| ProofStep {
+                    hash: hash.clone(),
+                    side: Side::Right,
+                })
+            } else {
+                Some(ProofStep {
+                    hash: level[index - 1].clone(),
+                    side: Side::Left,
+                })
+            };
+            // A node without a sibling is carried up unchanged, so it adds no step
+            proof.extend(sibling);
+            index /= 2;
         }
+        Some(proof)
+    }
+
+    /// Verifies that a transaction hash is part of the tree with the given root, by hashing
+    /// it up the path described by the proof.
+    pub fn verify_proof(tx_hash: &str, proof: &[ProofStep], root: &str) -> bool {
+        let mut current_hash = hash_leaf(tx_hash);
 
-        &current_hash == root
+        for step in proof {
+            current_hash = match step.side {
+                Side::Left => hash_node(&step.hash, &current_hash),
+                Side::Right => hash_node(&current_hash, &step.hash),
+            };
+        }
+
+        current_hash == root
     }
 }
 
-/// Combines two hashes and computes their parent hash.
-fn combine_and_hash(left: &String, right: &String) -> String {
-    let combined = format!("{}{}", left, right);
-    calculate_hash(&combined)
+/// Hashes a transaction hash into a leaf of the tree.
+fn hash_leaf(tx_hash: &str) -> String {
+    let mut data = vec![LEAF_PREFIX];
+    data.extend_from_slice(tx_hash.as_bytes());
+    hash_bytes(&data)
 }
 
-/// Builds the Merkle Root by recursively hashing the transaction hashes.
-fn build_merkle_root(mut transaction_hashes: Vec<String>) -> String {
-    // Base case: If only one hash is left, return it as the root
-    if transaction_hashes.len() == 1 {
-        return transaction_hashes[0].clone();
-    }
-
-    // If the number of transactions is odd, duplicate the last one
-    if transaction_hashes.len() % 2 != 0 {
-        let last_hash = transaction_hashes.last().unwrap().clone();
-        transaction_hashes.push(last_hash);
-    }
+/// Combines two child hashes and computes their parent hash.
+fn hash_node(left: &str, right: &str) -> String {
+    let mut data = vec![NODE_PREFIX];
+    data.extend_from_slice(left.as_bytes());
+    data.extend_from_slice(right.as_bytes());
+    hash_bytes(&data)
+}
 
-    let mut parent_level = vec![];
+/// Builds every level of the tree, from the leaves up to the root.
+fn build_levels(transaction_hashes: &[String]) -> Vec<Vec<String>> {
+    let mut levels = vec![transaction_hashes
+        .iter()
+        .map(|tx_hash| hash_leaf(tx_hash))
+        .collect::<Vec<_>>()];
 
-    // Combine pairs of transactions to compute their parent hashes
-    for i in (0..transaction_hashes.len()).step_by(2) {
-        let parent_hash = combine_and_hash(&transaction_hashes[i], &transaction_hashes[i + 1]);
-        parent_level.push(parent_hash);
+    // Combine pairs of nodes into their parents until only the root is left
+    while levels.last().unwrap().len() > 1 {
+        let parent_level = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => hash_node(left, right),
+                [single] => single.clone(),
+                _ => unreachable!("chunks of two"),
+            })
+            .collect();
+        levels.push(parent_level);
     }
-
-    // Recursively build the tree by computing parent levels until we get to the root
-    build_merkle_root(parent_level)
+    levels
 }
@@ -1,9 +1,17 @@
 pub mod block;
+pub mod header;
 pub mod ledger;
 pub mod merkle_tree;
+pub mod multisig;
+pub mod state;
 pub mod transaction;
+pub mod work_claim;
 
-pub use self::block::Block;
+pub use self::block::{Block, Seal};
+pub use self::header::{AuthorityVote, BlockHeader};
 pub use self::ledger::Ledger;
-pub use self::merkle_tree::MerkleTree;
-pub use self::transaction::Transaction;
+pub use self::merkle_tree::{MerkleTree, ProofStep, Side};
+pub use self::multisig::{Approval, MultisigPolicy};
+pub use self::state::LedgerState;
+pub use self::transaction::{Transaction, TransactionKind};
+pub use self::work_claim::{Attestation, WorkClaim};
@@ -0,0 +1,177 @@
+use crate::crypto::address::{Address, Network};
+use crate::crypto::signatures::{sign_message, verify_signature};
+use crate::utils::encoding::CanonicalEncoder;
+use ed25519_dalek::{Keypair, PublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+/// Largest number of members a multisig account may have.
+pub const MAX_MULTISIG_MEMBERS: usize = 16;
+
+/// A member's signature approving a transaction from a multisig account.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Approval {
+    /// Public key of the approving member.
+    pub signer: PublicKey,
+    /// Signature over the transaction's ID.
+    pub signature: Vec<u8>,
+}
+
+impl Approval {
+    /// Signs an approval of the transaction with the given ID.
+    pub fn sign(transaction_id: &str, member: &Keypair) -> Self {
+        Approval {
+            signer: member.public,
+            signature: sign_message(transaction_id, member),
+        }
+    }
+}
+
+/// The rules of a shared account: the keys of its members, and how many of them must approve
+/// a payment before it can be made.
+///
+/// The account's address is derived from a hash of the policy, so the policy cannot change
+/// without the account changing too. Transactions from the account carry the policy, which
+/// lets every node check the approvals against it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MultisigPolicy {
+    /// Number of members who must approve a transaction.
+    pub threshold: u32,
+    /// Public keys of the members, in the order they were given.
+    pub members: Vec<PublicKey>,
+}
+
+impl MultisigPolicy {
+    /// Creates a policy requiring `threshold` of the given members to approve each payment.
+    pub fn new(threshold: u32, members: Vec<PublicKey>) -> Self {
+        MultisigPolicy { threshold, members }
+    }
+
+    /// Checks that the policy can be met: it has between one and `MAX_MULTISIG_MEMBERS`
+    /// distinct members, and a threshold of at least one and at most the number of members.
+    pub fn is_valid(&self) -> bool {
+        let distinct: HashSet<[u8; 32]> = self.members.iter().map(|key| key.to_bytes()).collect();
+        !self.members.is_empty()
+            && self.members.len() <= MAX_MULTISIG_MEMBERS
+            && distinct.len() == self.members.len()
+            && self.threshold >= 1
+            && self.threshold as usize <= self.members.len()
+    }
+
+    /// Checks whether the given key belongs to a member.
+    pub fn is_member(&self, key: &PublicKey) -> bool {
+        self.members.contains(key)
+    }
+
+    /// Appends the policy to a canonical encoding.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        encoder.u32(self.threshold).count(self.members.len());
+        for member in &self.members {
+            encoder.bytes(member.as_bytes());
+        }
+    }
+
+    /// Hashes the policy's canonical encoding, which identifies the account.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut encoder = CanonicalEncoder::new("work-tokens/multisig");
+        self.encode(&mut encoder);
+        Sha256::digest(encoder.finish()).into()
+    }
+
+    /// Returns the account's address on the given network.
+    pub fn address(&self, network: Network) -> Address {
+        Address::from_multisig_hash(self.hash(), network)
+    }
+
+    /// Counts the members approving a transaction.
+    ///
+    /// # Arguments
+    /// * `transaction_id` - ID of the transaction the approvals are for.
+    /// * `approvals` - The approvals collected so far.
+    ///
+    /// # Returns
+    /// * `Option<usize>` - The number of approvals, or `None` if any of them is not a valid
+    ///   signature by a member, or a member approved more than once.
+    pub fn count_approvals(&self, transaction_id: &str, approvals: &[Approval]) -> Option<usize> {
+        let mut seen_signers = Vec::new();
+        for approval in approvals {
+            if !self.is_member(&approval.signer) || seen_signers.contains(&approval.signer) {
+                return None;
+            }
+            if !verify_signature(transaction_id, &approval.signature, &approval.signer) {
+                return None;
+            }
+            seen_signers.push(approval.signer);
+        }
+        Some(seen_signers.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn members(count: usize) -> Vec<Keypair> {
+        (0..count).map(|_| Keypair::generate(&mut OsRng)).collect()
+    }
+
+    fn policy(threshold: u32, members: &[Keypair]) -> MultisigPolicy {
+        MultisigPolicy::new(
+            threshold,
+            members.iter().map(|member| member.public).collect(),
+        )
+    }
+
+    #[test]
+    fn test_policy_must_be_satisfiable() {
+        let keys = members(3);
+        assert!(policy(2, &keys).is_valid());
+        assert!(policy(3, &keys).is_valid());
+        assert!(!policy(0, &keys).is_valid());
+        assert!(!policy(4, &keys).is_valid());
+        assert!(!policy(1, &[]).is_valid());
+
+        let mut repeated = policy(2, &keys);
+        repeated.members.push(keys[0].public);
+        assert!(!repeated.is_valid());
+        assert!(!policy(1, &members(MAX_MULTISIG_MEMBERS + 1)).is_valid());
+    }
+
+    #[test]
+    fn test_address_commits_to_the_whole_policy() {
+        let keys = members(3);
+        let address = policy(2, &keys).address(Network::Main);
+        assert_eq!(address, policy(2, &keys).address(Network::Main));
+        assert_ne!(address, policy(3, &keys).address(Network::Main));
+        assert_ne!(address, policy(2, &keys[..2]).address(Network::Main));
+        assert!(address.public_key().is_none());
+        assert_eq!(address.to_string().parse::<Address>(), Ok(address));
+    }
+
+    #[test]
+    fn test_counts_only_valid_member_approvals() {
+        let keys = members(3);
+        let policy = policy(2, &keys);
+        let approvals = vec![
+            Approval::sign("tx", &keys[0]),
+            Approval::sign("tx", &keys[2]),
+        ];
+        assert_eq!(policy.count_approvals("tx", &approvals), Some(2));
+        assert_eq!(policy.count_approvals("tx", &[]), Some(0));
+
+        // Approvals of another transaction, repeated approvals and outsiders are rejected
+        assert_eq!(policy.count_approvals("other", &approvals), None);
+        let repeated = vec![
+            Approval::sign("tx", &keys[0]),
+            Approval::sign("tx", &keys[0]),
+        ];
+        assert_eq!(policy.count_approvals("tx", &repeated), None);
+        let outsider = Keypair::generate(&mut OsRng);
+        assert_eq!(
+            policy.count_approvals("tx", &[Approval::sign("tx", &outsider)]),
+            None
+        );
+    }
+}
@@ -0,0 +1,1307 @@
+use crate::blockchain::block::Block;
+use crate::blockchain::transaction::{Transaction, TransactionKind, DEFAULT_CHAIN_ID};
+use crate::config::TokenSettings;
+use crate::consensus::dispute::{
+    dispute_account, Dispute, DisputeAction, DisputeError, PendingMint, Verdict,
+};
+use crate::consensus::registry::{
+    bond_account, RegistryAction, RegistryError, VerifierRecord, VerifierRules,
+};
+use crate::consensus::validator::{HoldingCap, ValidationError, Validator};
+use crate::crypto::address::{Address, Network};
+use crate::crypto::hash::hash_bytes;
+use crate::task::marketplace::{task_account, Task, TaskAction, TaskError, TaskFilter, TaskStatus};
+use crate::token::condition::Lock;
+use crate::token::fees::{proportional_shares, FeePolicy, FEE_POOL_ACCOUNT};
+use crate::token::issuance::{Issuance, DEFAULT_TOKEN_LIFETIME};
+use crate::token::lot::TokenLot;
+use crate::token::policy::{ExpiryPolicy, FixedTtl};
+use crate::token::supply::{EpochIssuance, IssuancePolicy, SupplyStats};
+use crate::utils::encoding::CanonicalEncoder;
+use ed25519_dalek::PublicKey;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::Arc;
+
+/// The spendable token lots derived from applying every block in the chain, in order.
+///
+/// This is the single source of truth for balances: it only ever changes when the ledger
+/// accepts a block, so it cannot drift away from the chain.
+#[derive(Debug, Clone)]
+pub struct LedgerState {
+    /// Unspent lots, keyed by lot ID.
+    pub lots: BTreeMap<String, TokenLot>,
+    /// Total number of tokens that have expired unspent since genesis.
+    pub expired_supply: u64,
+    /// Tokens swept as expired by each block, keyed by block height.
+    pub expired_by_block: BTreeMap<u64, u64>,
+    /// Tokens that expired while held by each account.
+    pub expired_by_account: BTreeMap<String, u64>,
+    /// Policy deciding when minted tokens expire and what lots are worth over time.
+    pub expiry_policy: Arc<dyn ExpiryPolicy>,
+    /// Limit on the live balance of each account, if one is enforced.
+    pub holding_cap: Option<HoldingCap>,
+    /// ID of the network, which every transaction must be signed for.
+    pub chain_id: u32,
+    /// The nonce each account's next transaction must carry, keyed by address. Accounts that
+    /// have never sent a transaction are absent and start at zero.
+    pub nonces: BTreeMap<String, u64>,
+    /// What happens to the fees transactions pay.
+    pub fee_policy: FeePolicy,
+    /// Total number of tokens paid as fees and burned since genesis.
+    pub burned_fees: u64,
+    /// Tokens minted to each worker during the current epoch, which decide their shares of
+    /// the fee pool under the commons fee policy.
+    pub epoch_work: BTreeMap<String, u64>,
+    /// Every task posted to the marketplace, keyed by the ID of the transaction posting it.
+    pub tasks: BTreeMap<String, Task>,
+    /// Who may attest work, and how verifiers are penalised for upheld disputes.
+    pub verifier_rules: VerifierRules,
+    /// Every verifier that has been admitted or has bonded tokens, keyed by address
+    /// (hex-encoded public key).
+    pub verifiers: BTreeMap<String, VerifierRecord>,
+    /// Total number of tokens slashed from verifiers' bonds or forfeited by dismissed
+    /// disputes, and burned, since genesis.
+    pub slashed_bonds: u64,
+    /// Work mints that can still be disputed or are under dispute, keyed by the ID of the
+    /// transaction that minted them.
+    pub pending_mints: BTreeMap<String, PendingMint>,
+    /// Total number of tokens minted for work and then reversed by upheld disputes.
+    pub reversed_supply: u64,
+    /// Total number of tokens minted for work since genesis.
+    pub minted_supply: u64,
+    /// Limits on how many tokens can be minted for work in each epoch.
+    pub issuance_policy: IssuancePolicy,
+    /// Tokens minted during the current issuance epoch, and what the supply schedule
+    /// measures to set the next epoch's cap.
+    pub issuance: EpochIssuance,
+    /// Work hashes of every work claim accepted so far. Each piece of work can only ever be
+    /// rewarded once.
+    pub claimed_work: BTreeSet<String>,
+}
+
+impl Default for LedgerState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LedgerState {
+    /// Creates an empty state, as it is before the genesis block.
+    pub fn new() -> Self {
+        Self::with_expiry_policy(Arc::new(FixedTtl {
+            lifetime: DEFAULT_TOKEN_LIFETIME,
+        }))
+    }
+
+    /// Creates an empty state that applies the given expiry policy.
+    pub fn with_expiry_policy(expiry_policy: Arc<dyn ExpiryPolicy>) -> Self {
+        LedgerState {
+            lots: BTreeMap::new(),
+            expired_supply: 0,
+            expired_by_block: BTreeMap::new(),
+            expired_by_account: BTreeMap::new(),
+            expiry_policy,
+            holding_cap: None,
+            chain_id: DEFAULT_CHAIN_ID,
+            nonces: BTreeMap::new(),
+            fee_policy: FeePolicy::default(),
+            burned_fees: 0,
+            epoch_work: BTreeMap::new(),
+            tasks: BTreeMap::new(),
+            verifier_rules: VerifierRules::default(),
+            verifiers: BTreeMap::new(),
+            slashed_bonds: 0,
+            pending_mints: BTreeMap::new(),
+            reversed_supply: 0,
+            minted_supply: 0,
+            issuance_policy: IssuancePolicy::default(),
+            issuance: EpochIssuance::default(),
+            claimed_work: BTreeSet::new(),
+        }
+    }
+
+    /// Creates an empty state that applies the expiry policy, holding cap, fee policy,
+    /// verifier rules and issuance policy from the given settings.
+    pub fn with_settings(settings: &TokenSettings) -> Self {
+        LedgerState {
+            holding_cap: settings.holding_cap(),
+            fee_policy: settings.build_fee_policy(),
+            verifier_rules: settings.build_verifier_rules(),
+            issuance_policy: settings.build_issuance_policy(),
+            ..Self::with_expiry_policy(settings.build_expiry_policy())
+        }
+    }
+
+    /// Calculates the state root: a hash of every unspent lot, the expired supply, every
+    /// account's next nonce, the burned fees, the work minted this epoch, every task, every
+    /// registered verifier, every mint that can still be disputed, the issuance so far and
+    /// every piece of work claimed, which block headers commit to so that nodes can check they
+    /// derived the same state.
+    pub fn state_root(&self) -> String {
+        let mut encoder = CanonicalEncoder::new("work-tokens/state");
+        encoder.count(self.lots.len());
+        for lot in self.lots.values() {
+            encoder
+                .str(&lot.id)
+                .str(&lot.owner)
+                .u64(lot.amount)
+                .u64(lot.mint_height)
+                .u64(lot.mint_time)
+                .option_u64(lot.expiration_time)
+                .u64(lot.value_height)
+                .option_u64(lot.pending_until);
+            match &lot.lock {
+                Some(lock) => {
+                    encoder.u8(1);
+                    lock.encode(&mut encoder);
+                }
+                None => {
+                    encoder.u8(0);
+                }
+            }
+        }
+        encoder.u64(self.expired_supply);
+        encoder.count(self.nonces.len());
+        for (account, nonce) in &self.nonces {
+            encoder.str(account).u64(*nonce);
+        }
+        encoder.u64(self.burned_fees);
+        encoder.count(self.epoch_work.len());
+        for (worker, minted) in &self.epoch_work {
+            encoder.str(worker).u64(*minted);
+        }
+        encoder.count(self.tasks.len());
+        for task in self.tasks.values() {
+            task.encode(&mut encoder);
+        }
+        encoder.count(self.verifiers.len());
+        for (verifier, record) in &self.verifiers {
+            encoder.str(verifier);
+            record.encode(&mut encoder);
+        }
+        encoder.u64(self.slashed_bonds);
+        encoder.count(self.pending_mints.len());
+        for (mint_id, mint) in &self.pending_mints {
+            encoder.str(mint_id);
+            mint.encode(&mut encoder);
+        }
+        encoder.u64(self.reversed_supply);
+        encoder.u64(self.minted_supply);
+        self.issuance.encode(&mut encoder);
+        encoder.count(self.claimed_work.len());
+        for work_hash in &self.claimed_work {
+            encoder.str(work_hash);
+        }
+        hash_bytes(&encoder.finish())
+    }
+
+    /// Gets the nonce an account's next transaction must carry.
+    pub fn next_nonce(&self, account: &str) -> u64 {
+        self.nonces.get(account).copied().unwrap_or(0)
+    }
+
+    /// Retrieves a task by the ID of the transaction that posted it.
+    pub fn get_task(&self, task_id: &str) -> Option<&Task> {
+        self.tasks.get(task_id)
+    }
+
+    /// Retrieves every task matching the filter at `timestamp`, in order of their IDs.
+    pub fn get_tasks(&self, filter: &TaskFilter, timestamp: u64) -> Vec<&Task> {
+        self.tasks
+            .values()
+            .filter(|task| filter.matches(task, timestamp))
+            .collect()
+    }
+
+    /// Gets the live value of a verifier's bond at the given block height and timestamp.
+    pub fn get_bond(&self, verifier: &str, height: u64, timestamp: u64) -> u64 {
+        self.get_balance(&bond_account(verifier), height, timestamp)
+    }
+
+    /// Retrieves an unspent lot by its ID.
+    pub fn get_lot(&self, lot_id: &str) -> Option<&TokenLot> {
+        self.lots.get(lot_id)
+    }
+
+    /// Retrieves all unspent lots owned by an account, including expired ones and those held
+    /// in escrow.
+    pub fn get_lots(&self, owner: &str) -> Vec<&TokenLot> {
+        self.lots
+            .values()
+            .filter(|lot| lot.owner == owner)
+            .collect()
+    }
+
+    /// Gets what a lot is worth at the block `height` under the expiry policy.
+    pub fn lot_value(&self, lot: &TokenLot, height: u64) -> u64 {
+        self.expiry_policy.lot_value(lot, height)
+    }
+
+    /// Picks lots owned by an account to cover `amount` at the block `height`, soonest-expiring
+    /// first, ignoring lots that have expired at `timestamp`, lots held in escrow and lots
+    /// still pending at `height`. Returns the lot IDs to use as transfer inputs, or `None` if
+    /// the account cannot cover the amount.
+    pub fn select_lots(
+        &self,
+        owner: &str,
+        amount: u64,
+        height: u64,
+        timestamp: u64,
+    ) -> Option<Vec<String>> {
+        let mut spendable: Vec<TokenLot> = self
+            .get_lots(owner)
+            .into_iter()
+            .filter(|lot| self.is_spendable(lot, height, timestamp))
+            .cloned()
+            .collect();
+        sort_by_expiry(&mut spendable);
+
+        let mut selected = Vec::new();
+        let mut total: u64 = 0;
+        for lot in spendable {
+            if total >= amount {
+                break;
+            }
+            total = total.saturating_add(self.lot_value(&lot, height));
+            selected.push(lot.id);
+        }
+
+        if total >= amount && !selected.is_empty() {
+            Some(selected)
+        } else {
+            None
+        }
+    }
+
+    /// Gets an account's total balance of lots that are still spendable at the given block
+    /// height and timestamp, valued under the expiry policy. Lots held in escrow do not count
+    /// until they are released, nor do minted lots until their mint is final.
+    pub fn get_balance(&self, owner: &str, height: u64, timestamp: u64) -> u64 {
+        self.get_lots(owner)
+            .iter()
+            .filter(|lot| self.is_spendable(lot, height, timestamp))
+            .map(|lot| self.lot_value(lot, height))
+            .sum()
+    }
+
+    /// Gets an account's total balance of minted lots that cannot be spent until their mint
+    /// is final, valued at the given block height.
+    pub fn get_pending_balance(&self, owner: &str, height: u64, timestamp: u64) -> u64 {
+        self.get_lots(owner)
+            .iter()
+            .filter(|lot| lot.is_pending(height) && !lot.has_expired(timestamp))
+            .map(|lot| self.lot_value(lot, height))
+            .sum()
+    }
+
+    /// Checks whether a lot can be spent by its owner without meeting a spend condition.
+    fn is_spendable(&self, lot: &TokenLot, height: u64, timestamp: u64) -> bool {
+        !lot.has_expired(timestamp) && lot.lock.is_none() && !lot.is_pending(height)
+    }
+
+    /// Gets the number of tokens swept as expired by the block at `height`.
+    pub fn get_expired_in_block(&self, height: u64) -> u64 {
+        self.expired_by_block.get(&height).copied().unwrap_or(0)
+    }
+
+    /// Gets the number of tokens that expired while held by an account.
+    pub fn get_expired_for_account(&self, owner: &str) -> u64 {
+        self.expired_by_account.get(owner).copied().unwrap_or(0)
+    }
+
+    /// Applies a block to the state: first starts a new issuance epoch if the block begins
+    /// one, sweeps every lot that has expired as of the block's timestamp, dismisses every
+    /// dispute whose voting has closed and makes final every undisputed mint whose challenge
+    /// window has closed, then applies its transactions in order, and finally settles the fees
+    /// they paid under the fee policy.
+    ///
+    /// Returns an error as soon as a transaction cannot be applied. The state may then be
+    /// partially updated, so callers should apply blocks to a copy and only keep it on success.
+    pub fn apply_block(&mut self, block: &Block) -> Result<(), ValidationError> {
+        let height = block.header.height;
+        let timestamp = block.header.timestamp;
+        self.start_epoch(height);
+        self.sweep_expired(height, timestamp);
+        let lapsed: Vec<String> = self
+            .pending_mints
+            .iter()
+            .filter(|(_, mint)| {
+                mint.dispute
+                    .as_ref()
+                    .is_some_and(|dispute| !dispute.is_open(height))
+            })
+            .map(|(mint_id, _)| mint_id.clone())
+            .collect();
+        for mint_id in lapsed {
+            self.dismiss_dispute(&mint_id, height);
+        }
+        self.pending_mints
+            .retain(|_, mint| mint.dispute.is_some() || height < mint.window_closes);
+        for transaction in &block.transactions {
+            self.apply_transaction(transaction, height, timestamp)?;
+        }
+        self.settle_fees(block);
+        Ok(())
+    }
+
+    /// Removes every lot that has expired as of `timestamp`, or has decayed to nothing by the
+    /// block at `height`, and records the expired supply against that block and the accounts
+    /// that held the lots.
+    ///
+    /// Returns the number of tokens swept.
+    pub fn sweep_expired(&mut self, height: u64, timestamp: u64) -> u64 {
+        let expired_ids: Vec<String> = self
+            .lots
+            .values()
+            .filter(|lot| lot.has_expired(timestamp) || self.lot_value(lot, height) == 0)
+            .map(|lot| lot.id.clone())
+            .collect();
+
+        let mut swept = 0;
+        for lot_id in expired_ids {
+            let lot = self.lots.remove(&lot_id).expect("expired lot exists");
+            self.record_expired(&lot.owner, height, lot.amount);
+            swept += lot.amount;
+        }
+        swept
+    }
+
+    /// Starts the issuance epoch the block at `height` belongs to, if it is not the current
+    /// one, so the supply schedule can set its cap from what it measured.
+    fn start_epoch(&mut self, height: u64) {
+        if self.issuance_policy.epoch(height) == self.issuance.epoch {
+            return;
+        }
+        let circulating = self.circulating_supply(height);
+        self.issuance
+            .roll_over(&self.issuance_policy, height, circulating);
+    }
+
+    /// Gets the live value of every lot at the given block height, including lots held in
+    /// escrow, bonds and the fee pool.
+    pub fn circulating_supply(&self, height: u64) -> u64 {
+        self.lots
+            .values()
+            .map(|lot| self.lot_value(lot, height))
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Reports the token supply as of the block at the given height and timestamp.
+    pub fn supply_stats(&self, height: u64, timestamp: u64) -> SupplyStats {
+        let live = |lot: &&TokenLot| !lot.has_expired(timestamp);
+        SupplyStats {
+            minted: self.minted_supply,
+            circulating: self
+                .lots
+                .values()
+                .filter(live)
+                .map(|lot| self.lot_value(lot, height))
+                .fold(0, u64::saturating_add),
+            pending: self
+                .lots
+                .values()
+                .filter(live)
+                .filter(|lot| lot.is_pending(height))
+                .map(|lot| self.lot_value(lot, height))
+                .fold(0, u64::saturating_add),
+            expired: self.expired_supply,
+            burned_fees: self.burned_fees,
+            slashed_bonds: self.slashed_bonds,
+            reversed: self.reversed_supply,
+            epoch: self.issuance.epoch,
+            epoch_minted: self.issuance.minted,
+            epoch_cap: self.issuance_policy.epoch_cap(&self.issuance),
+            worker_cap: self.issuance_policy.max_worker_issuance,
+            epoch_expired: self.issuance.expired,
+            epoch_transferred: self.issuance.transferred,
+            previous_velocity_bps: self.issuance.previous_velocity_bps,
+        }
+    }
+
+    /// Adds tokens lost to expiry or decay to the expired supply counters.
+    fn record_expired(&mut self, owner: &str, height: u64, amount: u64) {
+        if amount == 0 {
+            return;
+        }
+        self.expired_supply += amount;
+        self.issuance.expired = self.issuance.expired.saturating_add(amount);
+        *self.expired_by_block.entry(height).or_default() += amount;
+        *self
+            .expired_by_account
+            .entry(owner.to_string())
+            .or_default() += amount;
+    }
+
+    /// Applies a single transaction included in the block at `height` with the given timestamp.
+    ///
+    /// Transfers consume the lots they reference and create new lots for the recipient, for
+    /// the fee and, if the inputs exceed the amount and fee, a change lot for the sender. New
+    /// lots keep the mint details and expiry of the lot they were split from, and are revalued
+    /// at `height`; any value the inputs lost to decay is recorded as expired. Fees are burned
+    /// or held in the fee pool until the block is settled, depending on the fee policy. Work
+    /// claims mint a new lot for the worker, and pay their fee out of it. Every other kind of
+    /// transaction pays its fee from the lots it spends, like a transfer.
+    ///
+    /// Task actions move a task on the marketplace through its lifecycle. A posting pays the
+    /// task's reward into escrow like a transfer, the attestation that completes the task pays
+    /// it to the worker, and the client can take it back once the task has expired. Once the
+    /// verifier registry is enforced, tasks can only be assigned to registered verifiers, and
+    /// work claims and task attestations are only accepted from them, who gain reputation for
+    /// each one.
+    ///
+    /// Registry actions admit and remove verifiers. A bond is paid into the verifier's bond
+    /// account like a transfer, and is returned to them with its expiry intact when they
+    /// unbond. Verifiers are only penalised through disputes decided by the panel.
+    ///
+    /// While the challenge period is set, minted lots stay pending until the mint's challenge
+    /// window closes, and anyone may dispute the mint in the meantime by paying a bond like a
+    /// transfer. Disputed lots stay pending until the panel decides the dispute, which is
+    /// dismissed if the panel has not decided it by the end of the voting period.
+    ///
+    /// A work claim that would take the epoch's issuance, or the worker's, over its cap under
+    /// the issuance policy is rejected.
+    ///
+    /// A transfer with a spend condition locks the recipient's lots under it, with the sender
+    /// as the payer who may reclaim them. Spending a lot held in escrow releases it, so the
+    /// lots created from it are unlocked.
+    ///
+    /// Whatever the recipient receives over the holding cap goes to the commons account, or the
+    /// transaction is rejected if there is none. Tokens received in escrow only count towards
+    /// the cap once they are released, and the change the sender keeps from lots released from
+    /// escrow is capped the same way.
+    ///
+    /// Every transaction must be signed for this network and carry its sender's next nonce,
+    /// which it then uses up.
+    pub fn apply_transaction(
+        &mut self,
+        transaction: &Transaction,
+        height: u64,
+        timestamp: u64,
+    ) -> Result<(), ValidationError> {
+        if transaction.chain_id != self.chain_id {
+            return Err(ValidationError::WrongChain {
+                expected: self.chain_id,
+                found: transaction.chain_id,
+            });
+        }
+        let sender = transaction.sender_address();
+        let expected_nonce = self.next_nonce(&sender);
+        if transaction.nonce != expected_nonce {
+            return Err(ValidationError::InvalidNonce {
+                expected: expected_nonce,
+                found: transaction.nonce,
+            });
+        }
+        self.start_epoch(height);
+
+        let mut fees = Vec::new();
+        let mut recipient = transaction.to.to_string();
+        let mut minted = 0;
+        let mut mint_verifiers = Vec::new();
+
+        // Actions that move no tokens of their own pay their fee from the lots they spend,
+        // with any change returned to the sender
+        let pays_from_inputs = !matches!(
+            transaction.kind,
+            TransactionKind::Transfer
+                | TransactionKind::WorkClaim(_)
+                | TransactionKind::Task(TaskAction::Post(_))
+                | TransactionKind::Registry(RegistryAction::Bond)
+                | TransactionKind::Dispute(DisputeAction::File { .. })
+        );
+        let mut change = Vec::new();
+        let mut released = Vec::new();
+        if pays_from_inputs && transaction.fee > 0 {
+            Validator::validate_transfer(transaction, self, height, timestamp)?;
+            (change, released, fees) = self.spend_inputs(transaction, &sender, 0, height);
+        }
+        let outputs = match &transaction.kind {
+            TransactionKind::Transfer
+            | TransactionKind::Task(TaskAction::Post(_))
+            | TransactionKind::Registry(RegistryAction::Bond)
+            | TransactionKind::Dispute(DisputeAction::File { .. }) => {
+                if let Some(account) = self.deposit_account(transaction, height, timestamp)? {
+                    recipient = account;
+                }
+                Validator::validate_transfer(transaction, self, height, timestamp)?;
+                self.open_deposit(transaction, height);
+                if transaction.is_transfer() {
+                    let moved = self.moved_value(transaction, &recipient, height);
+                    self.issuance.transferred = self.issuance.transferred.saturating_add(moved);
+                }
+
+                let (outputs, released_change, paid_fees) =
+                    self.spend_inputs(transaction, &recipient, transaction.amount, height);
+                released = released_change;
+                fees = paid_fees;
+                outputs
+            }
+            TransactionKind::WorkClaim(claim) => {
+                Validator::validate_work_claim(transaction, self, height, timestamp)?;
+                if !self.claimed_work.insert(claim.work_hash.clone()) {
+                    return Err(ValidationError::WorkAlreadyClaimed(claim.work_hash.clone()));
+                }
+                for attestation in &claim.attestations {
+                    self.record_attestation(&attestation.verifier);
+                    mint_verifiers.push(attestation.verifier);
+                }
+                minted = transaction.amount;
+                let issuance =
+                    Issuance::from_work_claim(transaction, timestamp, self.expiry_policy.as_ref())
+                        .ok_or_else(|| {
+                            ValidationError::InvalidTransaction(transaction.id.clone())
+                        })?;
+
+                let mut outputs: Vec<(String, u64, TokenLot)> = issuance
+                    .issued_tokens
+                    .into_iter()
+                    .map(|token| {
+                        let source = TokenLot {
+                            id: String::new(),
+                            owner: transaction.to.to_string(),
+                            amount: token.amount,
+                            mint_height: height,
+                            mint_time: timestamp,
+                            expiration_time: token.expiration_time,
+                            value_height: height,
+                            lock: None,
+                            pending_until: self.verifier_rules.pending_until(height),
+                        };
+                        (transaction.to.to_string(), token.amount, source)
+                    })
+                    .collect();
+
+                // The fee comes out of the minted tokens, and is not held back with them
+                let mut fee_remaining = transaction.fee;
+                for (_, amount, source) in outputs.iter_mut() {
+                    let fee_paid = fee_remaining.min(*amount);
+                    fee_remaining -= fee_paid;
+                    *amount -= fee_paid;
+                    if fee_paid > 0 {
+                        let source = TokenLot {
+                            pending_until: None,
+                            ..source.clone()
+                        };
+                        fees.push((fee_paid, source));
+                    }
+                }
+                outputs.retain(|(_, amount, _)| *amount > 0);
+                outputs
+            }
+            TransactionKind::Task(action) => {
+                if let TaskAction::Attest { .. } = action {
+                    Validator::check_verifier(self, &transaction.from, height, timestamp)?;
+                }
+                let settled = self.apply_task_action(transaction, action, timestamp)?;
+                if let TaskAction::Attest { .. } = action {
+                    self.record_attestation(&transaction.from);
+                }
+                match settled {
+                    Some(task) => {
+                        // The escrowed reward goes to the worker, or back to the client
+                        recipient = match task.status {
+                            TaskStatus::Completed => {
+                                task.worker.clone().expect("completed task has a worker")
+                            }
+                            _ => task.client.clone(),
+                        };
+                        self.take_lots(&task_account(&task.id), height)
+                            .into_iter()
+                            .filter(|(value, _)| *value > 0)
+                            .map(|(value, lot)| (recipient.clone(), value, lot))
+                            .collect()
+                    }
+                    None => Vec::new(),
+                }
+            }
+            TransactionKind::Registry(action) => {
+                self.apply_registry_action(transaction, action, height)?
+            }
+            TransactionKind::Dispute(DisputeAction::Vote { mint_id, uphold }) => {
+                let (disputer, outputs) =
+                    self.apply_dispute_vote(transaction, mint_id, *uphold, height)?;
+                recipient = disputer;
+                outputs
+            }
+        };
+
+        if minted > 0 {
+            self.issuance
+                .issue(&self.issuance_policy, &recipient, minted)
+                .map_err(ValidationError::IssuanceCapExceeded)?;
+            self.minted_supply = self.minted_supply.saturating_add(minted);
+        }
+
+        // Escrowed payments and tokens held for a task, by the registry or by a dispute do not
+        // count towards the cap
+        let exempt = transaction.condition.is_some()
+            || matches!(transaction.task_action(), Some(TaskAction::Post(_)))
+            || transaction
+                .registry_action()
+                .is_some_and(|action| *action != RegistryAction::Unbond)
+            || matches!(
+                transaction.dispute_action(),
+                Some(DisputeAction::File { .. })
+            );
+        let outputs = match exempt {
+            true => outputs,
+            false => self.apply_holding_cap(&recipient, outputs, height, timestamp)?,
+        };
+        let minted_lots = outputs.len();
+        self.insert_outputs(&transaction.id, 0, outputs, height);
+        let released_index = minted_lots + change.len();
+        self.insert_outputs(&transaction.id, minted_lots, change, height);
+
+        // Change from lots released from escrow is new to the sender, so counts towards the cap
+        let released = self.apply_holding_cap(&sender, released, height, timestamp)?;
+        let fee_index = released_index + released.len();
+        self.insert_outputs(&transaction.id, released_index, released, height);
+        if minted > 0 && self.verifier_rules.challenge_period > 0 {
+            let lots = (0..minted_lots)
+                .map(|index| TokenLot::lot_id(&transaction.id, index))
+                .collect();
+            let mint = PendingMint {
+                worker: recipient.clone(),
+                amount: minted,
+                verifiers: mint_verifiers,
+                lots,
+                window_closes: height.saturating_add(self.verifier_rules.challenge_period),
+                dispute: None,
+            };
+            self.pending_mints.insert(transaction.id.clone(), mint);
+        }
+        match self.fee_policy {
+            FeePolicy::Burn => {
+                let burned: u64 = fees.iter().map(|(amount, _)| amount).sum();
+                self.burned_fees = self.burned_fees.saturating_add(burned);
+            }
+            FeePolicy::Producer | FeePolicy::Commons { .. } => {
+                let fees = fees
+                    .into_iter()
+                    .map(|(amount, source)| (FEE_POOL_ACCOUNT.to_string(), amount, source))
+                    .collect();
+                self.insert_outputs(&transaction.id, fee_index, fees, height);
+            }
+        }
+
+        if matches!(self.fee_policy, FeePolicy::Commons { .. }) && minted > 0 {
+            let work = self.epoch_work.entry(recipient).or_default();
+            *work = work.saturating_add(minted);
+        }
+        self.nonces.insert(sender, expected_nonce + 1);
+        Ok(())
+    }
+
+    /// Spends the lots a transaction lists, paying `amount` to `recipient` and then the fee
+    /// from the soonest-expiring lots first, and splitting the last lot needed so the rest
+    /// returns to the sender as change with the same expiry. Payments to the recipient are
+    /// locked under the transaction's spend condition, if it has one.
+    ///
+    /// # Returns
+    /// * `(Vec<(String, u64, TokenLot)>, Vec<(String, u64, TokenLot)>, Vec<(u64, TokenLot)>)` -
+    ///   The outputs to create, with the lot each was split from; separately, the change split
+    ///   from lots that were held in escrow; and the fees paid, with the lot each came from.
+    #[allow(clippy::type_complexity)]
+    fn spend_inputs(
+        &mut self,
+        transaction: &Transaction,
+        recipient: &str,
+        amount: u64,
+        height: u64,
+    ) -> (
+        Vec<(String, u64, TokenLot)>,
+        Vec<(String, u64, TokenLot)>,
+        Vec<(u64, TokenLot)>,
+    ) {
+        let sender = transaction.sender_address();
+        let mut escrowed = HashSet::new();
+        let mut spent_lots: Vec<TokenLot> = transaction
+            .inputs
+            .iter()
+            .map(|input| {
+                let lot = self.lots.remove(input).expect("validated input lot exists");
+                if lot.lock.is_some() {
+                    escrowed.insert(input.clone());
+                }
+                TokenLot { lock: None, ..lot }
+            })
+            .collect();
+        let lock = transaction.condition.clone().map(|condition| Lock {
+            condition,
+            payer: sender.clone(),
+        });
+        sort_by_expiry(&mut spent_lots);
+
+        let mut remaining = amount;
+        let mut fee_remaining = transaction.fee;
+        let mut outputs = Vec::new();
+        let mut released = Vec::new();
+        let mut fees = Vec::new();
+        for lot in spent_lots {
+            let value = self.lot_value(&lot, height);
+            self.record_expired(&sender, height, lot.amount - value);
+
+            let paid = remaining.min(value);
+            remaining -= paid;
+            if paid > 0 {
+                let source = TokenLot {
+                    lock: lock.clone(),
+                    ..lot.clone()
+                };
+                outputs.push((recipient.to_string(), paid, source));
+            }
+            let fee_paid = fee_remaining.min(value - paid);
+            fee_remaining -= fee_paid;
+            if fee_paid > 0 {
+                fees.push((fee_paid, lot.clone()));
+            }
+            if value > paid + fee_paid {
+                let change = (sender.clone(), value - paid - fee_paid, lot);
+                match escrowed.contains(&change.2.id) {
+                    true => released.push(change),
+                    false => outputs.push(change),
+                }
+            }
+        }
+        (outputs, released, fees)
+    }
+
+    /// Works out how much of a transfer's value changes owner, which is what counts towards the
+    /// epoch's velocity: the amount paid to anyone but the sender, and the lots the sender
+    /// releases from escrow, whose value passes to them from the payer. Payments into escrow
+    /// only count once released, so a payment the payer takes back never counts.
+    fn moved_value(&self, transaction: &Transaction, recipient: &str, height: u64) -> u64 {
+        let sender = transaction.sender_address();
+        let released: u64 = transaction
+            .inputs
+            .iter()
+            .filter_map(|input| self.lots.get(input))
+            .filter(|lot| lot.lock.as_ref().is_some_and(|lock| lock.payer != sender))
+            .map(|lot| self.lot_value(lot, height))
+            .sum();
+        let paid = match recipient != sender && transaction.condition.is_none() {
+            true => transaction.amount,
+            false => 0,
+        };
+        released.saturating_add(paid)
+    }
+
+    /// Applies a task action by the sender of `transaction` to the marketplace, other than a
+    /// posting, which is paid like a transfer. The action must be allowed by the task it names,
+    /// as of `timestamp`.
+    ///
+    /// # Returns
+    /// * `Result<Option<Task>, ValidationError>` - The task, if the action completed or
+    ///   refunded it, or why the action was rejected.
+    fn apply_task_action(
+        &mut self,
+        transaction: &Transaction,
+        action: &TaskAction,
+        timestamp: u64,
+    ) -> Result<Option<Task>, ValidationError> {
+        let sender = transaction.sender_address();
+        let task = match action {
+            TaskAction::Post(_) => {
+                return Err(ValidationError::InvalidTask(TaskError::InvalidPosting))
+            }
+            TaskAction::Claim { task_id }
+            | TaskAction::Deliver { task_id, .. }
+            | TaskAction::Attest { task_id }
+            | TaskAction::Refund { task_id } => self.tasks.get_mut(task_id).ok_or_else(|| {
+                ValidationError::InvalidTask(TaskError::UnknownTask(task_id.clone()))
+            })?,
+        };
+        let completed = task
+            .apply(action, &sender, &transaction.from, timestamp)
+            .map_err(ValidationError::InvalidTask)?;
+        Ok(completed.then(|| task.clone()))
+    }
+
+    /// Applies a change to the verifier registry by the sender of `transaction`, other than a
+    /// bond, which is paid like a transfer.
+    ///
+    /// Governors admit and remove verifiers. A verifier who unbonds gets every lot of their
+    /// bond back, with the lots' expiry intact, once no mint they attested can still be
+    /// disputed.
+    ///
+    /// # Returns
+    /// * `Result<Vec<(String, u64, TokenLot)>, ValidationError>` - The lots the action creates,
+    ///   or why it was rejected.
+    fn apply_registry_action(
+        &mut self,
+        transaction: &Transaction,
+        action: &RegistryAction,
+        height: u64,
+    ) -> Result<Vec<(String, u64, TokenLot)>, ValidationError> {
+        let sender = hex::encode(transaction.from.as_bytes());
+        let reject = |err| Err(ValidationError::InvalidRegistryAction(err));
+        let governs = self.verifier_rules.is_governor(&sender);
+        match action {
+            RegistryAction::Admit { verifier } => {
+                let verifier = hex::encode(verifier.as_bytes());
+                if !governs {
+                    return reject(RegistryError::NotGovernor(sender));
+                }
+                let record = self.verifiers.entry(verifier.clone()).or_default();
+                if record.admitted {
+                    return reject(RegistryError::AlreadyAdmitted(verifier));
+                }
+                record.admitted = true;
+                Ok(Vec::new())
+            }
+            RegistryAction::Remove { verifier } => {
+                let verifier = hex::encode(verifier.as_bytes());
+                if !governs {
+                    return reject(RegistryError::NotGovernor(sender));
+                }
+                match self.verifiers.get_mut(&verifier) {
+                    Some(record) if record.admitted => {
+                        record.admitted = false;
+                        Ok(Vec::new())
+                    }
+                    _ => reject(RegistryError::NotAdmitted(verifier)),
+                }
+            }
+            RegistryAction::Bond => reject(RegistryError::BondingDisabled),
+            RegistryAction::Unbond => {
+                // The bond must stay to be slashed while any mint the verifier attested can
+                // still be disputed
+                let at_stake = self.pending_mints.values().any(|mint| {
+                    (mint.is_open(height) || mint.dispute.is_some())
+                        && mint.verifiers.contains(&transaction.from)
+                });
+                if at_stake {
+                    return reject(RegistryError::BondAtStake(sender));
+                }
+                let bond = self.take_lots(&bond_account(&sender), height);
+                if bond.is_empty() {
+                    return reject(RegistryError::NoBond(sender));
+                }
+                let owner = transaction.sender_address();
+                Ok(bond
+                    .into_iter()
+                    .filter(|(value, _)| *value > 0)
+                    .map(|(value, lot)| (owner.clone(), value, lot))
+                    .collect())
+            }
+        }
+    }
+
+    /// Penalises a registered verifier for an upheld dispute: they lose reputation, and the
+    /// slashed share of their bond is burned from its soonest-expiring lots first.
+    ///
+    /// # Returns
+    /// * `Option<Vec<(String, u64, TokenLot)>>` - The lots left in the verifier's bond
+    ///   account, or `None` if the verifier is not registered.
+    fn penalize_verifier(
+        &mut self,
+        verifier: &str,
+        height: u64,
+    ) -> Option<Vec<(String, u64, TokenLot)>> {
+        let penalty = self.verifier_rules.dispute_penalty;
+        self.verifiers
+            .get_mut(verifier)?
+            .record_upheld_dispute(penalty);
+
+        let account = bond_account(verifier);
+        let bond = self.take_lots(&account, height);
+        let total: u64 = bond.iter().map(|(value, _)| value).sum();
+        let mut slashed = self.verifier_rules.slash_amount(total);
+        self.slashed_bonds = self.slashed_bonds.saturating_add(slashed);
+        let mut outputs = Vec::new();
+        for (value, lot) in bond {
+            let burned = slashed.min(value);
+            slashed -= burned;
+            if value > burned {
+                outputs.push((account.clone(), value - burned, lot));
+            }
+        }
+        Some(outputs)
+    }
+
+    /// Works out where the deposit paid by a task posting, a bond or a dispute goes, checking
+    /// that the network accepts it. Deposits are paid like transfers.
+    ///
+    /// # Returns
+    /// * `Result<Option<String>, ValidationError>` - The account that holds the deposit, or
+    ///   `None` for a plain transfer, or why the deposit was rejected.
+    fn deposit_account(
+        &self,
+        transaction: &Transaction,
+        height: u64,
+        timestamp: u64,
+    ) -> Result<Option<String>, ValidationError> {
+        let reject = |err| Err(ValidationError::InvalidDispute(err));
+        match &transaction.kind {
+            TransactionKind::Task(TaskAction::Post(posting)) => {
+                // Postings must fall due in the future, the client cannot verify their own
+                // task, and verifiers must be registered if the registry is enforced
+                let sender = transaction.sender_address();
+                let network = Network::for_chain(self.chain_id);
+                let client_verifies = posting
+                    .verifiers
+                    .iter()
+                    .any(|verifier| Address::new(verifier, network).to_string() == sender);
+                if posting.deadline <= timestamp || client_verifies {
+                    return Err(ValidationError::InvalidTask(TaskError::InvalidPosting));
+                }
+                for verifier in &posting.verifiers {
+                    Validator::check_verifier(self, verifier, height, timestamp)?;
+                }
+                Ok(Some(task_account(&transaction.id)))
+            }
+            TransactionKind::Registry(RegistryAction::Bond) => {
+                if self.verifier_rules.min_bond.is_none() {
+                    return Err(ValidationError::InvalidRegistryAction(
+                        RegistryError::BondingDisabled,
+                    ));
+                }
+                Ok(Some(bond_account(&hex::encode(
+                    transaction.from.as_bytes(),
+                ))))
+            }
+            TransactionKind::Dispute(DisputeAction::File { mint_id, .. }) => {
+                if self.verifier_rules.governors.is_empty() {
+                    return reject(DisputeError::NoPanel);
+                }
+                match self.pending_mints.get(mint_id) {
+                    Some(mint) if mint.is_open(height) => {}
+                    Some(mint) if mint.dispute.is_some() => {
+                        return reject(DisputeError::AlreadyDisputed(mint_id.clone()))
+                    }
+                    _ => return reject(DisputeError::UnknownMint(mint_id.clone())),
+                }
+                let required = self.verifier_rules.dispute_bond;
+                if transaction.amount < required {
+                    return reject(DisputeError::BondTooLow {
+                        required,
+                        found: transaction.amount,
+                    });
+                }
+                Ok(Some(dispute_account(mint_id)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Opens what a validated deposit pays for: a posting opens its task, a bond registers the
+    /// verifier, and a dispute holds the mint's lots until the panel decides it.
+    fn open_deposit(&mut self, transaction: &Transaction, height: u64) {
+        match &transaction.kind {
+            TransactionKind::Task(TaskAction::Post(posting)) => {
+                let sender = transaction.sender_address();
+                let task = Task::new(transaction.id.clone(), sender, posting.clone(), height);
+                self.tasks.insert(task.id.clone(), task);
+            }
+            TransactionKind::Registry(RegistryAction::Bond) => {
+                let verifier = hex::encode(transaction.from.as_bytes());
+                self.verifiers.entry(verifier).or_default();
+            }
+            TransactionKind::Dispute(DisputeAction::File { mint_id, evidence }) => {
+                let Some(mint) = self.pending_mints.get_mut(mint_id) else {
+                    return;
+                };
+                let voting_closes = height.saturating_add(self.verifier_rules.voting_period);
+                mint.dispute = Some(Dispute {
+                    disputer: transaction.sender_address(),
+                    evidence: evidence.clone(),
+                    votes: BTreeMap::new(),
+                    voting_closes,
+                });
+                for lot_id in &mint.lots {
+                    if let Some(lot) = self.lots.get_mut(lot_id) {
+                        lot.pending_until = Some(voting_closes);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Records a panelist's vote on the dispute over a mint, and resolves the dispute once
+    /// the panel reaches a verdict.
+    ///
+    /// An upheld dispute reverses the mint, penalises each registered verifier who attested
+    /// the work, and returns the bond to the disputer. A dismissed dispute makes the mint
+    /// final and burns the disputer's bond.
+    ///
+    /// # Returns
+    /// * `Result<(String, Vec<(String, u64, TokenLot)>), ValidationError>` - The disputer and
+    ///   the lots the vote creates, or why the vote was rejected.
+    #[allow(clippy::type_complexity)]
+    fn apply_dispute_vote(
+        &mut self,
+        transaction: &Transaction,
+        mint_id: &str,
+        uphold: bool,
+        height: u64,
+    ) -> Result<(String, Vec<(String, u64, TokenLot)>), ValidationError> {
+        let voter = hex::encode(transaction.from.as_bytes());
+        let panel = self.verifier_rules.governors.clone();
+        let dispute = self
+            .pending_mints
+            .get_mut(mint_id)
+            .and_then(|mint| mint.dispute.as_mut())
+            .ok_or_else(|| {
+                ValidationError::InvalidDispute(DisputeError::NotDisputed(mint_id.to_string()))
+            })?;
+        if !dispute.is_open(height) {
+            return Err(ValidationError::InvalidDispute(DisputeError::VotingClosed(
+                mint_id.to_string(),
+            )));
+        }
+        let disputer = dispute.disputer.clone();
+        let verdict = dispute
+            .vote(&panel, &voter, uphold)
+            .map_err(ValidationError::InvalidDispute)?;
+        match verdict {
+            None => Ok((disputer, Vec::new())),
+            Some(Verdict::Upheld) => {
+                let mint = self
+                    .pending_mints
+                    .remove(mint_id)
+                    .expect("disputed mint is pending");
+                let escrow = self.take_lots(&dispute_account(mint_id), height);
+                for lot_id in &mint.lots {
+                    if let Some(lot) = self.lots.remove(lot_id) {
+                        let value = self.lot_value(&lot, height);
+                        self.record_expired(&lot.owner, height, lot.amount - value);
+                        self.reversed_supply = self.reversed_supply.saturating_add(value);
+                    }
+                }
+                if let Some(work) = self.epoch_work.get_mut(&mint.worker) {
+                    *work = work.saturating_sub(mint.amount);
+                }
+
+                let mut outputs = Vec::new();
+                for verifier in &mint.verifiers {
+                    let verifier = hex::encode(verifier.as_bytes());
+                    outputs.extend(
+                        self.penalize_verifier(&verifier, height)
+                            .unwrap_or_default(),
+                    );
+                }
+                outputs.extend(
+                    escrow
+                        .into_iter()
+                        .filter(|(value, _)| *value > 0)
+                        .map(|(value, lot)| (disputer.clone(), value, lot)),
+                );
+                Ok((disputer, outputs))
+            }
+            Some(Verdict::Dismissed) => {
+                self.dismiss_dispute(mint_id, height);
+                Ok((disputer, Vec::new()))
+            }
+        }
+    }
+
+    /// Dismisses the dispute over a mint, whether the panel voted to or failed to decide it
+    /// in time: the mint is final, and the disputer's bond is burned.
+    fn dismiss_dispute(&mut self, mint_id: &str, height: u64) {
+        let Some(mint) = self.pending_mints.remove(mint_id) else {
+            return;
+        };
+        for lot_id in &mint.lots {
+            if let Some(lot) = self.lots.get_mut(lot_id) {
+                lot.pending_until = None;
+            }
+        }
+        let escrow = self.take_lots(&dispute_account(mint_id), height);
+        let forfeited: u64 = escrow.iter().map(|(value, _)| value).sum();
+        self.slashed_bonds = self.slashed_bonds.saturating_add(forfeited);
+    }
+
+    /// Credits a verifier in the registry with an accepted attestation. Verifiers outside the
+    /// registry have no record to credit.
+    fn record_attestation(&mut self, verifier: &PublicKey) {
+        if let Some(record) = self.verifiers.get_mut(&hex::encode(verifier.as_bytes())) {
+            record.record_attestation();
+        }
+    }
+
+    /// Removes every lot owned by an account, soonest-expiring first, and values each at
+    /// `height`. Whatever the lots lost to decay is recorded as expired.
+    fn take_lots(&mut self, owner: &str, height: u64) -> Vec<(u64, TokenLot)> {
+        let mut lots: Vec<TokenLot> = self.get_lots(owner).into_iter().cloned().collect();
+        sort_by_expiry(&mut lots);
+        lots.into_iter()
+            .map(|lot| {
+                self.lots.remove(&lot.id);
+                let value = self.lot_value(&lot, height);
+                self.record_expired(owner, height, lot.amount - value);
+                (value, lot)
+            })
+            .collect()
+    }
+
+    /// Creates a lot for each output, numbering them from `first_index`. Each lot keeps the
+    /// mint details and expiry of the lot it was split from, and is valued at `height`.
+    fn insert_outputs(
+        &mut self,
+        source_id: &str,
+        first_index: usize,
+        outputs: Vec<(String, u64, TokenLot)>,
+        height: u64,
+    ) {
+        for (index, (owner, amount, source)) in outputs.into_iter().enumerate() {
+            let lot = TokenLot {
+                id: TokenLot::lot_id(source_id, first_index + index),
+                owner,
+                amount,
+                value_height: height,
+                ..source
+            };
+            self.lots.insert(lot.id.clone(), lot);
+        }
+    }
+
+    /// Pays out the fee pool once a block's transactions are applied, as the fee policy
+    /// decides: to the block's beneficiary under the producer policy, or among the epoch's
+    /// workers at the end of each epoch under the commons policy.
+    ///
+    /// Payouts come from the soonest-expiring pooled lots first and keep their expiry. A
+    /// payout that would push its recipient over the holding cap is limited like any other
+    /// incoming tokens, and is skipped if there is no commons account to take the excess.
+    /// Whatever is not paid out is burned under the producer policy, and stays in the pool
+    /// for the next epoch under the commons policy.
+    fn settle_fees(&mut self, block: &Block) {
+        let height = block.header.height;
+        let timestamp = block.header.timestamp;
+        let collected = self.get_balance(FEE_POOL_ACCOUNT, height, timestamp);
+        let shares = match self.fee_policy {
+            FeePolicy::Burn => return,
+            FeePolicy::Producer => match &block.header.beneficiary {
+                Some(beneficiary) => vec![(beneficiary.to_string(), collected)],
+                None => Vec::new(),
+            },
+            FeePolicy::Commons { .. } => {
+                if !self.fee_policy.is_epoch_end(height) {
+                    return;
+                }
+                let shares = proportional_shares(collected, &self.epoch_work);
+                self.epoch_work.clear();
+                if shares.is_empty() {
+                    return;
+                }
+                shares
+            }
+        };
+
+        let mut pool = self.take_lots(FEE_POOL_ACCOUNT, height);
+
+        let mut outputs = Vec::new();
+        let mut next = 0;
+        for (account, share) in shares {
+            let balance = self.held_balance(&account, height, timestamp);
+            if Validator::check_holding_cap(self.holding_cap.as_ref(), &account, balance, share)
+                .is_err()
+            {
+                continue;
+            }
+            let mut payment = Vec::new();
+            let mut remaining = share;
+            while remaining > 0 {
+                let (value, lot) = &mut pool[next];
+                let paid = remaining.min(*value);
+                *value -= paid;
+                remaining -= paid;
+                payment.push((account.clone(), paid, lot.clone()));
+                if *value == 0 {
+                    next += 1;
+                }
+            }
+            let payment = self
+                .apply_holding_cap(&account, payment, height, timestamp)
+                .expect("payout was checked against the holding cap");
+            outputs.extend(payment);
+        }
+
+        let unpaid: Vec<(u64, TokenLot)> =
+            pool.drain(next..).filter(|(value, _)| *value > 0).collect();
+        match self.fee_policy {
+            FeePolicy::Commons { .. } => outputs.extend(
+                unpaid
+                    .into_iter()
+                    .map(|(value, lot)| (FEE_POOL_ACCOUNT.to_string(), value, lot)),
+            ),
+            FeePolicy::Burn | FeePolicy::Producer => {
+                let burned: u64 = unpaid.iter().map(|(value, _)| value).sum();
+                self.burned_fees = self.burned_fees.saturating_add(burned);
+            }
+        }
+        self.insert_outputs(&fee_settlement_id(height), 0, outputs, height);
+    }
+
+    /// Gets the balance an account holds towards the holding cap: its live balance and its
+    /// minted lots that are pending until their mint is final.
+    fn held_balance(&self, owner: &str, height: u64, timestamp: u64) -> u64 {
+        self.get_balance(owner, height, timestamp)
+            .saturating_add(self.get_pending_balance(owner, height, timestamp))
+    }
+
+    /// Limits what `recipient` receives from a transaction's outputs to what the holding cap
+    /// allows on top of the balance it holds, pending lots included, moving the excess into
+    /// outputs owned by the commons account. The excess keeps the expiry of the lot it was
+    /// split from.
+    fn apply_holding_cap(
+        &self,
+        recipient: &str,
+        outputs: Vec<(String, u64, TokenLot)>,
+        height: u64,
+        timestamp: u64,
+    ) -> Result<Vec<(String, u64, TokenLot)>, ValidationError> {
+        let incoming: u64 = outputs
+            .iter()
+            .filter(|(owner, _, _)| owner == recipient)
+            .map(|(_, amount, _)| amount)
+            .sum();
+        let balance = self.held_balance(recipient, height, timestamp);
+        let mut allowance =
+            Validator::check_holding_cap(self.holding_cap.as_ref(), recipient, balance, incoming)?;
+        if allowance >= incoming {
+            return Ok(outputs);
+        }
+
+        let commons = self
+            .holding_cap
+            .as_ref()
+            .and_then(|cap| cap.commons_account.clone())
+            .expect("excess is only allowed with a commons account");
+        let mut capped = Vec::new();
+        for (owner, amount, source) in outputs {
+            if owner != recipient {
+                capped.push((owner, amount, source));
+                continue;
+            }
+            let kept = amount.min(allowance);
+            allowance -= kept;
+            if kept > 0 {
+                capped.push((owner, kept, source.clone()));
+            }
+            if amount > kept {
+                capped.push((commons.clone(), amount - kept, source));
+            }
+        }
+        Ok(capped)
+    }
+}
+
+/// Builds the ID that the lots created by settling fees after the block at `height` are
+/// numbered under, in place of a transaction ID.
+fn fee_settlement_id(height: u64) -> String {
+    format!("fees-{}", height)
+}
+
+/// Orders lots so the soonest-expiring come first and lots that never expire come last, with
+/// the longest-held first among lots with the same expiry. The sort is stable, so any remaining
+/// ties keep their original order.
+fn sort_by_expiry(lots: &mut [TokenLot]) {
+    lots.sort_by_key(|lot| {
+        (
+            lot.expiration_time.is_none(),
+            lot.expiration_time,
+            lot.value_height,
+        )
+    });
+}
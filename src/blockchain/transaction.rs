@@ -1,56 +1,488 @@
-use crate::crypto::hash::calculate_hash;
+use crate::blockchain::multisig::{Approval, MultisigPolicy};
+use crate::blockchain::work_claim::WorkClaim;
+use crate::consensus::dispute::DisputeAction;
+use crate::consensus::registry::RegistryAction;
+use crate::crypto::address::{Address, Network};
+use crate::crypto::hash::hash_bytes;
 use crate::crypto::signatures::{sign_message, verify_signature};
+use crate::task::marketplace::{TaskAction, TaskPosting};
+use crate::token::condition::{SpendCondition, Witness};
+use crate::utils::encoding::CanonicalEncoder;
 use ed25519_dalek::PublicKey;
 use serde::{Deserialize, Serialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Chain ID of the main Work Tokens network. Test networks use other IDs, so transactions
+/// signed for one network are rejected on every other.
+pub const DEFAULT_CHAIN_ID: u32 = 1;
+
+/// The kind of state change a transaction makes.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub enum TransactionKind {
+    /// Moves existing tokens from the sender to the recipient.
+    #[default]
+    Transfer,
+    /// Mints new tokens to the sender for attested work.
+    WorkClaim(WorkClaim),
+    /// Records a step in the lifecycle of a task on the marketplace. Completing a task mints
+    /// its reward to the worker.
+    Task(TaskAction),
+    /// Changes the registry of verifiers allowed to attest work.
+    Registry(RegistryAction),
+    /// Disputes a work mint during its challenge window, or votes on such a dispute.
+    Dispute(DisputeAction),
+}
+
 /// Represents a transaction in the blockchain.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Transaction {
     /// Unique ID of the transaction, derived from a hash of its contents.
     pub id: String,
-    /// Public key of the sender (from address).
+    /// Public key of the sender (from address). For a transaction from a multisig account,
+    /// this is the member who proposed it.
     pub from: PublicKey, // Store the actual public key, not just a string address
-    /// Public key of the recipient (to address as String).
-    pub to: String,
+    /// ID of the network the transaction is meant for.
+    #[serde(default = "default_chain_id")]
+    pub chain_id: u32,
+    /// The sender's sequence number: each account's transactions must be included in order,
+    /// starting from zero, and each nonce can only be used once.
+    #[serde(default)]
+    pub nonce: u64,
+    /// Address of the recipient. It must belong to the network the transaction is for.
+    pub to: Address,
     /// Amount of tokens being transferred.
     pub amount: u64,
+    /// Tokens the sender pays on top of the amount for the transaction to be included. What
+    /// happens to them is decided by the ledger's fee policy.
+    #[serde(default)]
+    pub fee: u64,
     /// Timestamp of when the transaction was created.
     pub timestamp: u64,
     /// Optional expiration timestamp for tokens.
     pub expiration: Option<u64>,
+    /// What this transaction does (a plain transfer by default).
+    #[serde(default)]
+    pub kind: TransactionKind,
+    /// IDs of the sender's token lots spent by a transfer.
+    #[serde(default)]
+    pub inputs: Vec<String>,
     /// Digital signature of the transaction, proving authenticity.
     pub signature: Option<Vec<u8>>,
+    /// Policy of the multisig account the transaction spends from, if the sender is one.
+    #[serde(default)]
+    pub multisig: Option<MultisigPolicy>,
+    /// Members' approvals of a transaction from a multisig account, which take the place of
+    /// the signature.
+    #[serde(default)]
+    pub approvals: Vec<Approval>,
+    /// Condition the recipient must meet to spend what a transfer pays it, which holds the
+    /// payment in escrow.
+    #[serde(default)]
+    pub condition: Option<SpendCondition>,
+    /// What a transfer reveals to meet the spend conditions of the lots it spends.
+    #[serde(default)]
+    pub witnesses: Vec<Witness>,
 }
 
 impl Transaction {
     /// Creates a new transaction.
-    pub fn new(from: PublicKey, to: String, amount: u64, expiration: Option<u64>) -> Self {
+    pub fn new(from: PublicKey, to: Address, amount: u64, expiration: Option<u64>) -> Self {
         let timestamp = get_current_timestamp();
         let mut tx = Transaction {
             id: String::new(), // We'll compute this after initialization
             from,
+            chain_id: DEFAULT_CHAIN_ID,
+            nonce: 0,
             to,
             amount,
+            fee: 0,
             timestamp,
             expiration,
+            kind: TransactionKind::Transfer,
+            inputs: Vec::new(),
             signature: None,
+            multisig: None,
+            approvals: Vec::new(),
+            condition: None,
+            witnesses: Vec::new(),
         };
         tx.id = tx.calculate_hash(); // Set transaction ID based on its contents
         tx
     }
 
-    /// Calculates the hash (ID) of the transaction based on its contents.
-    pub fn calculate_hash(&self) -> String {
-        let data = format!(
-            "{:?}{}{}{}{}",
-            self.from, // Since 'from' is now PublicKey, use `{:?}` for formatting
-            self.to,
-            self.amount,
-            self.timestamp,
-            self.expiration.unwrap_or(0)
+    /// Creates a new transfer that spends the given token lots owned by the sender.
+    pub fn new_transfer(from: PublicKey, to: Address, amount: u64, inputs: Vec<String>) -> Self {
+        let mut tx = Transaction::new(from, to, amount, None);
+        tx.inputs = inputs;
+        tx.id = tx.calculate_hash();
+        tx
+    }
+
+    /// Creates a new work-claim transaction, which mints `amount` tokens to the worker once
+    /// accepted into a block. The expiration is left to the ledger, which derives it from its
+    /// expiry policy and the work category.
+    ///
+    /// Verifiers attest the claim with `attest`, after which the worker signs it with `sign`.
+    pub fn new_work_claim(
+        worker: PublicKey,
+        work_hash: String,
+        description: String,
+        category: String,
+        amount: u64,
+    ) -> Self {
+        let mut tx = Transaction {
+            id: String::new(),
+            from: worker,
+            chain_id: DEFAULT_CHAIN_ID,
+            nonce: 0,
+            to: Address::new(&worker, Network::for_chain(DEFAULT_CHAIN_ID)),
+            amount,
+            fee: 0,
+            timestamp: get_current_timestamp(),
+            expiration: None,
+            kind: TransactionKind::WorkClaim(WorkClaim::new(work_hash, description, category)),
+            inputs: Vec::new(),
+            signature: None,
+            multisig: None,
+            approvals: Vec::new(),
+            condition: None,
+            witnesses: Vec::new(),
+        };
+        tx.id = tx.calculate_hash();
+        tx
+    }
+
+    /// Creates a new transaction posting a task, which spends the given lots owned by the
+    /// sender to put up the task's reward. The ledger holds the reward until it pays it to the
+    /// worker, once enough verifiers have attested the delivery, or refunds it to the sender.
+    pub fn new_task_posting(sender: PublicKey, posting: TaskPosting, inputs: Vec<String>) -> Self {
+        let mut tx = Transaction::new(
+            sender,
+            Address::new(&sender, Network::for_chain(DEFAULT_CHAIN_ID)),
+            posting.reward,
+            None,
+        );
+        tx.kind = TransactionKind::Task(TaskAction::Post(posting));
+        tx.inputs = inputs;
+        tx.id = tx.calculate_hash();
+        tx
+    }
+
+    /// Creates a new transaction recording a task action by the sender, other than a posting.
+    /// These actions move no tokens of their own.
+    pub fn new_task_action(sender: PublicKey, action: TaskAction) -> Self {
+        let mut tx = Transaction::new(
+            sender,
+            Address::new(&sender, Network::for_chain(DEFAULT_CHAIN_ID)),
+            0,
+            None,
+        );
+        tx.kind = TransactionKind::Task(action);
+        tx.id = tx.calculate_hash();
+        tx
+    }
+
+    /// Creates a new transaction recording a change to the verifier registry by the sender.
+    /// A bond spends the given lots owned by the sender to bond `amount`; every other action
+    /// moves no tokens of its own.
+    pub fn new_registry_action(
+        sender: PublicKey,
+        action: RegistryAction,
+        amount: u64,
+        inputs: Vec<String>,
+    ) -> Self {
+        let mut tx = Transaction::new(
+            sender,
+            Address::new(&sender, Network::for_chain(DEFAULT_CHAIN_ID)),
+            amount,
+            None,
+        );
+        tx.kind = TransactionKind::Registry(action);
+        tx.inputs = inputs;
+        tx.id = tx.calculate_hash();
+        tx
+    }
+
+    /// Creates a new transaction recording a step in a dispute by the sender. Filing a dispute
+    /// spends the given lots owned by the sender to put up `amount` as a bond; a vote moves no
+    /// tokens.
+    pub fn new_dispute_action(
+        sender: PublicKey,
+        action: DisputeAction,
+        amount: u64,
+        inputs: Vec<String>,
+    ) -> Self {
+        let mut tx = Transaction::new(
+            sender,
+            Address::new(&sender, Network::for_chain(DEFAULT_CHAIN_ID)),
+            amount,
+            None,
         );
-        calculate_hash(&data)
+        tx.kind = TransactionKind::Dispute(action);
+        tx.inputs = inputs;
+        tx.id = tx.calculate_hash();
+        tx
+    }
+
+    /// Sets the sender's sequence number for this transaction. Call before signing.
+    pub fn with_nonce(mut self, nonce: u64) -> Self {
+        self.nonce = nonce;
+        self.id = self.calculate_hash();
+        self
+    }
+
+    /// Sets the fee the sender pays for this transaction. Call before signing.
+    pub fn with_fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self.id = self.calculate_hash();
+        self
+    }
+
+    /// Sets the network this transaction is meant for. The recipient of a work claim or task
+    /// action moves to the sender's address on that network. Call before signing.
+    pub fn with_chain_id(mut self, chain_id: u32) -> Self {
+        self.chain_id = chain_id;
+        if !self.is_transfer() {
+            self.to = self.sender();
+        }
+        self.id = self.calculate_hash();
+        self
+    }
+
+    /// Makes this a transaction from the multisig account with the given policy, proposed by
+    /// the member in `from`. Members then approve it with `approve` instead of signing it.
+    /// Call before approving.
+    pub fn with_multisig(mut self, policy: MultisigPolicy) -> Self {
+        self.multisig = Some(policy);
+        self.id = self.calculate_hash();
+        self
+    }
+
+    /// Holds the amount sent in escrow under the given spend condition. Call before signing.
+    pub fn with_condition(mut self, condition: SpendCondition) -> Self {
+        self.condition = Some(condition);
+        self.id = self.calculate_hash();
+        self
+    }
+
+    /// Adds a witness meeting the spend condition of one of the lots spent. Call before
+    /// signing.
+    pub fn with_witness(mut self, witness: Witness) -> Self {
+        self.witnesses.push(witness);
+        self.id = self.calculate_hash();
+        self
+    }
+
+    /// Returns the witness for the lot with the given ID, if the transaction carries one.
+    pub fn witness(&self, lot_id: &str) -> Option<&Witness> {
+        self.witnesses
+            .iter()
+            .find(|witness| witness.input == lot_id)
+    }
+
+    /// Adds a verifier's attestation to a work-claim transaction. The attestation is bound to
+    /// the transaction's chain ID, so set that first.
+    /// Returns `false` if this is not a work claim.
+    pub fn attest(&mut self, verifier: &ed25519_dalek::Keypair) -> bool {
+        match &mut self.kind {
+            TransactionKind::WorkClaim(claim) => {
+                claim.attest(self.chain_id, &self.from, self.amount, verifier);
+                self.id = self.calculate_hash();
+                true
+            }
+            TransactionKind::Transfer
+            | TransactionKind::Task(_)
+            | TransactionKind::Registry(_)
+            | TransactionKind::Dispute(_) => false,
+        }
+    }
+
+    /// Returns the work claim carried by this transaction, if any.
+    pub fn work_claim(&self) -> Option<&WorkClaim> {
+        match &self.kind {
+            TransactionKind::WorkClaim(claim) => Some(claim),
+            TransactionKind::Transfer
+            | TransactionKind::Task(_)
+            | TransactionKind::Registry(_)
+            | TransactionKind::Dispute(_) => None,
+        }
+    }
+
+    /// Returns the task action recorded by this transaction, if any.
+    pub fn task_action(&self) -> Option<&TaskAction> {
+        match &self.kind {
+            TransactionKind::Task(action) => Some(action),
+            TransactionKind::Transfer
+            | TransactionKind::WorkClaim(_)
+            | TransactionKind::Registry(_)
+            | TransactionKind::Dispute(_) => None,
+        }
+    }
+
+    /// Returns the change to the verifier registry made by this transaction, if any.
+    pub fn registry_action(&self) -> Option<&RegistryAction> {
+        match &self.kind {
+            TransactionKind::Registry(action) => Some(action),
+            TransactionKind::Transfer
+            | TransactionKind::WorkClaim(_)
+            | TransactionKind::Task(_)
+            | TransactionKind::Dispute(_) => None,
+        }
+    }
+
+    /// Returns the step in a dispute recorded by this transaction, if any.
+    pub fn dispute_action(&self) -> Option<&DisputeAction> {
+        match &self.kind {
+            TransactionKind::Dispute(action) => Some(action),
+            TransactionKind::Transfer
+            | TransactionKind::WorkClaim(_)
+            | TransactionKind::Task(_)
+            | TransactionKind::Registry(_) => None,
+        }
+    }
+
+    /// Checks whether this is a plain transfer of existing tokens to a recipient.
+    pub fn is_transfer(&self) -> bool {
+        matches!(self.kind, TransactionKind::Transfer)
+    }
+
+    /// Returns how many tokens the transaction takes from the lots it spends: the amount sent,
+    /// deposited or put up as a task's reward, plus the fee. Work claims pay their fee out of
+    /// the tokens they mint, so take nothing.
+    pub fn spent_amount(&self) -> u64 {
+        match &self.kind {
+            TransactionKind::WorkClaim(_) => 0,
+            TransactionKind::Transfer | TransactionKind::Task(TaskAction::Post(_)) => {
+                self.amount.saturating_add(self.fee)
+            }
+            TransactionKind::Task(_)
+            | TransactionKind::Registry(_)
+            | TransactionKind::Dispute(_) => match self.deposit() {
+                Some(true) => self.amount.saturating_add(self.fee),
+                _ => self.fee,
+            },
+        }
+    }
+
+    /// Returns the sender's address on the network the transaction is for: the multisig
+    /// account's address if the transaction spends from one.
+    pub fn sender(&self) -> Address {
+        let network = Network::for_chain(self.chain_id);
+        match &self.multisig {
+            Some(policy) => policy.address(network),
+            None => Address::new(&self.from, network),
+        }
+    }
+
+    /// Returns the sender's address in its encoded form, which is how the ledger identifies
+    /// the sender's account.
+    pub fn sender_address(&self) -> String {
+        self.sender().to_string()
+    }
+
+    /// Builds the canonical byte encoding of everything in the transaction except its ID and
+    /// signature. This is what the ID is a hash of, and so what the sender signs.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut encoder = CanonicalEncoder::new("work-tokens/transaction");
+        encoder
+            .bytes(self.from.as_bytes())
+            .u32(self.chain_id)
+            .u64(self.nonce)
+            .str(&self.to.to_string())
+            .u64(self.amount)
+            .u64(self.fee)
+            .u64(self.timestamp)
+            .option_u64(self.expiration)
+            .count(self.inputs.len());
+        for input in &self.inputs {
+            encoder.str(input);
+        }
+        match &self.kind {
+            TransactionKind::Transfer => {
+                encoder.u8(0);
+            }
+            TransactionKind::WorkClaim(claim) => {
+                encoder
+                    .u8(1)
+                    .str(&claim.work_hash)
+                    .str(&claim.description)
+                    .str(&claim.category)
+                    .count(claim.attestations.len());
+                for attestation in &claim.attestations {
+                    encoder
+                        .bytes(attestation.verifier.as_bytes())
+                        .bytes(&attestation.signature);
+                }
+            }
+            TransactionKind::Task(action) => {
+                encoder.u8(2);
+                action.encode(&mut encoder);
+            }
+            TransactionKind::Registry(action) => {
+                encoder.u8(3);
+                action.encode(&mut encoder);
+            }
+            TransactionKind::Dispute(action) => {
+                encoder.u8(4);
+                action.encode(&mut encoder);
+            }
+        }
+        match &self.multisig {
+            Some(policy) => {
+                encoder.u8(1);
+                policy.encode(&mut encoder);
+            }
+            None => {
+                encoder.u8(0);
+            }
+        }
+        match &self.condition {
+            Some(condition) => {
+                encoder.u8(1);
+                condition.encode(&mut encoder);
+            }
+            None => {
+                encoder.u8(0);
+            }
+        }
+        encoder.count(self.witnesses.len());
+        for witness in &self.witnesses {
+            witness.encode(&mut encoder);
+        }
+        encoder.finish()
+    }
+
+    /// Calculates the hash (ID) of the transaction from its canonical encoding.
+    pub fn calculate_hash(&self) -> String {
+        hash_bytes(&self.canonical_bytes())
+    }
+
+    /// Encodes the whole transaction canonically: its contents, as in `canonical_bytes`,
+    /// followed by the signature and approvals that authorise it.
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut encoder = CanonicalEncoder::new("work-tokens/signed-transaction");
+        encoder.bytes(&self.canonical_bytes());
+        match &self.signature {
+            Some(signature) => {
+                encoder.u8(1).bytes(signature);
+            }
+            None => {
+                encoder.u8(0);
+            }
+        }
+        encoder.count(self.approvals.len());
+        for approval in &self.approvals {
+            encoder
+                .bytes(approval.signer.as_bytes())
+                .bytes(&approval.signature);
+        }
+        encoder.finish()
+    }
+
+    /// Calculates the hash of the whole transaction, signature and approvals included, which
+    /// is what a block's merkle tree commits to.
+    pub fn calculate_signed_hash(&self) -> String {
+        hash_bytes(&self.signed_bytes())
     }
 
     /// Signs the transaction with the sender's private key.
@@ -60,8 +492,53 @@ impl Transaction {
         self.signature = Some(sign_message(&message, private_key));
     }
 
-    /// Verifies that the transaction is properly signed by the sender.
+    /// Adds a member's approval to a transaction from a multisig account, replacing any
+    /// approval the member gave before.
+    ///
+    /// # Arguments
+    /// * `approval` - The member's signature over the transaction's ID.
+    ///
+    /// # Returns
+    /// * `bool` - `false` if this is not a multisig transaction, or the approval is not a
+    ///   valid signature by one of the account's members.
+    pub fn add_approval(&mut self, approval: Approval) -> bool {
+        let Some(policy) = &self.multisig else {
+            return false;
+        };
+        if policy
+            .count_approvals(&self.calculate_hash(), std::slice::from_ref(&approval))
+            .is_none()
+        {
+            return false;
+        }
+        self.approvals
+            .retain(|existing| existing.signer != approval.signer);
+        self.approvals.push(approval);
+        true
+    }
+
+    /// Approves a transaction from a multisig account with a member's key.
+    /// Returns `false` if this is not a multisig transaction or the key is not a member's.
+    pub fn approve(&mut self, member: &ed25519_dalek::Keypair) -> bool {
+        let approval = Approval::sign(&self.calculate_hash(), member);
+        self.add_approval(approval)
+    }
+
+    /// Returns how many more approvals a transaction from a multisig account needs before
+    /// it can be included, or `None` if it is not a multisig transaction or carries an
+    /// invalid approval.
+    pub fn approvals_needed(&self) -> Option<usize> {
+        let policy = self.multisig.as_ref()?;
+        let approved = policy.count_approvals(&self.calculate_hash(), &self.approvals)?;
+        Some((policy.threshold as usize).saturating_sub(approved))
+    }
+
+    /// Verifies that the transaction is properly signed by the sender, or for a multisig
+    /// account, approved by at least the threshold of its members.
     pub fn verify_signature(&self) -> bool {
+        if self.multisig.is_some() {
+            return self.approvals_needed() == Some(0);
+        }
         if let Some(signature) = &self.signature {
             let message = self.calculate_hash();
             verify_signature(&message, signature, &self.from) // Use 'from' public key directly
@@ -70,19 +547,127 @@ impl Transaction {
         }
     }
 
+    /// For a registry or dispute action, returns whether it puts up a deposit: a verifier's
+    /// bond or a disputer's bond. Returns `None` for any other transaction.
+    fn deposit(&self) -> Option<bool> {
+        match &self.kind {
+            TransactionKind::Registry(action) => Some(*action == RegistryAction::Bond),
+            TransactionKind::Dispute(action) => Some(matches!(action, DisputeAction::File { .. })),
+            TransactionKind::Transfer
+            | TransactionKind::WorkClaim(_)
+            | TransactionKind::Task(_) => None,
+        }
+    }
+
     /// Validates the transaction by ensuring it has all required fields,
     /// and that it is signed and the signature is valid.
     pub fn validate(&self) -> bool {
-        if self.amount == 0 {
+        self.validate_contents() && self.verify_signature()
+    }
+
+    /// Validates everything about the transaction except its signature or approvals, so a
+    /// transaction from a multisig account can be checked while it collects approvals.
+    pub fn validate_contents(&self) -> bool {
+        // Task actions, whose amount is the reward a posting puts up, and registry and dispute
+        // actions without a deposit, which move no tokens, are checked below
+        let moves_tokens = self.task_action().is_none() && self.deposit() != Some(false);
+        if moves_tokens && self.amount == 0 {
             return false; // Invalid if no amount is transferred
         }
-        if self.to.is_empty() {
-            return false; // Invalid if no recipient is specified
+        if self.to.network() != Network::for_chain(self.chain_id) {
+            return false; // Invalid if the recipient is on another network
+        }
+        if self.id != self.calculate_hash() {
+            return false; // Invalid if the ID does not match the contents
+        }
+        if let TransactionKind::WorkClaim(claim) = &self.kind {
+            // Minting creates tokens rather than spending existing lots
+            if !self.inputs.is_empty() {
+                return false;
+            }
+            // Minted tokens always go to the worker, less the fee, and their expiry is set by
+            // the ledger
+            if self.to != self.sender() || self.expiration.is_some() || self.fee >= self.amount {
+                return false;
+            }
+            if !claim.verify_attestations(self.chain_id, &self.from, self.amount) {
+                return false;
+            }
+            // Work is claimed by the worker alone, and minted tokens are never held in escrow
+            if self.multisig.is_some() || self.condition.is_some() || !self.witnesses.is_empty() {
+                return false;
+            }
+        }
+        if let TransactionKind::Task(action) = &self.kind {
+            // A posting puts up the task's reward from the lots it spends, and other task
+            // actions only record the task's progress, spending lots only to pay a fee
+            let escrowed = match action {
+                TaskAction::Post(posting) => posting.reward,
+                _ => 0,
+            };
+            if self.amount != escrowed
+                || (self.amount == 0 && self.fee == 0 && !self.inputs.is_empty())
+                || self.to != self.sender()
+                || self.expiration.is_some()
+            {
+                return false;
+            }
+            if self.condition.is_some() || !self.witnesses.is_empty() {
+                return false;
+            }
+            match action {
+                TaskAction::Post(posting) if !posting.is_valid() => return false,
+                // Verifiers attest with their own key
+                TaskAction::Attest { .. } if self.multisig.is_some() => return false,
+                _ => {}
+            }
         }
-        self.verify_signature() // Call verify_signature with the from public key stored in the transaction
+        if let Some(deposit) = self.deposit() {
+            // Bonds and disputes pay the deposit from the lots they spend in full, and other
+            // actions spend lots only to pay a fee
+            if !deposit && (self.amount > 0 || (self.fee == 0 && !self.inputs.is_empty())) {
+                return false;
+            }
+            if self.to != self.sender() || self.expiration.is_some() {
+                return false;
+            }
+            // Governors, verifiers and disputers act with their own keys, and deposits are
+            // never held in escrow
+            if self.multisig.is_some() || self.condition.is_some() {
+                return false;
+            }
+        }
+        if self
+            .condition
+            .as_ref()
+            .is_some_and(|condition| !condition.is_valid())
+        {
+            return false;
+        }
+        // Each witness must be for a different lot the transaction spends
+        for (index, witness) in self.witnesses.iter().enumerate() {
+            if !self.inputs.contains(&witness.input)
+                || self.witnesses[..index]
+                    .iter()
+                    .any(|earlier| earlier.input == witness.input)
+            {
+                return false;
+            }
+        }
+        if let Some(policy) = &self.multisig {
+            // The proposer must be a member of an account whose policy can be met
+            if !policy.is_valid() || !policy.is_member(&self.from) {
+                return false;
+            }
+        }
+        true
     }
 }
 
+fn default_chain_id() -> u32 {
+    DEFAULT_CHAIN_ID
+}
+
 /// Helper function to get the current timestamp in seconds since UNIX epoch.
 fn get_current_timestamp() -> u64 {
     let start = SystemTime::now();
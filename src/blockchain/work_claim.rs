@@ -0,0 +1,85 @@
+use crate::crypto::hash::hash_bytes;
+use crate::crypto::signatures::{sign_message, verify_signature};
+use crate::utils::encoding::CanonicalEncoder;
+use ed25519_dalek::{Keypair, PublicKey};
+use serde::{Deserialize, Serialize};
+
+/// A verifier's signed statement that a piece of work was completed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Attestation {
+    /// Public key of the verifier vouching for the work.
+    pub verifier: PublicKey,
+    /// Signature over the claim's attestation message.
+    pub signature: Vec<u8>,
+}
+
+/// The payload of a work-claim transaction: proof that work was done, and who vouches for it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WorkClaim {
+    /// Hash (or other content identifier) of the completed work.
+    pub work_hash: String,
+    /// Short human-readable description of the work.
+    pub description: String,
+    /// Category of the work, which expiry policies may use to set the token lifetime.
+    #[serde(default)]
+    pub category: String,
+    /// Attestations from verifiers confirming the work was done.
+    pub attestations: Vec<Attestation>,
+}
+
+impl WorkClaim {
+    /// Creates a new, not yet attested, work claim.
+    pub fn new(work_hash: String, description: String, category: String) -> Self {
+        WorkClaim {
+            work_hash,
+            description,
+            category,
+            attestations: Vec::new(),
+        }
+    }
+
+    /// Builds the message verifiers sign. It binds the network, the worker, the work and the
+    /// amount claimed, so an attestation cannot be reused on another chain, for a different
+    /// worker or for a larger reward.
+    pub fn attestation_message(&self, chain_id: u32, worker: &PublicKey, amount: u64) -> String {
+        let data = CanonicalEncoder::new("work-tokens/attestation")
+            .u32(chain_id)
+            .bytes(worker.as_bytes())
+            .str(&self.work_hash)
+            .str(&self.description)
+            .str(&self.category)
+            .u64(amount)
+            .finish();
+        hash_bytes(&data)
+    }
+
+    /// Adds an attestation signed by the given verifier.
+    pub fn attest(&mut self, chain_id: u32, worker: &PublicKey, amount: u64, verifier: &Keypair) {
+        let message = self.attestation_message(chain_id, worker, amount);
+        self.attestations.push(Attestation {
+            verifier: verifier.public,
+            signature: sign_message(&message, verifier),
+        });
+    }
+
+    /// Checks that the claim is well formed and carries at least one valid attestation.
+    /// Workers cannot attest their own work, and each verifier may only attest once.
+    pub fn verify_attestations(&self, chain_id: u32, worker: &PublicKey, amount: u64) -> bool {
+        if self.work_hash.is_empty() || self.attestations.is_empty() {
+            return false;
+        }
+
+        let message = self.attestation_message(chain_id, worker, amount);
+        let mut seen_verifiers = Vec::new();
+        for attestation in &self.attestations {
+            if attestation.verifier == *worker || seen_verifiers.contains(&attestation.verifier) {
+                return false;
+            }
+            if !verify_signature(&message, &attestation.signature, &attestation.verifier) {
+                return false;
+            }
+            seen_verifiers.push(attestation.verifier);
+        }
+        true
+    }
+}
@@ -1,3 +1,6 @@
 pub mod settings;
 
-pub use self::settings::{ConsensusSettings, DatabaseSettings, Settings, WalletSettings};
+pub use self::settings::{
+    BlockSettings, ConsensusEngineKind, ConsensusSettings, DatabaseSettings, ExpiryPolicyKind,
+    FeePolicyKind, MempoolSettings, Settings, SupplyScheduleKind, TokenSettings, WalletSettings,
+};
@@ -1,5 +1,29 @@
+use crate::blockchain::transaction::DEFAULT_CHAIN_ID;
+use crate::consensus::dispute::{
+    DEFAULT_CHALLENGE_PERIOD, DEFAULT_DISPUTE_BOND, DEFAULT_VOTING_PERIOD,
+};
+use crate::consensus::engine::ConsensusEngine;
+use crate::consensus::miner::Miner;
+use crate::consensus::proof_of_authority::{
+    ProofOfAuthority, DEFAULT_BLOCK_PERIOD, DEFAULT_MAX_CLOCK_DRIFT, DEFAULT_PROPOSER_TIMEOUT,
+};
+use crate::consensus::proof_of_work::{
+    ProofOfWork, DEFAULT_MAX_RETARGET_FACTOR, DEFAULT_RETARGET_INTERVAL, DEFAULT_TARGET_BLOCK_TIME,
+};
+use crate::consensus::registry::{VerifierRules, DEFAULT_DISPUTE_PENALTY, DEFAULT_SLASH_BPS};
+use crate::consensus::validator::HoldingCap;
+use crate::mempool::mempool::{Mempool, DEFAULT_MEMPOOL_MAX_BYTES};
+use crate::node::builder::{BlockBuilder, DEFAULT_MAX_BLOCK_BYTES, DEFAULT_MAX_BLOCK_TRANSACTIONS};
+use crate::token::fees::{FeePolicy, DEFAULT_FEE_EPOCH_LENGTH};
+use crate::token::issuance::DEFAULT_TOKEN_LIFETIME;
+use crate::token::policy::{
+    ExpiryPolicy, ExponentialDemurrage, FixedTtl, LinearDemurrage, TieredTtl, BASIS_POINTS,
+};
+use crate::token::supply::{IssuancePolicy, SupplySchedule, DEFAULT_ISSUANCE_EPOCH_LENGTH};
 use config::{Config, ConfigBuilder, ConfigError, Environment, File};
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Struct representing the application configuration settings.
 #[derive(Debug, Deserialize)]
@@ -7,12 +31,62 @@ pub struct Settings {
     pub consensus: ConsensusSettings,
     pub database: DatabaseSettings,
     pub wallet: WalletSettings,
+    #[serde(default)]
+    pub token: TokenSettings,
+    #[serde(default)]
+    pub mempool: MempoolSettings,
+    #[serde(default)]
+    pub block: BlockSettings,
 }
 
 /// Struct representing consensus-specific settings.
 #[derive(Debug, Deserialize)]
 pub struct ConsensusSettings {
+    /// Which consensus engine decides who may produce blocks.
+    #[serde(default)]
+    pub engine: ConsensusEngineKind,
+    /// Identifies the network, so transactions signed for another network cannot be replayed
+    /// on this one.
+    #[serde(default = "default_chain_id")]
+    pub chain_id: u32,
+    /// Initial Proof-of-Work difficulty, as the number of leading zeros required in block hashes.
     pub difficulty: usize,
+    /// Number of blocks between difficulty retargets.
+    #[serde(default = "default_retarget_interval")]
+    pub retarget_interval: u64,
+    /// Number of seconds the network aims to take per block.
+    #[serde(default = "default_target_block_time")]
+    pub target_block_time: u64,
+    /// The most a single retarget can multiply or divide the target by.
+    #[serde(default = "default_max_retarget_factor")]
+    pub max_retarget_factor: u64,
+    /// Number of threads to mine blocks with.
+    #[serde(default = "default_mining_threads")]
+    pub mining_threads: usize,
+    /// Addresses of the authorities allowed to seal blocks from genesis under
+    /// Proof-of-Authority, in turn order.
+    #[serde(default)]
+    pub authorities: Vec<String>,
+    /// Minimum number of seconds between blocks under Proof-of-Authority.
+    #[serde(default = "default_block_period")]
+    pub block_period: u64,
+    /// Seconds to wait for each missing proposer under Proof-of-Authority.
+    #[serde(default = "default_proposer_timeout")]
+    pub proposer_timeout: u64,
+    /// Seconds a block's timestamp may be ahead of the local clock under Proof-of-Authority.
+    #[serde(default = "default_max_clock_drift")]
+    pub max_clock_drift: u64,
+}
+
+/// The consensus engines that can be selected in the configuration.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsensusEngineKind {
+    /// Miners compete to find block hashes that meet a target.
+    #[default]
+    ProofOfWork,
+    /// Known authorities take turns sealing blocks by signing them.
+    ProofOfAuthority,
 }
 
 /// Struct representing database-specific settings.
@@ -27,6 +101,330 @@ pub struct WalletSettings {
     pub wallet_dir: String,
 }
 
+/// Struct representing mempool settings.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct MempoolSettings {
+    /// Limit on the total size of pending transactions, in bytes. The lowest-priority
+    /// transactions are evicted past it.
+    pub max_bytes: usize,
+    /// Lowest fee any transaction must pay to be accepted into the mempool.
+    pub min_fee: u64,
+}
+
+impl MempoolSettings {
+    /// Builds the mempool selected by these settings.
+    pub fn build_mempool(&self) -> Mempool {
+        Mempool::new(self.max_bytes).with_min_fee(self.min_fee)
+    }
+}
+
+impl Default for MempoolSettings {
+    fn default() -> Self {
+        MempoolSettings {
+            max_bytes: DEFAULT_MEMPOOL_MAX_BYTES,
+            min_fee: 0,
+        }
+    }
+}
+
+/// Struct representing block assembly settings.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct BlockSettings {
+    /// Limit on the total size of the transactions in a block, in bytes.
+    pub max_bytes: usize,
+    /// Limit on the number of transactions in a block.
+    pub max_transactions: usize,
+}
+
+impl BlockSettings {
+    /// Builds the block builder selected by these settings.
+    pub fn build_builder(&self) -> BlockBuilder {
+        BlockBuilder::new(self.max_bytes, self.max_transactions)
+    }
+}
+
+impl Default for BlockSettings {
+    fn default() -> Self {
+        BlockSettings {
+            max_bytes: DEFAULT_MAX_BLOCK_BYTES,
+            max_transactions: DEFAULT_MAX_BLOCK_TRANSACTIONS,
+        }
+    }
+}
+
+/// Struct representing token issuance settings.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TokenSettings {
+    /// How long minted tokens stay spendable, in seconds. Also the lifetime for categories
+    /// without their own entry under the tiered policy.
+    pub token_lifetime: u64,
+    /// Which expiry policy the ledger applies to minted tokens.
+    pub expiry_policy: ExpiryPolicyKind,
+    /// Value lost per block under the demurrage policies, in basis points. At most 10,000.
+    pub decay_rate_bps: u64,
+    /// Token lifetime per work category under the tiered policy, in seconds.
+    pub category_lifetimes: HashMap<String, u64>,
+    /// Maximum live balance any single account may hold. No cap is enforced if unset.
+    pub max_account_balance: Option<u64>,
+    /// Account that receives whatever would push a recipient over `max_account_balance`. If
+    /// unset, transactions and mints over the cap are rejected instead.
+    pub commons_account: Option<String>,
+    /// What happens to the fees transactions pay.
+    pub fee_policy: FeePolicyKind,
+    /// Number of blocks between redistributions of the fee pool under the commons policy.
+    pub fee_epoch_length: u64,
+    /// Addresses (hex-encoded public keys) of the governors who admit verifiers and vote on
+    /// disputes against their attestations.
+    pub governors: Vec<String>,
+    /// Bond that registers a verifier without a governor's admission. Bonds are not accepted
+    /// if unset. Anyone may attest work while there are no governors and no minimum bond.
+    pub min_verifier_bond: Option<u64>,
+    /// Share of a verifier's bond slashed when a dispute against them is upheld, in basis
+    /// points.
+    pub slash_bps: u64,
+    /// Reputation a verifier loses when a dispute against them is upheld.
+    pub dispute_penalty: i64,
+    /// Number of blocks after a work mint during which anyone may dispute it. The minted lots
+    /// cannot be spent until the window closes. Mints are final at once if zero.
+    pub challenge_period: u64,
+    /// Smallest bond a disputer must put up, which they lose if the panel dismisses the
+    /// dispute.
+    pub dispute_bond: u64,
+    /// Number of blocks after a dispute is filed within which the panel must decide it. A
+    /// dispute the panel has not decided by then is dismissed.
+    pub dispute_voting_period: u64,
+    /// Number of blocks in an issuance epoch, over which the issuance caps apply.
+    pub issuance_epoch_length: u64,
+    /// Cap on the tokens minted for work across all workers in an epoch, before the supply
+    /// schedule adapts it. Unlimited if unset.
+    pub max_epoch_issuance: Option<u64>,
+    /// Cap on the tokens minted to any single worker in an epoch. Unlimited if unset.
+    pub max_worker_issuance: Option<u64>,
+    /// How the epoch cap adapts from one epoch to the next.
+    pub supply_schedule: SupplyScheduleKind,
+    /// Rate the supply schedule adapts by, in basis points: the share of the previous epoch's
+    /// expired tokens added to the cap, or the target velocity of the circulating supply.
+    pub schedule_rate_bps: u64,
+}
+
+/// The expiry policies that can be selected in the configuration.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryPolicyKind {
+    /// Tokens expire `token_lifetime` seconds after being minted.
+    #[default]
+    FixedTtl,
+    /// Tokens lose `decay_rate_bps` of their original value every block.
+    LinearDemurrage,
+    /// Tokens lose `decay_rate_bps` of their remaining value every block.
+    ExponentialDemurrage,
+    /// Tokens expire after a lifetime that depends on the work category.
+    Tiered,
+}
+
+/// The supply schedules that can be selected in the configuration.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SupplyScheduleKind {
+    /// Every epoch has the same cap.
+    #[default]
+    Fixed,
+    /// The cap grows by `schedule_rate_bps` of the tokens that expired in the previous epoch.
+    ExpiryLinked,
+    /// The cap scales with the share of the circulating supply transferred in the previous
+    /// epoch, relative to a target of `schedule_rate_bps`.
+    VelocityLinked,
+}
+
+/// The fee policies that can be selected in the configuration.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FeePolicyKind {
+    /// Fees are destroyed.
+    #[default]
+    Burn,
+    /// Fees go to the producer of the block that includes them.
+    Producer,
+    /// Fees collect in a pool shared out among active workers every `fee_epoch_length` blocks.
+    Commons,
+}
+
+impl Default for TokenSettings {
+    fn default() -> Self {
+        TokenSettings {
+            token_lifetime: DEFAULT_TOKEN_LIFETIME,
+            expiry_policy: ExpiryPolicyKind::default(),
+            decay_rate_bps: 0,
+            category_lifetimes: HashMap::new(),
+            max_account_balance: None,
+            commons_account: None,
+            fee_policy: FeePolicyKind::default(),
+            fee_epoch_length: DEFAULT_FEE_EPOCH_LENGTH,
+            governors: Vec::new(),
+            min_verifier_bond: None,
+            slash_bps: DEFAULT_SLASH_BPS,
+            dispute_penalty: DEFAULT_DISPUTE_PENALTY,
+            challenge_period: DEFAULT_CHALLENGE_PERIOD,
+            dispute_bond: DEFAULT_DISPUTE_BOND,
+            dispute_voting_period: DEFAULT_VOTING_PERIOD,
+            issuance_epoch_length: DEFAULT_ISSUANCE_EPOCH_LENGTH,
+            max_epoch_issuance: None,
+            max_worker_issuance: None,
+            supply_schedule: SupplyScheduleKind::default(),
+            schedule_rate_bps: 0,
+        }
+    }
+}
+
+impl TokenSettings {
+    /// Checks that the settings are within the bounds the ledger can apply: a decay rate can
+    /// take at most the whole of a lot's value per block.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.decay_rate_bps > BASIS_POINTS {
+            return Err(ConfigError::Message(format!(
+                "decay_rate_bps of {} is above {}",
+                self.decay_rate_bps, BASIS_POINTS
+            )));
+        }
+        Ok(())
+    }
+
+    /// Builds the expiry policy selected by these settings.
+    pub fn build_expiry_policy(&self) -> Arc<dyn ExpiryPolicy> {
+        match self.expiry_policy {
+            ExpiryPolicyKind::FixedTtl => Arc::new(FixedTtl {
+                lifetime: self.token_lifetime,
+            }),
+            ExpiryPolicyKind::LinearDemurrage => Arc::new(LinearDemurrage {
+                rate_bps: self.decay_rate_bps,
+            }),
+            ExpiryPolicyKind::ExponentialDemurrage => Arc::new(ExponentialDemurrage {
+                rate_bps: self.decay_rate_bps,
+            }),
+            ExpiryPolicyKind::Tiered => Arc::new(TieredTtl {
+                default_lifetime: self.token_lifetime,
+                category_lifetimes: self.category_lifetimes.clone(),
+            }),
+        }
+    }
+
+    /// Builds the fee policy selected by these settings.
+    pub fn build_fee_policy(&self) -> FeePolicy {
+        match self.fee_policy {
+            FeePolicyKind::Burn => FeePolicy::Burn,
+            FeePolicyKind::Producer => FeePolicy::Producer,
+            FeePolicyKind::Commons => FeePolicy::Commons {
+                epoch_length: self.fee_epoch_length,
+            },
+        }
+    }
+
+    /// Builds the verifier registry rules selected by these settings.
+    pub fn build_verifier_rules(&self) -> VerifierRules {
+        VerifierRules {
+            governors: self.governors.clone(),
+            min_bond: self.min_verifier_bond,
+            slash_bps: self.slash_bps,
+            dispute_penalty: self.dispute_penalty,
+            challenge_period: self.challenge_period,
+            dispute_bond: self.dispute_bond,
+            voting_period: self.dispute_voting_period,
+        }
+    }
+
+    /// Builds the issuance policy selected by these settings.
+    pub fn build_issuance_policy(&self) -> IssuancePolicy {
+        let schedule = match self.supply_schedule {
+            SupplyScheduleKind::Fixed => SupplySchedule::Fixed,
+            SupplyScheduleKind::ExpiryLinked => SupplySchedule::ExpiryLinked {
+                replacement_bps: self.schedule_rate_bps,
+            },
+            SupplyScheduleKind::VelocityLinked => SupplySchedule::VelocityLinked {
+                target_velocity_bps: self.schedule_rate_bps,
+            },
+        };
+        IssuancePolicy {
+            epoch_length: self.issuance_epoch_length,
+            max_epoch_issuance: self.max_epoch_issuance,
+            max_worker_issuance: self.max_worker_issuance,
+            schedule,
+        }
+    }
+
+    /// Builds the holding cap selected by these settings, if any.
+    pub fn holding_cap(&self) -> Option<HoldingCap> {
+        self.max_account_balance.map(|max_balance| HoldingCap {
+            max_balance,
+            commons_account: self.commons_account.clone(),
+        })
+    }
+}
+
+impl ConsensusSettings {
+    /// Builds the Proof-of-Work rules selected by these settings.
+    pub fn build_proof_of_work(&self) -> ProofOfWork {
+        ProofOfWork {
+            difficulty: self.difficulty,
+            retarget_interval: self.retarget_interval,
+            target_block_time: self.target_block_time,
+            max_retarget_factor: self.max_retarget_factor,
+        }
+    }
+
+    /// Builds the consensus engine selected by these settings.
+    pub fn build_engine(&self) -> Arc<dyn ConsensusEngine> {
+        match self.engine {
+            ConsensusEngineKind::ProofOfWork => Arc::new(self.build_proof_of_work()),
+            ConsensusEngineKind::ProofOfAuthority => Arc::new(ProofOfAuthority {
+                authorities: self.authorities.clone(),
+                block_period: self.block_period,
+                proposer_timeout: self.proposer_timeout,
+                max_clock_drift: self.max_clock_drift,
+            }),
+        }
+    }
+
+    /// Builds the miner selected by these settings.
+    pub fn build_miner(&self) -> Miner {
+        Miner::new(self.mining_threads)
+    }
+}
+
+fn default_chain_id() -> u32 {
+    DEFAULT_CHAIN_ID
+}
+
+fn default_retarget_interval() -> u64 {
+    DEFAULT_RETARGET_INTERVAL
+}
+
+fn default_target_block_time() -> u64 {
+    DEFAULT_TARGET_BLOCK_TIME
+}
+
+fn default_max_retarget_factor() -> u64 {
+    DEFAULT_MAX_RETARGET_FACTOR
+}
+
+fn default_mining_threads() -> usize {
+    1
+}
+
+fn default_block_period() -> u64 {
+    DEFAULT_BLOCK_PERIOD
+}
+
+fn default_proposer_timeout() -> u64 {
+    DEFAULT_PROPOSER_TIMEOUT
+}
+
+fn default_max_clock_drift() -> u64 {
+    DEFAULT_MAX_CLOCK_DRIFT
+}
+
 impl Settings {
     /// Loads the settings from configuration files and environment variables.
     ///
@@ -38,6 +436,24 @@ impl Settings {
             .add_source(Environment::with_prefix("APP"));
 
         let settings: Config = builder.build()?;
-        settings.try_deserialize::<Settings>()
+        let settings = settings.try_deserialize::<Settings>()?;
+        settings.token.validate()?;
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decay_rate_cannot_exceed_whole_value() {
+        let mut settings = TokenSettings {
+            decay_rate_bps: BASIS_POINTS,
+            ..TokenSettings::default()
+        };
+        assert!(settings.validate().is_ok());
+        settings.decay_rate_bps = BASIS_POINTS + 1;
+        assert!(settings.validate().is_err());
     }
 }
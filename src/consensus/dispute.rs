@@ -0,0 +1,306 @@
+use crate::utils::encoding::CanonicalEncoder;
+use ed25519_dalek::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Default number of blocks after a work mint during which it can be disputed. Mints are
+/// final at once when the period is zero.
+pub const DEFAULT_CHALLENGE_PERIOD: u64 = 0;
+
+/// Default bond a disputer must put up, which they lose if the dispute is dismissed.
+pub const DEFAULT_DISPUTE_BOND: u64 = 10;
+
+/// Default number of blocks after a dispute is filed within which the panel must decide it.
+/// A dispute the panel has not decided by then is dismissed.
+pub const DEFAULT_VOTING_PERIOD: u64 = 100;
+
+/// Returns the account that holds the disputer's bond while a dispute over the mint made by
+/// the given transaction is open.
+pub fn dispute_account(mint_id: &str) -> String {
+    format!("dispute:{}", mint_id)
+}
+
+/// A step in a dispute over a work mint, recorded on the chain by a dispute transaction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum DisputeAction {
+    /// Anyone disputes the mint made by a transaction while its challenge window is open,
+    /// putting up the transaction's amount as a bond by spending its inputs like a transfer.
+    File {
+        mint_id: String,
+        /// Why the work was never done, such as a content hash of supporting documents.
+        evidence: String,
+    },
+    /// A panelist votes to uphold or dismiss a dispute.
+    Vote { mint_id: String, uphold: bool },
+}
+
+impl DisputeAction {
+    /// Appends the action to a canonical encoding.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        match self {
+            DisputeAction::File { mint_id, evidence } => {
+                encoder.u8(0).str(mint_id).str(evidence);
+            }
+            DisputeAction::Vote { mint_id, uphold } => {
+                encoder.u8(1).str(mint_id).u8(*uphold as u8);
+            }
+        }
+    }
+}
+
+/// Reasons a dispute action can be rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DisputeError {
+    /// The network has no panel to resolve disputes.
+    NoPanel,
+    /// No mint made by this transaction can be disputed: it does not exist, or its challenge
+    /// window has closed.
+    UnknownMint(String),
+    /// The mint is already under dispute.
+    AlreadyDisputed(String),
+    /// The bond put up is less than the network requires.
+    BondTooLow { required: u64, found: u64 },
+    /// The mint is not under dispute.
+    NotDisputed(String),
+    /// Only panelists may vote on disputes.
+    NotPanelist(String),
+    /// The panelist has already voted on the dispute.
+    AlreadyVoted(String),
+    /// Voting on the dispute has closed, so it is dismissed.
+    VotingClosed(String),
+}
+
+impl fmt::Display for DisputeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisputeError::NoPanel => write!(f, "there is no panel to resolve disputes"),
+            DisputeError::UnknownMint(mint_id) => {
+                write!(f, "mint {} cannot be disputed", mint_id)
+            }
+            DisputeError::AlreadyDisputed(mint_id) => {
+                write!(f, "mint {} is already under dispute", mint_id)
+            }
+            DisputeError::BondTooLow { required, found } => {
+                write!(f, "dispute bond of {} is below {}", found, required)
+            }
+            DisputeError::NotDisputed(mint_id) => {
+                write!(f, "mint {} is not under dispute", mint_id)
+            }
+            DisputeError::NotPanelist(voter) => write!(f, "{} is not a panelist", voter),
+            DisputeError::AlreadyVoted(voter) => {
+                write!(f, "{} has already voted on the dispute", voter)
+            }
+            DisputeError::VotingClosed(mint_id) => {
+                write!(f, "voting on the dispute over mint {} has closed", mint_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DisputeError {}
+
+/// How a dispute was resolved.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    /// The work was never done: the mint is reversed and its verifiers penalised.
+    Upheld,
+    /// The mint stands, and the disputer loses their bond.
+    Dismissed,
+}
+
+/// A challenge to a work mint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Dispute {
+    /// Address of the account that filed the dispute, which gets the bond back if the
+    /// dispute is upheld.
+    pub disputer: String,
+    /// Why the work was never done.
+    pub evidence: String,
+    /// Each panelist's vote so far, keyed by address (hex-encoded public key): `true` to
+    /// uphold the dispute.
+    pub votes: BTreeMap<String, bool>,
+    /// Height at which voting closes. The dispute is dismissed if the panel has not decided
+    /// it by then.
+    pub voting_closes: u64,
+}
+
+impl Dispute {
+    /// Checks whether the panel can still vote on the dispute at the block `height`.
+    pub fn is_open(&self, height: u64) -> bool {
+        height < self.voting_closes
+    }
+
+    /// Records a panelist's vote, and returns the verdict once a majority of the panel has
+    /// voted the same way.
+    ///
+    /// # Arguments
+    /// * `panel` - Addresses (hex-encoded public keys) of the panelists.
+    /// * `voter` - Address of the panelist voting.
+    /// * `uphold` - Whether the panelist votes to uphold the dispute.
+    ///
+    /// # Returns
+    /// * `Result<Option<Verdict>, DisputeError>` - The verdict, if the vote decided the
+    ///   dispute, or why the vote was rejected.
+    pub fn vote(
+        &mut self,
+        panel: &[String],
+        voter: &str,
+        uphold: bool,
+    ) -> Result<Option<Verdict>, DisputeError> {
+        if !panel.iter().any(|panelist| panelist == voter) {
+            return Err(DisputeError::NotPanelist(voter.to_string()));
+        }
+        if self.votes.contains_key(voter) {
+            return Err(DisputeError::AlreadyVoted(voter.to_string()));
+        }
+        self.votes.insert(voter.to_string(), uphold);
+
+        let majority = panel.len() / 2 + 1;
+        let upholding = self.votes.values().filter(|vote| **vote).count();
+        if upholding >= majority {
+            Ok(Some(Verdict::Upheld))
+        } else if self.votes.len() - upholding >= majority {
+            Ok(Some(Verdict::Dismissed))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Tokens minted for work whose challenge window is still open, or that is under dispute.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PendingMint {
+    /// Address of the worker the tokens were minted to.
+    pub worker: String,
+    /// Number of tokens minted.
+    pub amount: u64,
+    /// Verifiers who attested the work, and are penalised if a dispute is upheld.
+    pub verifiers: Vec<PublicKey>,
+    /// IDs of the lots the mint created, which stay pending until the mint is final.
+    pub lots: Vec<String>,
+    /// Height at which the challenge window closes.
+    pub window_closes: u64,
+    /// The dispute over the mint, if one was filed.
+    pub dispute: Option<Dispute>,
+}
+
+impl PendingMint {
+    /// Checks whether the mint can still be disputed at the block `height`.
+    pub fn is_open(&self, height: u64) -> bool {
+        self.dispute.is_none() && height < self.window_closes
+    }
+
+    /// Appends the pending mint to a canonical encoding.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        encoder
+            .str(&self.worker)
+            .u64(self.amount)
+            .count(self.verifiers.len());
+        for verifier in &self.verifiers {
+            encoder.bytes(verifier.as_bytes());
+        }
+        encoder.count(self.lots.len());
+        for lot in &self.lots {
+            encoder.str(lot);
+        }
+        encoder.u64(self.window_closes);
+        match &self.dispute {
+            Some(dispute) => {
+                encoder
+                    .u8(1)
+                    .str(&dispute.disputer)
+                    .str(&dispute.evidence)
+                    .count(dispute.votes.len());
+                for (voter, uphold) in &dispute.votes {
+                    encoder.str(voter).u8(*uphold as u8);
+                }
+                encoder.u64(dispute.voting_closes);
+            }
+            None => {
+                encoder.u8(0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispute() -> Dispute {
+        Dispute {
+            disputer: "disputer".to_string(),
+            evidence: "evidence".to_string(),
+            votes: BTreeMap::new(),
+            voting_closes: 10,
+        }
+    }
+
+    fn panel(size: usize) -> Vec<String> {
+        (0..size)
+            .map(|index| format!("panelist-{}", index))
+            .collect()
+    }
+
+    #[test]
+    fn test_majority_of_panel_decides() {
+        let panel = panel(3);
+        let mut upheld = dispute();
+        assert_eq!(upheld.vote(&panel, &panel[0], true), Ok(None));
+        assert_eq!(upheld.vote(&panel, &panel[1], false), Ok(None));
+        assert_eq!(
+            upheld.vote(&panel, &panel[2], true),
+            Ok(Some(Verdict::Upheld))
+        );
+
+        let mut dismissed = dispute();
+        assert_eq!(dismissed.vote(&panel, &panel[0], false), Ok(None));
+        assert_eq!(
+            dismissed.vote(&panel, &panel[1], false),
+            Ok(Some(Verdict::Dismissed))
+        );
+    }
+
+    #[test]
+    fn test_only_panelists_vote_once() {
+        let panel = panel(2);
+        let mut dispute = dispute();
+        assert_eq!(
+            dispute.vote(&panel, "outsider", true),
+            Err(DisputeError::NotPanelist("outsider".to_string()))
+        );
+        assert_eq!(dispute.vote(&panel, &panel[0], true), Ok(None));
+        assert_eq!(
+            dispute.vote(&panel, &panel[0], true),
+            Err(DisputeError::AlreadyVoted(panel[0].clone()))
+        );
+        assert_eq!(
+            dispute.vote(&panel, &panel[1], true),
+            Ok(Some(Verdict::Upheld))
+        );
+    }
+
+    #[test]
+    fn test_mint_can_be_disputed_until_window_closes() {
+        let mut mint = PendingMint {
+            worker: "worker".to_string(),
+            amount: 10,
+            verifiers: Vec::new(),
+            lots: vec!["tx:0".to_string()],
+            window_closes: 5,
+            dispute: None,
+        };
+        assert!(mint.is_open(4));
+        assert!(!mint.is_open(5));
+        mint.dispute = Some(dispute());
+        assert!(!mint.is_open(4));
+    }
+
+    #[test]
+    fn test_dispute_can_be_voted_on_until_voting_closes() {
+        let dispute = dispute();
+        assert!(dispute.is_open(9));
+        assert!(!dispute.is_open(10));
+    }
+}
@@ -0,0 +1,95 @@
+use crate::blockchain::block::Block;
+use crate::blockchain::header::MAX_TARGET;
+use crate::blockchain::ledger::MAX_FUTURE_BLOCK_TIME;
+use crate::consensus::miner::{CancellationToken, Miner};
+use crate::consensus::validator::ValidationError;
+use ed25519_dalek::Keypair;
+use std::fmt::Debug;
+
+/// What a node seals blocks with. Each engine uses the part it needs: proof of work mines
+/// blocks with the miner, and proof of authority signs them with the keypair.
+pub struct Sealer<'a> {
+    /// The node's keypair, if it has one.
+    pub keypair: Option<&'a Keypair>,
+    /// The miner to search for a nonce with.
+    pub miner: &'a Miner,
+    /// Token that abandons sealing when cancelled, for example because the tip moved on.
+    pub cancel: &'a CancellationToken,
+}
+
+impl Sealer<'_> {
+    /// Returns the sealer's address (the hex-encoded public key), or an empty string if it
+    /// has no keypair.
+    pub fn address(&self) -> String {
+        self.keypair
+            .map(|keypair| hex::encode(keypair.public.as_bytes()))
+            .unwrap_or_default()
+    }
+}
+
+/// Decides who may produce each block and how they prove they were entitled to.
+///
+/// Engines must be deterministic: every node has to reach the same verdict on the same chain,
+/// so implementations may only depend on block data.
+pub trait ConsensusEngine: Debug + Send + Sync {
+    /// Returns the proof-of-work target the genesis block carries. Engines that do not use
+    /// proof of work leave every block at `MAX_TARGET`.
+    fn initial_target(&self) -> [u8; 32] {
+        MAX_TARGET
+    }
+
+    /// Returns the proof-of-work target the block extending `chain` must carry.
+    fn next_target(&self, _chain: &[Block]) -> [u8; 32] {
+        MAX_TARGET
+    }
+
+    /// Returns how much a block counts towards its branch's cumulative work, which decides
+    /// which branch is the main chain. By default every block counts the same, so the
+    /// longest branch wins.
+    fn block_work(&self, _block: &Block) -> u128 {
+        1
+    }
+
+    /// Checks that a block extending `chain` was sealed by someone entitled to produce it.
+    ///
+    /// # Arguments
+    /// * `chain` - The blocks from genesis up to and including the block's parent.
+    /// * `block` - The block to check.
+    ///
+    /// # Returns
+    /// * `Result<(), ValidationError>` - Why the block's seal is invalid, if it is.
+    fn verify_seal(&self, chain: &[Block], block: &Block) -> Result<(), ValidationError>;
+
+    /// Returns the earliest timestamp the block extending `chain` may carry if `sealer`
+    /// seals it, or `None` if they may not seal it at all. By default anyone may seal a
+    /// block as soon as its parent is produced.
+    ///
+    /// # Arguments
+    /// * `chain` - The blocks from genesis up to and including the new block's parent.
+    /// * `sealer` - The address of whoever would seal the block.
+    ///
+    /// # Returns
+    /// * `Option<u64>` - The earliest timestamp the sealer's block may carry.
+    fn earliest_timestamp(&self, chain: &[Block], _sealer: &str) -> Option<u64> {
+        chain.last().map(|parent| parent.header.timestamp)
+    }
+
+    /// Returns how far ahead of the local clock, in seconds, a block's timestamp may be before
+    /// the block is rejected. Engines that hand out turns by timestamp keep this small, so
+    /// nobody can take their turn early by stamping a block with a time still to come.
+    fn max_future_time(&self) -> u64 {
+        MAX_FUTURE_BLOCK_TIME
+    }
+
+    /// Seals a block template, whose header and state root are already final apart from
+    /// the seal, so that it passes `verify_seal`.
+    ///
+    /// # Arguments
+    /// * `block` - The block to seal. On success its hash and seal are updated.
+    /// * `sealer` - What the node seals blocks with.
+    ///
+    /// # Returns
+    /// * `bool` - Returns `true` if the block was sealed, or `false` if sealing was
+    ///   cancelled or the sealer lacks what this engine seals with.
+    fn seal(&self, block: &mut Block, sealer: &Sealer<'_>) -> bool;
+}
@@ -0,0 +1,625 @@
+use crate::consensus::validator::ValidationError;
+use crate::crypto::hash::hash_bytes;
+use crate::crypto::signatures::{sign_message, verify_signature};
+use crate::utils::encoding::CanonicalEncoder;
+use ed25519_dalek::{Keypair, PublicKey};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// The two voting steps a validator goes through for each block.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VoteStep {
+    /// A vote that the block is valid and should be finalized.
+    Prevote,
+    /// A commitment to the block, cast once more than two thirds of validators prevoted it.
+    Precommit,
+}
+
+/// A validator's signed prevote or precommit for a block.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FinalityVote {
+    /// Which step of voting this vote is for.
+    pub step: VoteStep,
+    /// Height of the block voted for.
+    pub height: u64,
+    /// Voting round at this height. A new round starts if a round fails to finalize a block.
+    pub round: u64,
+    /// Hash of the block voted for.
+    pub block_hash: String,
+    /// Public key of the validator casting the vote.
+    pub validator: PublicKey,
+    /// The validator's signature over the vote.
+    pub signature: Vec<u8>,
+}
+
+impl FinalityVote {
+    /// Creates a vote for a block, signed by the validator.
+    ///
+    /// # Arguments
+    /// * `step` - Whether this is a prevote or a precommit.
+    /// * `height` - Height of the block.
+    /// * `round` - Voting round at this height.
+    /// * `block_hash` - Hash of the block.
+    /// * `validator` - The validator's keypair.
+    ///
+    /// # Returns
+    /// * `FinalityVote` - The signed vote.
+    pub fn new(
+        step: VoteStep,
+        height: u64,
+        round: u64,
+        block_hash: String,
+        validator: &Keypair,
+    ) -> Self {
+        let mut vote = FinalityVote {
+            step,
+            height,
+            round,
+            block_hash,
+            validator: validator.public,
+            signature: Vec::new(),
+        };
+        vote.signature = sign_message(&vote.calculate_hash(), validator);
+        vote
+    }
+
+    /// Returns the validator's address (the hex-encoded public key).
+    pub fn validator_address(&self) -> String {
+        hex::encode(self.validator.as_bytes())
+    }
+
+    /// Builds the canonical byte encoding of everything in the vote except its signature.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let step = match self.step {
+            VoteStep::Prevote => 0,
+            VoteStep::Precommit => 1,
+        };
+        CanonicalEncoder::new("work-tokens/finality-vote")
+            .u8(step)
+            .u64(self.height)
+            .u64(self.round)
+            .str(&self.block_hash)
+            .bytes(self.validator.as_bytes())
+            .finish()
+    }
+
+    /// Calculates the hash of the vote, which is what the validator signs.
+    pub fn calculate_hash(&self) -> String {
+        hash_bytes(&self.canonical_bytes())
+    }
+
+    /// Verifies that the vote is signed by its validator.
+    pub fn verify_signature(&self) -> bool {
+        verify_signature(&self.calculate_hash(), &self.signature, &self.validator)
+    }
+}
+
+/// Proof that a validator signed two conflicting votes for the same height, round and step.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Equivocation {
+    /// The vote seen first.
+    pub first: FinalityVote,
+    /// The conflicting vote.
+    pub second: FinalityVote,
+}
+
+/// What a vote changed about finality, if anything.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FinalityEvent {
+    /// More than two thirds of validators prevoted the block, so validators may precommit it.
+    Prevoted {
+        height: u64,
+        round: u64,
+        block_hash: String,
+    },
+    /// More than two thirds of validators precommitted the block, so it is final.
+    Finalized { height: u64, block_hash: String },
+}
+
+/// How many heights past the latest finalized block votes are accepted for. Votes further
+/// ahead are refused, so a validator cannot make the gadget hold votes for every height.
+pub const MAX_VOTE_LOOKAHEAD: u64 = 64;
+
+/// Identifies the latest vote each validator has cast per height and step. A validator moves
+/// through rounds in order, so only its vote in the latest round is kept.
+type VoteSlot = (u64, VoteStep, String);
+
+/// A block this validator has precommitted. Until the block is final, the validator prevotes
+/// it in every later round at the same height, and only precommits another block once that
+/// block gathers a prevote quorum in a later round.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lock {
+    /// Height of the locked block.
+    pub height: u64,
+    /// Round the block was precommitted in.
+    pub round: u64,
+    /// Hash of the locked block.
+    pub block_hash: String,
+}
+
+/// A finality gadget in the style of Tendermint: a fixed set of validators prevote and then
+/// precommit blocks, and a block is final once more than two thirds of them precommit it in a
+/// round where more than two thirds prevoted it.
+///
+/// The gadget runs alongside block production, whichever engine produces the blocks. Each
+/// node keeps its own gadget, feeds it every vote it receives, casts its own votes through
+/// [`FinalityGadget::prevote`] and [`FinalityGadget::precommit`], which apply the voting
+/// rules, and acts on the events it returns.
+#[derive(Debug, Clone)]
+pub struct FinalityGadget {
+    /// Addresses (hex-encoded public keys) of the validators, each with an equal vote.
+    pub validators: Vec<String>,
+    /// The latest vote counted from each validator, by the slot it fills.
+    votes: HashMap<VoteSlot, FinalityVote>,
+    /// The latest round at each height in which more than two thirds of validators prevoted a
+    /// block, with that block's hash.
+    prevote_quorums: HashMap<u64, (u64, String)>,
+    /// The block this validator is locked on, if any.
+    lock: Option<Lock>,
+    /// Height, round and step of every vote this validator has cast that is not yet final.
+    cast: HashSet<(u64, u64, VoteStep)>,
+    /// Conflicting votes seen from validators.
+    equivocations: Vec<Equivocation>,
+    /// Height and hash of the latest block finalized.
+    finalized: Option<(u64, String)>,
+}
+
+impl FinalityGadget {
+    /// Creates a gadget for the given validator set.
+    pub fn new(validators: Vec<String>) -> Self {
+        FinalityGadget {
+            validators,
+            votes: HashMap::new(),
+            prevote_quorums: HashMap::new(),
+            lock: None,
+            cast: HashSet::new(),
+            equivocations: Vec::new(),
+            finalized: None,
+        }
+    }
+
+    /// Returns the number of votes needed to pass a step: more than two thirds of the
+    /// validators.
+    pub fn quorum(&self) -> usize {
+        self.validators.len() * 2 / 3 + 1
+    }
+
+    /// Casts this validator's prevote for a block it has accepted, at most once per height
+    /// and round.
+    ///
+    /// A validator locked on a block at this height prevotes the locked block instead.
+    ///
+    /// # Arguments
+    /// * `height` - Height of the block.
+    /// * `round` - Voting round at this height.
+    /// * `block_hash` - Hash of the block.
+    /// * `validator` - This validator's keypair.
+    ///
+    /// # Returns
+    /// * `Option<FinalityVote>` - The prevote to broadcast, or `None` if the height is already
+    ///   final or this validator has already prevoted in this round.
+    pub fn prevote(
+        &mut self,
+        height: u64,
+        round: u64,
+        block_hash: String,
+        validator: &Keypair,
+    ) -> Option<FinalityVote> {
+        if self.is_settled(height) || !self.cast.insert((height, round, VoteStep::Prevote)) {
+            return None;
+        }
+        let block_hash = match &self.lock {
+            Some(lock) if lock.height == height => lock.block_hash.clone(),
+            _ => block_hash,
+        };
+        Some(FinalityVote::new(
+            VoteStep::Prevote,
+            height,
+            round,
+            block_hash,
+            validator,
+        ))
+    }
+
+    /// Casts this validator's precommit for the block more than two thirds of validators
+    /// prevoted at this height and round, and locks on it.
+    ///
+    /// # Arguments
+    /// * `height` - Height of the block.
+    /// * `round` - Voting round at this height.
+    /// * `validator` - This validator's keypair.
+    ///
+    /// # Returns
+    /// * `Option<FinalityVote>` - The precommit to broadcast, or `None` if no block has a
+    ///   prevote quorum in this round, this validator has already precommitted in it, or it is
+    ///   locked on another block from this round or later.
+    pub fn precommit(
+        &mut self,
+        height: u64,
+        round: u64,
+        validator: &Keypair,
+    ) -> Option<FinalityVote> {
+        if self.is_settled(height) {
+            return None;
+        }
+        let block_hash = match self.prevote_quorums.get(&height) {
+            Some((quorum_round, block_hash)) if *quorum_round == round => block_hash.clone(),
+            _ => return None,
+        };
+        if let Some(lock) = &self.lock {
+            if lock.height == height && lock.block_hash != block_hash && lock.round >= round {
+                return None;
+            }
+        }
+        if !self.cast.insert((height, round, VoteStep::Precommit)) {
+            return None;
+        }
+        self.lock = Some(Lock {
+            height,
+            round,
+            block_hash: block_hash.clone(),
+        });
+        Some(FinalityVote::new(
+            VoteStep::Precommit,
+            height,
+            round,
+            block_hash,
+            validator,
+        ))
+    }
+
+    /// Counts a vote from a validator.
+    ///
+    /// A validator that votes for two different blocks at the same height, round and step is
+    /// equivocating: the evidence is recorded and the second vote is not counted. Votes for
+    /// heights already final are ignored, and votes more than [`MAX_VOTE_LOOKAHEAD`] heights
+    /// past the latest finalized block are refused.
+    ///
+    /// # Arguments
+    /// * `vote` - The vote received.
+    ///
+    /// # Returns
+    /// * `Result<Option<FinalityEvent>, ValidationError>` - The event this vote triggered, if
+    ///   any, or why the vote was rejected.
+    pub fn add_vote(
+        &mut self,
+        vote: FinalityVote,
+    ) -> Result<Option<FinalityEvent>, ValidationError> {
+        let validator = vote.validator_address();
+        if !self.validators.contains(&validator) {
+            return Err(ValidationError::UnknownValidator(validator));
+        }
+        if !vote.verify_signature() {
+            return Err(ValidationError::InvalidVoteSignature);
+        }
+        if self.is_settled(vote.height) {
+            return Ok(None);
+        }
+        let finalized_height = self.finalized_height().unwrap_or(0);
+        if vote.height > finalized_height.saturating_add(MAX_VOTE_LOOKAHEAD) {
+            return Err(ValidationError::VoteTooFarAhead(vote.height));
+        }
+
+        let slot = (vote.height, vote.step, validator.clone());
+        if let Some(existing) = self.votes.get(&slot) {
+            if existing.round > vote.round
+                || (existing.round == vote.round && existing.block_hash == vote.block_hash)
+            {
+                return Ok(None);
+            }
+            if existing.round == vote.round {
+                self.equivocations.push(Equivocation {
+                    first: existing.clone(),
+                    second: vote,
+                });
+                return Err(ValidationError::Equivocation(validator));
+            }
+        }
+        self.votes.insert(slot, vote.clone());
+
+        if self.count_votes(&vote) < self.quorum() {
+            return Ok(None);
+        }
+        match vote.step {
+            VoteStep::Prevote => {
+                let quorum = (vote.round, vote.block_hash.clone());
+                if self.prevote_quorums.get(&vote.height) == Some(&quorum)
+                    || self
+                        .prevote_quorums
+                        .get(&vote.height)
+                        .is_some_and(|(round, _)| *round > vote.round)
+                {
+                    return Ok(None);
+                }
+                self.prevote_quorums.insert(vote.height, quorum);
+
+                // The precommits may have arrived before the prevotes
+                let precommit = FinalityVote {
+                    step: VoteStep::Precommit,
+                    ..vote.clone()
+                };
+                if self.count_votes(&precommit) >= self.quorum() {
+                    return Ok(Some(self.finalize(vote.height, vote.block_hash)));
+                }
+                Ok(Some(FinalityEvent::Prevoted {
+                    height: vote.height,
+                    round: vote.round,
+                    block_hash: vote.block_hash,
+                }))
+            }
+            VoteStep::Precommit => {
+                let prevoted = self
+                    .prevote_quorums
+                    .get(&vote.height)
+                    .is_some_and(|(round, hash)| *round == vote.round && *hash == vote.block_hash);
+                if !prevoted {
+                    return Ok(None);
+                }
+                Ok(Some(self.finalize(vote.height, vote.block_hash)))
+            }
+        }
+    }
+
+    /// Returns the height of the latest block finalized, if any.
+    pub fn finalized_height(&self) -> Option<u64> {
+        self.finalized.as_ref().map(|(height, _)| *height)
+    }
+
+    /// Returns the hash of the latest block finalized, if any.
+    pub fn finalized_hash(&self) -> Option<&str> {
+        self.finalized.as_ref().map(|(_, hash)| hash.as_str())
+    }
+
+    /// Returns the block this validator is locked on, if any.
+    pub fn lock(&self) -> Option<&Lock> {
+        self.lock.as_ref()
+    }
+
+    /// Returns the conflicting votes seen from equivocating validators.
+    pub fn equivocations(&self) -> &[Equivocation] {
+        &self.equivocations
+    }
+
+    /// Returns whether a block at `height` or above it is already final.
+    fn is_settled(&self, height: u64) -> bool {
+        self.finalized_height()
+            .is_some_and(|finalized| finalized >= height)
+    }
+
+    /// Records a block as final and forgets every vote, quorum and lock at or below its
+    /// height, which can no longer change anything.
+    fn finalize(&mut self, height: u64, block_hash: String) -> FinalityEvent {
+        self.finalized = Some((height, block_hash.clone()));
+        self.votes.retain(|(voted, _, _), _| *voted > height);
+        self.prevote_quorums.retain(|voted, _| *voted > height);
+        self.cast.retain(|(voted, _, _)| *voted > height);
+        if self.lock.as_ref().is_some_and(|lock| lock.height <= height) {
+            self.lock = None;
+        }
+        FinalityEvent::Finalized { height, block_hash }
+    }
+
+    /// Counts the votes for the same block, height, round and step as `vote`.
+    fn count_votes(&self, vote: &FinalityVote) -> usize {
+        self.votes
+            .iter()
+            .filter(|((height, step, _), counted)| {
+                *height == vote.height
+                    && *step == vote.step
+                    && counted.round == vote.round
+                    && counted.block_hash == vote.block_hash
+            })
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn validators(count: usize) -> (Vec<Keypair>, FinalityGadget) {
+        let keypairs: Vec<Keypair> = (0..count).map(|_| Keypair::generate(&mut OsRng)).collect();
+        let addresses = keypairs
+            .iter()
+            .map(|keypair| hex::encode(keypair.public.as_bytes()))
+            .collect();
+        (keypairs, FinalityGadget::new(addresses))
+    }
+
+    #[test]
+    fn test_quorum_is_more_than_two_thirds() {
+        assert_eq!(FinalityGadget::new(vec!["a".into(); 3]).quorum(), 3);
+        assert_eq!(FinalityGadget::new(vec!["a".into(); 4]).quorum(), 3);
+        assert_eq!(FinalityGadget::new(vec!["a".into(); 6]).quorum(), 5);
+        assert_eq!(FinalityGadget::new(vec!["a".into(); 7]).quorum(), 5);
+    }
+
+    #[test]
+    fn test_precommit_quorum_finalizes_block() {
+        let (keypairs, mut gadget) = validators(4);
+        let vote = |step, keypair| FinalityVote::new(step, 1, 0, "block".to_string(), keypair);
+
+        assert_eq!(
+            gadget.add_vote(vote(VoteStep::Prevote, &keypairs[0])),
+            Ok(None)
+        );
+        assert_eq!(
+            gadget.add_vote(vote(VoteStep::Prevote, &keypairs[1])),
+            Ok(None)
+        );
+        assert_eq!(
+            gadget.add_vote(vote(VoteStep::Prevote, &keypairs[2])),
+            Ok(Some(FinalityEvent::Prevoted {
+                height: 1,
+                round: 0,
+                block_hash: "block".to_string()
+            }))
+        );
+
+        for keypair in &keypairs[..2] {
+            assert_eq!(
+                gadget.add_vote(vote(VoteStep::Precommit, keypair)),
+                Ok(None)
+            );
+        }
+        assert_eq!(gadget.finalized_height(), None);
+        assert_eq!(
+            gadget.add_vote(vote(VoteStep::Precommit, &keypairs[2])),
+            Ok(Some(FinalityEvent::Finalized {
+                height: 1,
+                block_hash: "block".to_string()
+            }))
+        );
+        assert_eq!(gadget.finalized_hash(), Some("block"));
+
+        // The last precommit changes nothing
+        assert_eq!(
+            gadget.add_vote(vote(VoteStep::Precommit, &keypairs[3])),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_rejects_votes_from_outside_the_set() {
+        let (keypairs, mut gadget) = validators(1);
+        let outsider = Keypair::generate(&mut OsRng);
+        let vote = FinalityVote::new(VoteStep::Prevote, 1, 0, "block".to_string(), &outsider);
+        assert_eq!(
+            gadget.add_vote(vote),
+            Err(ValidationError::UnknownValidator(hex::encode(
+                outsider.public.as_bytes()
+            )))
+        );
+
+        let mut forged = FinalityVote::new(VoteStep::Prevote, 1, 0, "block".to_string(), &outsider);
+        forged.validator = keypairs[0].public;
+        assert_eq!(
+            gadget.add_vote(forged),
+            Err(ValidationError::InvalidVoteSignature)
+        );
+    }
+
+    #[test]
+    fn test_detects_equivocation() {
+        let (keypairs, mut gadget) = validators(4);
+        let first = FinalityVote::new(VoteStep::Prevote, 1, 0, "a".to_string(), &keypairs[0]);
+        let second = FinalityVote::new(VoteStep::Prevote, 1, 0, "b".to_string(), &keypairs[0]);
+
+        assert_eq!(gadget.add_vote(first.clone()), Ok(None));
+        // Hearing the same vote twice is not equivocation
+        assert_eq!(gadget.add_vote(first.clone()), Ok(None));
+        assert_eq!(
+            gadget.add_vote(second.clone()),
+            Err(ValidationError::Equivocation(hex::encode(
+                keypairs[0].public.as_bytes()
+            )))
+        );
+        assert_eq!(gadget.equivocations(), &[Equivocation { first, second }]);
+
+        // Voting again in a later round is allowed
+        let next_round = FinalityVote::new(VoteStep::Prevote, 1, 1, "b".to_string(), &keypairs[0]);
+        assert_eq!(gadget.add_vote(next_round), Ok(None));
+    }
+
+    #[test]
+    fn test_precommits_only_count_with_a_prevote_quorum() {
+        let (keypairs, mut gadget) = validators(4);
+        let vote = |step, keypair| FinalityVote::new(step, 1, 0, "block".to_string(), keypair);
+
+        for keypair in &keypairs[..3] {
+            assert_eq!(
+                gadget.add_vote(vote(VoteStep::Precommit, keypair)),
+                Ok(None)
+            );
+        }
+        assert_eq!(gadget.finalized_height(), None);
+
+        // The prevotes arrive late, and the quorum they reach finalizes the block
+        for keypair in &keypairs[..2] {
+            assert_eq!(gadget.add_vote(vote(VoteStep::Prevote, keypair)), Ok(None));
+        }
+        assert_eq!(
+            gadget.add_vote(vote(VoteStep::Prevote, &keypairs[2])),
+            Ok(Some(FinalityEvent::Finalized {
+                height: 1,
+                block_hash: "block".to_string()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_precommit_needs_a_prevote_quorum_and_locks() {
+        let (keypairs, mut gadget) = validators(4);
+        let own = &keypairs[0];
+        assert_eq!(gadget.precommit(1, 0, own), None);
+
+        let prevote = gadget.prevote(1, 0, "a".to_string(), own).unwrap();
+        assert_eq!(gadget.prevote(1, 0, "a".to_string(), own), None);
+        assert_eq!(gadget.add_vote(prevote), Ok(None));
+        for keypair in &keypairs[1..3] {
+            let vote = FinalityVote::new(VoteStep::Prevote, 1, 0, "a".to_string(), keypair);
+            gadget.add_vote(vote).unwrap();
+        }
+
+        let precommit = gadget.precommit(1, 0, own).unwrap();
+        assert_eq!(precommit.block_hash, "a");
+        assert_eq!(gadget.precommit(1, 0, own), None);
+        assert_eq!(
+            gadget.lock(),
+            Some(&Lock {
+                height: 1,
+                round: 0,
+                block_hash: "a".to_string()
+            })
+        );
+
+        // In the next round, the locked validator prevotes its locked block
+        let prevote = gadget.prevote(1, 1, "b".to_string(), own).unwrap();
+        assert_eq!(prevote.block_hash, "a");
+
+        // A prevote quorum for another block in a later round releases the lock
+        for keypair in &keypairs[1..] {
+            let vote = FinalityVote::new(VoteStep::Prevote, 1, 1, "b".to_string(), keypair);
+            gadget.add_vote(vote).unwrap();
+        }
+        assert_eq!(gadget.precommit(1, 1, own).unwrap().block_hash, "b");
+        assert_eq!(gadget.lock().unwrap().block_hash, "b");
+    }
+
+    #[test]
+    fn test_votes_are_pruned_once_final() {
+        let (keypairs, mut gadget) = validators(4);
+        for step in [VoteStep::Prevote, VoteStep::Precommit] {
+            for keypair in &keypairs[..3] {
+                let vote = FinalityVote::new(step, 2, 0, "block".to_string(), keypair);
+                gadget.add_vote(vote).unwrap();
+            }
+        }
+        assert_eq!(gadget.finalized_height(), Some(2));
+        assert!(gadget.votes.is_empty());
+        assert!(gadget.prevote_quorums.is_empty());
+
+        // Votes for heights already final are not held
+        let stale = FinalityVote::new(VoteStep::Prevote, 1, 0, "old".to_string(), &keypairs[3]);
+        assert_eq!(gadget.add_vote(stale), Ok(None));
+        assert!(gadget.votes.is_empty());
+        assert_eq!(
+            gadget.prevote(2, 1, "block".to_string(), &keypairs[0]),
+            None
+        );
+
+        let height = 2 + MAX_VOTE_LOOKAHEAD + 1;
+        let ahead = FinalityVote::new(
+            VoteStep::Prevote,
+            height,
+            0,
+            "new".to_string(),
+            &keypairs[3],
+        );
+        assert_eq!(
+            gadget.add_vote(ahead),
+            Err(ValidationError::VoteTooFarAhead(height))
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::blockchain::block::Block;
+use crate::consensus::proof_of_work::ProofOfWork;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How many hashes a mining thread tries between checks for cancellation.
+const CANCEL_CHECK_INTERVAL: u64 = 256;
+
+/// A shared flag for abandoning work in progress, such as mining a block on a stale tip.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    /// Creates a token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the work this token was handed to, and any clones of it.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Checks if the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// The outcome of a mining attempt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiningReport {
+    /// Whether a valid nonce was found. `false` if mining was cancelled first.
+    pub found: bool,
+    /// Number of hashes tried across all threads.
+    pub hashes: u64,
+    /// How long mining ran for.
+    pub elapsed: Duration,
+}
+
+impl MiningReport {
+    /// Returns the hash rate achieved, in hashes per second.
+    pub fn hashrate(&self) -> f64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.hashes as f64 / seconds
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Mines blocks by splitting the nonce space across a number of worker threads.
+#[derive(Debug, Clone)]
+pub struct Miner {
+    /// Number of worker threads to mine with.
+    pub threads: usize,
+    /// The outcome of the most recent mining attempt, shared between clones.
+    last_report: Arc<Mutex<Option<MiningReport>>>,
+}
+
+impl Miner {
+    /// Creates a miner that uses the given number of threads (at least one).
+    pub fn new(threads: usize) -> Self {
+        Miner {
+            threads: threads.max(1),
+            last_report: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Mines a block by searching for a nonce whose block hash meets the target in its header.
+    ///
+    /// Thread `i` of `n` tries the nonces `start + i`, `start + i + n`, and so on, so the
+    /// threads never repeat each other's work. When a thread runs out of nonces it increments
+    /// the header's extra nonce and starts again from nonce `i`, so mining never stops for
+    /// lack of nonces. The timestamp is never changed, since the state root depends on it.
+    ///
+    /// # Arguments
+    /// * `block` - The block to mine. On success its nonce, extra nonce and hash are updated.
+    /// * `cancel` - Token that stops mining early when cancelled.
+    ///
+    /// # Returns
+    /// * `MiningReport` - Whether a nonce was found, and how much hashing it took.
+    pub fn mine(&self, block: &mut Block, cancel: &CancellationToken) -> MiningReport {
+        let started = Instant::now();
+        let found = AtomicBool::new(false);
+        let hashes = AtomicU64::new(0);
+        let solution = Mutex::new(None);
+        let threads = self.threads as u64;
+
+        thread::scope(|scope| {
+            for thread_index in 0..threads {
+                let mut header = block.header.clone();
+                let (found, hashes, solution) = (&found, &hashes, &solution);
+                scope.spawn(move || {
+                    let mut nonce = header.nonce.checked_add(thread_index);
+                    let mut tried = 0;
+                    while !found.load(Ordering::Relaxed) {
+                        if tried % CANCEL_CHECK_INTERVAL == 0 && cancel.is_cancelled() {
+                            break;
+                        }
+
+                        // Out of nonces: move on to the next extra nonce
+                        let Some(current) = nonce else {
+                            header.extra_nonce = header.extra_nonce.wrapping_add(1);
+                            nonce = Some(thread_index);
+                            continue;
+                        };
+
+                        header.nonce = current;
+                        tried += 1;
+                        if ProofOfWork::meets_target(&header.calculate_hash(), &header.target) {
+                            if !found.swap(true, Ordering::Relaxed) {
+                                *solution.lock().unwrap() = Some((current, header.extra_nonce));
+                            }
+                            break;
+                        }
+                        nonce = current.checked_add(threads);
+                    }
+                    hashes.fetch_add(tried, Ordering::Relaxed);
+                });
+            }
+        });
+
+        let solution = solution.into_inner().unwrap();
+        if let Some((nonce, extra_nonce)) = solution {
+            block.header.nonce = nonce;
+            block.header.extra_nonce = extra_nonce;
+            block.hash = block.calculate_hash();
+        }
+
+        let report = MiningReport {
+            found: solution.is_some(),
+            hashes: hashes.into_inner(),
+            elapsed: started.elapsed(),
+        };
+        *self.last_report.lock().expect("Mining lock poisoned") = Some(report.clone());
+        report
+    }
+
+    /// Returns the outcome of the most recent mining attempt, if any.
+    pub fn last_report(&self) -> Option<MiningReport> {
+        self.last_report
+            .lock()
+            .expect("Mining lock poisoned")
+            .clone()
+    }
+}
+
+impl Default for Miner {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_with_difficulty(difficulty: usize) -> Block {
+        let mut block = Block::new(1, "prev_hash".to_string(), vec![], 0);
+        block.header.target = ProofOfWork::difficulty_target(difficulty);
+        block
+    }
+
+    #[test]
+    fn test_multithreaded_mining_finds_valid_nonce() {
+        let mut block = block_with_difficulty(3);
+        let report = Miner::new(4).mine(&mut block, &CancellationToken::new());
+
+        assert!(report.found);
+        assert!(report.hashes > 0);
+        assert!(block.validate());
+        assert!(ProofOfWork::meets_target(&block.hash, &block.header.target));
+    }
+
+    #[test]
+    fn test_mining_rolls_extra_nonce_on_overflow() {
+        let mut block = block_with_difficulty(2);
+        block.header.timestamp = 1_700_000_000;
+        block.header.nonce = u64::MAX - 1;
+        let report = Miner::new(2).mine(&mut block, &CancellationToken::new());
+
+        // Neither of the last two nonces meets the target for this header
+        assert!(report.found);
+        assert!(block.header.extra_nonce > 0);
+        assert!(block.validate());
+        assert!(ProofOfWork::meets_target(&block.hash, &block.header.target));
+    }
+
+    #[test]
+    fn test_cancelled_mining_stops() {
+        // Only an all-zero hash meets a zero target, so this never finds a nonce
+        let mut block = block_with_difficulty(64);
+        let original_hash = block.hash.clone();
+        let cancel = CancellationToken::new();
+
+        let canceller = {
+            let cancel = cancel.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                cancel.cancel();
+            })
+        };
+        let report = Miner::new(2).mine(&mut block, &cancel);
+        canceller.join().unwrap();
+
+        assert!(!report.found);
+        assert!(report.hashes > 0);
+        assert!(report.hashrate() > 0.0);
+        assert_eq!(block.hash, original_hash);
+    }
+}
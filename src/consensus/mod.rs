@@ -1,5 +1,19 @@
+pub mod dispute;
+pub mod engine;
+pub mod finality;
+pub mod miner;
+pub mod proof_of_authority;
 pub mod proof_of_work;
+pub mod registry;
 pub mod validator;
 
+pub use self::dispute::{Dispute, DisputeAction, PendingMint, Verdict};
+pub use self::engine::{ConsensusEngine, Sealer};
+pub use self::finality::{
+    Equivocation, FinalityEvent, FinalityGadget, FinalityVote, Lock, VoteStep,
+};
+pub use self::miner::{CancellationToken, Miner, MiningReport};
+pub use self::proof_of_authority::ProofOfAuthority;
 pub use self::proof_of_work::ProofOfWork;
-pub use self::validator::Validator;
+pub use self::registry::{RegistryAction, VerifierRecord, VerifierRules, VerifierStatus};
+pub use self::validator::{HoldingCap, ValidationError, Validator};
@@ -0,0 +1,249 @@
+use crate::blockchain::block::{Block, Seal};
+use crate::blockchain::header::AuthorityVote;
+use crate::consensus::engine::{ConsensusEngine, Sealer};
+use crate::consensus::validator::ValidationError;
+use crate::crypto::signatures::{sign_message, verify_signature};
+use ed25519_dalek::Keypair;
+use std::collections::{HashMap, HashSet};
+
+/// Default minimum number of seconds between blocks.
+pub const DEFAULT_BLOCK_PERIOD: u64 = 5;
+/// Default number of seconds to wait for each proposer ahead of an authority in turn order
+/// before that authority may seal in their place.
+pub const DEFAULT_PROPOSER_TIMEOUT: u64 = 10;
+/// Default number of seconds a block's timestamp may be ahead of the local clock.
+pub const DEFAULT_MAX_CLOCK_DRIFT: u64 = 1;
+
+/// Proof-of-Authority rules: a set of known authorities, such as co-ops, employers or guilds,
+/// take turns sealing blocks by signing them.
+///
+/// The authority in turn for a block is picked round-robin by height. If it does not produce
+/// the block, each authority after it in turn order may take over once another
+/// `proposer_timeout` seconds have passed. Authorities vote to add or remove authorities by
+/// sealing blocks that carry the vote, and a change takes effect once more than half of the
+/// current authorities have voted for it.
+///
+/// Turns are judged by block timestamps, so blocks stamped more than `max_clock_drift`
+/// seconds ahead of the local clock are rejected, and authorities wait for their slot
+/// rather than stamping a block with a time still to come.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProofOfAuthority {
+    /// Addresses (hex-encoded public keys) of the authorities at genesis, in turn order.
+    pub authorities: Vec<String>,
+    /// Minimum number of seconds between a block and its parent.
+    pub block_period: u64,
+    /// Seconds to wait for each proposer ahead in turn order before taking over from them.
+    pub proposer_timeout: u64,
+    /// Seconds a block's timestamp may be ahead of the local clock.
+    pub max_clock_drift: u64,
+}
+
+impl ProofOfAuthority {
+    /// Creates Proof-of-Authority rules for the given genesis authorities, with the default
+    /// block period, proposer timeout and clock drift.
+    pub fn new(authorities: Vec<String>) -> Self {
+        ProofOfAuthority {
+            authorities,
+            block_period: DEFAULT_BLOCK_PERIOD,
+            proposer_timeout: DEFAULT_PROPOSER_TIMEOUT,
+            max_clock_drift: DEFAULT_MAX_CLOCK_DRIFT,
+        }
+    }
+
+    /// Returns the authorities allowed to seal the block extending `chain`, in turn order,
+    /// after applying the votes in every block so far.
+    pub fn authorities_at(&self, chain: &[Block]) -> Vec<String> {
+        self.replay(chain).authorities
+    }
+
+    /// Returns the authority whose turn it is to seal the block extending `chain`.
+    pub fn in_turn_authority(&self, chain: &[Block]) -> Option<String> {
+        let parent = &chain.last().expect("Chain has a genesis block").header;
+        let authorities = self.authorities_at(chain);
+        authorities
+            .get(((parent.height + 1) % authorities.len().max(1) as u64) as usize)
+            .cloned()
+    }
+
+    /// Seals a block by signing its hash.
+    ///
+    /// # Arguments
+    /// * `block` - The block to seal. Its hash must already be final.
+    /// * `keypair` - The authority's keypair.
+    pub fn seal_block(block: &mut Block, keypair: &Keypair) {
+        block.seal = Some(Seal {
+            signer: keypair.public,
+            signature: sign_message(&block.hash, keypair),
+        });
+    }
+
+    /// Works out when `authority` may seal, given the authorities in turn order.
+    fn slot_start(&self, chain: &[Block], authorities: &[String], authority: &str) -> Option<u64> {
+        let parent = &chain.last().expect("Chain has a genesis block").header;
+        let position = authorities.iter().position(|a| a == authority)?;
+        let count = authorities.len();
+        let in_turn = ((parent.height + 1) % count as u64) as usize;
+        let turns_behind = (position + count - in_turn) % count;
+
+        Some(
+            parent
+                .timestamp
+                .saturating_add(self.block_period)
+                .saturating_add(self.proposer_timeout.saturating_mul(turns_behind as u64)),
+        )
+    }
+
+    /// Replays the votes in every block of the chain on top of the genesis authorities.
+    fn replay(&self, chain: &[Block]) -> AuthoritySet {
+        let mut set = AuthoritySet::new(self.authorities.clone());
+        for block in chain.iter().skip(1) {
+            if let (Some(seal), Some(vote)) = (&block.seal, &block.header.vote) {
+                // Every block in the chain was verified, so its vote applies cleanly
+                let _ = set.apply(&hex::encode(seal.signer.as_bytes()), vote);
+            }
+        }
+        set
+    }
+}
+
+impl ConsensusEngine for ProofOfAuthority {
+    fn verify_seal(&self, chain: &[Block], block: &Block) -> Result<(), ValidationError> {
+        let seal = block.seal.as_ref().ok_or(ValidationError::MissingSeal)?;
+        if !verify_signature(&block.hash, &seal.signature, &seal.signer) {
+            return Err(ValidationError::InvalidSeal);
+        }
+
+        let signer = hex::encode(seal.signer.as_bytes());
+        let mut set = self.replay(chain);
+        let earliest = self
+            .slot_start(chain, &set.authorities, &signer)
+            .ok_or_else(|| ValidationError::UnknownAuthority(signer.clone()))?;
+        if block.header.timestamp < earliest {
+            return Err(ValidationError::OutOfTurn {
+                authority: signer,
+                earliest,
+            });
+        }
+
+        match &block.header.vote {
+            Some(vote) => set.apply(&signer, vote),
+            None => Ok(()),
+        }
+    }
+
+    /// An authority may seal once its slot starts: a block period after the parent, plus a
+    /// proposer timeout for each authority ahead of it in turn order.
+    fn earliest_timestamp(&self, chain: &[Block], authority: &str) -> Option<u64> {
+        let authorities = self.authorities_at(chain);
+        self.slot_start(chain, &authorities, authority)
+    }
+
+    fn max_future_time(&self) -> u64 {
+        self.max_clock_drift
+    }
+
+    fn seal(&self, block: &mut Block, sealer: &Sealer<'_>) -> bool {
+        match sealer.keypair {
+            Some(keypair) => {
+                ProofOfAuthority::seal_block(block, keypair);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// The authorities at some point in the chain, along with the votes cast since the set last
+/// changed.
+struct AuthoritySet {
+    authorities: Vec<String>,
+    /// The authorities that have voted on each candidate for addition or removal.
+    votes: HashMap<String, HashSet<String>>,
+}
+
+impl AuthoritySet {
+    fn new(authorities: Vec<String>) -> Self {
+        AuthoritySet {
+            authorities,
+            votes: HashMap::new(),
+        }
+    }
+
+    /// Records a vote, and changes the set once a majority of authorities agree.
+    ///
+    /// Whether a vote adds or removes its subject follows from whether the subject is already
+    /// an authority, so every vote recorded on a subject points the same way.
+    fn apply(&mut self, voter: &str, vote: &AuthorityVote) -> Result<(), ValidationError> {
+        let is_authority = self.authorities.contains(&vote.authority);
+        if vote.add == is_authority || (!vote.add && self.authorities.len() == 1) {
+            return Err(ValidationError::InvalidVote(vote.authority.clone()));
+        }
+
+        let voters = self.votes.entry(vote.authority.clone()).or_default();
+        voters.insert(voter.to_string());
+        if voters.len() * 2 <= self.authorities.len() {
+            return Ok(());
+        }
+
+        self.votes.remove(&vote.authority);
+        if vote.add {
+            self.authorities.push(vote.authority.clone());
+        } else {
+            self.authorities
+                .retain(|authority| *authority != vote.authority);
+            // A removed authority's votes no longer count
+            for voters in self.votes.values_mut() {
+                voters.remove(&vote.authority);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vote(authority: &str, add: bool) -> AuthorityVote {
+        AuthorityVote {
+            authority: authority.to_string(),
+            add,
+        }
+    }
+
+    #[test]
+    fn test_vote_needs_majority() {
+        let mut set = AuthoritySet::new(vec!["a".into(), "b".into(), "c".into()]);
+
+        set.apply("a", &vote("d", true)).unwrap();
+        // Voting twice does not count twice
+        set.apply("a", &vote("d", true)).unwrap();
+        assert_eq!(set.authorities, vec!["a", "b", "c"]);
+
+        set.apply("b", &vote("d", true)).unwrap();
+        assert_eq!(set.authorities, vec!["a", "b", "c", "d"]);
+        assert!(set.votes.is_empty());
+    }
+
+    #[test]
+    fn test_removed_authority_votes_are_dropped() {
+        let mut set = AuthoritySet::new(vec!["a".into(), "b".into(), "c".into(), "d".into()]);
+
+        set.apply("d", &vote("e", true)).unwrap();
+        set.apply("a", &vote("d", false)).unwrap();
+        set.apply("b", &vote("d", false)).unwrap();
+        set.apply("c", &vote("d", false)).unwrap();
+        assert_eq!(set.authorities, vec!["a", "b", "c"]);
+        assert!(set.votes["e"].is_empty());
+    }
+
+    #[test]
+    fn test_votes_that_change_nothing_are_invalid() {
+        let mut set = AuthoritySet::new(vec!["a".into()]);
+
+        assert!(set.apply("a", &vote("a", true)).is_err());
+        assert!(set.apply("a", &vote("b", false)).is_err());
+        // The last authority cannot be removed
+        assert!(set.apply("a", &vote("a", false)).is_err());
+    }
+}
@@ -1,12 +1,49 @@
 use crate::blockchain::block::Block;
-use crate::crypto::hash::calculate_hash;
+use crate::blockchain::header::MAX_TARGET;
+use crate::consensus::engine::{ConsensusEngine, Sealer};
+use crate::consensus::miner::{CancellationToken, Miner};
+use crate::consensus::validator::ValidationError;
 
+/// Default number of blocks between difficulty retargets.
+pub const DEFAULT_RETARGET_INTERVAL: u64 = 10;
+/// Default number of seconds the network aims to take per block.
+pub const DEFAULT_TARGET_BLOCK_TIME: u64 = 60;
+/// Default limit on how far a single retarget can move the target, as a factor either way.
+pub const DEFAULT_MAX_RETARGET_FACTOR: u64 = 4;
+
+/// Proof-of-Work rules: the initial target, and how the target is adjusted to keep block
+/// production at a steady pace as hash power joins and leaves.
+#[derive(Debug, Clone, PartialEq)]
 pub struct ProofOfWork {
-    pub difficulty: usize, // Difficulty level, represented by the number of leading zeros required in the hash
+    pub difficulty: usize, // Initial difficulty level, represented by the number of leading zeros required in the hash
+    /// Number of blocks between retargets. Retargeting is disabled below 2.
+    pub retarget_interval: u64,
+    /// Number of seconds the network aims to take per block.
+    pub target_block_time: u64,
+    /// The most a single retarget can multiply or divide the target by.
+    pub max_retarget_factor: u64,
+}
+
+impl Default for ProofOfWork {
+    fn default() -> Self {
+        Self::new(0)
+    }
 }
 
 impl ProofOfWork {
-    /// Mines a new block by finding a valid nonce.
+    /// Creates Proof-of-Work rules starting at the given difficulty, with the default
+    /// retargeting parameters.
+    pub fn new(difficulty: usize) -> Self {
+        ProofOfWork {
+            difficulty,
+            retarget_interval: DEFAULT_RETARGET_INTERVAL,
+            target_block_time: DEFAULT_TARGET_BLOCK_TIME,
+            max_retarget_factor: DEFAULT_MAX_RETARGET_FACTOR,
+        }
+    }
+
+    /// Mines a block by finding a nonce whose block hash meets the target in its header, on the
+    /// current thread. Use a `Miner` to mine with several threads or to be able to cancel.
     ///
     /// # Arguments
     /// * `block` - A mutable reference to the block being mined.
@@ -14,33 +51,207 @@ impl ProofOfWork {
     /// # Returns
     /// * `bool` - Returns `true` when a valid nonce is found and the block is mined.
     pub fn mine_block(&self, block: &mut Block) -> bool {
-        let target = Self::difficulty_target(self.difficulty);
-        while !Self::is_valid_hash(&block.calculate_hash(), &target) {
-            block.nonce += 1; // Increment the nonce to try a new hash
+        Miner::new(1).mine(block, &CancellationToken::new()).found
+    }
+
+    /// Returns the target blocks must meet before the first retarget.
+    pub fn initial_target(&self) -> [u8; 32] {
+        Self::difficulty_target(self.difficulty)
+    }
+
+    /// Calculates the target the next block must carry, given the chain it extends.
+    ///
+    /// The target only changes at heights that are a multiple of the retarget interval. It is
+    /// then scaled by how long the last interval's blocks actually took compared to the target
+    /// block time, limited to the maximum retarget factor either way and never easier than
+    /// `MAX_TARGET`.
+    ///
+    /// # Arguments
+    /// * `chain` - The blocks from genesis up to and including the new block's parent.
+    ///
+    /// # Returns
+    /// * `[u8; 32]` - The target the next block must carry.
+    pub fn next_target(&self, chain: &[Block]) -> [u8; 32] {
+        let parent = &chain.last().expect("Chain has a genesis block").header;
+        let height = parent.height + 1;
+        if self.retarget_interval < 2 || !height.is_multiple_of(self.retarget_interval) {
+            return parent.target;
         }
-        true
+
+        // The interval's blocks span `retarget_interval - 1` block times
+        let first = &chain[(height - self.retarget_interval) as usize].header;
+        let expected_timespan = self
+            .target_block_time
+            .saturating_mul(self.retarget_interval - 1);
+        let factor = self.max_retarget_factor.max(1);
+        let actual_timespan = parent
+            .timestamp
+            .saturating_sub(first.timestamp)
+            .clamp(
+                expected_timespan / factor,
+                expected_timespan.saturating_mul(factor),
+            )
+            .max(1);
+
+        retarget(&parent.target, actual_timespan, expected_timespan.max(1))
     }
 
-    /// Creates the difficulty target based on the current difficulty level.
+    /// Checks if a hash meets a target, comparing both as 256-bit numbers.
     ///
     /// # Arguments
-    /// * `difficulty` - The number of leading zeros required in the hash.
+    /// * `hash` - The hex-encoded hash of the block.
+    /// * `target` - The target the hash must not exceed.
     ///
     /// # Returns
-    /// * `String` - A string representing the target (e.g., "0000...").
-    fn difficulty_target(difficulty: usize) -> String {
-        "0".repeat(difficulty) + &"f".repeat(64 - difficulty) // For simplicity, assume SHA-256 hash
+    /// * `bool` - Returns `true` if the hash is valid (i.e., no greater than the target).
+    pub fn meets_target(hash: &str, target: &[u8; 32]) -> bool {
+        let mut hash_bytes = [0; 32];
+        if hex::decode_to_slice(hash, &mut hash_bytes).is_err() {
+            return false;
+        }
+        // Big-endian byte arrays compare the same way as the numbers they encode
+        hash_bytes <= *target
+    }
+
+    /// Estimates the number of hashes needed to meet a target, `2^256 / (target + 1)`.
+    ///
+    /// Only the most significant 128 bits of the target are used, which keeps the result in a
+    /// `u128` while staying exact enough to compare branches. Every target counts as at
+    /// least one hash.
+    pub fn target_work(target: &[u8; 32]) -> u128 {
+        let high = u128::from_be_bytes(target[..16].try_into().expect("16-byte half"));
+        if high == u128::MAX {
+            return 1;
+        }
+        // 2^128 / (high + 1), without overflowing
+        ((u128::MAX - high) / (high + 1)).saturating_add(1)
     }
 
-    /// Checks if the hash meets the difficulty target.
+    /// Creates the difficulty target based on a difficulty level.
     ///
     /// # Arguments
-    /// * `hash` - The hash of the block.
-    /// * `target` - The difficulty target.
+    /// * `difficulty` - The number of leading zeros required in the hash.
     ///
     /// # Returns
-    /// * `bool` - Returns `true` if the hash is valid (i.e., has the required number of leading zeros).
-    fn is_valid_hash(hash: &str, target: &str) -> bool {
-        hash <= target
+    /// * `[u8; 32]` - The 256-bit target, whose hex form is e.g. "0000ffff...".
+    pub fn difficulty_target(difficulty: usize) -> [u8; 32] {
+        let difficulty = difficulty.min(64); // A SHA-256 hash has 64 hex digits
+        let target = "0".repeat(difficulty) + &"f".repeat(64 - difficulty);
+        let mut bytes = [0; 32];
+        hex::decode_to_slice(target, &mut bytes).expect("Target is 64 hex digits");
+        bytes
+    }
+}
+
+impl ConsensusEngine for ProofOfWork {
+    fn initial_target(&self) -> [u8; 32] {
+        ProofOfWork::initial_target(self)
+    }
+
+    fn next_target(&self, chain: &[Block]) -> [u8; 32] {
+        ProofOfWork::next_target(self, chain)
+    }
+
+    fn block_work(&self, block: &Block) -> u128 {
+        ProofOfWork::target_work(&block.header.target)
+    }
+
+    fn verify_seal(&self, _chain: &[Block], block: &Block) -> Result<(), ValidationError> {
+        if ProofOfWork::meets_target(&block.hash, &block.header.target) {
+            Ok(())
+        } else {
+            Err(ValidationError::InsufficientWork)
+        }
+    }
+
+    fn seal(&self, block: &mut Block, sealer: &Sealer<'_>) -> bool {
+        sealer.miner.mine(block, sealer.cancel).found
+    }
+}
+
+/// Scales a 256-bit target by `numerator / denominator`, saturating at `MAX_TARGET`.
+fn retarget(target: &[u8; 32], numerator: u64, denominator: u64) -> [u8; 32] {
+    // Work in 64-bit limbs, most significant first, with an extra limb for overflow
+    let mut limbs = [0u64; 5];
+    for (limb, chunk) in limbs[1..].iter_mut().zip(target.chunks(8)) {
+        *limb = u64::from_be_bytes(chunk.try_into().expect("8-byte chunk"));
+    }
+
+    let mut carry: u128 = 0;
+    for limb in limbs.iter_mut().rev() {
+        let product = *limb as u128 * numerator as u128 + carry;
+        *limb = product as u64;
+        carry = product >> 64;
+    }
+
+    let mut remainder: u128 = 0;
+    for limb in limbs.iter_mut() {
+        let dividend = (remainder << 64) | *limb as u128;
+        *limb = (dividend / denominator as u128) as u64;
+        remainder = dividend % denominator as u128;
+    }
+
+    if limbs[0] != 0 {
+        return MAX_TARGET;
+    }
+    let mut scaled = [0; 32];
+    for (chunk, limb) in scaled.chunks_mut(8).zip(&limbs[1..]) {
+        chunk.copy_from_slice(&limb.to_be_bytes());
+    }
+    scaled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_meets_target_compares_numerically() {
+        let target = ProofOfWork::difficulty_target(2);
+        assert!(ProofOfWork::meets_target(&"00ff".repeat(16), &target));
+        assert!(ProofOfWork::meets_target(
+            &("00".to_string() + &"f".repeat(62)),
+            &target
+        ));
+        assert!(!ProofOfWork::meets_target(
+            &("01".to_string() + &"0".repeat(62)),
+            &target
+        ));
+        assert!(!ProofOfWork::meets_target("not a hash", &target));
+    }
+
+    #[test]
+    fn test_target_work_grows_as_target_shrinks() {
+        assert_eq!(ProofOfWork::target_work(&MAX_TARGET), 1);
+        assert_eq!(
+            ProofOfWork::target_work(&ProofOfWork::difficulty_target(1)),
+            16
+        );
+        assert_eq!(
+            ProofOfWork::target_work(&ProofOfWork::difficulty_target(4)),
+            1 << 16
+        );
+        assert_eq!(
+            ProofOfWork::target_work(&ProofOfWork::difficulty_target(64)),
+            u128::MAX
+        );
+    }
+
+    #[test]
+    fn test_retarget_scales_256_bit_targets() {
+        let target = ProofOfWork::difficulty_target(4);
+
+        let harder = retarget(&target, 1, 4);
+        assert_eq!(hex::encode(harder), "00003".to_string() + &"f".repeat(59));
+
+        let easier = retarget(&target, 2, 1);
+        assert_eq!(
+            hex::encode(easier),
+            "0001".to_string() + &"f".repeat(59) + "e"
+        );
+
+        // Easing past the largest 256-bit number saturates instead of wrapping
+        assert_eq!(retarget(&MAX_TARGET, 4, 1), MAX_TARGET);
+        assert_eq!(retarget(&target, 3, 3), target);
     }
 }
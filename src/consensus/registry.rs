@@ -0,0 +1,311 @@
+use crate::consensus::dispute::{
+    DEFAULT_CHALLENGE_PERIOD, DEFAULT_DISPUTE_BOND, DEFAULT_VOTING_PERIOD,
+};
+use crate::utils::encoding::CanonicalEncoder;
+use ed25519_dalek::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Default share of a verifier's bond slashed when a dispute against them is upheld, in
+/// basis points.
+pub const DEFAULT_SLASH_BPS: u64 = 5_000;
+
+/// Default reputation a verifier loses when a dispute against them is upheld.
+pub const DEFAULT_DISPUTE_PENALTY: i64 = 10;
+
+/// Reputation a verifier gains for each attestation the ledger accepts.
+pub const REPUTATION_PER_ATTESTATION: i64 = 1;
+
+/// Returns the account that holds a verifier's bond. No key can sign for it, so its lots can
+/// only leave when the verifier unbonds or is slashed.
+pub fn bond_account(verifier: &str) -> String {
+    format!("bond:{}", verifier)
+}
+
+/// A change to the verifier registry, recorded on the chain by a registry transaction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum RegistryAction {
+    /// A governor admits a verifier to the registry.
+    Admit { verifier: PublicKey },
+    /// A governor withdraws a verifier's admission. Any bond stays with the verifier, who
+    /// remains registered while it covers the minimum bond.
+    Remove { verifier: PublicKey },
+    /// The sender bonds the transaction's amount, spending its inputs like a transfer, and is
+    /// registered once its bond covers the minimum.
+    Bond,
+    /// The sender takes back their whole bond, once no mint they attested can still be
+    /// disputed.
+    Unbond,
+}
+
+impl RegistryAction {
+    /// Appends the action to a canonical encoding.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        match self {
+            RegistryAction::Admit { verifier } => {
+                encoder.u8(0).bytes(verifier.as_bytes());
+            }
+            RegistryAction::Remove { verifier } => {
+                encoder.u8(1).bytes(verifier.as_bytes());
+            }
+            RegistryAction::Bond => {
+                encoder.u8(2);
+            }
+            RegistryAction::Unbond => {
+                encoder.u8(3);
+            }
+        }
+    }
+}
+
+/// Reasons a registry action can be rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryError {
+    /// Only governors may admit or remove verifiers.
+    NotGovernor(String),
+    /// The network does not accept bonds.
+    BondingDisabled,
+    /// The verifier is not in the registry.
+    UnknownVerifier(String),
+    /// The verifier has already been admitted.
+    AlreadyAdmitted(String),
+    /// The verifier has no admission to withdraw.
+    NotAdmitted(String),
+    /// The sender has no bond to take back.
+    NoBond(String),
+    /// The verifier attested a mint that is still open to dispute or under dispute, so their
+    /// bond cannot be taken back yet.
+    BondAtStake(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::NotGovernor(sender) => write!(f, "{} is not a governor", sender),
+            RegistryError::BondingDisabled => write!(f, "verifier bonds are not accepted"),
+            RegistryError::UnknownVerifier(verifier) => {
+                write!(f, "verifier {} is not registered", verifier)
+            }
+            RegistryError::AlreadyAdmitted(verifier) => {
+                write!(f, "verifier {} is already admitted", verifier)
+            }
+            RegistryError::NotAdmitted(verifier) => {
+                write!(f, "verifier {} is not admitted", verifier)
+            }
+            RegistryError::NoBond(verifier) => write!(f, "verifier {} has no bond", verifier),
+            RegistryError::BondAtStake(verifier) => {
+                write!(
+                    f,
+                    "verifier {} attested mints that can still be disputed",
+                    verifier
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+/// Who may attest work, and what happens to verifiers whose attestations are disputed.
+///
+/// The registry is only enforced once it has governors or a minimum bond. Without either,
+/// anyone may attest work, as on a development network. The governors also sit on the panel
+/// that resolves disputes over work mints, so mints can only be disputed once there are
+/// governors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifierRules {
+    /// Addresses (hex-encoded public keys) of the governors who admit verifiers and vote on
+    /// disputes.
+    pub governors: Vec<String>,
+    /// Bond that registers a verifier without an admission. Bonds are not accepted if unset.
+    pub min_bond: Option<u64>,
+    /// Share of a verifier's bond slashed when a dispute against them is upheld, in basis
+    /// points.
+    pub slash_bps: u64,
+    /// Reputation a verifier loses when a dispute against them is upheld.
+    pub dispute_penalty: i64,
+    /// Number of blocks after a work mint during which it can be disputed, and its lots
+    /// cannot be spent.
+    pub challenge_period: u64,
+    /// Smallest bond a disputer must put up.
+    pub dispute_bond: u64,
+    /// Number of blocks after a dispute is filed within which the panel must decide it.
+    pub voting_period: u64,
+}
+
+impl Default for VerifierRules {
+    fn default() -> Self {
+        VerifierRules {
+            governors: Vec::new(),
+            min_bond: None,
+            slash_bps: DEFAULT_SLASH_BPS,
+            dispute_penalty: DEFAULT_DISPUTE_PENALTY,
+            challenge_period: DEFAULT_CHALLENGE_PERIOD,
+            dispute_bond: DEFAULT_DISPUTE_BOND,
+            voting_period: DEFAULT_VOTING_PERIOD,
+        }
+    }
+}
+
+impl VerifierRules {
+    /// Checks whether only registered verifiers may attest work.
+    pub fn is_enforced(&self) -> bool {
+        !self.governors.is_empty() || self.min_bond.is_some()
+    }
+
+    /// Checks whether the given address (hex-encoded public key) belongs to a governor.
+    pub fn is_governor(&self, key: &str) -> bool {
+        self.governors.iter().any(|governor| governor == key)
+    }
+
+    /// Checks whether a verifier with the given record and bond may attest work.
+    pub fn is_active(&self, record: &VerifierRecord, bond: u64) -> bool {
+        record.admitted || self.min_bond.is_some_and(|min_bond| bond >= min_bond)
+    }
+
+    /// Returns the height from which tokens minted in the block at `height` can be spent, if
+    /// they must first wait out the challenge window.
+    pub fn pending_until(&self, height: u64) -> Option<u64> {
+        (self.challenge_period > 0).then(|| height.saturating_add(self.challenge_period))
+    }
+
+    /// Returns how much of a bond is slashed for an upheld dispute, rounded down.
+    pub fn slash_amount(&self, bond: u64) -> u64 {
+        (bond as u128 * self.slash_bps.min(10_000) as u128 / 10_000) as u64
+    }
+}
+
+/// What the registry knows about a verifier. The verifier's bond is held as lots owned by
+/// their `bond_account`, so it expires and decays like any other tokens.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct VerifierRecord {
+    /// Whether a governor has admitted the verifier.
+    pub admitted: bool,
+    /// Grows with every accepted attestation and falls with every upheld dispute.
+    pub reputation: i64,
+    /// Number of the verifier's attestations the ledger has accepted.
+    pub accepted_attestations: u64,
+    /// Number of disputes against the verifier that were upheld.
+    pub upheld_disputes: u64,
+}
+
+impl VerifierRecord {
+    /// Credits the verifier with an accepted attestation.
+    pub fn record_attestation(&mut self) {
+        self.accepted_attestations += 1;
+        self.reputation = self.reputation.saturating_add(REPUTATION_PER_ATTESTATION);
+    }
+
+    /// Penalises the verifier for an upheld dispute.
+    pub fn record_upheld_dispute(&mut self, penalty: i64) {
+        self.upheld_disputes += 1;
+        self.reputation = self.reputation.saturating_sub(penalty);
+    }
+
+    /// Appends the record to a canonical encoding.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        encoder
+            .u8(self.admitted as u8)
+            .u64(self.reputation as u64)
+            .u64(self.accepted_attestations)
+            .u64(self.upheld_disputes);
+    }
+}
+
+/// A verifier's standing in the registry, as reported to clients.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VerifierStatus {
+    /// Address (hex-encoded public key) of the verifier.
+    pub verifier: String,
+    /// The verifier's record.
+    pub record: VerifierRecord,
+    /// Live value of the verifier's bond.
+    pub bond: u64,
+    /// Whether the verifier may attest work.
+    pub active: bool,
+}
+
+/// Builds each registered verifier's status from their records and bonds.
+pub fn verifier_statuses(
+    rules: &VerifierRules,
+    records: &BTreeMap<String, VerifierRecord>,
+    bond: impl Fn(&str) -> u64,
+) -> Vec<VerifierStatus> {
+    records
+        .iter()
+        .map(|(verifier, record)| {
+            let bond = bond(verifier);
+            VerifierStatus {
+                verifier: verifier.clone(),
+                record: record.clone(),
+                bond,
+                active: rules.is_active(record, bond),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_is_open_until_configured() {
+        let rules = VerifierRules::default();
+        assert!(!rules.is_enforced());
+
+        let governed = VerifierRules {
+            governors: vec!["governor".to_string()],
+            ..VerifierRules::default()
+        };
+        assert!(governed.is_enforced());
+        assert!(governed.is_governor("governor"));
+        assert!(!governed.is_governor("verifier"));
+
+        let bonded = VerifierRules {
+            min_bond: Some(100),
+            ..VerifierRules::default()
+        };
+        assert!(bonded.is_enforced());
+    }
+
+    #[test]
+    fn test_verifiers_are_active_when_admitted_or_bonded() {
+        let rules = VerifierRules {
+            min_bond: Some(100),
+            ..VerifierRules::default()
+        };
+        let mut record = VerifierRecord::default();
+        assert!(!rules.is_active(&record, 99));
+        assert!(rules.is_active(&record, 100));
+        record.admitted = true;
+        assert!(rules.is_active(&record, 0));
+
+        let governed = VerifierRules {
+            governors: vec!["governor".to_string()],
+            ..VerifierRules::default()
+        };
+        assert!(!governed.is_active(&VerifierRecord::default(), u64::MAX));
+    }
+
+    #[test]
+    fn test_reputation_rises_with_attestations_and_falls_with_disputes() {
+        let mut record = VerifierRecord::default();
+        record.record_attestation();
+        record.record_attestation();
+        assert_eq!(record.reputation, 2);
+        record.record_upheld_dispute(DEFAULT_DISPUTE_PENALTY);
+        assert_eq!(record.reputation, 2 - DEFAULT_DISPUTE_PENALTY);
+        assert_eq!(record.accepted_attestations, 2);
+        assert_eq!(record.upheld_disputes, 1);
+
+        let rules = VerifierRules::default();
+        assert_eq!(rules.slash_amount(101), 50);
+        let everything = VerifierRules {
+            slash_bps: 20_000,
+            ..rules
+        };
+        assert_eq!(everything.slash_amount(101), 101);
+    }
+}
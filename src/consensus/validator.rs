@@ -1,11 +1,200 @@
+use crate::blockchain::block::Block;
+use crate::blockchain::state::LedgerState;
 use crate::blockchain::transaction::Transaction;
-use crate::crypto::signatures::verify_signature;
-use crate::wallet::Wallet;
+use crate::consensus::dispute::DisputeError;
+use crate::consensus::registry::{bond_account, RegistryError};
+use crate::task::marketplace::TaskError;
+use crate::token::supply::IssuanceError;
+use ed25519_dalek::PublicKey;
+use std::collections::HashSet;
+use std::fmt;
+
+/// Reasons a transaction or block can be rejected by the consensus rules.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidationError {
+    /// The transaction is malformed, unsigned or carries an invalid signature or attestation.
+    InvalidTransaction(String),
+    /// The work in a claim has already been rewarded.
+    WorkAlreadyClaimed(String),
+    /// A transfer does not spend any lots.
+    NoInputs,
+    /// A lot is listed more than once in the same transfer.
+    DuplicateInput(String),
+    /// A lot does not exist or has already been spent.
+    MissingInput(String),
+    /// A lot belongs to someone other than the sender.
+    UnownedInput(String),
+    /// A lot has expired.
+    ExpiredInput(String),
+    /// A lot was minted for work that can still be disputed, or is under dispute.
+    PendingInput(String),
+    /// A lot is held in escrow, and the transfer spending it does not meet its spend
+    /// condition.
+    ConditionNotMet(String),
+    /// The lots spent are worth less than the amount sent plus the fee.
+    InsufficientFunds { available: u64, required: u64 },
+    /// The recipient would end up holding more than the holding cap allows.
+    HoldingCapExceeded {
+        account: String,
+        balance: u64,
+        cap: u64,
+    },
+    /// A transaction was signed for a different network.
+    WrongChain { expected: u32, found: u32 },
+    /// A transaction's nonce is not the sender's next sequence number: it has been used
+    /// already, or transactions before it are still missing.
+    InvalidNonce { expected: u64, found: u64 },
+    /// A task action cannot be taken on the task it names.
+    InvalidTask(TaskError),
+    /// Work was attested by a verifier the registry does not allow to attest.
+    UnregisteredVerifier(String),
+    /// A change to the verifier registry was rejected.
+    InvalidRegistryAction(RegistryError),
+    /// A dispute, or a vote on one, was rejected.
+    InvalidDispute(DisputeError),
+    /// Tokens cannot be minted without exceeding an issuance cap.
+    IssuanceCapExceeded(IssuanceError),
+    /// A block's hash does not meet its proof-of-work target.
+    InsufficientWork,
+    /// A block that must be sealed by an authority carries no seal.
+    MissingSeal,
+    /// A block's seal is not a valid signature over its hash.
+    InvalidSeal,
+    /// A block was sealed by someone outside the authority set.
+    UnknownAuthority(String),
+    /// An authority sealed a block before its turn, as given by the earliest timestamp it may
+    /// seal at.
+    OutOfTurn { authority: String, earliest: u64 },
+    /// A block carries a vote that cannot change the authority set.
+    InvalidVote(String),
+    /// A finality vote was cast by someone outside the validator set.
+    UnknownValidator(String),
+    /// A finality vote is not validly signed by its validator.
+    InvalidVoteSignature,
+    /// A validator voted for two different blocks at the same height, round and step.
+    Equivocation(String),
+    /// A finality vote is for a height too far past the latest finalized block to be held.
+    VoteTooFarAhead(u64),
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::InvalidTransaction(id) => {
+                write!(f, "transaction {} is invalid or improperly signed", id)
+            }
+            ValidationError::WorkAlreadyClaimed(work_hash) => {
+                write!(f, "work {} has already been claimed", work_hash)
+            }
+            ValidationError::NoInputs => write!(f, "transfer does not spend any lots"),
+            ValidationError::DuplicateInput(lot) => {
+                write!(f, "lot {} is spent more than once", lot)
+            }
+            ValidationError::MissingInput(lot) => {
+                write!(f, "lot {} does not exist or is already spent", lot)
+            }
+            ValidationError::UnownedInput(lot) => {
+                write!(f, "lot {} is not owned by the sender", lot)
+            }
+            ValidationError::ExpiredInput(lot) => write!(f, "lot {} has expired", lot),
+            ValidationError::PendingInput(lot) => {
+                write!(f, "lot {} is pending until its mint is final", lot)
+            }
+            ValidationError::ConditionNotMet(lot) => {
+                write!(f, "spend condition of lot {} is not met", lot)
+            }
+            ValidationError::InsufficientFunds {
+                available,
+                required,
+            } => write!(
+                f,
+                "inputs are worth {} but {} is required",
+                available, required
+            ),
+            ValidationError::HoldingCapExceeded {
+                account,
+                balance,
+                cap,
+            } => write!(
+                f,
+                "account {} would hold {}, over the holding cap of {}",
+                account, balance, cap
+            ),
+            ValidationError::WrongChain { expected, found } => write!(
+                f,
+                "transaction is for chain {} but this is chain {}",
+                found, expected
+            ),
+            ValidationError::InvalidNonce { expected, found } => {
+                write!(f, "nonce {} is out of order, expected {}", found, expected)
+            }
+            ValidationError::InvalidTask(err) => write!(f, "{}", err),
+            ValidationError::UnregisteredVerifier(verifier) => {
+                write!(f, "{} is not a registered verifier", verifier)
+            }
+            ValidationError::InvalidRegistryAction(err) => write!(f, "{}", err),
+            ValidationError::InvalidDispute(err) => write!(f, "{}", err),
+            ValidationError::IssuanceCapExceeded(err) => write!(f, "{}", err),
+            ValidationError::InsufficientWork => {
+                write!(f, "block hash does not meet its target")
+            }
+            ValidationError::MissingSeal => write!(f, "block is not sealed"),
+            ValidationError::InvalidSeal => write!(f, "block seal is not a valid signature"),
+            ValidationError::UnknownAuthority(authority) => {
+                write!(f, "{} is not an authority", authority)
+            }
+            ValidationError::OutOfTurn {
+                authority,
+                earliest,
+            } => write!(
+                f,
+                "authority {} may not seal a block before {}",
+                authority, earliest
+            ),
+            ValidationError::InvalidVote(authority) => {
+                write!(
+                    f,
+                    "vote on authority {} would not change the set",
+                    authority
+                )
+            }
+            ValidationError::UnknownValidator(validator) => {
+                write!(f, "{} is not a validator", validator)
+            }
+            ValidationError::InvalidVoteSignature => {
+                write!(f, "finality vote is not validly signed")
+            }
+            ValidationError::Equivocation(validator) => {
+                write!(f, "validator {} voted for conflicting blocks", validator)
+            }
+            ValidationError::VoteTooFarAhead(height) => {
+                write!(
+                    f,
+                    "finality vote for height {} is too far past the finalized block",
+                    height
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Limit on the live balance a single account may hold.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoldingCap {
+    /// Maximum live balance of any account, other than the commons account.
+    pub max_balance: u64,
+    /// Account that receives anything over the cap. Without one, transactions and mints that
+    /// would exceed the cap are rejected.
+    pub commons_account: Option<String>,
+}
 
 pub struct Validator;
 
 impl Validator {
     /// Validates a block by checking if all transactions are valid and if the block is properly formatted.
+    /// A block may not claim the same piece of work twice.
     ///
     /// # Arguments
     /// * `block` - A reference to the block to be validated.
@@ -13,10 +202,16 @@ impl Validator {
     /// # Returns
     /// * `bool` - Returns `true` if the block is valid, `false` otherwise.
     pub fn validate_block(block: &Block) -> bool {
+        let mut claimed_work = HashSet::new();
         for transaction in &block.transactions {
             if !Self::validate_transaction(transaction) {
                 return false;
             }
+            if let Some(claim) = transaction.work_claim() {
+                if !claimed_work.insert(claim.work_hash.as_str()) {
+                    return false;
+                }
+            }
         }
         true
     }
@@ -29,13 +224,206 @@ impl Validator {
     /// # Returns
     /// * `bool` - Returns `true` if the transaction is valid, `false` otherwise.
     pub fn validate_transaction(transaction: &Transaction) -> bool {
-        let public_key = Wallet::get_public_key_from_address(&transaction.from); // Fixed sender -> from
-
-        // Verify signature and hash (convert hash to byte slice)
-        verify_signature(
-            &public_key,
-            transaction.calculate_hash().as_bytes(), // Fixed by converting String to &[u8]
-            &transaction.signature,
-        )
+        transaction.validate()
+    }
+
+    /// Validates a block against the ledger state it would be applied to, ensuring every
+    /// transfer spends existing, unexpired lots and that no lot is spent twice.
+    ///
+    /// # Arguments
+    /// * `block` - A reference to the block to be validated.
+    /// * `state` - The ledger state at the block's parent.
+    ///
+    /// # Returns
+    /// * `Result<(), ValidationError>` - Why the block cannot be applied, if it cannot.
+    pub fn validate_block_against_state(
+        block: &Block,
+        state: &LedgerState,
+    ) -> Result<(), ValidationError> {
+        state.clone().apply_block(block)
+    }
+
+    /// Validates a transaction that has not been included in a block yet, as if it were
+    /// included in the block at `height` with the given timestamp.
+    ///
+    /// # Arguments
+    /// * `transaction` - The transaction to be validated.
+    /// * `state` - The current ledger state.
+    /// * `height` - Height of the block the transaction would be included in.
+    /// * `timestamp` - Timestamp to judge expiry against.
+    ///
+    /// # Returns
+    /// * `Result<(), ValidationError>` - Why the transaction would be rejected, if it would.
+    pub fn validate_pending_transaction(
+        transaction: &Transaction,
+        state: &LedgerState,
+        height: u64,
+        timestamp: u64,
+    ) -> Result<(), ValidationError> {
+        if !Self::validate_transaction(transaction) {
+            return Err(ValidationError::InvalidTransaction(transaction.id.clone()));
+        }
+        state
+            .clone()
+            .apply_transaction(transaction, height, timestamp)
+    }
+
+    /// Validates the lots a transfer, deposit or fee payment spends against the current ledger
+    /// state.
+    ///
+    /// Every input must be an unspent lot owned by the sender that has not expired at
+    /// `timestamp` and is not pending at `height`, no lot may be listed twice, and the inputs'
+    /// value at `height` under the expiry policy must cover the amount sent plus the fee.
+    ///
+    /// A lot held in escrow can be spent by its owner once the transfer's witness for it
+    /// meets every requirement of its spend condition at `height` and `timestamp`, or by the
+    /// account that paid it in once its refund timelock is reached.
+    ///
+    /// # Arguments
+    /// * `transaction` - The transfer to be validated.
+    /// * `state` - The ledger state the transfer would be applied to.
+    /// * `height` - Height of the block including the transfer.
+    /// * `timestamp` - Timestamp of the block including the transfer.
+    ///
+    /// # Returns
+    /// * `Result<(), ValidationError>` - Why the inputs are invalid, if they are.
+    pub fn validate_transfer(
+        transaction: &Transaction,
+        state: &LedgerState,
+        height: u64,
+        timestamp: u64,
+    ) -> Result<(), ValidationError> {
+        if transaction.inputs.is_empty() {
+            return Err(ValidationError::NoInputs);
+        }
+
+        let sender = transaction.sender_address();
+        let mut seen_inputs = HashSet::new();
+        let mut input_total: u64 = 0;
+        for input in &transaction.inputs {
+            if !seen_inputs.insert(input.as_str()) {
+                return Err(ValidationError::DuplicateInput(input.clone()));
+            }
+            let Some(lot) = state.get_lot(input) else {
+                return Err(ValidationError::MissingInput(input.clone()));
+            };
+            match &lot.lock {
+                None if lot.owner != sender => {
+                    return Err(ValidationError::UnownedInput(input.clone()));
+                }
+                None => {}
+                Some(lock) => {
+                    if lot.owner != sender && lock.payer != sender {
+                        return Err(ValidationError::UnownedInput(input.clone()));
+                    }
+                    let released = lot.owner == sender
+                        && lock.can_release(input, transaction.witness(input), height, timestamp);
+                    let refunded = lock.payer == sender && lock.can_refund(height, timestamp);
+                    if !released && !refunded {
+                        return Err(ValidationError::ConditionNotMet(input.clone()));
+                    }
+                }
+            }
+            if lot.has_expired(timestamp) {
+                return Err(ValidationError::ExpiredInput(input.clone()));
+            }
+            if lot.is_pending(height) {
+                return Err(ValidationError::PendingInput(input.clone()));
+            }
+            input_total = input_total.saturating_add(state.lot_value(lot, height));
+        }
+
+        // Any excess over the amount and fee is returned to the sender as change
+        let required = transaction.spent_amount();
+        if input_total < required {
+            return Err(ValidationError::InsufficientFunds {
+                available: input_total,
+                required,
+            });
+        }
+        Ok(())
+    }
+
+    /// Validates a work claim against the verifier registry: once the registry is enforced,
+    /// every attestation must come from a verifier who is admitted or holds the minimum bond.
+    ///
+    /// # Arguments
+    /// * `transaction` - The work-claim transaction.
+    /// * `state` - The ledger state the claim would be applied to.
+    /// * `height` - Height of the block including the claim, at which bonds are valued.
+    /// * `timestamp` - Timestamp of the block including the claim.
+    ///
+    /// # Returns
+    /// * `Result<(), ValidationError>` - The first unregistered verifier, if any.
+    pub fn validate_work_claim(
+        transaction: &Transaction,
+        state: &LedgerState,
+        height: u64,
+        timestamp: u64,
+    ) -> Result<(), ValidationError> {
+        let Some(claim) = transaction.work_claim() else {
+            return Err(ValidationError::InvalidTransaction(transaction.id.clone()));
+        };
+        for attestation in &claim.attestations {
+            Self::check_verifier(state, &attestation.verifier, height, timestamp)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the verifier registry allows a verifier to attest work in the block at
+    /// `height` with the given timestamp. Anyone may attest while the registry is not enforced.
+    pub fn check_verifier(
+        state: &LedgerState,
+        verifier: &PublicKey,
+        height: u64,
+        timestamp: u64,
+    ) -> Result<(), ValidationError> {
+        if !state.verifier_rules.is_enforced() {
+            return Ok(());
+        }
+        let key = hex::encode(verifier.as_bytes());
+        let bond = state.get_balance(&bond_account(&key), height, timestamp);
+        match state.verifiers.get(&key) {
+            Some(record) if state.verifier_rules.is_active(record, bond) => Ok(()),
+            _ => Err(ValidationError::UnregisteredVerifier(key)),
+        }
+    }
+
+    /// Checks how much of an incoming amount an account may keep under the holding cap.
+    ///
+    /// # Arguments
+    /// * `cap` - The holding cap in force, if any.
+    /// * `account` - The account receiving the tokens.
+    /// * `balance` - The account's live balance before receiving them.
+    /// * `incoming` - The amount being received.
+    ///
+    /// # Returns
+    /// * `Result<u64, ValidationError>` - The amount the account may keep. Anything above it
+    ///   goes to the commons account, or the transaction is rejected if there is none.
+    pub fn check_holding_cap(
+        cap: Option<&HoldingCap>,
+        account: &str,
+        balance: u64,
+        incoming: u64,
+    ) -> Result<u64, ValidationError> {
+        let Some(cap) = cap else {
+            return Ok(incoming);
+        };
+        if cap.commons_account.as_deref() == Some(account) {
+            return Ok(incoming);
+        }
+
+        let new_balance = balance.saturating_add(incoming);
+        if new_balance <= cap.max_balance {
+            return Ok(incoming);
+        }
+        match cap.commons_account {
+            Some(_) => Ok(cap.max_balance.saturating_sub(balance)),
+            None => Err(ValidationError::HoldingCapExceeded {
+                account: account.to_string(),
+                balance: new_balance,
+                cap: cap.max_balance,
+            }),
+        }
     }
 }
@@ -0,0 +1,428 @@
+use crate::blockchain::transaction::DEFAULT_CHAIN_ID;
+use ed25519_dalek::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the address format, carried as the first symbol of every address. Addresses
+/// of this version encode a single public key.
+pub const ADDRESS_VERSION: u8 = 0;
+/// Version of addresses that encode the hash of a multisig account's policy.
+pub const MULTISIG_ADDRESS_VERSION: u8 = 1;
+
+/// Human-readable prefix of addresses on the main network.
+pub const MAIN_PREFIX: &str = "wt";
+/// Human-readable prefix of addresses on test networks.
+pub const TEST_PREFIX: &str = "twt";
+
+/// The 32 characters of the bech32 alphabet, indexed by the 5-bit value each encodes.
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+/// Constant the bech32m checksum is XORed with, which distinguishes it from bech32.
+const BECH32M_CONST: u32 = 0x2bc8_30a3;
+/// Number of 5-bit symbols in the checksum.
+const CHECKSUM_LENGTH: usize = 6;
+/// Longest string the bech32 checksum is guaranteed to catch errors in.
+const MAX_LENGTH: usize = 90;
+
+/// The networks an address can belong to, which decide its prefix.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Network {
+    /// The main Work Tokens network.
+    Main,
+    /// Any test network.
+    Test,
+}
+
+impl Network {
+    /// Returns the network a chain ID belongs to: the default chain ID is the main network,
+    /// and every other ID is a test network.
+    pub fn for_chain(chain_id: u32) -> Self {
+        if chain_id == DEFAULT_CHAIN_ID {
+            Network::Main
+        } else {
+            Network::Test
+        }
+    }
+
+    /// Returns the human-readable prefix of the network's addresses.
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Network::Main => MAIN_PREFIX,
+            Network::Test => TEST_PREFIX,
+        }
+    }
+}
+
+/// Reasons a string can fail to parse as an address.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AddressError {
+    /// The string is not valid bech32m: it is too long or too short, mixes upper and lower
+    /// case, or uses characters outside the alphabet.
+    InvalidEncoding,
+    /// The checksum does not match, so the address was mistyped or corrupted.
+    InvalidChecksum,
+    /// The prefix does not belong to any network.
+    UnknownPrefix(String),
+    /// The address uses a format version this node does not know.
+    UnsupportedVersion(u8),
+    /// The address does not encode a valid public key.
+    InvalidKey,
+}
+
+impl fmt::Display for AddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressError::InvalidEncoding => write!(f, "address is not valid bech32m"),
+            AddressError::InvalidChecksum => write!(f, "address checksum does not match"),
+            AddressError::UnknownPrefix(prefix) => {
+                write!(f, "address prefix {} is not a known network", prefix)
+            }
+            AddressError::UnsupportedVersion(version) => {
+                write!(f, "address version {} is not supported", version)
+            }
+            AddressError::InvalidKey => write!(f, "address does not encode a valid public key"),
+        }
+    }
+}
+
+impl std::error::Error for AddressError {}
+
+/// The kinds of account an address can identify.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AddressKind {
+    /// An account controlled by a single key.
+    Key,
+    /// A shared account controlled by a threshold of its members' keys.
+    Multisig,
+}
+
+impl AddressKind {
+    /// Returns the address version that encodes this kind of account.
+    pub fn version(&self) -> u8 {
+        match self {
+            AddressKind::Key => ADDRESS_VERSION,
+            AddressKind::Multisig => MULTISIG_ADDRESS_VERSION,
+        }
+    }
+}
+
+/// An account address: the account's public key, or the hash of a multisig account's
+/// policy, encoded as bech32m with the network's prefix, the address version and a
+/// checksum, so typos are caught before tokens are sent.
+///
+/// Addresses are written out in their encoded form, such as `wt1q...`, which is also how the
+/// ledger identifies the accounts that own token lots.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(try_from = "String", into = "String")]
+pub struct Address {
+    network: Network,
+    kind: AddressKind,
+    payload: [u8; 32],
+}
+
+impl Address {
+    /// Creates the address of a public key on the given network.
+    pub fn new(public_key: &PublicKey, network: Network) -> Self {
+        Address {
+            network,
+            kind: AddressKind::Key,
+            payload: public_key.to_bytes(),
+        }
+    }
+
+    /// Creates the address of a multisig account from the hash of its policy.
+    pub fn from_multisig_hash(policy_hash: [u8; 32], network: Network) -> Self {
+        Address {
+            network,
+            kind: AddressKind::Multisig,
+            payload: policy_hash,
+        }
+    }
+
+    /// Creates the address of a hex-encoded public key on the given network.
+    pub fn from_hex_key(public_key: &str, network: Network) -> Result<Self, AddressError> {
+        let bytes = hex::decode(public_key).map_err(|_| AddressError::InvalidKey)?;
+        let key = PublicKey::from_bytes(&bytes).map_err(|_| AddressError::InvalidKey)?;
+        Ok(Self::new(&key, network))
+    }
+
+    /// Returns the network the address belongs to.
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    /// Returns the kind of account the address identifies.
+    pub fn kind(&self) -> AddressKind {
+        self.kind
+    }
+
+    /// Returns the public key the address was derived from, unless it is a multisig address.
+    pub fn public_key(&self) -> Option<PublicKey> {
+        match self.kind {
+            AddressKind::Key => {
+                Some(PublicKey::from_bytes(&self.payload).expect("Key addresses hold valid keys"))
+            }
+            AddressKind::Multisig => None,
+        }
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut data = vec![self.kind.version()];
+        data.extend(convert_bits(&self.payload, 8, 5, true).expect("Bytes regroup"));
+        write!(f, "{}", bech32m_encode(self.network.prefix(), &data))
+    }
+}
+
+impl FromStr for Address {
+    type Err = AddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (prefix, data) = bech32m_decode(s)?;
+        let network = match prefix.as_str() {
+            MAIN_PREFIX => Network::Main,
+            TEST_PREFIX => Network::Test,
+            _ => return Err(AddressError::UnknownPrefix(prefix)),
+        };
+        let (&version, payload) = data.split_first().ok_or(AddressError::InvalidEncoding)?;
+        let kind = match version {
+            ADDRESS_VERSION => AddressKind::Key,
+            MULTISIG_ADDRESS_VERSION => AddressKind::Multisig,
+            _ => return Err(AddressError::UnsupportedVersion(version)),
+        };
+        let payload: [u8; 32] = convert_bits(payload, 5, 8, false)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(AddressError::InvalidEncoding)?;
+        if kind == AddressKind::Key {
+            PublicKey::from_bytes(&payload).map_err(|_| AddressError::InvalidKey)?;
+        }
+        Ok(Address {
+            network,
+            kind,
+            payload,
+        })
+    }
+}
+
+impl TryFrom<String> for Address {
+    type Error = AddressError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Address> for String {
+    fn from(address: Address) -> Self {
+        address.to_string()
+    }
+}
+
+/// Computes the bech32 checksum polynomial over a sequence of 5-bit values.
+fn polymod(values: &[u8]) -> u32 {
+    const GENERATOR: [u32; 5] = [
+        0x3b6a_57b2,
+        0x2650_8e6d,
+        0x1ea1_19fa,
+        0x3d42_33dd,
+        0x2a14_62b3,
+    ];
+    let mut checksum: u32 = 1;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x01ff_ffff) << 5) ^ *value as u32;
+        for (bit, generator) in GENERATOR.iter().enumerate() {
+            if (top >> bit) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// Expands the prefix into the values the checksum covers, so the checksum commits to it.
+fn expand_prefix(prefix: &str) -> Vec<u8> {
+    let bytes = prefix.as_bytes();
+    let mut values: Vec<u8> = bytes.iter().map(|byte| byte >> 5).collect();
+    values.push(0);
+    values.extend(bytes.iter().map(|byte| byte & 31));
+    values
+}
+
+/// Encodes a prefix and 5-bit data values as a bech32m string.
+fn bech32m_encode(prefix: &str, data: &[u8]) -> String {
+    let mut values = expand_prefix(prefix);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; CHECKSUM_LENGTH]);
+    let checksum = polymod(&values) ^ BECH32M_CONST;
+
+    let mut encoded = format!("{}1", prefix);
+    let checksum_values =
+        (0..CHECKSUM_LENGTH).map(|i| ((checksum >> (5 * (CHECKSUM_LENGTH - 1 - i))) & 31) as u8);
+    for value in data.iter().copied().chain(checksum_values) {
+        encoded.push(CHARSET[value as usize] as char);
+    }
+    encoded
+}
+
+/// Decodes a bech32m string into its prefix and 5-bit data values, checking the checksum.
+fn bech32m_decode(encoded: &str) -> Result<(String, Vec<u8>), AddressError> {
+    if encoded.len() > MAX_LENGTH
+        || encoded.bytes().any(|byte| !(33..=126).contains(&byte))
+        || (encoded.bytes().any(|byte| byte.is_ascii_lowercase())
+            && encoded.bytes().any(|byte| byte.is_ascii_uppercase()))
+    {
+        return Err(AddressError::InvalidEncoding);
+    }
+    let encoded = encoded.to_ascii_lowercase();
+    let (prefix, data) = encoded
+        .rsplit_once('1')
+        .ok_or(AddressError::InvalidEncoding)?;
+    if prefix.is_empty() || data.len() < CHECKSUM_LENGTH {
+        return Err(AddressError::InvalidEncoding);
+    }
+
+    let data = data
+        .bytes()
+        .map(|byte| {
+            CHARSET
+                .iter()
+                .position(|symbol| *symbol == byte)
+                .map(|value| value as u8)
+        })
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(AddressError::InvalidEncoding)?;
+    let mut values = expand_prefix(prefix);
+    values.extend_from_slice(&data);
+    if polymod(&values) != BECH32M_CONST {
+        return Err(AddressError::InvalidChecksum);
+    }
+    Ok((
+        prefix.to_string(),
+        data[..data.len() - CHECKSUM_LENGTH].to_vec(),
+    ))
+}
+
+/// Regroups a sequence of `from`-bit values into `to`-bit values. When not padding, leftover
+/// bits must be zero and fewer than `from`, or the input is rejected.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Option<Vec<u8>> {
+    let mut accumulator: u32 = 0;
+    let mut bits = 0;
+    let max_value = (1 << to) - 1;
+    let mut converted = Vec::new();
+    for value in data {
+        if (*value as u32) >> from != 0 {
+            return None;
+        }
+        accumulator = (accumulator << from) | *value as u32;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            converted.push(((accumulator >> bits) & max_value) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            converted.push(((accumulator << (to - bits)) & max_value) as u8);
+        }
+    } else if bits >= from || (accumulator << (to - bits)) & max_value != 0 {
+        return None;
+    }
+    Some(converted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Keypair;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn test_bech32m_reference_vectors() {
+        for valid in [
+            "A1LQFN3A",
+            "a1lqfn3a",
+            "abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx",
+            "split1checkupstagehandshakeupstreamerranterredcaperredlc445v",
+        ] {
+            let (prefix, data) = bech32m_decode(valid).unwrap();
+            assert_eq!(bech32m_encode(&prefix, &data), valid.to_ascii_lowercase());
+        }
+
+        // A valid bech32 string is not valid bech32m
+        assert_eq!(
+            bech32m_decode("split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w"),
+            Err(AddressError::InvalidChecksum)
+        );
+        assert_eq!(
+            bech32m_decode("A1lQFN3A"),
+            Err(AddressError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn test_address_round_trips() {
+        let keypair = Keypair::generate(&mut OsRng);
+        for network in [Network::Main, Network::Test] {
+            let address = Address::new(&keypair.public, network);
+            let encoded = address.to_string();
+            assert!(encoded.starts_with(&format!("{}1", network.prefix())));
+            assert_eq!(encoded.parse::<Address>(), Ok(address));
+            assert_eq!(encoded.to_uppercase().parse::<Address>(), Ok(address));
+            assert_eq!(address.public_key(), Some(keypair.public));
+        }
+
+        let hex_key = hex::encode(keypair.public.as_bytes());
+        assert_eq!(
+            Address::from_hex_key(&hex_key, Network::Main),
+            Ok(Address::new(&keypair.public, Network::Main))
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_addresses() {
+        let keypair = Keypair::generate(&mut OsRng);
+        let encoded = Address::new(&keypair.public, Network::Main).to_string();
+
+        // A single mistyped character is caught by the checksum
+        let mut typo: Vec<char> = encoded.chars().collect();
+        typo[10] = if typo[10] == 'q' { 'p' } else { 'q' };
+        let typo: String = typo.into_iter().collect();
+        assert_eq!(typo.parse::<Address>(), Err(AddressError::InvalidChecksum));
+
+        let truncated = &encoded[..encoded.len() - 1];
+        assert!(truncated.parse::<Address>().is_err());
+        assert_eq!(
+            "receiver".parse::<Address>(),
+            Err(AddressError::InvalidEncoding)
+        );
+
+        let mut data = vec![ADDRESS_VERSION];
+        data.extend(convert_bits(keypair.public.as_bytes(), 8, 5, true).unwrap());
+        assert_eq!(
+            bech32m_encode("xx", &data).parse::<Address>(),
+            Err(AddressError::UnknownPrefix("xx".to_string()))
+        );
+        data[0] = 2;
+        assert_eq!(
+            bech32m_encode(MAIN_PREFIX, &data).parse::<Address>(),
+            Err(AddressError::UnsupportedVersion(2))
+        );
+        data[0] = ADDRESS_VERSION;
+        assert_eq!(
+            bech32m_encode(MAIN_PREFIX, &data[..10]).parse::<Address>(),
+            Err(AddressError::InvalidEncoding)
+        );
+    }
+
+    #[test]
+    fn test_serializes_as_encoded_string() {
+        let keypair = Keypair::generate(&mut OsRng);
+        let address = Address::new(&keypair.public, Network::Test);
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(json, format!("\"{}\"", address));
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+        assert!(serde_json::from_str::<Address>("\"twt1invalid\"").is_err());
+    }
+}
@@ -10,11 +10,24 @@ use sha2::{Digest, Sha256};
 ///
 /// A `String` representing the hexadecimal form of the SHA-256 hash.
 pub fn calculate_hash(input: &str) -> String {
+    hash_bytes(input.as_bytes())
+}
+
+/// Calculates the SHA-256 hash of the given bytes.
+///
+/// # Arguments
+///
+/// * `input` - The bytes to be hashed, such as a canonical encoding.
+///
+/// # Returns
+///
+/// A `String` representing the hexadecimal form of the SHA-256 hash.
+pub fn hash_bytes(input: &[u8]) -> String {
     // Create a Sha256 object
     let mut hasher = Sha256::new();
 
     // Write input data
-    hasher.update(input.as_bytes());
+    hasher.update(input);
 
     // Finalize the hash and convert the output to a byte array
     let result = hasher.finalize();
@@ -39,6 +52,11 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hash_bytes_matches_string_hash() {
+        assert_eq!(hash_bytes(b"test"), calculate_hash("test"));
+    }
+
     #[test]
     fn test_empty_string_hash() {
         let input = "";
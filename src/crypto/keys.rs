@@ -1,8 +1,7 @@
-use ed25519_dalek::{Keypair, PublicKey, SecretKey, SIGNATURE_LENGTH};
+use ed25519_dalek::{Keypair, PublicKey, SecretKey};
 use rand::rngs::OsRng;
 use std::fs;
 use std::io::{self, Write};
-use std::path::Path;
 
 /// Generates a new Ed25519 keypair (public and private keys).
 ///
@@ -112,9 +111,10 @@ mod tests {
     #[test]
     fn test_load_public_key() {
         let keypair = generate_keypair();
-        let public_key_path = "test_public.key";
+        let private_key_path = "test_load_public_private.key";
+        let public_key_path = "test_load_public_public.key";
 
-        save_keypair(&keypair, "unused_private.key", public_key_path).expect("Failed to save keys");
+        save_keypair(&keypair, private_key_path, public_key_path).expect("Failed to save keys");
 
         let loaded_public_key =
             load_public_key(public_key_path).expect("Failed to load public key");
@@ -123,6 +123,7 @@ mod tests {
         assert_eq!(keypair.public.as_bytes(), loaded_public_key.as_bytes());
 
         // Clean up test files
+        remove_file(private_key_path).unwrap();
         remove_file(public_key_path).unwrap();
     }
 }
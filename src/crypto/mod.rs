@@ -1,7 +1,9 @@
+pub mod address;
 pub mod hash;
 pub mod keys;
 pub mod signatures;
 
-pub use self::hash::calculate_hash;
+pub use self::address::{Address, AddressError, AddressKind, Network};
+pub use self::hash::{calculate_hash, hash_bytes};
 pub use self::keys::{generate_keypair, load_keypair_from_private, load_public_key, save_keypair};
 pub use self::signatures::{sign_message, verify_signature};
@@ -1,6 +1,5 @@
 use ed25519_dalek::{Keypair, PublicKey, Signature, Signer, Verifier};
 use rand::rngs::OsRng;
-use serde::{Deserialize, Serialize};
 
 /// Generates a new Ed25519 keypair (private and public key).
 ///
@@ -39,10 +38,13 @@ pub fn sign_message(message: &str, keypair: &Keypair) -> Vec<u8> {
 ///
 /// # Returns
 ///
-/// A boolean indicating whether the signature is valid.
+/// A boolean indicating whether the signature is valid. Malformed signatures are rejected
+/// rather than causing a panic, since they may come from untrusted peers.
 pub fn verify_signature(message: &str, signature: &[u8], public_key: &PublicKey) -> bool {
-    let signature = Signature::from_bytes(signature).expect("Invalid signature format");
-    public_key.verify(message.as_bytes(), &signature).is_ok()
+    match Signature::from_bytes(signature) {
+        Ok(signature) => public_key.verify(message.as_bytes(), &signature).is_ok(),
+        Err(_) => false,
+    }
 }
 
 #[cfg(test)]
@@ -3,8 +3,13 @@ pub mod blockchain;
 pub mod config;
 pub mod consensus;
 pub mod crypto;
+pub mod mempool;
 pub mod network;
+pub mod node;
 pub mod storage;
+pub mod task;
+#[cfg(test)]
+mod test_utils;
 pub mod token;
 pub mod utils;
 pub mod wallet;
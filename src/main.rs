@@ -1,3 +1,5 @@
+use core::config::Settings;
+
 fn main() {
     let settings = Settings::new().expect("Failed to load settings");
     println!("Consensus Difficulty: {}", settings.consensus.difficulty);
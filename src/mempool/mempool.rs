@@ -0,0 +1,1273 @@
+use crate::blockchain::block::Block;
+use crate::blockchain::ledger::Ledger;
+use crate::blockchain::multisig::Approval;
+use crate::blockchain::state::LedgerState;
+use crate::blockchain::transaction::Transaction;
+use crate::consensus::validator::{ValidationError, Validator};
+use crate::token::lot::TokenLot;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+/// Default limit on the total size of the transactions held in the mempool, in bytes.
+pub const DEFAULT_MEMPOOL_MAX_BYTES: usize = 8 * 1024 * 1024;
+
+/// Most transactions a single sender may have queued waiting for its earlier nonces.
+pub const MAX_QUEUED_PER_SENDER: usize = 16;
+
+/// Most transactions a single multisig account may have waiting for approvals.
+pub const MAX_PROPOSALS_PER_ACCOUNT: usize = 16;
+
+/// Reasons a transaction can be refused by the mempool.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MempoolError {
+    /// The transaction is already in the mempool.
+    Duplicate(String),
+    /// The transaction spends a lot or claims work that a pending transaction already does.
+    Conflict(String),
+    /// The transaction would not be accepted in the next block.
+    Invalid(ValidationError),
+    /// The transaction pays less than the mempool's minimum fee.
+    FeeTooLow { minimum: u64, found: u64 },
+    /// The mempool is full of transactions with a higher priority.
+    Full,
+    /// The sender already has as many transactions queued for earlier nonces as it may.
+    TooManyQueued(String),
+    /// The multisig account already has as many transactions waiting for approvals as it may.
+    TooManyProposals(String),
+    /// No transaction with this ID is waiting for approvals.
+    UnknownProposal(String),
+}
+
+impl fmt::Display for MempoolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MempoolError::Duplicate(id) => write!(f, "transaction {} is already pending", id),
+            MempoolError::Conflict(id) => {
+                write!(f, "transaction conflicts with pending transaction {}", id)
+            }
+            MempoolError::Invalid(err) => write!(f, "{}", err),
+            MempoolError::FeeTooLow { minimum, found } => {
+                write!(f, "fee {} is below the minimum of {}", found, minimum)
+            }
+            MempoolError::Full => write!(f, "mempool is full"),
+            MempoolError::TooManyQueued(sender) => write!(
+                f,
+                "{} has too many transactions waiting for earlier nonces",
+                sender
+            ),
+            MempoolError::TooManyProposals(account) => write!(
+                f,
+                "{} has too many transactions waiting for approvals",
+                account
+            ),
+            MempoolError::UnknownProposal(id) => {
+                write!(f, "transaction {} is not awaiting approvals", id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MempoolError {}
+
+impl From<ValidationError> for MempoolError {
+    fn from(err: ValidationError) -> Self {
+        MempoolError::Invalid(err)
+    }
+}
+
+/// A pending transaction, along with what the mempool needs to prioritize it.
+#[derive(Debug, Clone)]
+pub struct MempoolEntry {
+    pub transaction: Transaction,
+    /// Size of the serialized transaction, in bytes.
+    pub size: usize,
+    /// When the soonest-expiring lot the transaction spends expires, if any of them do.
+    pub earliest_expiry: Option<u64>,
+    /// Order in which the transaction arrived, lowest first.
+    pub sequence: u64,
+}
+
+impl MempoolEntry {
+    /// Sort key putting the most urgent transactions first: those paying the highest fee,
+    /// then those spending the soonest-expiring lots, so tokens about to expire move before
+    /// they are lost, and then the longest-waiting.
+    fn priority_key(&self) -> (Reverse<u64>, u64, u64) {
+        (
+            Reverse(self.transaction.fee),
+            self.earliest_expiry.unwrap_or(u64::MAX),
+            self.sequence,
+        )
+    }
+}
+
+/// Pool of transactions waiting to be included in a block.
+///
+/// Every transaction is validated against the ledger before it is accepted, and the pool
+/// never holds two transactions that spend the same lot, claim the same work or use the same
+/// nonce. A transaction may depend on other pending transactions, its parents, by spending
+/// their outputs or by taking the next nonce after theirs, as long as it is valid once they
+/// are applied. A transaction whose nonce is further ahead is queued until the transactions
+/// before it arrive. When the pool grows past its size limit, the lowest-priority
+/// transactions are evicted, along with any pending transactions that depend on them.
+///
+/// Transactions from multisig accounts can also be proposed before their members have
+/// approved them. They are held apart from the pending transactions, and added to the pool
+/// once they carry enough approvals.
+#[derive(Debug)]
+pub struct Mempool {
+    /// Pending transactions, keyed by transaction ID.
+    entries: HashMap<String, MempoolEntry>,
+    /// The pending transaction spending each lot, keyed by lot ID.
+    spent_lots: HashMap<String, String>,
+    /// The pending transaction claiming each piece of work, keyed by work hash.
+    claimed_work: HashMap<String, String>,
+    /// The pending transaction using each nonce, keyed by sender address and nonce.
+    sender_nonces: HashMap<(String, u64), String>,
+    /// Transactions waiting for their sender's earlier nonces, keyed by sender address and
+    /// nonce. Each sender may have at most `MAX_QUEUED_PER_SENDER` of them, spending no more
+    /// than its balance in the ledger.
+    queued: BTreeMap<(String, u64), Transaction>,
+    /// Total size of queued transactions, in bytes. Queued transactions have a limit of
+    /// `max_bytes` of their own, past which those paying the lowest fees are evicted.
+    queued_bytes: usize,
+    /// Transactions from multisig accounts still collecting approvals, keyed by transaction
+    /// ID. Each account may have at most `MAX_PROPOSALS_PER_ACCOUNT` of them.
+    proposals: HashMap<String, MempoolEntry>,
+    /// Total size of proposed transactions, in bytes. Proposals have a limit of `max_bytes`
+    /// of their own, past which the lowest-priority ones are evicted.
+    proposal_bytes: usize,
+    /// Limit on the total size of pending transactions, in bytes.
+    pub max_bytes: usize,
+    /// Lowest fee any transaction must pay to be accepted.
+    pub min_fee: u64,
+    /// Total size of pending transactions, in bytes.
+    total_bytes: usize,
+    /// Sequence number for the next transaction to arrive.
+    next_sequence: u64,
+}
+
+impl Mempool {
+    /// Creates an empty mempool holding at most `max_bytes` of transactions.
+    pub fn new(max_bytes: usize) -> Self {
+        Mempool {
+            entries: HashMap::new(),
+            spent_lots: HashMap::new(),
+            claimed_work: HashMap::new(),
+            sender_nonces: HashMap::new(),
+            queued: BTreeMap::new(),
+            queued_bytes: 0,
+            proposals: HashMap::new(),
+            proposal_bytes: 0,
+            max_bytes,
+            min_fee: 0,
+            total_bytes: 0,
+            next_sequence: 0,
+        }
+    }
+
+    /// Sets the lowest fee any transaction must pay to be accepted.
+    pub fn with_min_fee(mut self, min_fee: u64) -> Self {
+        self.min_fee = min_fee;
+        self
+    }
+
+    /// Validates a transaction against the ledger and the pending transactions, and adds it
+    /// to the pool. The transaction is validated as if its pending ancestors were applied
+    /// first.
+    ///
+    /// A transaction whose nonce is ahead of its sender's next one is queued instead, and
+    /// added once the transactions before it are. Adding a transaction adds any queued
+    /// transaction that was waiting for it.
+    ///
+    /// # Arguments
+    /// * `transaction` - The transaction received.
+    /// * `ledger` - The ledger to validate the transaction against.
+    ///
+    /// # Returns
+    /// * `Result<(), MempoolError>` - Why the transaction was refused, if it was.
+    pub fn add(&mut self, transaction: Transaction, ledger: &Ledger) -> Result<(), MempoolError> {
+        let (sender, mut nonce) = (transaction.sender_address(), transaction.nonce);
+        let mut added = transaction.id.clone();
+        self.add_one(transaction, ledger)?;
+
+        // The next nonces may have been waiting for this one
+        while self.entries.contains_key(&added) {
+            nonce += 1;
+            let Some(next) = self.unqueue(&(sender.clone(), nonce)) else {
+                break;
+            };
+            added = next.id.clone();
+            let _ = self.add_one(next, ledger);
+        }
+        Ok(())
+    }
+
+    /// Adds a single transaction to the pool, or queues it if its nonce is ahead, without
+    /// adding any queued transactions that were waiting for it.
+    fn add_one(&mut self, transaction: Transaction, ledger: &Ledger) -> Result<(), MempoolError> {
+        let nonce_key = (transaction.sender_address(), transaction.nonce);
+        if self.entries.contains_key(&transaction.id)
+            || self
+                .queued
+                .get(&nonce_key)
+                .is_some_and(|queued| queued.id == transaction.id)
+        {
+            return Err(MempoolError::Duplicate(transaction.id));
+        }
+        if transaction.fee < self.min_fee {
+            return Err(MempoolError::FeeTooLow {
+                minimum: self.min_fee,
+                found: transaction.fee,
+            });
+        }
+        if let Some(conflict) = self.find_conflict(&transaction) {
+            return Err(MempoolError::Conflict(conflict.clone()));
+        }
+        if let Some(queued) = self.queued.get(&nonce_key) {
+            return Err(MempoolError::Conflict(queued.id.clone()));
+        }
+
+        let state = self.state_with_ancestors(&transaction, ledger);
+        match ledger.apply_pending_transaction(
+            &transaction,
+            &mut state.clone(),
+            ledger.next_block_time(),
+        ) {
+            Err(ValidationError::InvalidNonce { expected, found }) if found > expected => {
+                return self.queue(transaction, ledger);
+            }
+            result => result?,
+        }
+
+        let earliest_expiry = earliest_expiry(&transaction, &state);
+        let size = transaction_size(&transaction);
+        let id = transaction.id.clone();
+        self.insert(MempoolEntry {
+            transaction,
+            size,
+            earliest_expiry,
+            sequence: self.next_sequence,
+        });
+        self.next_sequence += 1;
+
+        self.evict();
+        if !self.entries.contains_key(&id) {
+            return Err(MempoolError::Full);
+        }
+        Ok(())
+    }
+
+    /// Holds a transaction from a multisig account while its members approve it. A
+    /// transaction that already carries enough approvals is added to the pool straight away.
+    ///
+    /// Proposing a transaction that is already held merges the approvals it carries into the
+    /// held one, so members can approve it independently and submit their copies. Until they
+    /// are fully approved, proposals are checked against the ledger for everything but their
+    /// approvals: the nonce must not be used yet, and the lots spent must be the account's to
+    /// spend and cover what the transaction sends and its fee.
+    ///
+    /// # Arguments
+    /// * `transaction` - The transaction from a multisig account, with any approvals so far.
+    /// * `ledger` - The ledger to validate the transaction against once it is approved.
+    ///
+    /// # Returns
+    /// * `Result<usize, MempoolError>` - How many more approvals the transaction needs, or
+    ///   why it was refused.
+    pub fn propose(
+        &mut self,
+        transaction: Transaction,
+        ledger: &Ledger,
+    ) -> Result<usize, MempoolError> {
+        let invalid =
+            || MempoolError::Invalid(ValidationError::InvalidTransaction(transaction.id.clone()));
+        if !transaction.validate_contents() {
+            return Err(invalid());
+        }
+        let needed = transaction.approvals_needed().ok_or_else(invalid)?;
+        if let Some(held) = self.proposals.get(&transaction.id) {
+            let mut needed = held.transaction.approvals_needed().unwrap_or(usize::MAX);
+            for approval in transaction.approvals {
+                needed = self.approve(&transaction.id, approval, ledger)?;
+                if needed == 0 {
+                    break;
+                }
+            }
+            return Ok(needed);
+        }
+        if needed == 0 {
+            self.add(transaction, ledger)?;
+            return Ok(0);
+        }
+
+        if transaction.fee < self.min_fee {
+            return Err(MempoolError::FeeTooLow {
+                minimum: self.min_fee,
+                found: transaction.fee,
+            });
+        }
+        check_proposal(&transaction, ledger)?;
+        let account = transaction.sender_address();
+        let held = self
+            .proposals
+            .values()
+            .filter(|held| held.transaction.sender_address() == account)
+            .count();
+        if held >= MAX_PROPOSALS_PER_ACCOUNT {
+            return Err(MempoolError::TooManyProposals(account));
+        }
+
+        let entry = MempoolEntry {
+            size: transaction_size(&transaction),
+            earliest_expiry: earliest_expiry(&transaction, &ledger.state),
+            sequence: self.next_sequence,
+            transaction,
+        };
+        self.next_sequence += 1;
+        while self.proposal_bytes + entry.size > self.max_bytes {
+            let lowest = self
+                .proposals
+                .values()
+                .max_by_key(|held| held.priority_key())
+                .filter(|held| held.priority_key() > entry.priority_key())
+                .map(|held| held.transaction.id.clone());
+            let Some(lowest) = lowest else {
+                return Err(MempoolError::Full);
+            };
+            self.remove_proposal(&lowest);
+        }
+        self.proposal_bytes += entry.size;
+        self.proposals.insert(entry.transaction.id.clone(), entry);
+        Ok(needed)
+    }
+
+    /// Adds a member's approval to a proposed transaction from a multisig account, and adds
+    /// the transaction to the pool once it carries enough approvals.
+    ///
+    /// # Arguments
+    /// * `id` - ID of the proposed transaction.
+    /// * `approval` - The member's signature over the transaction's ID.
+    /// * `ledger` - The ledger to validate the transaction against once it is approved.
+    ///
+    /// # Returns
+    /// * `Result<usize, MempoolError>` - How many more approvals the transaction needs, or
+    ///   why the approval or the approved transaction was refused.
+    pub fn approve(
+        &mut self,
+        id: &str,
+        approval: Approval,
+        ledger: &Ledger,
+    ) -> Result<usize, MempoolError> {
+        let proposal = self
+            .proposals
+            .get_mut(id)
+            .ok_or_else(|| MempoolError::UnknownProposal(id.to_string()))?;
+        if !proposal.transaction.add_approval(approval) {
+            return Err(ValidationError::InvalidTransaction(id.to_string()).into());
+        }
+        let size = transaction_size(&proposal.transaction);
+        self.proposal_bytes = self.proposal_bytes - proposal.size + size;
+        proposal.size = size;
+
+        let needed = proposal
+            .transaction
+            .approvals_needed()
+            .unwrap_or(usize::MAX);
+        if needed > 0 {
+            return Ok(needed);
+        }
+        let transaction = self.remove_proposal(id).expect("Proposal is held");
+        self.add(transaction, ledger)?;
+        Ok(0)
+    }
+
+    /// Retrieves a transaction that is waiting for approvals by its ID.
+    pub fn get_proposal(&self, id: &str) -> Option<&Transaction> {
+        self.proposals.get(id).map(|proposal| &proposal.transaction)
+    }
+
+    /// Returns the number of transactions waiting for approvals.
+    pub fn proposals_len(&self) -> usize {
+        self.proposals.len()
+    }
+
+    /// Stops holding a transaction that is waiting for approvals.
+    pub fn remove_proposal(&mut self, id: &str) -> Option<Transaction> {
+        let proposal = self.proposals.remove(id)?;
+        self.proposal_bytes -= proposal.size;
+        Some(proposal.transaction)
+    }
+
+    /// Removes a transaction from the pool.
+    pub fn remove(&mut self, id: &str) -> Option<Transaction> {
+        let entry = self.entries.remove(id)?;
+        for lot_id in &entry.transaction.inputs {
+            self.spent_lots.remove(lot_id);
+        }
+        if let Some(claim) = entry.transaction.work_claim() {
+            self.claimed_work.remove(&claim.work_hash);
+        }
+        self.sender_nonces
+            .remove(&(entry.transaction.sender_address(), entry.transaction.nonce));
+        self.total_bytes -= entry.size;
+        Some(entry.transaction)
+    }
+
+    /// Removes a transaction from the pool along with every pending transaction that depends
+    /// on it, directly or further down the line.
+    pub fn remove_with_descendants(&mut self, id: &str) {
+        let mut doomed = vec![id.to_string()];
+        while let Some(id) = doomed.pop() {
+            let Some(removed) = self.remove(&id) else {
+                continue;
+            };
+            let next_nonce = (removed.sender_address(), removed.nonce + 1);
+            doomed.extend(self.sender_nonces.get(&next_nonce).cloned());
+            doomed.extend(
+                self.entries
+                    .values()
+                    .filter(|entry| {
+                        entry
+                            .transaction
+                            .inputs
+                            .iter()
+                            .any(|lot_id| TokenLot::source_transaction(lot_id) == id)
+                    })
+                    .map(|entry| entry.transaction.id.clone()),
+            );
+        }
+    }
+
+    /// Removes the transactions included in a block, along with any pending transactions
+    /// that conflict with them and their descendants. Pending transactions spending the
+    /// outputs of included ones stay, since those outputs are now in the ledger.
+    pub fn remove_block_transactions(&mut self, block: &Block) {
+        for transaction in &block.transactions {
+            self.remove(&transaction.id);
+            while let Some(conflict) = self.find_conflict(transaction).cloned() {
+                self.remove_with_descendants(&conflict);
+            }
+        }
+    }
+
+    /// Drops pending transactions that are no longer valid against the ledger, for example
+    /// because a lot they spend has expired, and adds back transactions a reorg dropped from
+    /// the chain. Called whenever the ledger's tip changes.
+    ///
+    /// Transactions are checked in the order they would be included in a block, so a
+    /// transaction whose parent is dropped is dropped too, unless only its nonce is now
+    /// ahead, in which case it is queued again. Queued transactions whose earlier nonces
+    /// have since been included are added, and proposals whose nonces have been used, or
+    /// whose lots have been spent or have expired, are dropped.
+    pub fn update(&mut self, ledger: &mut Ledger) {
+        let timestamp = ledger.next_block_time();
+        let mut state = ledger.state.clone();
+        let mut invalid = Vec::new();
+        let mut requeue = Vec::new();
+        for transaction in self.transactions_by_priority() {
+            let mut next_state = state.clone();
+            match ledger.apply_pending_transaction(transaction, &mut next_state, timestamp) {
+                Ok(()) => state = next_state,
+                Err(ValidationError::InvalidNonce { expected, found }) if found > expected => {
+                    requeue.push(transaction.id.clone())
+                }
+                Err(_) => invalid.push(transaction.id.clone()),
+            }
+        }
+        for id in invalid {
+            self.remove(&id);
+        }
+        for id in requeue {
+            if let Some(transaction) = self.remove(&id) {
+                let _ = self.queue(transaction, ledger);
+            }
+        }
+
+        let queued: Vec<(String, u64)> = self.queued.keys().cloned().collect();
+        for key in queued {
+            // Adding one queued transaction may already have added those after it
+            if let Some(transaction) = self.unqueue(&key) {
+                let _ = self.add(transaction, ledger);
+            }
+        }
+
+        for transaction in ledger.take_orphaned_transactions() {
+            let _ = self.add(transaction, ledger);
+        }
+
+        let stale: Vec<String> = self
+            .proposals
+            .values()
+            .filter(|proposal| check_proposal(&proposal.transaction, ledger).is_err())
+            .map(|proposal| proposal.transaction.id.clone())
+            .collect();
+        for id in stale {
+            self.remove_proposal(&id);
+        }
+    }
+
+    /// Returns the pending transactions in the order a block should include them: most
+    /// urgent first, except that every transaction comes after the parents it spends from.
+    pub fn transactions_by_priority(&self) -> Vec<&Transaction> {
+        let mut entries: Vec<&MempoolEntry> = self.entries.values().collect();
+        entries.sort_by_key(|entry| entry.priority_key());
+        self.in_dependency_order(entries.iter().map(|entry| entry.transaction.id.as_str()))
+    }
+
+    /// Retrieves a pending transaction by its ID.
+    pub fn get(&self, id: &str) -> Option<&MempoolEntry> {
+        self.entries.get(id)
+    }
+
+    /// Checks whether a transaction is pending.
+    pub fn contains(&self, id: &str) -> bool {
+        self.entries.contains_key(id)
+    }
+
+    /// Returns the number of pending transactions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks whether there are no pending transactions.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the total size of the pending transactions, in bytes.
+    pub fn size_bytes(&self) -> usize {
+        self.total_bytes
+    }
+
+    /// Returns the nonce an account's next transaction should carry: the next one after the
+    /// ledger's and the account's pending transactions.
+    pub fn next_nonce(&self, account: &str, ledger: &Ledger) -> u64 {
+        let mut nonce = ledger.get_next_nonce(account);
+        while self
+            .sender_nonces
+            .contains_key(&(account.to_string(), nonce))
+        {
+            nonce += 1;
+        }
+        nonce
+    }
+
+    /// Returns the number of transactions queued until their sender's earlier nonces arrive.
+    pub fn queued_len(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Finds a pending transaction that spends a lot, claims work or uses a nonce that
+    /// `transaction` does.
+    fn find_conflict(&self, transaction: &Transaction) -> Option<&String> {
+        transaction
+            .inputs
+            .iter()
+            .find_map(|lot_id| self.spent_lots.get(lot_id))
+            .or_else(|| {
+                transaction
+                    .work_claim()
+                    .and_then(|claim| self.claimed_work.get(&claim.work_hash))
+            })
+            .or_else(|| {
+                self.sender_nonces
+                    .get(&(transaction.sender_address(), transaction.nonce))
+            })
+    }
+
+    /// Returns the IDs of the pending transactions `transaction` depends on: those whose
+    /// outputs it spends, and the one using its sender's previous nonce.
+    fn pending_parents<'a>(&'a self, transaction: &'a Transaction) -> Vec<&'a str> {
+        let previous_nonce = transaction.nonce.checked_sub(1).and_then(|nonce| {
+            self.sender_nonces
+                .get(&(transaction.sender_address(), nonce))
+        });
+        transaction
+            .inputs
+            .iter()
+            .map(|lot_id| TokenLot::source_transaction(lot_id))
+            .filter(|id| self.entries.contains_key(*id))
+            .chain(previous_nonce.map(String::as_str))
+            .collect()
+    }
+
+    /// Holds a transaction until its sender's earlier nonces arrive.
+    ///
+    /// The sender's queued transactions together may not spend more than its balance in the
+    /// ledger. A sender at its limit of queued transactions makes room by dropping its
+    /// furthest-ahead one, if that is further ahead than this one, and a full queue makes room
+    /// by evicting the transactions paying the lowest fees, if they pay less than this one.
+    fn queue(&mut self, transaction: Transaction, ledger: &Ledger) -> Result<(), MempoolError> {
+        let sender = transaction.sender_address();
+        let sender_range = (sender.clone(), 0)..=(sender.clone(), u64::MAX);
+        let required = self
+            .queued
+            .range(sender_range.clone())
+            .map(|(_, queued)| queued)
+            .chain(std::iter::once(&transaction))
+            .fold(0u64, |total, queued| {
+                total.saturating_add(queued.spent_amount())
+            });
+        let available = ledger.get_balance(&sender);
+        if required > available {
+            return Err(ValidationError::InsufficientFunds {
+                available,
+                required,
+            }
+            .into());
+        }
+
+        if self.queued.range(sender_range.clone()).count() >= MAX_QUEUED_PER_SENDER {
+            let furthest = *self
+                .queued
+                .range(sender_range)
+                .map(|((_, nonce), _)| nonce)
+                .next_back()
+                .expect("Sender has queued transactions");
+            if furthest < transaction.nonce {
+                return Err(MempoolError::TooManyQueued(sender));
+            }
+            self.unqueue(&(sender, furthest));
+        }
+
+        let size = transaction_size(&transaction);
+        while self.queued_bytes + size > self.max_bytes {
+            let lowest = self
+                .queued
+                .iter()
+                .min_by_key(|((_, nonce), queued)| (queued.fee, Reverse(*nonce)))
+                .filter(|(_, queued)| queued.fee < transaction.fee)
+                .map(|(key, _)| key.clone());
+            let Some(lowest) = lowest else {
+                return Err(MempoolError::Full);
+            };
+            self.unqueue(&lowest);
+        }
+        self.queued_bytes += size;
+        self.queued.insert(
+            (transaction.sender_address(), transaction.nonce),
+            transaction,
+        );
+        Ok(())
+    }
+
+    /// Stops holding a queued transaction, returning it.
+    fn unqueue(&mut self, key: &(String, u64)) -> Option<Transaction> {
+        let transaction = self.queued.remove(key)?;
+        self.queued_bytes -= transaction_size(&transaction);
+        Some(transaction)
+    }
+
+    /// Lists the given pending transactions in order, each preceded by any pending ancestors
+    /// that have not been listed yet.
+    fn in_dependency_order<'a>(
+        &'a self,
+        ids: impl Iterator<Item = &'a str>,
+    ) -> Vec<&'a Transaction> {
+        let mut ordered = Vec::new();
+        let mut visited = HashSet::new();
+        for id in ids {
+            self.visit(id, &mut visited, &mut ordered);
+        }
+        ordered
+    }
+
+    fn visit<'a>(
+        &'a self,
+        id: &'a str,
+        visited: &mut HashSet<&'a str>,
+        ordered: &mut Vec<&'a Transaction>,
+    ) {
+        if !visited.insert(id) {
+            return;
+        }
+        let transaction = &self.entries[id].transaction;
+        for parent in self.pending_parents(transaction) {
+            self.visit(parent, visited, ordered);
+        }
+        ordered.push(transaction);
+    }
+
+    /// Builds the state the ledger would be in for the next block once the pending ancestors
+    /// of `transaction` are applied.
+    fn state_with_ancestors(&self, transaction: &Transaction, ledger: &Ledger) -> LedgerState {
+        let timestamp = ledger.next_block_time();
+        let mut state = ledger.state.clone();
+        for ancestor in self.in_dependency_order(self.pending_parents(transaction).into_iter()) {
+            // An ancestor that no longer applies leaves its outputs missing, so anything
+            // spending them is rejected
+            let _ = ledger.apply_pending_transaction(ancestor, &mut state, timestamp);
+        }
+        state
+    }
+
+    fn insert(&mut self, entry: MempoolEntry) {
+        let id = &entry.transaction.id;
+        for lot_id in &entry.transaction.inputs {
+            self.spent_lots.insert(lot_id.clone(), id.clone());
+        }
+        if let Some(claim) = entry.transaction.work_claim() {
+            self.claimed_work
+                .insert(claim.work_hash.clone(), id.clone());
+        }
+        self.sender_nonces.insert(
+            (entry.transaction.sender_address(), entry.transaction.nonce),
+            id.clone(),
+        );
+        self.total_bytes += entry.size;
+        self.entries.insert(id.clone(), entry);
+    }
+
+    /// Evicts the lowest-priority transactions until the pool fits its size limit.
+    fn evict(&mut self) {
+        while self.total_bytes > self.max_bytes {
+            let lowest = self
+                .entries
+                .values()
+                .max_by_key(|entry| entry.priority_key())
+                .map(|entry| entry.transaction.id.clone())
+                .expect("A pool over its limit is not empty");
+            self.remove_with_descendants(&lowest);
+        }
+    }
+}
+
+/// Returns when the soonest-expiring lot a transaction spends expires, if any of them do.
+fn earliest_expiry(transaction: &Transaction, state: &LedgerState) -> Option<u64> {
+    transaction
+        .inputs
+        .iter()
+        .filter_map(|lot_id| state.get_lot(lot_id))
+        .filter_map(|lot| lot.expiration_time)
+        .min()
+}
+
+/// Checks a transaction still collecting approvals against the ledger as of the next block,
+/// for everything that does not depend on its approvals: its nonce must not be used yet, and
+/// the lots it spends must be the account's to spend and cover what it sends and its fee.
+fn check_proposal(transaction: &Transaction, ledger: &Ledger) -> Result<(), MempoolError> {
+    let expected = ledger.get_next_nonce(&transaction.sender_address());
+    if transaction.nonce < expected {
+        return Err(ValidationError::InvalidNonce {
+            expected,
+            found: transaction.nonce,
+        }
+        .into());
+    }
+    if transaction.inputs.is_empty() && transaction.spent_amount() == 0 {
+        return Ok(());
+    }
+    let height = ledger.get_latest_block().header.height + 1;
+    Validator::validate_transfer(transaction, &ledger.state, height, ledger.next_block_time())?;
+    Ok(())
+}
+
+/// Returns the size of a serialized transaction, in bytes.
+fn transaction_size(transaction: &Transaction) -> usize {
+    bincode::serialize(transaction)
+        .expect("Transactions serialize")
+        .len()
+}
+
+impl Default for Mempool {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMPOOL_MAX_BYTES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blockchain::multisig::MultisigPolicy;
+    use crate::crypto::address::{Address, Network};
+    use crate::test_utils::{new_address, transfer, work_claim};
+    use ed25519_dalek::Keypair;
+    use rand::rngs::OsRng;
+
+    /// Appends a block with the given transactions, `seconds` after now.
+    fn add_block(ledger: &mut Ledger, transactions: Vec<Transaction>, seconds: u64) {
+        let latest_block = ledger.get_latest_block();
+        let mut block = Block::new(
+            latest_block.header.height + 1,
+            latest_block.hash.clone(),
+            transactions,
+            0,
+        );
+        block.header.timestamp += seconds;
+        ledger.commit_state_root(&mut block).unwrap();
+        assert!(ledger.add_block(block));
+    }
+
+    /// Mints `amount` to a new worker, returning the worker and the minted lot's ID.
+    fn funded_worker(ledger: &mut Ledger, amount: u64, seconds: u64) -> (Keypair, String) {
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = work_claim(&worker, 0, &format!("work_{}", seconds), amount);
+        let lot_id = format!("{}:0", claim.id);
+        add_block(ledger, vec![claim], seconds);
+        (worker, lot_id)
+    }
+
+    #[test]
+    fn test_rejects_duplicates_and_conflicts() {
+        let mut ledger = Ledger::new();
+        let mut mempool = Mempool::default();
+        let (worker, lot_id) = funded_worker(&mut ledger, 50, 0);
+
+        let first = transfer(&worker, 1, new_address(), 50, vec![lot_id.clone()]);
+        assert_eq!(mempool.add(first.clone(), &ledger), Ok(()));
+        assert_eq!(
+            mempool.add(first.clone(), &ledger),
+            Err(MempoolError::Duplicate(first.id.clone()))
+        );
+
+        let double_spend = transfer(&worker, 1, new_address(), 50, vec![lot_id]);
+        assert_eq!(
+            mempool.add(double_spend, &ledger),
+            Err(MempoolError::Conflict(first.id.clone()))
+        );
+
+        let claimer = Keypair::generate(&mut OsRng);
+        let claim = work_claim(&claimer, 0, "work_x", 10);
+        assert_eq!(mempool.add(claim.clone(), &ledger), Ok(()));
+        assert_eq!(
+            mempool.add(work_claim(&claimer, 0, "work_x", 20), &ledger),
+            Err(MempoolError::Conflict(claim.id))
+        );
+        assert_eq!(mempool.len(), 2);
+    }
+
+    #[test]
+    fn test_rejects_transactions_invalid_against_ledger() {
+        let mut ledger = Ledger::new();
+        let mut mempool = Mempool::default();
+        let (worker, lot_id) = funded_worker(&mut ledger, 50, 0);
+
+        let thief = Keypair::generate(&mut OsRng);
+        assert_eq!(
+            mempool.add(
+                transfer(&thief, 0, new_address(), 50, vec![lot_id.clone()]),
+                &ledger
+            ),
+            Err(MempoolError::Invalid(ValidationError::UnownedInput(lot_id)))
+        );
+        assert_eq!(
+            mempool.add(work_claim(&worker, 0, "work_0", 50), &ledger),
+            Err(MempoolError::Invalid(ValidationError::WorkAlreadyClaimed(
+                "work_0".to_string()
+            )))
+        );
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn test_orders_soonest_expiring_lots_first() {
+        let mut ledger = Ledger::new();
+        let mut mempool = Mempool::default();
+        let (early_worker, early_lot) = funded_worker(&mut ledger, 50, 0);
+        let (late_worker, late_lot) = funded_worker(&mut ledger, 50, 10);
+
+        let claim = work_claim(&Keypair::generate(&mut OsRng), 0, "work_new", 10);
+        let late = transfer(&late_worker, 1, new_address(), 50, vec![late_lot]);
+        let early = transfer(&early_worker, 1, new_address(), 50, vec![early_lot]);
+        for tx in [claim.clone(), late.clone(), early.clone()] {
+            mempool.add(tx, &ledger).unwrap();
+        }
+
+        let ordered: Vec<&str> = mempool
+            .transactions_by_priority()
+            .iter()
+            .map(|tx| tx.id.as_str())
+            .collect();
+        assert_eq!(
+            ordered,
+            vec![early.id.as_str(), late.id.as_str(), claim.id.as_str()]
+        );
+    }
+
+    #[test]
+    fn test_orders_higher_fees_first_and_enforces_minimum() {
+        let mut ledger = Ledger::new();
+        let mut mempool = Mempool::default().with_min_fee(2);
+        let (early_worker, early_lot) = funded_worker(&mut ledger, 50, 0);
+        let (late_worker, late_lot) = funded_worker(&mut ledger, 50, 10);
+
+        let mut cheap = Transaction::new_transfer(
+            early_worker.public,
+            new_address(),
+            40,
+            vec![early_lot.clone()],
+        )
+        .with_nonce(1)
+        .with_fee(1);
+        cheap.sign(&early_worker);
+        assert_eq!(
+            mempool.add(cheap, &ledger),
+            Err(MempoolError::FeeTooLow {
+                minimum: 2,
+                found: 1
+            })
+        );
+
+        // Work claims must pay the minimum too, out of the tokens they mint
+        let claimant = Keypair::generate(&mut OsRng);
+        let unpaid = work_claim(&claimant, 0, "work_new", 10);
+        assert_eq!(
+            mempool.add(unpaid, &ledger),
+            Err(MempoolError::FeeTooLow {
+                minimum: 2,
+                found: 0
+            })
+        );
+        let mut claim = Transaction::new_work_claim(
+            claimant.public,
+            "work_new".to_string(),
+            "Completed task".to_string(),
+            "general".to_string(),
+            10,
+        )
+        .with_fee(2);
+        claim.attest(&Keypair::generate(&mut OsRng));
+        claim.sign(&claimant);
+
+        // A higher fee outranks spending sooner-expiring lots
+        let mut early =
+            Transaction::new_transfer(early_worker.public, new_address(), 40, vec![early_lot])
+                .with_nonce(1)
+                .with_fee(2);
+        early.sign(&early_worker);
+        let mut late =
+            Transaction::new_transfer(late_worker.public, new_address(), 40, vec![late_lot])
+                .with_nonce(1)
+                .with_fee(5);
+        late.sign(&late_worker);
+        for tx in [claim.clone(), early.clone(), late.clone()] {
+            mempool.add(tx, &ledger).unwrap();
+        }
+
+        let ordered: Vec<&str> = mempool
+            .transactions_by_priority()
+            .iter()
+            .map(|tx| tx.id.as_str())
+            .collect();
+        assert_eq!(
+            ordered,
+            vec![late.id.as_str(), early.id.as_str(), claim.id.as_str()]
+        );
+    }
+
+    #[test]
+    fn test_evicts_lowest_priority_when_full() {
+        let mut ledger = Ledger::new();
+        let (early_worker, early_lot) = funded_worker(&mut ledger, 50, 0);
+        let (late_worker, late_lot) = funded_worker(&mut ledger, 50, 10);
+        let late = transfer(&late_worker, 1, new_address(), 50, vec![late_lot]);
+        let early = transfer(&early_worker, 1, new_address(), 50, vec![early_lot]);
+
+        // Room for exactly one transfer
+        let mut mempool = Mempool::new(bincode::serialize(&late).unwrap().len());
+        mempool.add(late.clone(), &ledger).unwrap();
+        assert_eq!(mempool.add(early.clone(), &ledger), Ok(()));
+        assert!(!mempool.contains(&late.id));
+        assert_eq!(mempool.size_bytes(), mempool.get(&early.id).unwrap().size);
+
+        // A lower-priority transaction is turned away instead
+        assert_eq!(mempool.add(late, &ledger), Err(MempoolError::Full));
+        assert!(mempool.contains(&early.id));
+    }
+
+    #[test]
+    fn test_update_drops_transactions_included_in_blocks() {
+        let mut ledger = Ledger::new();
+        let mut mempool = Mempool::default();
+        let (worker, lot_id) = funded_worker(&mut ledger, 50, 0);
+        let pending = transfer(&worker, 1, new_address(), 50, vec![lot_id.clone()]);
+        mempool.add(pending, &ledger).unwrap();
+
+        // Another node mined a different spend of the same lot
+        add_block(
+            &mut ledger,
+            vec![transfer(&worker, 1, new_address(), 50, vec![lot_id])],
+            0,
+        );
+        mempool.update(&mut ledger);
+        assert!(mempool.is_empty());
+        assert_eq!(mempool.size_bytes(), 0);
+    }
+
+    #[test]
+    fn test_accepts_and_evicts_chained_transactions() {
+        let mut ledger = Ledger::new();
+        let (worker, lot_id) = funded_worker(&mut ledger, 50, 0);
+        let recipient = Keypair::generate(&mut OsRng);
+        let recipient_address = Address::new(&recipient.public, Network::Main);
+
+        let parent = transfer(&worker, 1, recipient_address, 50, vec![lot_id.clone()]);
+        let child = transfer(
+            &recipient,
+            0,
+            new_address(),
+            50,
+            vec![format!("{}:0", parent.id)],
+        );
+        let mut mempool = Mempool::default();
+        mempool.add(parent.clone(), &ledger).unwrap();
+        assert_eq!(mempool.add(child.clone(), &ledger), Ok(()));
+
+        // A conflicting spend of the parent's lot is mined, so the child goes with it
+        add_block(
+            &mut ledger,
+            vec![transfer(&worker, 1, new_address(), 50, vec![lot_id])],
+            0,
+        );
+        mempool.remove_block_transactions(ledger.get_latest_block());
+        assert!(mempool.is_empty());
+
+        // Removing a parent with its descendants takes the child too
+        let (worker, lot_id) = funded_worker(&mut ledger, 50, 10);
+        let parent = transfer(&worker, 1, recipient_address, 50, vec![lot_id]);
+        let child = transfer(
+            &recipient,
+            0,
+            new_address(),
+            50,
+            vec![format!("{}:0", parent.id)],
+        );
+        mempool.add(parent.clone(), &ledger).unwrap();
+        mempool.add(child, &ledger).unwrap();
+        mempool.remove_with_descendants(&parent.id);
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn test_collects_approvals_for_multisig_proposals() {
+        let mut ledger = Ledger::new();
+        let (worker, lot_id) = funded_worker(&mut ledger, 50, 0);
+        let members: Vec<Keypair> = (0..3).map(|_| Keypair::generate(&mut OsRng)).collect();
+        let policy = MultisigPolicy::new(2, members.iter().map(|member| member.public).collect());
+        let funding = transfer(&worker, 1, policy.address(Network::Main), 50, vec![lot_id]);
+        let treasury_lot = format!("{}:0", funding.id);
+        add_block(&mut ledger, vec![funding], 10);
+
+        let mut mempool = Mempool::default();
+        let mut payment =
+            Transaction::new_transfer(members[0].public, new_address(), 50, vec![treasury_lot])
+                .with_multisig(policy);
+        payment.approve(&members[0]);
+        assert_eq!(mempool.propose(payment.clone(), &ledger), Ok(1));
+        assert_eq!(mempool.proposals_len(), 1);
+        assert!(mempool.is_empty());
+
+        // Resubmitting adds nothing new, and outsiders cannot approve
+        assert_eq!(mempool.propose(payment.clone(), &ledger), Ok(1));
+        let outsider = Keypair::generate(&mut OsRng);
+        assert_eq!(
+            mempool.approve(&payment.id, Approval::sign(&payment.id, &outsider), &ledger),
+            Err(MempoolError::Invalid(ValidationError::InvalidTransaction(
+                payment.id.clone()
+            )))
+        );
+        assert_eq!(
+            mempool.approve("missing", Approval::sign("missing", &members[1]), &ledger),
+            Err(MempoolError::UnknownProposal("missing".to_string()))
+        );
+
+        // The second approval completes it, and it moves into the pool
+        let approval = Approval::sign(&payment.id, &members[2]);
+        assert_eq!(mempool.approve(&payment.id, approval, &ledger), Ok(0));
+        assert_eq!(mempool.proposals_len(), 0);
+        assert!(mempool.get_proposal(&payment.id).is_none());
+        assert_eq!(
+            mempool
+                .get(&payment.id)
+                .unwrap()
+                .transaction
+                .approvals
+                .len(),
+            2
+        );
+
+        // Proposals are only for multisig accounts
+        let (worker, lot_id) = funded_worker(&mut ledger, 50, 20);
+        assert!(mempool
+            .propose(
+                transfer(&worker, 1, new_address(), 50, vec![lot_id]),
+                &ledger
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_limits_and_funds_checks_proposals() {
+        let mut ledger = Ledger::new();
+        let (worker, lot_id) = funded_worker(&mut ledger, 50, 0);
+        let members: Vec<Keypair> = (0..3).map(|_| Keypair::generate(&mut OsRng)).collect();
+        let policy = MultisigPolicy::new(2, members.iter().map(|member| member.public).collect());
+        let treasury = policy.address(Network::Main).to_string();
+        let funding = transfer(&worker, 1, policy.address(Network::Main), 50, vec![lot_id]);
+        let treasury_lot = format!("{}:0", funding.id);
+        add_block(&mut ledger, vec![funding], 10);
+        let (_, other_lot) = funded_worker(&mut ledger, 50, 20);
+
+        let proposal = |nonce, amount, fee, inputs: Vec<String>| {
+            let mut tx =
+                Transaction::new_transfer(members[0].public, new_address(), amount, inputs)
+                    .with_multisig(policy.clone())
+                    .with_nonce(nonce)
+                    .with_fee(fee);
+            tx.approve(&members[0]);
+            tx
+        };
+
+        // Proposals must be funded by the account's own lots
+        let mut mempool = Mempool::default();
+        assert_eq!(
+            mempool.propose(proposal(0, 60, 0, vec![treasury_lot.clone()]), &ledger),
+            Err(MempoolError::Invalid(ValidationError::InsufficientFunds {
+                available: 50,
+                required: 60
+            }))
+        );
+        assert_eq!(
+            mempool.propose(proposal(0, 10, 0, vec![other_lot.clone()]), &ledger),
+            Err(MempoolError::Invalid(ValidationError::UnownedInput(
+                other_lot
+            )))
+        );
+
+        // Each account may only have so many waiting
+        for nonce in 0..MAX_PROPOSALS_PER_ACCOUNT as u64 {
+            let tx = proposal(nonce, 10, 0, vec![treasury_lot.clone()]);
+            assert_eq!(mempool.propose(tx, &ledger), Ok(1));
+        }
+        let extra = proposal(
+            MAX_PROPOSALS_PER_ACCOUNT as u64,
+            10,
+            0,
+            vec![treasury_lot.clone()],
+        );
+        assert_eq!(
+            mempool.propose(extra, &ledger),
+            Err(MempoolError::TooManyProposals(treasury.clone()))
+        );
+
+        // Once proposals fill their space, those paying less are evicted
+        let cheap = proposal(0, 10, 0, vec![treasury_lot.clone()]);
+        let mut mempool = Mempool::new(transaction_size(&cheap) * 2);
+        let cheaper = proposal(1, 10, 0, vec![treasury_lot.clone()]);
+        assert_eq!(mempool.propose(cheap.clone(), &ledger), Ok(1));
+        assert_eq!(mempool.propose(cheaper.clone(), &ledger), Ok(1));
+        assert_eq!(
+            mempool.propose(proposal(2, 10, 0, vec![treasury_lot.clone()]), &ledger),
+            Err(MempoolError::Full)
+        );
+        let paying = proposal(2, 10, 1, vec![treasury_lot.clone()]);
+        assert_eq!(mempool.propose(paying.clone(), &ledger), Ok(1));
+        assert!(mempool.get_proposal(&cheap.id).is_some());
+        assert!(mempool.get_proposal(&cheaper.id).is_none());
+
+        // Proposals whose lots are spent are dropped
+        let mut spend = proposal(0, 50, 0, vec![treasury_lot]);
+        spend.approve(&members[1]);
+        add_block(&mut ledger, vec![spend], 30);
+        mempool.update(&mut ledger);
+        assert_eq!(mempool.proposals_len(), 0);
+    }
+
+    #[test]
+    fn test_queues_future_nonces_until_gap_is_filled() {
+        let mut ledger = Ledger::new();
+        let mut mempool = Mempool::default();
+        let (worker, lot_id) = funded_worker(&mut ledger, 50, 0);
+        let sender = Address::new(&worker.public, Network::Main).to_string();
+        assert_eq!(mempool.next_nonce(&sender, &ledger), 1);
+
+        // Nonce 2 arrives before nonce 1, and waits for it
+        let first = transfer(&worker, 1, new_address(), 20, vec![lot_id]);
+        let change = format!("{}:1", first.id);
+        let second = transfer(&worker, 2, new_address(), 30, vec![change]);
+        assert_eq!(mempool.add(second.clone(), &ledger), Ok(()));
+        assert!(mempool.is_empty());
+        assert_eq!(mempool.queued_len(), 1);
+        assert_eq!(
+            mempool.add(second.clone(), &ledger),
+            Err(MempoolError::Duplicate(second.id.clone()))
+        );
+
+        mempool.add(first.clone(), &ledger).unwrap();
+        assert_eq!(mempool.queued_len(), 0);
+        let ordered: Vec<&str> = mempool
+            .transactions_by_priority()
+            .iter()
+            .map(|tx| tx.id.as_str())
+            .collect();
+        assert_eq!(ordered, vec![first.id.as_str(), second.id.as_str()]);
+        assert_eq!(mempool.next_nonce(&sender, &ledger), 3);
+
+        // A used nonce is refused
+        let reused = work_claim(&worker, 0, "work_reused", 10);
+        assert_eq!(
+            mempool.add(reused, &ledger),
+            Err(MempoolError::Invalid(ValidationError::InvalidNonce {
+                expected: 1,
+                found: 0
+            }))
+        );
+
+        // Once the first is mined, the second stays pending on its own
+        add_block(&mut ledger, vec![first], 0);
+        mempool.update(&mut ledger);
+        assert_eq!(mempool.len(), 1);
+        assert!(mempool.contains(&second.id));
+    }
+
+    #[test]
+    fn test_limits_queued_transactions() {
+        let mut ledger = Ledger::new();
+        let mut mempool = Mempool::default();
+        let (worker, _) = funded_worker(&mut ledger, 50, 0);
+        let queued = |nonce, amount, fee| {
+            let mut tx = Transaction::new_transfer(worker.public, new_address(), amount, vec![])
+                .with_nonce(nonce)
+                .with_fee(fee);
+            tx.sign(&worker);
+            tx
+        };
+
+        // Queued transactions cannot spend more than the sender holds
+        assert_eq!(
+            mempool.add(queued(2, 60, 0), &ledger),
+            Err(MempoolError::Invalid(ValidationError::InsufficientFunds {
+                available: 50,
+                required: 60
+            }))
+        );
+
+        let limit = MAX_QUEUED_PER_SENDER as u64;
+        for nonce in 3..3 + limit {
+            assert_eq!(mempool.add(queued(nonce, 1, 0), &ledger), Ok(()));
+        }
+        let sender = Address::new(&worker.public, Network::Main).to_string();
+        assert_eq!(
+            mempool.add(queued(3 + limit, 1, 0), &ledger),
+            Err(MempoolError::TooManyQueued(sender.clone()))
+        );
+
+        // A nonce closer to being valid takes the place of the furthest ahead
+        assert_eq!(mempool.add(queued(2, 1, 0), &ledger), Ok(()));
+        assert_eq!(mempool.queued_len(), MAX_QUEUED_PER_SENDER);
+        assert!(mempool.queued.contains_key(&(sender.clone(), 2)));
+        assert!(!mempool.queued.contains_key(&(sender.clone(), 2 + limit)));
+
+        // Once the queue is full, transactions paying higher fees evict those paying less
+        let mut mempool = Mempool::new(transaction_size(&queued(2, 1, 0)) * 2);
+        assert_eq!(mempool.add(queued(2, 1, 0), &ledger), Ok(()));
+        assert_eq!(mempool.add(queued(3, 1, 0), &ledger), Ok(()));
+        assert_eq!(
+            mempool.add(queued(4, 1, 0), &ledger),
+            Err(MempoolError::Full)
+        );
+        assert_eq!(mempool.add(queued(4, 1, 1), &ledger), Ok(()));
+        assert!(mempool.queued.contains_key(&(sender.clone(), 2)));
+        assert!(!mempool.queued.contains_key(&(sender, 3)));
+    }
+}
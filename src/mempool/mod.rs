@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod mempool;
+
+pub use self::mempool::{Mempool, MempoolEntry, MempoolError};
@@ -1,3 +1,6 @@
+use crate::blockchain::block::Block;
+use crate::blockchain::transaction::Transaction;
+use crate::consensus::finality::{FinalityVote, VoteStep};
 use serde::{Deserialize, Serialize};
 
 /// Enum to represent the type of message in the P2P network.
@@ -6,6 +9,10 @@ pub enum MessageType {
     Hello,
     Block,
     Transaction,
+    /// A validator's finality prevote for a block.
+    Prevote,
+    /// A validator's finality precommit for a block.
+    Precommit,
 }
 
 /// Struct to represent a message in the P2P network.
@@ -30,11 +37,92 @@ impl Message {
             payload,
         }
     }
+
+    /// Creates a block message carrying a block.
+    ///
+    /// # Arguments
+    /// * `block` - The block to send.
+    ///
+    /// # Returns
+    /// * `Message` - A `Block` message.
+    pub fn block(block: &Block) -> Self {
+        let payload = serde_json::to_string(block).expect("Blocks serialize to JSON");
+        Message::new(MessageType::Block, payload)
+    }
+
+    /// Reads the block carried by a block message.
+    ///
+    /// # Returns
+    /// * `Option<Block>` - The block, or `None` if this is not a block message or the payload
+    ///   is malformed.
+    pub fn to_block(&self) -> Option<Block> {
+        if self.message_type != MessageType::Block {
+            return None;
+        }
+        serde_json::from_str(&self.payload).ok()
+    }
+
+    /// Creates a transaction message carrying a transaction.
+    ///
+    /// # Arguments
+    /// * `transaction` - The transaction to send.
+    ///
+    /// # Returns
+    /// * `Message` - A `Transaction` message.
+    pub fn transaction(transaction: &Transaction) -> Self {
+        let payload = serde_json::to_string(transaction).expect("Transactions serialize to JSON");
+        Message::new(MessageType::Transaction, payload)
+    }
+
+    /// Reads the transaction carried by a transaction message.
+    ///
+    /// # Returns
+    /// * `Option<Transaction>` - The transaction, or `None` if this is not a transaction
+    ///   message or the payload is malformed.
+    pub fn to_transaction(&self) -> Option<Transaction> {
+        if self.message_type != MessageType::Transaction {
+            return None;
+        }
+        serde_json::from_str(&self.payload).ok()
+    }
+
+    /// Creates a prevote or precommit message carrying a finality vote.
+    ///
+    /// # Arguments
+    /// * `vote` - The vote to send.
+    ///
+    /// # Returns
+    /// * `Message` - A `Prevote` or `Precommit` message, matching the vote's step.
+    pub fn finality_vote(vote: &FinalityVote) -> Self {
+        let message_type = match vote.step {
+            VoteStep::Prevote => MessageType::Prevote,
+            VoteStep::Precommit => MessageType::Precommit,
+        };
+        let payload = serde_json::to_string(vote).expect("Votes serialize to JSON");
+        Message::new(message_type, payload)
+    }
+
+    /// Reads the finality vote carried by a prevote or precommit message.
+    ///
+    /// # Returns
+    /// * `Option<FinalityVote>` - The vote, or `None` if this is not a vote message or the
+    ///   payload is malformed or does not match the message type.
+    pub fn to_finality_vote(&self) -> Option<FinalityVote> {
+        let step = match self.message_type {
+            MessageType::Prevote => VoteStep::Prevote,
+            MessageType::Precommit => VoteStep::Precommit,
+            _ => return None,
+        };
+        serde_json::from_str::<FinalityVote>(&self.payload)
+            .ok()
+            .filter(|vote| vote.step == step)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::address::{Address, Network};
 
     #[test]
     fn test_message_serialization() {
@@ -46,4 +134,46 @@ mod tests {
         assert_eq!(message.message_type, deserialized.message_type);
         assert_eq!(message.payload, deserialized.payload);
     }
+
+    #[test]
+    fn test_finality_vote_round_trip() {
+        let validator = ed25519_dalek::Keypair::generate(&mut rand::rngs::OsRng);
+        let vote = FinalityVote::new(VoteStep::Precommit, 3, 0, "block".to_string(), &validator);
+
+        let message = Message::finality_vote(&vote);
+        assert_eq!(message.message_type, MessageType::Precommit);
+        assert_eq!(message.to_finality_vote(), Some(vote));
+
+        // A vote relabelled as the other step is rejected
+        let relabelled = Message::new(MessageType::Prevote, message.payload);
+        assert_eq!(relabelled.to_finality_vote(), None);
+    }
+
+    #[test]
+    fn test_block_round_trip() {
+        let block = Block::new(1, "0".to_string(), vec![], 0);
+
+        let message = Message::block(&block);
+        assert_eq!(message.message_type, MessageType::Block);
+        assert_eq!(message.to_block().map(|block| block.hash), Some(block.hash));
+
+        // Another message type carrying the same payload is not a block
+        let relabelled = Message::new(MessageType::Transaction, message.payload);
+        assert!(relabelled.to_block().is_none());
+    }
+
+    #[test]
+    fn test_transaction_round_trip() {
+        let sender = ed25519_dalek::Keypair::generate(&mut rand::rngs::OsRng);
+        let receiver = Address::new(&sender.public, Network::Main);
+        let transaction = Transaction::new(sender.public, receiver, 10, None);
+
+        let message = Message::transaction(&transaction);
+        assert_eq!(message.message_type, MessageType::Transaction);
+        assert_eq!(
+            message.to_transaction().map(|transaction| transaction.id),
+            Some(transaction.id)
+        );
+        assert!(message.to_block().is_none());
+    }
 }
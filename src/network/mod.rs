@@ -2,4 +2,4 @@ pub mod message;
 pub mod p2p;
 
 pub use self::message::{Message, MessageType};
-pub use self::p2p::P2PNetwork;
+pub use self::p2p::{MessageHandler, P2PNetwork};
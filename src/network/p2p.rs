@@ -1,5 +1,5 @@
 use crate::network::message::{Message, MessageType};
-use std::io::{Read, Write};
+use std::io::Write;
 use std::net::{TcpListener, TcpStream};
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -9,7 +9,14 @@ pub struct Peer {
     pub address: String,
 }
 
-/// Struct to manage the P2P network.
+/// Whatever acts on the messages a node receives from its peers, such as the node itself.
+pub trait MessageHandler: Send + Sync {
+    /// Handles a message received from a peer.
+    fn handle_message(&self, message: Message);
+}
+
+/// Struct to manage the P2P network. Clones share the same list of peers.
+#[derive(Clone)]
 pub struct P2PNetwork {
     peers: Arc<Mutex<Vec<Peer>>>, // List of connected peers
 }
@@ -22,18 +29,25 @@ impl P2PNetwork {
         }
     }
 
-    /// Starts the node as a server, accepting incoming connections.
-    pub fn start_server(&self, address: &str) {
+    /// Starts the node as a server, accepting incoming connections and handing every message
+    /// received on them to `handler`.
+    pub fn start_server(&self, address: &str, handler: Arc<dyn MessageHandler>) {
         let listener = TcpListener::bind(address).expect("Failed to bind to address");
 
         println!("Node listening on: {}", address);
 
+        self.serve(listener, handler);
+    }
+
+    /// Accepts incoming connections on a listener that is already bound, handing every
+    /// message received on them to `handler`.
+    pub fn serve(&self, listener: TcpListener, handler: Arc<dyn MessageHandler>) {
         for stream in listener.incoming() {
             match stream {
                 Ok(stream) => {
-                    let peers = Arc::clone(&self.peers);
+                    let handler = Arc::clone(&handler);
                     thread::spawn(move || {
-                        P2PNetwork::handle_connection(stream, peers);
+                        P2PNetwork::handle_connection(stream, handler.as_ref());
                     });
                 }
                 Err(e) => {
@@ -67,34 +81,32 @@ impl P2PNetwork {
         }
     }
 
-    /// Handles incoming connections from other nodes.
-    fn handle_connection(mut stream: TcpStream, peers: Arc<Mutex<Vec<Peer>>>) {
-        let mut buffer = [0; 1024];
-        while match stream.read(&mut buffer) {
-            Ok(size) if size > 0 => {
-                let message: Message =
-                    bincode::deserialize(&buffer[..size]).expect("Failed to deserialize message");
-                P2PNetwork::process_message(message, Arc::clone(&peers));
-                true
+    /// Sends a message to every connected peer.
+    pub fn broadcast(&self, message: &Message) {
+        let serialized_message = bincode::serialize(message).unwrap();
+        for peer in self.peers.lock().unwrap().iter() {
+            match TcpStream::connect(&peer.address) {
+                Ok(mut stream) => {
+                    if let Err(e) = stream.write_all(&serialized_message) {
+                        eprintln!("Failed to send message to {}: {}", peer.address, e);
+                    }
+                }
+                Err(e) => eprintln!("Failed to connect to peer {}: {}", peer.address, e),
             }
-            _ => false,
-        } {}
+        }
     }
 
-    /// Processes incoming messages based on the message type.
-    fn process_message(message: Message, _peers: Arc<Mutex<Vec<Peer>>>) {
-        match message.message_type {
-            MessageType::Hello => {
-                println!("Received hello message: {}", message.payload);
-            }
-            MessageType::Block => {
-                println!("Received block message: {}", message.payload);
-                // Handle block processing
-            }
-            MessageType::Transaction => {
-                println!("Received transaction message: {}", message.payload);
-                // Handle transaction processing
-            }
+    /// Handles incoming connections from other nodes, reading messages until the peer
+    /// closes the connection or sends something that is not a message.
+    fn handle_connection(mut stream: TcpStream, handler: &dyn MessageHandler) {
+        while let Ok(message) = bincode::deserialize_from::<_, Message>(&mut stream) {
+            handler.handle_message(message);
         }
     }
 }
+
+impl Default for P2PNetwork {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,314 @@
+use crate::blockchain::block::Block;
+use crate::blockchain::ledger::Ledger;
+use crate::blockchain::transaction::Transaction;
+use crate::consensus::validator::ValidationError;
+use crate::crypto::address::{Address, Network};
+use crate::mempool::mempool::Mempool;
+use std::collections::HashSet;
+
+/// Default limit on the total size of the transactions in a block, in bytes.
+pub const DEFAULT_MAX_BLOCK_BYTES: usize = 1024 * 1024;
+/// Default limit on the number of transactions in a block.
+pub const DEFAULT_MAX_BLOCK_TRANSACTIONS: usize = 1000;
+
+/// Assembles block templates on top of the ledger's tip from pending transactions, ready for
+/// the consensus engine to seal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockBuilder {
+    /// Limit on the total size of the transactions in a block, in bytes.
+    pub max_bytes: usize,
+    /// Limit on the number of transactions in a block.
+    pub max_transactions: usize,
+}
+
+impl BlockBuilder {
+    /// Creates a builder that fills blocks up to the given limits.
+    pub fn new(max_bytes: usize, max_transactions: usize) -> Self {
+        BlockBuilder {
+            max_bytes,
+            max_transactions,
+        }
+    }
+
+    /// Builds a block template extending the ledger's tip.
+    ///
+    /// Tokens are only ever minted by work claims, so there is no coinbase: the work claims
+    /// the producer passes in go first, followed by the mempool's transactions in priority
+    /// order, which places every transaction after the pending transactions it spends from.
+    /// Each transaction is applied in turn, and any that no longer applies, would repeat a
+    /// claim already in the block or does not fit within the limits is left out, along with
+    /// anything spending its outputs.
+    ///
+    /// The block carries the target the engine requires and the current time, which must not
+    /// be earlier than the engine lets the sealer seal at: under Proof of Authority, the
+    /// sealer has to wait for their slot. It names the sealer as the beneficiary of its fees.
+    /// Its merkle root and state root are filled in; only the seal is left to the engine.
+    ///
+    /// # Arguments
+    /// * `ledger` - The ledger whose tip the block extends.
+    /// * `mempool` - The pending transactions to fill the block with.
+    /// * `work_claims` - Work claims the producer wants included ahead of the mempool's.
+    /// * `sealer` - The hex-encoded public key of whoever will seal the block, if any.
+    ///
+    /// # Returns
+    /// * `Result<Block, ValidationError>` - The unsealed block, or why none can be built, for
+    ///   example because the sealer is not an authority or their slot has not started.
+    pub fn build(
+        &self,
+        ledger: &Ledger,
+        mempool: &Mempool,
+        work_claims: Vec<Transaction>,
+        sealer: &str,
+    ) -> Result<Block, ValidationError> {
+        let tip = ledger.get_latest_block();
+        let height = tip.header.height + 1;
+        let earliest = ledger
+            .consensus
+            .earliest_timestamp(&ledger.chain, sealer)
+            .ok_or_else(|| ValidationError::UnknownAuthority(sealer.to_string()))?;
+        let timestamp = ledger.next_block_time();
+        if timestamp < earliest {
+            return Err(ValidationError::OutOfTurn {
+                authority: sealer.to_string(),
+                earliest,
+            });
+        }
+
+        // Expired lots are swept before a block's transactions are applied
+        let mut state = ledger.state.clone();
+        state.sweep_expired(height, timestamp);
+
+        let mut transactions = Vec::new();
+        let mut size = 0;
+        let mut claimed_work = HashSet::new();
+        for transaction in work_claims.iter().chain(mempool.transactions_by_priority()) {
+            if transactions.len() >= self.max_transactions {
+                break;
+            }
+            let transaction_size = bincode::serialize(transaction)
+                .expect("Transactions serialize")
+                .len();
+            if size + transaction_size > self.max_bytes {
+                continue;
+            }
+            let work_hash = transaction
+                .work_claim()
+                .map(|claim| claim.work_hash.clone());
+            if work_hash
+                .as_ref()
+                .is_some_and(|work_hash| claimed_work.contains(work_hash))
+            {
+                continue;
+            }
+
+            let mut next_state = state.clone();
+            if ledger
+                .apply_pending_transaction(transaction, &mut next_state, timestamp)
+                .is_err()
+            {
+                continue;
+            }
+            state = next_state;
+            size += transaction_size;
+            claimed_work.extend(work_hash);
+            transactions.push(transaction.clone());
+        }
+
+        let mut block = Block::new(height, tip.hash.clone(), transactions, 0);
+        block.header.timestamp = timestamp;
+        block.header.target = ledger.next_target();
+        let network = Network::for_chain(ledger.state.chain_id);
+        block.header.beneficiary = Address::from_hex_key(sealer, network).ok();
+        ledger.commit_state_root(&mut block)?;
+        Ok(block)
+    }
+}
+
+impl Default for BlockBuilder {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_BLOCK_BYTES, DEFAULT_MAX_BLOCK_TRANSACTIONS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::proof_of_authority::{ProofOfAuthority, DEFAULT_BLOCK_PERIOD};
+    use crate::test_utils::{address, new_address, transfer, work_claim};
+    use ed25519_dalek::Keypair;
+    use rand::rngs::OsRng;
+    use std::sync::Arc;
+
+    /// Returns the hex-encoded public key that identifies an authority.
+    fn authority(keypair: &Keypair) -> String {
+        hex::encode(keypair.public.as_bytes())
+    }
+
+    #[test]
+    fn test_orders_children_after_parents() {
+        let mut ledger = Ledger::new();
+        let mut mempool = Mempool::default();
+        let worker = Keypair::generate(&mut OsRng);
+        let recipient = Keypair::generate(&mut OsRng);
+
+        // A claim, a transfer of the lot it mints and an onward transfer, all pending at once
+        let claim = work_claim(&worker, 0, "work_1", 50);
+        let payment = transfer(
+            &worker,
+            1,
+            address(&recipient),
+            50,
+            vec![format!("{}:0", claim.id)],
+        );
+        let onward_recipient = new_address();
+        let onward = transfer(
+            &recipient,
+            0,
+            onward_recipient,
+            50,
+            vec![format!("{}:0", payment.id)],
+        );
+        // The onward transfer is not valid until the lot it spends is pending
+        assert!(mempool.add(onward.clone(), &ledger).is_err());
+        for tx in [claim.clone(), payment.clone(), onward.clone()] {
+            mempool.add(tx, &ledger).unwrap();
+        }
+        assert_eq!(mempool.len(), 3);
+
+        let block = BlockBuilder::default()
+            .build(&ledger, &mempool, vec![], "")
+            .unwrap();
+        let ids: Vec<&str> = block.transactions.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![claim.id.as_str(), payment.id.as_str(), onward.id.as_str()]
+        );
+
+        assert!(ledger.add_block(block));
+        assert_eq!(ledger.get_balance(&onward_recipient.to_string()), 50);
+        mempool.update(&mut ledger);
+        assert!(mempool.is_empty());
+    }
+
+    #[test]
+    fn test_respects_size_and_count_limits() {
+        let ledger = Ledger::new();
+        let mut mempool = Mempool::default();
+        let claims: Vec<Transaction> = (0..3)
+            .map(|i| {
+                work_claim(
+                    &Keypair::generate(&mut OsRng),
+                    0,
+                    &format!("work_{}", i),
+                    10,
+                )
+            })
+            .collect();
+        for tx in &claims {
+            mempool.add(tx.clone(), &ledger).unwrap();
+        }
+
+        let by_count = BlockBuilder::new(DEFAULT_MAX_BLOCK_BYTES, 2)
+            .build(&ledger, &mempool, vec![], "")
+            .unwrap();
+        assert_eq!(by_count.transactions.len(), 2);
+
+        let claim_size = bincode::serialize(&claims[0]).unwrap().len();
+        let by_size = BlockBuilder::new(claim_size, DEFAULT_MAX_BLOCK_TRANSACTIONS)
+            .build(&ledger, &mempool, vec![], "")
+            .unwrap();
+        assert_eq!(by_size.transactions.len(), 1);
+
+        // A child left out with its parent does not make it in on its own
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = work_claim(&worker, 0, "work_big", 50);
+        let spend = transfer(
+            &worker,
+            1,
+            address(&worker),
+            50,
+            vec![format!("{}:0", claim.id)],
+        );
+        let mut mempool = Mempool::default();
+        mempool.add(claim, &ledger).unwrap();
+        mempool.add(spend.clone(), &ledger).unwrap();
+        let block = BlockBuilder::new(bincode::serialize(&spend).unwrap().len(), 10)
+            .build(&ledger, &mempool, vec![], "")
+            .unwrap();
+        assert!(block.transactions.is_empty());
+    }
+
+    #[test]
+    fn test_own_work_claims_go_first_and_are_not_repeated() {
+        let ledger = Ledger::new();
+        let mut mempool = Mempool::default();
+        let worker = Keypair::generate(&mut OsRng);
+        let pending = work_claim(&worker, 0, "work_1", 10);
+        let own = work_claim(&worker, 0, "work_1", 20);
+        let other = work_claim(&worker, 1, "work_2", 30);
+        mempool.add(pending, &ledger).unwrap();
+        mempool.add(other.clone(), &ledger).unwrap();
+
+        let block = BlockBuilder::default()
+            .build(&ledger, &mempool, vec![own.clone()], "")
+            .unwrap();
+        let ids: Vec<&str> = block.transactions.iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ids, vec![own.id.as_str(), other.id.as_str()]);
+        assert!(block.validate());
+        assert_eq!(
+            block.header.merkle_root,
+            Block::calculate_merkle_root(&block.transactions)
+        );
+    }
+
+    #[test]
+    fn test_proof_of_authority_templates_start_at_sealer_slot() {
+        let authority_key = Keypair::generate(&mut OsRng);
+        let rules = ProofOfAuthority::new(vec![authority(&authority_key)]);
+        let ledger = Ledger::new().with_consensus(Arc::new(rules));
+        let mempool = Mempool::default();
+
+        let outsider = authority(&Keypair::generate(&mut OsRng));
+        assert_eq!(
+            BlockBuilder::default()
+                .build(&ledger, &mempool, vec![], &outsider)
+                .unwrap_err(),
+            ValidationError::UnknownAuthority(outsider)
+        );
+
+        let block = BlockBuilder::default()
+            .build(&ledger, &mempool, vec![], &authority(&authority_key))
+            .unwrap();
+        let earliest = ledger.get_latest_block().header.timestamp + DEFAULT_BLOCK_PERIOD;
+        assert!(block.header.timestamp >= earliest);
+        // The sealer collects the block's fees
+        assert_eq!(block.header.beneficiary, Some(address(&authority_key)));
+    }
+
+    #[test]
+    fn test_proof_of_authority_templates_wait_for_sealer_slot() {
+        let authority_key = Keypair::generate(&mut OsRng);
+        let sealer = authority(&authority_key);
+        let rules = ProofOfAuthority::new(vec![sealer.clone()]);
+        let mut ledger = Ledger::new().with_consensus(Arc::new(rules));
+        let mempool = Mempool::default();
+
+        // Once a block is stamped with the current time, the next slot is still to come
+        let mut block = BlockBuilder::default()
+            .build(&ledger, &mempool, vec![], &sealer)
+            .unwrap();
+        ProofOfAuthority::seal_block(&mut block, &authority_key);
+        assert!(ledger.add_block(block.clone()));
+
+        let earliest = block.header.timestamp + DEFAULT_BLOCK_PERIOD;
+        assert_eq!(
+            BlockBuilder::default()
+                .build(&ledger, &mempool, vec![], &sealer)
+                .unwrap_err(),
+            ValidationError::OutOfTurn {
+                authority: sealer,
+                earliest
+            }
+        );
+    }
+}
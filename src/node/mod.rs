@@ -0,0 +1,6 @@
+pub mod builder;
+#[allow(clippy::module_inception)]
+pub mod node;
+
+pub use self::builder::BlockBuilder;
+pub use self::node::Node;
@@ -0,0 +1,701 @@
+use crate::blockchain::block::Block;
+use crate::blockchain::ledger::Ledger;
+use crate::blockchain::transaction::Transaction;
+use crate::consensus::engine::Sealer;
+use crate::consensus::finality::{FinalityEvent, FinalityGadget, FinalityVote, MAX_VOTE_LOOKAHEAD};
+use crate::consensus::miner::{CancellationToken, Miner, MiningReport};
+use crate::mempool::mempool::{Mempool, MempoolError};
+use crate::network::message::{Message, MessageType};
+use crate::network::p2p::{MessageHandler, P2PNetwork};
+use crate::node::builder::BlockBuilder;
+use ed25519_dalek::Keypair;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long a validator waits for a height to be finalized in a voting round before moving
+/// on to the next round and prevoting again.
+pub const DEFAULT_ROUND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A node's part in finality: the gadget it counts votes with, the voting round it is in at
+/// each height that is not yet final, and a block the validators finalized that the ledger
+/// has not received yet.
+struct Finality {
+    gadget: FinalityGadget,
+    /// The round at each height the node has prevoted at, with when the round started.
+    rounds: BTreeMap<u64, (u64, Instant)>,
+    /// Hash of the latest block the validators finalized, until the ledger finalizes it too.
+    pending: Option<String>,
+}
+
+/// A node that follows the chain and produces new blocks on top of it from its mempool.
+///
+/// Sealing runs without holding the ledger lock, so blocks from peers can still be accepted
+/// while a block is being mined. Accepting one cancels the sealing in progress, since a block
+/// built on the old tip can no longer extend the chain.
+///
+/// A node with a finality gadget prevotes every block it adds to the main chain, precommits
+/// blocks that gather a prevote quorum and finalizes blocks in the ledger once they gather a
+/// precommit quorum, sending its votes and blocks to its peers. A height that is not final
+/// within the round timeout moves on to the next round, in which the node prevotes again.
+pub struct Node {
+    /// The ledger the node follows, shared with the RPC server.
+    pub ledger: Arc<Mutex<Ledger>>,
+    /// The transactions waiting to be included in a block, shared with the RPC server.
+    pub mempool: Arc<Mutex<Mempool>>,
+    /// Assembles new blocks from the mempool.
+    pub builder: BlockBuilder,
+    /// The miner used to seal blocks under Proof of Work.
+    pub miner: Miner,
+    /// The node's keypair, used to seal blocks under Proof of Authority.
+    pub keypair: Option<Keypair>,
+    /// The peers the node sends its blocks and finality votes to.
+    pub network: P2PNetwork,
+    /// How long the node waits in a voting round before moving on to the next one.
+    pub round_timeout: Duration,
+    /// Cancels the block currently being sealed, if any.
+    mining: Mutex<Option<CancellationToken>>,
+    /// The node's finality gadget and voting rounds, if it takes part in finality. The
+    /// finality lock is always taken before the ledger lock.
+    finality: Option<Mutex<Finality>>,
+}
+
+impl Node {
+    /// Creates a node that follows the given ledger and mines with the given miner, with an
+    /// empty mempool and the default block limits.
+    pub fn new(ledger: Arc<Mutex<Ledger>>, miner: Miner) -> Self {
+        Node {
+            ledger,
+            mempool: Arc::new(Mutex::new(Mempool::default())),
+            builder: BlockBuilder::default(),
+            miner,
+            keypair: None,
+            network: P2PNetwork::new(),
+            round_timeout: DEFAULT_ROUND_TIMEOUT,
+            mining: Mutex::new(None),
+            finality: None,
+        }
+    }
+
+    /// Sets the mempool the node fills blocks from.
+    pub fn with_mempool(mut self, mempool: Arc<Mutex<Mempool>>) -> Self {
+        self.mempool = mempool;
+        self
+    }
+
+    /// Sets the builder the node assembles blocks with.
+    pub fn with_builder(mut self, builder: BlockBuilder) -> Self {
+        self.builder = builder;
+        self
+    }
+
+    /// Sets the keypair the node seals blocks with, for engines where blocks are signed, and
+    /// votes with, if it is a validator.
+    pub fn with_keypair(mut self, keypair: Keypair) -> Self {
+        self.keypair = Some(keypair);
+        self
+    }
+
+    /// Sets the network the node sends its blocks and finality votes over.
+    pub fn with_network(mut self, network: P2PNetwork) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Has the node take part in finality through the given gadget. It votes if its keypair
+    /// belongs to one of the gadget's validators, and only follows the votes otherwise.
+    pub fn with_finality(mut self, gadget: FinalityGadget) -> Self {
+        self.finality = Some(Mutex::new(Finality {
+            gadget,
+            rounds: BTreeMap::new(),
+            pending: None,
+        }));
+        self
+    }
+
+    /// Sets how long the node waits in a voting round before moving on to the next one.
+    pub fn with_round_timeout(mut self, round_timeout: Duration) -> Self {
+        self.round_timeout = round_timeout;
+        self
+    }
+
+    /// Starts a new mining job, cancelling the one in progress, if any.
+    ///
+    /// # Returns
+    /// * `CancellationToken` - The token that is cancelled when the chain tip moves on.
+    pub fn begin_mining(&self) -> CancellationToken {
+        let cancel = CancellationToken::new();
+        if let Some(previous) = self
+            .mining
+            .lock()
+            .expect("Mining lock poisoned")
+            .replace(cancel.clone())
+        {
+            previous.cancel();
+        }
+        cancel
+    }
+
+    /// Cancels the mining in progress, if any.
+    pub fn cancel_mining(&self) {
+        if let Some(cancel) = self.mining.lock().expect("Mining lock poisoned").take() {
+            cancel.cancel();
+        }
+    }
+
+    /// Builds a block from the mempool on top of the current tip, has the ledger's consensus
+    /// engine seal it, and adds it to the ledger. Under Proof of Authority, the node first
+    /// waits for its slot to start.
+    ///
+    /// # Arguments
+    /// * `work_claims` - Work claims to include ahead of the mempool's transactions.
+    ///
+    /// # Returns
+    /// * `Option<Block>` - The block, once it extends the chain, or `None` if no block can
+    ///   be built, sealing was cancelled or is not possible for this node, or the tip moved
+    ///   on before the block was sealed.
+    pub fn produce_block(&self, work_claims: Vec<Transaction>) -> Option<Block> {
+        let cancel = self.begin_mining();
+        let sealer = Sealer {
+            keypair: self.keypair.as_ref(),
+            miner: &self.miner,
+            cancel: &cancel,
+        };
+
+        let earliest = {
+            let ledger = self.ledger.lock().expect("Ledger lock poisoned");
+            ledger
+                .consensus
+                .earliest_timestamp(&ledger.chain, &sealer.address())
+        };
+        if let Some(earliest) = earliest {
+            if !wait_until(earliest, &cancel) {
+                return None;
+            }
+        }
+
+        let (mut block, consensus) = {
+            let ledger = self.ledger.lock().expect("Ledger lock poisoned");
+            let mempool = self.mempool.lock().expect("Mempool lock poisoned");
+            match self
+                .builder
+                .build(&ledger, &mempool, work_claims, &sealer.address())
+            {
+                Ok(block) => (block, ledger.consensus.clone()),
+                Err(err) => {
+                    println!("Error: Cannot build block: {}", err);
+                    return None;
+                }
+            }
+        };
+
+        if !consensus.seal(&mut block, &sealer) {
+            return None;
+        }
+
+        let extended = {
+            let mut ledger = self.ledger.lock().expect("Ledger lock poisoned");
+            let extended =
+                ledger.add_block(block.clone()) && ledger.get_latest_block().hash == block.hash;
+            if extended {
+                self.update_mempool(&mut ledger);
+            }
+            extended
+        };
+        if !extended {
+            return None;
+        }
+        self.network.broadcast(&Message::block(&block));
+        if let Some(finality) = &self.finality {
+            self.prevote_chain(&mut finality.lock().expect("Finality lock poisoned"));
+        }
+        Some(block)
+    }
+
+    /// Adds a block received from a peer to the ledger. If it moves the tip, whether by
+    /// extending the chain or by a reorg, the mining in progress is cancelled, since it was
+    /// building on the old tip, and the mempool is brought up to date with the new tip.
+    ///
+    /// A node taking part in finality then finalizes the block the validators finalized
+    /// before it arrived, if this block is it or leads to it, and prevotes the blocks now on
+    /// the main chain.
+    ///
+    /// # Arguments
+    /// * `block` - The block received.
+    ///
+    /// # Returns
+    /// * `bool` - Returns `true` if the block was added to the ledger.
+    pub fn receive_block(&self, block: Block) -> bool {
+        let (added, tip_moved) = {
+            let mut ledger = self.ledger.lock().expect("Ledger lock poisoned");
+            let tip = ledger.get_latest_block().hash.clone();
+            let added = ledger.add_block(block);
+            let tip_moved = ledger.get_latest_block().hash != tip;
+            if tip_moved {
+                self.update_mempool(&mut ledger);
+            }
+            (added, tip_moved)
+        };
+        if tip_moved {
+            self.cancel_mining();
+        }
+        if let (true, Some(finality)) = (added, &self.finality) {
+            let mut finality = finality.lock().expect("Finality lock poisoned");
+            self.finalize_pending(&mut finality);
+            self.prevote_chain(&mut finality);
+        }
+        added
+    }
+
+    /// Validates a transaction relayed by a peer against the ledger and the pending
+    /// transactions, and adds it to the mempool.
+    ///
+    /// # Arguments
+    /// * `transaction` - The transaction received.
+    ///
+    /// # Returns
+    /// * `Result<(), MempoolError>` - Why the transaction was refused, if it was.
+    pub fn receive_transaction(&self, transaction: Transaction) -> Result<(), MempoolError> {
+        let ledger = self.ledger.lock().expect("Ledger lock poisoned");
+        self.mempool
+            .lock()
+            .expect("Mempool lock poisoned")
+            .add(transaction, &ledger)
+    }
+
+    /// Counts a finality vote received from a peer. Does nothing unless the node takes part
+    /// in finality.
+    ///
+    /// # Arguments
+    /// * `vote` - The vote received.
+    pub fn receive_vote(&self, vote: FinalityVote) {
+        if let Some(finality) = &self.finality {
+            self.count_vote(&mut finality.lock().expect("Finality lock poisoned"), vote);
+        }
+    }
+
+    /// Moves every height whose voting round has lasted longer than the round timeout on to
+    /// the next round, and prevotes the main chain's block at it again. A validator locked on
+    /// a block at that height prevotes the locked block instead.
+    pub fn advance_rounds(&self) {
+        let Some(finality) = &self.finality else {
+            return;
+        };
+        let mut finality = finality.lock().expect("Finality lock poisoned");
+        let now = Instant::now();
+        for (round, started) in finality.rounds.values_mut() {
+            if now.duration_since(*started) >= self.round_timeout {
+                *round += 1;
+                *started = now;
+            }
+        }
+        self.prevote_chain(&mut finality);
+    }
+
+    /// Starts a thread that moves voting rounds on as they time out, until the node is
+    /// dropped.
+    pub fn start_round_timer(self: &Arc<Self>) {
+        let node = Arc::downgrade(self);
+        thread::spawn(move || loop {
+            let Some(node) = node.upgrade() else {
+                return;
+            };
+            node.advance_rounds();
+            let period = (node.round_timeout / 4).max(Duration::from_millis(10));
+            drop(node);
+            thread::sleep(period);
+        });
+    }
+
+    /// Returns the voting round the node is in at a height that is not yet final, if it has
+    /// prevoted at that height.
+    pub fn round(&self, height: u64) -> Option<u64> {
+        let finality = self.finality.as_ref()?;
+        let finality = finality.lock().expect("Finality lock poisoned");
+        finality.rounds.get(&height).map(|(round, _)| *round)
+    }
+
+    /// Returns the hash rate of the most recent mining attempt, in hashes per second.
+    pub fn hashrate(&self) -> f64 {
+        self.miner
+            .last_report()
+            .as_ref()
+            .map_or(0.0, MiningReport::hashrate)
+    }
+
+    /// Prevotes, in the node's current round at each height, every block on the main chain
+    /// above the finalized block that the node has not prevoted in that round yet, then counts
+    /// the votes and sends them to its peers. Does nothing unless the node is a validator.
+    fn prevote_chain(&self, finality: &mut Finality) {
+        let Some(keypair) = &self.keypair else {
+            return;
+        };
+        let blocks: Vec<(u64, String)> = {
+            let ledger = self.ledger.lock().expect("Ledger lock poisoned");
+            ledger
+                .chain
+                .iter()
+                .skip(ledger.finalized_height as usize + 1)
+                .take(MAX_VOTE_LOOKAHEAD as usize)
+                .map(|block| (block.header.height, block.hash.clone()))
+                .collect()
+        };
+        for (height, block_hash) in blocks {
+            let settled = finality
+                .gadget
+                .finalized_height()
+                .is_some_and(|finalized| finalized >= height);
+            if settled {
+                continue;
+            }
+            let round = finality
+                .rounds
+                .entry(height)
+                .or_insert((0, Instant::now()))
+                .0;
+            if let Some(vote) = finality.gadget.prevote(height, round, block_hash, keypair) {
+                self.network.broadcast(&Message::finality_vote(&vote));
+                self.count_vote(finality, vote);
+            }
+        }
+    }
+
+    /// Hands a finality vote to the gadget and acts on what it reports: precommits a block
+    /// that reached a prevote quorum, if this node is a validator and the block is on its main
+    /// chain, and finalizes a block that reached a precommit quorum.
+    fn count_vote(&self, finality: &mut Finality, vote: FinalityVote) {
+        let mut votes = vec![vote];
+        while let Some(vote) = votes.pop() {
+            match finality.gadget.add_vote(vote) {
+                Ok(Some(FinalityEvent::Prevoted {
+                    height,
+                    round,
+                    block_hash,
+                })) => {
+                    let Some(keypair) = &self.keypair else {
+                        continue;
+                    };
+                    let accepted = self
+                        .ledger
+                        .lock()
+                        .expect("Ledger lock poisoned")
+                        .get_block_by_hash(&block_hash)
+                        .is_some();
+                    if !accepted {
+                        continue;
+                    }
+                    if let Some(precommit) = finality.gadget.precommit(height, round, keypair) {
+                        self.network.broadcast(&Message::finality_vote(&precommit));
+                        votes.push(precommit);
+                    }
+                }
+                Ok(Some(FinalityEvent::Finalized { height, block_hash })) => {
+                    finality.rounds.retain(|voted, _| *voted > height);
+                    finality.pending = Some(block_hash);
+                    self.finalize_pending(finality);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("Rejected finality vote: {}", e),
+            }
+        }
+    }
+
+    /// Finalizes the block the validators finalized in the ledger, which reorganizes onto its
+    /// branch if it is on a side branch. A block the ledger has not received yet stays
+    /// pending, and is finalized once it arrives.
+    fn finalize_pending(&self, finality: &mut Finality) {
+        let Some(hash) = finality.pending.clone() else {
+            return;
+        };
+        let tip_moved = {
+            let mut ledger = self.ledger.lock().expect("Ledger lock poisoned");
+            if ledger.find_block(&hash).is_none() {
+                return;
+            }
+            let tip = ledger.get_latest_block().hash.clone();
+            if ledger.finalize_block(&hash) {
+                finality.pending = None;
+            }
+            let tip_moved = ledger.get_latest_block().hash != tip;
+            if tip_moved {
+                self.update_mempool(&mut ledger);
+            }
+            tip_moved
+        };
+        if tip_moved {
+            self.cancel_mining();
+        }
+    }
+
+    /// Drops transactions the new tip included or invalidated from the mempool, and takes
+    /// back any a reorg dropped. The ledger lock is always taken before the mempool lock.
+    fn update_mempool(&self, ledger: &mut Ledger) {
+        self.mempool
+            .lock()
+            .expect("Mempool lock poisoned")
+            .update(ledger);
+    }
+}
+
+impl MessageHandler for Node {
+    /// Adds blocks from peers to the ledger, their transactions to the mempool, and counts
+    /// their finality votes.
+    fn handle_message(&self, message: Message) {
+        match message.message_type {
+            MessageType::Hello => {
+                println!("Received hello message: {}", message.payload);
+            }
+            MessageType::Block => match message.to_block() {
+                Some(block) => {
+                    self.receive_block(block);
+                }
+                None => eprintln!("Ignored block message: {}", message.payload),
+            },
+            MessageType::Transaction => match message.to_transaction() {
+                Some(transaction) => {
+                    if let Err(err) = self.receive_transaction(transaction) {
+                        eprintln!("Rejected transaction from peer: {}", err);
+                    }
+                }
+                None => eprintln!("Ignored transaction message: {}", message.payload),
+            },
+            MessageType::Prevote | MessageType::Precommit => match message.to_finality_vote() {
+                Some(vote) => self.receive_vote(vote),
+                None => eprintln!("Ignored finality vote: {}", message.payload),
+            },
+        }
+    }
+}
+
+/// Waits until the local clock reaches `timestamp`, in seconds since the UNIX epoch.
+///
+/// # Returns
+/// * `bool` - Returns `false` if the token was cancelled before then.
+fn wait_until(timestamp: u64, cancel: &CancellationToken) -> bool {
+    loop {
+        if cancel.is_cancelled() {
+            return false;
+        }
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Time went backwards");
+        let Some(remaining) = Duration::from_secs(timestamp).checked_sub(now) else {
+            return true;
+        };
+        thread::sleep(remaining.min(Duration::from_millis(100)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consensus::finality::VoteStep;
+    use crate::consensus::proof_of_authority::ProofOfAuthority;
+    use crate::consensus::proof_of_work::ProofOfWork;
+    use crate::test_utils::work_claim;
+    use rand::rngs::OsRng;
+
+    fn node_with_difficulty(difficulty: usize) -> Node {
+        let ledger = Ledger::new().with_consensus(Arc::new(ProofOfWork::new(difficulty)));
+        Node::new(Arc::new(Mutex::new(ledger)), Miner::new(2))
+    }
+
+    /// Creates four validators, returning their keypairs and a gadget for them.
+    fn validators() -> (Vec<Keypair>, FinalityGadget) {
+        let keypairs: Vec<Keypair> = (0..4).map(|_| Keypair::generate(&mut OsRng)).collect();
+        let addresses = keypairs
+            .iter()
+            .map(|keypair| hex::encode(keypair.public.as_bytes()))
+            .collect();
+        (keypairs, FinalityGadget::new(addresses))
+    }
+
+    /// Has each of the given validators send the node its vote for a block.
+    fn send_votes(node: &Node, keypairs: &[Keypair], step: VoteStep, round: u64, block: &Block) {
+        for keypair in keypairs {
+            let vote = FinalityVote::new(
+                step,
+                block.header.height,
+                round,
+                block.hash.clone(),
+                keypair,
+            );
+            node.handle_message(Message::finality_vote(&vote));
+        }
+    }
+
+    #[test]
+    fn test_produce_block_extends_chain() {
+        let node = node_with_difficulty(2);
+        let block = node.produce_block(vec![]).expect("Block should be mined");
+
+        let ledger = node.ledger.lock().unwrap();
+        assert_eq!(ledger.chain.len(), 2);
+        assert_eq!(ledger.get_latest_block().hash, block.hash);
+        assert!(ledger.validate_chain());
+    }
+
+    #[test]
+    fn test_produce_block_drains_mempool() {
+        let node = node_with_difficulty(1);
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = work_claim(&worker, 0, "work_1", 50);
+        {
+            let ledger = node.ledger.lock().unwrap();
+            node.mempool
+                .lock()
+                .unwrap()
+                .add(claim.clone(), &ledger)
+                .unwrap();
+        }
+
+        let block = node.produce_block(vec![]).unwrap();
+        assert_eq!(block.transactions.len(), 1);
+        assert_eq!(block.transactions[0].id, claim.id);
+        assert!(node.mempool.lock().unwrap().is_empty());
+        assert!(node.hashrate() >= 0.0);
+    }
+
+    #[test]
+    fn test_proof_of_authority_node_seals_with_keypair() {
+        let authority = Keypair::generate(&mut OsRng);
+        let rules = ProofOfAuthority::new(vec![hex::encode(authority.public.as_bytes())]);
+        let ledger = Arc::new(Mutex::new(Ledger::new().with_consensus(Arc::new(rules))));
+
+        // Without the authority's key, the block cannot be sealed
+        let node = Node::new(ledger.clone(), Miner::default());
+        assert!(node.produce_block(vec![]).is_none());
+
+        let node = Node::new(ledger, Miner::default()).with_keypair(authority);
+        let block = node.produce_block(vec![]).unwrap();
+        assert!(block.seal.is_some());
+        assert!(node.ledger.lock().unwrap().validate_chain());
+    }
+
+    #[test]
+    fn test_proof_of_authority_node_waits_for_its_slot() {
+        let authority = Keypair::generate(&mut OsRng);
+        let rules = ProofOfAuthority {
+            block_period: 1,
+            ..ProofOfAuthority::new(vec![hex::encode(authority.public.as_bytes())])
+        };
+        let ledger = Arc::new(Mutex::new(Ledger::new().with_consensus(Arc::new(rules))));
+        let node = Node::new(ledger, Miner::default()).with_keypair(authority);
+
+        // The second block is stamped no earlier than its slot, and no later than the clock
+        let first = node.produce_block(vec![]).unwrap();
+        let second = node.produce_block(vec![]).unwrap();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        assert!(second.header.timestamp > first.header.timestamp);
+        assert!(second.header.timestamp <= now);
+    }
+
+    #[test]
+    fn test_receiving_block_cancels_mining() {
+        let miner_node = node_with_difficulty(1);
+        let block = miner_node.produce_block(vec![]).unwrap();
+
+        let node = node_with_difficulty(1);
+        let cancel = node.begin_mining();
+        assert!(node.receive_block(block.clone()));
+        assert!(cancel.is_cancelled());
+
+        // A block that does not extend the tip leaves the new mining job running
+        let cancel = node.begin_mining();
+        assert!(!node.receive_block(block));
+        assert!(!cancel.is_cancelled());
+    }
+
+    #[test]
+    fn test_transactions_from_peers_reach_the_mempool() {
+        let node = node_with_difficulty(1);
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = work_claim(&worker, 0, "work_1", 50);
+
+        node.handle_message(Message::transaction(&claim));
+        assert_eq!(node.mempool.lock().unwrap().len(), 1);
+
+        // The same transaction relayed again is refused
+        assert!(node.receive_transaction(claim).is_err());
+        assert_eq!(node.mempool.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_new_mining_job_cancels_previous() {
+        let node = node_with_difficulty(1);
+        let first = node.begin_mining();
+        let second = node.begin_mining();
+
+        assert!(first.is_cancelled());
+        assert!(!second.is_cancelled());
+    }
+
+    #[test]
+    fn test_produced_block_is_finalized_by_votes_from_peers() {
+        let (mut keypairs, gadget) = validators();
+        let node = node_with_difficulty(1)
+            .with_keypair(keypairs.remove(0))
+            .with_finality(gadget);
+
+        // The node prevotes its own block, and two peers' prevotes make a quorum
+        let block = node.produce_block(vec![]).unwrap();
+        assert_eq!(node.round(1), Some(0));
+        send_votes(&node, &keypairs[..2], VoteStep::Prevote, 0, &block);
+        assert_eq!(node.ledger.lock().unwrap().finalized_height, 0);
+
+        // With its own precommit, two more finalize the block
+        send_votes(&node, &keypairs[..2], VoteStep::Precommit, 0, &block);
+        assert_eq!(node.ledger.lock().unwrap().finalized_height, 1);
+        assert_eq!(node.round(1), None);
+    }
+
+    #[test]
+    fn test_block_finalized_before_it_arrives_is_finalized_on_arrival() {
+        let (keypairs, gadget) = validators();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = work_claim(&worker, 0, "work_1", 50);
+        let producer = node_with_difficulty(1);
+        let finalized = producer.produce_block(vec![claim]).unwrap();
+
+        // The node has built a heavier chain of its own in the meantime
+        let node = node_with_difficulty(1).with_finality(gadget);
+        node.produce_block(vec![]).unwrap();
+        node.produce_block(vec![]).unwrap();
+
+        // Three of the four validators finalize the producer's block
+        send_votes(&node, &keypairs[..3], VoteStep::Prevote, 0, &finalized);
+        send_votes(&node, &keypairs[..3], VoteStep::Precommit, 0, &finalized);
+        assert_eq!(node.ledger.lock().unwrap().finalized_height, 0);
+
+        // Once the block arrives, the node reorganizes onto it despite its lesser work
+        node.handle_message(Message::block(&finalized));
+        let ledger = node.ledger.lock().unwrap();
+        assert_eq!(ledger.finalized_height, 1);
+        assert_eq!(ledger.get_latest_block().hash, finalized.hash);
+        assert!(ledger.side_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_timed_out_round_moves_on_and_prevotes_again() {
+        let (mut keypairs, gadget) = validators();
+        let node = node_with_difficulty(1)
+            .with_keypair(keypairs.remove(0))
+            .with_finality(gadget)
+            .with_round_timeout(Duration::ZERO);
+        let block = node.produce_block(vec![]).unwrap();
+
+        // Only one peer prevoted in the first round, so no block reached a quorum
+        send_votes(&node, &keypairs[..1], VoteStep::Prevote, 0, &block);
+        node.advance_rounds();
+        assert_eq!(node.round(1), Some(1));
+
+        // In the next round, the node's new prevote counts towards the quorum
+        send_votes(&node, &keypairs[..2], VoteStep::Prevote, 1, &block);
+        send_votes(&node, &keypairs[..2], VoteStep::Precommit, 1, &block);
+        assert_eq!(node.ledger.lock().unwrap().finalized_height, 1);
+    }
+}
@@ -1,7 +1,5 @@
 use crate::blockchain::block::Block;
 use crate::blockchain::transaction::Transaction;
-use ed25519_dalek::Keypair;
-use rand::rngs::OsRng;
 use rusqlite::{params, Connection, Result};
 
 /// Struct to manage database connections and operations.
@@ -43,7 +41,7 @@ impl Database {
         let block_data = serde_json::to_string(block).expect("Failed to serialize block");
         self.connection.execute(
             "INSERT INTO blocks (block_hash, previous_hash, data) VALUES (?1, ?2, ?3)",
-            params![block.hash, block.previous_hash, block_data],
+            params![block.hash, block.header.previous_hash, block_data],
         )?;
         Ok(())
     }
@@ -94,6 +92,9 @@ mod tests {
     use super::*;
     use crate::blockchain::block::Block;
     use crate::blockchain::transaction::Transaction;
+    use crate::crypto::address::{Address, Network};
+    use ed25519_dalek::Keypair;
+    use rand::rngs::OsRng;
 
     #[test]
     fn test_save_and_get_block() {
@@ -116,7 +117,8 @@ mod tests {
         let keypair: Keypair = Keypair::generate(&mut csprng);
 
         // Create a transaction using the sender's public key
-        let transaction = Transaction::new(keypair.public, "receiver".to_string(), 100, None);
+        let receiver = Address::new(&Keypair::generate(&mut csprng).public, Network::Main);
+        let transaction = Transaction::new(keypair.public, receiver, 100, None);
 
         // Save the transaction in the database
         db.save_transaction(&block.hash, &transaction)
@@ -0,0 +1,469 @@
+use crate::utils::encoding::CanonicalEncoder;
+use ed25519_dalek::PublicKey;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Largest number of verifiers a task may assign.
+pub const MAX_TASK_VERIFIERS: usize = 16;
+
+/// Returns the account that holds a task's reward in escrow from when the client posts it
+/// until it is paid to the worker or refunded.
+pub fn task_account(task_id: &str) -> String {
+    format!("task:{}", task_id)
+}
+
+/// A step in a task's lifecycle, recorded on the chain by a task transaction.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TaskAction {
+    /// A client posts a task, putting up its reward by spending the transaction's inputs like
+    /// a transfer. The task's ID is the ID of the transaction posting it.
+    Post(TaskPosting),
+    /// A worker takes on an open task.
+    Claim { task_id: String },
+    /// The worker who claimed a task submits the content hash of what they delivered.
+    Deliver {
+        task_id: String,
+        delivery_hash: String,
+    },
+    /// An assigned verifier attests that the delivery completes the task.
+    Attest { task_id: String },
+    /// The client takes back the reward of a task that expired.
+    Refund { task_id: String },
+}
+
+impl TaskAction {
+    /// Appends the action to a canonical encoding.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        match self {
+            TaskAction::Post(posting) => {
+                encoder
+                    .u8(0)
+                    .str(&posting.title)
+                    .str(&posting.description)
+                    .str(&posting.category)
+                    .u64(posting.reward)
+                    .u64(posting.deadline)
+                    .u32(posting.required_attestations)
+                    .count(posting.verifiers.len());
+                for verifier in &posting.verifiers {
+                    encoder.bytes(verifier.as_bytes());
+                }
+            }
+            TaskAction::Claim { task_id } => {
+                encoder.u8(1).str(task_id);
+            }
+            TaskAction::Deliver {
+                task_id,
+                delivery_hash,
+            } => {
+                encoder.u8(2).str(task_id).str(delivery_hash);
+            }
+            TaskAction::Attest { task_id } => {
+                encoder.u8(3).str(task_id);
+            }
+            TaskAction::Refund { task_id } => {
+                encoder.u8(4).str(task_id);
+            }
+        }
+    }
+}
+
+/// What a client asks for when posting a task.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TaskPosting {
+    /// Short title of the task.
+    pub title: String,
+    /// What the task involves.
+    pub description: String,
+    /// Category of the work, which expiry policies may use to set the reward's lifetime.
+    pub category: String,
+    /// Tokens the client holds in escrow while the task is open, paid to the worker once it
+    /// is completed.
+    pub reward: u64,
+    /// Timestamp by which the task must be delivered.
+    pub deadline: u64,
+    /// Public keys of the verifiers who may attest completion.
+    pub verifiers: Vec<PublicKey>,
+    /// Number of verifiers who must attest before the task is completed.
+    pub required_attestations: u32,
+}
+
+impl TaskPosting {
+    /// Checks that the posting is well formed: it has a title and a reward, and between one
+    /// and `MAX_TASK_VERIFIERS` distinct verifiers, enough of whom can attest it.
+    pub fn is_valid(&self) -> bool {
+        let mut verifiers: Vec<[u8; 32]> =
+            self.verifiers.iter().map(|key| key.to_bytes()).collect();
+        verifiers.sort_unstable();
+        verifiers.dedup();
+        !self.title.is_empty()
+            && self.reward > 0
+            && !self.verifiers.is_empty()
+            && self.verifiers.len() <= MAX_TASK_VERIFIERS
+            && verifiers.len() == self.verifiers.len()
+            && self.required_attestations >= 1
+            && self.required_attestations as usize <= self.verifiers.len()
+    }
+}
+
+/// Where a task is in its lifecycle.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+    /// Posted and waiting for a worker.
+    Open,
+    /// Taken on by a worker, who has not delivered yet.
+    Claimed,
+    /// Delivered, and waiting for the verifiers' attestations.
+    Delivered,
+    /// Attested by enough verifiers, and the reward paid to the worker.
+    Completed,
+    /// Not delivered by the deadline, so the client can take the reward back.
+    Expired,
+    /// Expired, and the reward returned to the client.
+    Refunded,
+}
+
+/// Reasons a task action can be rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskError {
+    /// The posting is malformed, or its deadline has already passed.
+    InvalidPosting,
+    /// No task with this ID has been posted.
+    UnknownTask(String),
+    /// The task is not in a status that allows the action.
+    WrongStatus { task_id: String, status: TaskStatus },
+    /// The sender is not allowed to take the action: clients and verifiers cannot claim
+    /// their own tasks, only the worker can deliver, only an assigned verifier who has not
+    /// attested yet can attest, and only the client can take the reward back.
+    NotPermitted(String),
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskError::InvalidPosting => write!(f, "task posting is invalid"),
+            TaskError::UnknownTask(task_id) => write!(f, "task {} does not exist", task_id),
+            TaskError::WrongStatus { task_id, status } => {
+                write!(f, "task {} is {:?}", task_id, status)
+            }
+            TaskError::NotPermitted(task_id) => {
+                write!(f, "sender may not act on task {}", task_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TaskError {}
+
+/// A task as recorded on the chain, with everything that has happened to it so far.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Task {
+    /// ID of the transaction that posted the task.
+    pub id: String,
+    /// Address of the client who posted the task.
+    pub client: String,
+    /// What the client asked for.
+    pub posting: TaskPosting,
+    /// Where the task is in its lifecycle, as of the last action taken on it. Use `status_at`
+    /// to take the deadline into account.
+    pub status: TaskStatus,
+    /// Address of the worker who claimed the task, if any.
+    pub worker: Option<String>,
+    /// Content hash of the delivery, once delivered.
+    pub delivery_hash: Option<String>,
+    /// Verifiers who have attested the delivery.
+    pub attestations: Vec<PublicKey>,
+    /// Height of the block that included the posting.
+    pub posted_height: u64,
+}
+
+impl Task {
+    /// Creates an open task from a posting included in the block at `height`.
+    pub fn new(id: String, client: String, posting: TaskPosting, height: u64) -> Self {
+        Task {
+            id,
+            client,
+            posting,
+            status: TaskStatus::Open,
+            worker: None,
+            delivery_hash: None,
+            attestations: Vec::new(),
+            posted_height: height,
+        }
+    }
+
+    /// Returns the task's status at `timestamp`: a task that has not been delivered by its
+    /// deadline has expired. A delivered task can still be attested after the deadline.
+    pub fn status_at(&self, timestamp: u64) -> TaskStatus {
+        match self.status {
+            TaskStatus::Open | TaskStatus::Claimed if timestamp > self.posting.deadline => {
+                TaskStatus::Expired
+            }
+            status => status,
+        }
+    }
+
+    /// Applies an action taken on the task by `sender` in a block with the given timestamp.
+    ///
+    /// # Arguments
+    /// * `action` - The action taken. Postings create tasks rather than act on them.
+    /// * `sender` - Address of the account taking the action.
+    /// * `signer` - Public key that signed the action, which identifies verifiers.
+    /// * `timestamp` - Timestamp of the block including the action.
+    ///
+    /// # Returns
+    /// * `Result<bool, TaskError>` - Whether the action settled the task, by completing it or
+    ///   refunding it, or why it was rejected.
+    pub fn apply(
+        &mut self,
+        action: &TaskAction,
+        sender: &str,
+        signer: &PublicKey,
+        timestamp: u64,
+    ) -> Result<bool, TaskError> {
+        let status = self.status_at(timestamp);
+        let wrong_status = || TaskError::WrongStatus {
+            task_id: self.id.clone(),
+            status,
+        };
+        let not_permitted = || TaskError::NotPermitted(self.id.clone());
+        match action {
+            TaskAction::Post(_) => Err(TaskError::InvalidPosting),
+            TaskAction::Claim { .. } => {
+                if status != TaskStatus::Open {
+                    return Err(wrong_status());
+                }
+                if sender == self.client || self.posting.verifiers.contains(signer) {
+                    return Err(not_permitted());
+                }
+                self.status = TaskStatus::Claimed;
+                self.worker = Some(sender.to_string());
+                Ok(false)
+            }
+            TaskAction::Deliver { delivery_hash, .. } => {
+                if status != TaskStatus::Claimed {
+                    return Err(wrong_status());
+                }
+                if self.worker.as_deref() != Some(sender) || delivery_hash.is_empty() {
+                    return Err(not_permitted());
+                }
+                self.status = TaskStatus::Delivered;
+                self.delivery_hash = Some(delivery_hash.clone());
+                Ok(false)
+            }
+            TaskAction::Attest { .. } => {
+                if status != TaskStatus::Delivered {
+                    return Err(wrong_status());
+                }
+                if !self.posting.verifiers.contains(signer) || self.attestations.contains(signer) {
+                    return Err(not_permitted());
+                }
+                self.attestations.push(*signer);
+                if self.attestations.len() >= self.posting.required_attestations as usize {
+                    self.status = TaskStatus::Completed;
+                    return Ok(true);
+                }
+                Ok(false)
+            }
+            TaskAction::Refund { .. } => {
+                if status != TaskStatus::Expired {
+                    return Err(wrong_status());
+                }
+                if sender != self.client {
+                    return Err(not_permitted());
+                }
+                self.status = TaskStatus::Refunded;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Appends the task to a canonical encoding, for the state root.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        encoder.str(&self.id).str(&self.client);
+        TaskAction::Post(self.posting.clone()).encode(encoder);
+        encoder
+            .u8(self.status as u8)
+            .str(self.worker.as_deref().unwrap_or_default())
+            .str(self.delivery_hash.as_deref().unwrap_or_default())
+            .count(self.attestations.len());
+        for verifier in &self.attestations {
+            encoder.bytes(verifier.as_bytes());
+        }
+        encoder.u64(self.posted_height);
+    }
+}
+
+/// Criteria for listing tasks. Every criterion that is set must match.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TaskFilter {
+    /// Only tasks in this status, taking deadlines into account.
+    pub status: Option<TaskStatus>,
+    /// Only tasks in this category of work.
+    pub category: Option<String>,
+    /// Only tasks posted by this client address.
+    pub client: Option<String>,
+    /// Only tasks claimed by this worker address.
+    pub worker: Option<String>,
+    /// Only tasks with at least this reward.
+    pub min_reward: Option<u64>,
+}
+
+impl TaskFilter {
+    /// Checks whether a task matches the filter at `timestamp`.
+    pub fn matches(&self, task: &Task, timestamp: u64) -> bool {
+        self.status
+            .is_none_or(|status| task.status_at(timestamp) == status)
+            && self
+                .category
+                .as_ref()
+                .is_none_or(|category| task.posting.category == *category)
+            && self
+                .client
+                .as_ref()
+                .is_none_or(|client| task.client == *client)
+            && self
+                .worker
+                .as_ref()
+                .is_none_or(|worker| task.worker.as_ref() == Some(worker))
+            && self
+                .min_reward
+                .is_none_or(|min_reward| task.posting.reward >= min_reward)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Keypair;
+    use rand::rngs::OsRng;
+
+    fn task(verifiers: &[&Keypair], required_attestations: u32) -> Task {
+        let posting = TaskPosting {
+            title: "Fix the fence".to_string(),
+            description: "Replace the broken panels".to_string(),
+            category: "care".to_string(),
+            reward: 50,
+            deadline: 1_000,
+            verifiers: verifiers.iter().map(|verifier| verifier.public).collect(),
+            required_attestations,
+        };
+        assert!(posting.is_valid());
+        Task::new("task".to_string(), "client".to_string(), posting, 1)
+    }
+
+    #[test]
+    fn test_lifecycle_completes_after_required_attestations() {
+        let (a, b) = (Keypair::generate(&mut OsRng), Keypair::generate(&mut OsRng));
+        let worker_key = Keypair::generate(&mut OsRng);
+        let mut task = task(&[&a, &b], 2);
+        let claim = TaskAction::Claim {
+            task_id: task.id.clone(),
+        };
+        let deliver = TaskAction::Deliver {
+            task_id: task.id.clone(),
+            delivery_hash: "delivery".to_string(),
+        };
+        let attest = TaskAction::Attest {
+            task_id: task.id.clone(),
+        };
+
+        // Nothing can be delivered or attested before the task is claimed
+        assert!(task
+            .apply(&deliver, "worker", &worker_key.public, 10)
+            .is_err());
+        assert!(task.apply(&attest, "verifier", &a.public, 10).is_err());
+        assert_eq!(
+            task.apply(&claim, "client", &worker_key.public, 10),
+            Err(TaskError::NotPermitted(task.id.clone()))
+        );
+        assert_eq!(
+            task.apply(&claim, "worker", &worker_key.public, 10),
+            Ok(false)
+        );
+        assert_eq!(task.worker.as_deref(), Some("worker"));
+        assert!(task.apply(&claim, "other", &worker_key.public, 10).is_err());
+
+        assert_eq!(
+            task.apply(&deliver, "other", &worker_key.public, 20),
+            Err(TaskError::NotPermitted(task.id.clone()))
+        );
+        assert_eq!(
+            task.apply(&deliver, "worker", &worker_key.public, 20),
+            Ok(false)
+        );
+
+        // Attestations count once per assigned verifier, and may come after the deadline
+        assert_eq!(task.apply(&attest, "a", &a.public, 2_000), Ok(false));
+        assert!(task.apply(&attest, "a", &a.public, 2_000).is_err());
+        assert!(task
+            .apply(&attest, "worker", &worker_key.public, 2_000)
+            .is_err());
+        assert_eq!(task.apply(&attest, "b", &b.public, 2_000), Ok(true));
+        assert_eq!(task.status_at(2_000), TaskStatus::Completed);
+    }
+
+    #[test]
+    fn test_undelivered_tasks_expire() {
+        let verifier = Keypair::generate(&mut OsRng);
+        let worker_key = Keypair::generate(&mut OsRng);
+        let mut task = task(&[&verifier], 1);
+        assert_eq!(task.status_at(1_000), TaskStatus::Open);
+        assert_eq!(task.status_at(1_001), TaskStatus::Expired);
+
+        let claim = TaskAction::Claim {
+            task_id: task.id.clone(),
+        };
+        assert_eq!(
+            task.apply(&claim, "worker", &worker_key.public, 1_001),
+            Err(TaskError::WrongStatus {
+                task_id: task.id.clone(),
+                status: TaskStatus::Expired
+            })
+        );
+        task.apply(&claim, "worker", &worker_key.public, 500)
+            .unwrap();
+        assert_eq!(task.status_at(1_001), TaskStatus::Expired);
+    }
+
+    #[test]
+    fn test_filters_match_every_criterion() {
+        let verifier = Keypair::generate(&mut OsRng);
+        let task = task(&[&verifier], 1);
+        assert!(TaskFilter::default().matches(&task, 0));
+
+        let filter = TaskFilter {
+            status: Some(TaskStatus::Open),
+            category: Some("care".to_string()),
+            client: Some("client".to_string()),
+            min_reward: Some(50),
+            ..TaskFilter::default()
+        };
+        assert!(filter.matches(&task, 0));
+        assert!(!filter.matches(&task, 1_001));
+        assert!(!TaskFilter {
+            min_reward: Some(51),
+            ..filter.clone()
+        }
+        .matches(&task, 0));
+        assert!(!TaskFilter {
+            worker: Some("worker".to_string()),
+            ..filter
+        }
+        .matches(&task, 0));
+    }
+
+    #[test]
+    fn test_postings_must_be_attestable() {
+        let verifier = Keypair::generate(&mut OsRng);
+        let mut posting = task(&[&verifier], 1).posting;
+        posting.required_attestations = 2;
+        assert!(!posting.is_valid());
+        posting.required_attestations = 1;
+        posting.verifiers.push(verifier.public);
+        assert!(!posting.is_valid());
+        posting.verifiers.clear();
+        assert!(!posting.is_valid());
+    }
+}
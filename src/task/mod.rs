@@ -0,0 +1,3 @@
+pub mod marketplace;
+
+pub use self::marketplace::{Task, TaskAction, TaskError, TaskFilter, TaskPosting, TaskStatus};
@@ -0,0 +1,43 @@
+use crate::blockchain::transaction::Transaction;
+use crate::crypto::address::{Address, Network};
+use ed25519_dalek::Keypair;
+use rand::rngs::OsRng;
+
+/// Creates a work claim signed by the worker and attested by a new verifier.
+pub fn work_claim(worker: &Keypair, nonce: u64, work_hash: &str, amount: u64) -> Transaction {
+    let verifier = Keypair::generate(&mut OsRng);
+    let mut tx = Transaction::new_work_claim(
+        worker.public,
+        work_hash.to_string(),
+        "Completed task".to_string(),
+        "general".to_string(),
+        amount,
+    )
+    .with_nonce(nonce);
+    tx.attest(&verifier);
+    tx.sign(worker);
+    tx
+}
+
+/// Creates a transfer signed by the sender.
+pub fn transfer(
+    sender: &Keypair,
+    nonce: u64,
+    to: Address,
+    amount: u64,
+    inputs: Vec<String>,
+) -> Transaction {
+    let mut tx = Transaction::new_transfer(sender.public, to, amount, inputs).with_nonce(nonce);
+    tx.sign(sender);
+    tx
+}
+
+/// Returns a keypair's address on the main network.
+pub fn address(keypair: &Keypair) -> Address {
+    Address::new(&keypair.public, Network::Main)
+}
+
+/// Returns the address of a new keypair on the main network.
+pub fn new_address() -> Address {
+    address(&Keypair::generate(&mut OsRng))
+}
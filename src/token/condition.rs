@@ -0,0 +1,283 @@
+use crate::blockchain::work_claim::Attestation;
+use crate::crypto::hash::hash_bytes;
+use crate::crypto::signatures::{sign_message, verify_signature};
+use crate::utils::encoding::CanonicalEncoder;
+use ed25519_dalek::{Keypair, PublicKey};
+use serde::{Deserialize, Serialize};
+
+/// Largest number of requirements a spend condition may carry.
+pub const MAX_REQUIREMENTS: usize = 8;
+
+/// A point in the chain's history, given by block height or block time.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Timelock {
+    /// Reached by the block at this height and every block after it.
+    Height(u64),
+    /// Reached by the first block with this timestamp or a later one.
+    Time(u64),
+}
+
+impl Timelock {
+    /// Checks whether the block at `height` with the given timestamp has reached the lock.
+    pub fn is_reached(&self, height: u64, timestamp: u64) -> bool {
+        match self {
+            Timelock::Height(unlock_height) => height >= *unlock_height,
+            Timelock::Time(unlock_time) => timestamp >= *unlock_time,
+        }
+    }
+
+    fn encode(&self, encoder: &mut CanonicalEncoder) {
+        match self {
+            Timelock::Height(height) => encoder.u8(0).u64(*height),
+            Timelock::Time(time) => encoder.u8(1).u64(*time),
+        };
+    }
+}
+
+/// Something the recipient of a conditional transfer must satisfy to spend what it received.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Requirement {
+    /// The spender must reveal a preimage whose hex-encoded SHA-256 hash is this value.
+    HashLock(String),
+    /// The lot cannot be spent before the timelock is reached.
+    After(Timelock),
+    /// The verifier must sign the release of the lot, for example once it has attested the
+    /// work the lot pays for.
+    VerifierSignature(PublicKey),
+}
+
+/// Conditions attached to the recipient's lots by a transfer, which turn the transfer into
+/// an escrow: the recipient may only spend the lots once every requirement is met, and the
+/// sender may take them back once the refund timelock is reached, if the recipient has not
+/// spent them by then.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpendCondition {
+    /// Requirements the recipient must meet, all of them.
+    pub requirements: Vec<Requirement>,
+    /// When the sender may reclaim the lots, if ever.
+    pub refund_after: Option<Timelock>,
+}
+
+impl SpendCondition {
+    /// Creates a condition the recipient can spend under once every requirement is met.
+    pub fn new(requirements: Vec<Requirement>) -> Self {
+        SpendCondition {
+            requirements,
+            refund_after: None,
+        }
+    }
+
+    /// Lets the sender reclaim the lots once the timelock is reached.
+    pub fn with_refund_after(mut self, timelock: Timelock) -> Self {
+        self.refund_after = Some(timelock);
+        self
+    }
+
+    /// Checks that the condition has between one and `MAX_REQUIREMENTS` requirements.
+    pub fn is_valid(&self) -> bool {
+        !self.requirements.is_empty() && self.requirements.len() <= MAX_REQUIREMENTS
+    }
+
+    /// Appends the condition to a canonical encoding.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        encoder.count(self.requirements.len());
+        for requirement in &self.requirements {
+            match requirement {
+                Requirement::HashLock(hash) => {
+                    encoder.u8(0).str(hash);
+                }
+                Requirement::After(timelock) => {
+                    encoder.u8(1);
+                    timelock.encode(encoder);
+                }
+                Requirement::VerifierSignature(verifier) => {
+                    encoder.u8(2).bytes(verifier.as_bytes());
+                }
+            }
+        }
+        match &self.refund_after {
+            Some(timelock) => {
+                encoder.u8(1);
+                timelock.encode(encoder);
+            }
+            None => {
+                encoder.u8(0);
+            }
+        }
+    }
+}
+
+/// A spend condition held by a lot, along with the account that paid the lot in and may
+/// reclaim it under the refund timelock.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Lock {
+    /// The condition the recipient must meet.
+    pub condition: SpendCondition,
+    /// Address of the sender of the conditional transfer.
+    pub payer: String,
+}
+
+impl Lock {
+    /// Checks whether the recipient may spend the lot with the given ID in the block at
+    /// `height` with the given timestamp, using what the witness reveals.
+    pub fn can_release(
+        &self,
+        lot_id: &str,
+        witness: Option<&Witness>,
+        height: u64,
+        timestamp: u64,
+    ) -> bool {
+        self.condition
+            .requirements
+            .iter()
+            .all(|requirement| match requirement {
+                Requirement::HashLock(hash) => witness
+                    .and_then(|witness| witness.preimage.as_ref())
+                    .is_some_and(|preimage| hash_bytes(preimage) == *hash),
+                Requirement::After(timelock) => timelock.is_reached(height, timestamp),
+                Requirement::VerifierSignature(verifier) => {
+                    let message = release_message(lot_id);
+                    witness.is_some_and(|witness| {
+                        witness.releases.iter().any(|release| {
+                            release.verifier == *verifier
+                                && verify_signature(&message, &release.signature, verifier)
+                        })
+                    })
+                }
+            })
+    }
+
+    /// Checks whether the payer may reclaim the lot in the block at `height` with the given
+    /// timestamp.
+    pub fn can_refund(&self, height: u64, timestamp: u64) -> bool {
+        self.condition
+            .refund_after
+            .is_some_and(|timelock| timelock.is_reached(height, timestamp))
+    }
+
+    /// Appends the lock to a canonical encoding.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        self.condition.encode(encoder);
+        encoder.str(&self.payer);
+    }
+}
+
+/// What a transfer reveals to meet the spend condition of one of the lots it spends.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Witness {
+    /// ID of the lot the witness is for.
+    pub input: String,
+    /// Preimage of the lot's hash-lock, if it has one.
+    pub preimage: Option<Vec<u8>>,
+    /// Verifiers' signatures releasing the lot.
+    pub releases: Vec<Attestation>,
+}
+
+impl Witness {
+    /// Creates an empty witness for the lot with the given ID.
+    pub fn new(input: String) -> Self {
+        Witness {
+            input,
+            preimage: None,
+            releases: Vec::new(),
+        }
+    }
+
+    /// Reveals the preimage of the lot's hash-lock.
+    pub fn with_preimage(mut self, preimage: Vec<u8>) -> Self {
+        self.preimage = Some(preimage);
+        self
+    }
+
+    /// Adds a verifier's release of the lot.
+    pub fn with_release(mut self, release: Attestation) -> Self {
+        self.releases.push(release);
+        self
+    }
+
+    /// Appends the witness to a canonical encoding.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        encoder.str(&self.input);
+        match &self.preimage {
+            Some(preimage) => encoder.u8(1).bytes(preimage),
+            None => encoder.u8(0),
+        };
+        encoder.count(self.releases.len());
+        for release in &self.releases {
+            encoder
+                .bytes(release.verifier.as_bytes())
+                .bytes(&release.signature);
+        }
+    }
+}
+
+/// Builds the message a verifier signs to release a lot. It names the lot, so a release
+/// cannot be reused for any other lot.
+pub fn release_message(lot_id: &str) -> String {
+    let data = CanonicalEncoder::new("work-tokens/release")
+        .str(lot_id)
+        .finish();
+    hash_bytes(&data)
+}
+
+/// Signs the release of the lot with the given ID by a verifier.
+pub fn sign_release(lot_id: &str, verifier: &Keypair) -> Attestation {
+    Attestation {
+        verifier: verifier.public,
+        signature: sign_message(&release_message(lot_id), verifier),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    fn lock(requirements: Vec<Requirement>) -> Lock {
+        Lock {
+            condition: SpendCondition::new(requirements).with_refund_after(Timelock::Height(10)),
+            payer: "payer".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_timelocks_by_height_and_time() {
+        assert!(!Timelock::Height(5).is_reached(4, u64::MAX));
+        assert!(Timelock::Height(5).is_reached(5, 0));
+        assert!(!Timelock::Time(100).is_reached(u64::MAX, 99));
+        assert!(Timelock::Time(100).is_reached(0, 100));
+
+        let lock = lock(vec![Requirement::After(Timelock::Time(100))]);
+        assert!(!lock.can_release("tx:0", None, 1, 99));
+        assert!(lock.can_release("tx:0", None, 1, 100));
+        assert!(!lock.can_refund(9, 0));
+        assert!(lock.can_refund(10, 0));
+    }
+
+    #[test]
+    fn test_hash_lock_needs_preimage() {
+        let lock = lock(vec![Requirement::HashLock(hash_bytes(b"secret"))]);
+        let witness = Witness::new("tx:0".to_string());
+        assert!(!lock.can_release("tx:0", None, 1, 1));
+        assert!(!lock.can_release("tx:0", Some(&witness), 1, 1));
+        let wrong = witness.clone().with_preimage(b"guess".to_vec());
+        assert!(!lock.can_release("tx:0", Some(&wrong), 1, 1));
+        let revealed = witness.with_preimage(b"secret".to_vec());
+        assert!(lock.can_release("tx:0", Some(&revealed), 1, 1));
+    }
+
+    #[test]
+    fn test_release_signature_is_bound_to_verifier_and_lot() {
+        let verifier = Keypair::generate(&mut OsRng);
+        let lock = lock(vec![Requirement::VerifierSignature(verifier.public)]);
+
+        let released =
+            Witness::new("tx:0".to_string()).with_release(sign_release("tx:0", &verifier));
+        assert!(lock.can_release("tx:0", Some(&released), 1, 1));
+        assert!(!lock.can_release("tx:1", Some(&released), 1, 1));
+
+        let impostor = Keypair::generate(&mut OsRng);
+        let forged = Witness::new("tx:0".to_string()).with_release(sign_release("tx:0", &impostor));
+        assert!(!lock.can_release("tx:0", Some(&forged), 1, 1));
+    }
+}
@@ -1,5 +1,3 @@
-use std::time::{SystemTime, UNIX_EPOCH};
-
 /// Structure representing a token with an optional expiration time.
 #[derive(Debug, Clone)]
 pub struct Token {
@@ -8,45 +6,61 @@ pub struct Token {
 }
 
 impl Token {
-    /// Checks if the token has expired.
+    /// Checks if the token has expired as of the given time.
+    ///
+    /// Expiry is always judged against an explicit timestamp (normally that of a block) rather
+    /// than the local clock, so every node reaches the same answer.
+    ///
+    /// # Arguments
+    /// * `current_time` - The timestamp to judge expiry against.
     ///
     /// # Returns
     /// * `true` if the token has expired, `false` otherwise.
-    pub fn has_expired(&self) -> bool {
+    pub fn has_expired(&self, current_time: u64) -> bool {
         if let Some(expiration) = self.expiration_time {
-            let current_time = get_current_timestamp();
             return current_time > expiration;
         }
         false
     }
 }
 
-/// Helper function to get the current timestamp (in seconds since UNIX epoch).
-fn get_current_timestamp() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_token_expiration() {
+        let current_time = 1_000_000;
         let token = Token {
             amount: 100,
-            expiration_time: Some(get_current_timestamp() - 100), // Expired token
+            expiration_time: Some(current_time - 100), // Expired token
         };
 
-        assert!(token.has_expired());
+        assert!(token.has_expired(current_time));
 
         let non_expired_token = Token {
             amount: 100,
-            expiration_time: Some(get_current_timestamp() + 1000), // Still valid
+            expiration_time: Some(current_time + 1000), // Still valid
         };
 
-        assert!(!non_expired_token.has_expired());
+        assert!(!non_expired_token.has_expired(current_time));
+    }
+
+    #[test]
+    fn test_token_expiration_boundary() {
+        let token = Token {
+            amount: 100,
+            expiration_time: Some(500),
+        };
+
+        // A token is still spendable at its exact expiration time
+        assert!(!token.has_expired(500));
+        assert!(token.has_expired(501));
+
+        let perpetual_token = Token {
+            amount: 100,
+            expiration_time: None,
+        };
+        assert!(!perpetual_token.has_expired(u64::MAX));
     }
 }
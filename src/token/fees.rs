@@ -0,0 +1,102 @@
+use std::collections::BTreeMap;
+
+/// Account that holds collected fees until the fee policy pays them out. No key can sign for
+/// it, so its lots can only leave through the ledger's own settlement.
+pub const FEE_POOL_ACCOUNT: &str = "fee-pool";
+
+/// Default number of blocks between commons redistributions.
+pub const DEFAULT_FEE_EPOCH_LENGTH: u64 = 100;
+
+/// Decides what happens to the fees transactions pay.
+///
+/// Collected fees keep the expiry of the lots they were paid from, so fees cannot be used
+/// to extend the life of tokens, and fees held in the pool can expire like any other lot.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FeePolicy {
+    /// Fees are destroyed, shrinking the supply.
+    #[default]
+    Burn,
+    /// Fees go to the beneficiary named in the header of the block that includes them, and
+    /// are burned if the block names none.
+    Producer,
+    /// Fees collect in the fee pool, which is shared out among the workers who minted tokens
+    /// during each epoch, in proportion to what they minted, at the end of the epoch.
+    Commons {
+        /// Number of blocks in an epoch. Epochs end at heights that are multiples of it.
+        epoch_length: u64,
+    },
+}
+
+impl FeePolicy {
+    /// Checks whether the fee pool is shared out after the block at `height`.
+    pub fn is_epoch_end(&self, height: u64) -> bool {
+        match self {
+            FeePolicy::Commons { epoch_length } => {
+                *epoch_length > 0 && height > 0 && height.is_multiple_of(*epoch_length)
+            }
+            FeePolicy::Burn | FeePolicy::Producer => false,
+        }
+    }
+}
+
+/// Splits `total` among accounts in proportion to their weights, rounding every share down.
+///
+/// # Arguments
+/// * `total` - The amount to share out.
+/// * `weights` - Each account's weight, such as the tokens it minted during the epoch.
+///
+/// # Returns
+/// * `Vec<(String, u64)>` - Each account's share, in address order, leaving out accounts
+///   whose share rounds down to nothing. Whatever rounding leaves over is not shared out.
+pub fn proportional_shares(total: u64, weights: &BTreeMap<String, u64>) -> Vec<(String, u64)> {
+    let total_weight: u128 = weights.values().map(|weight| *weight as u128).sum();
+    if total_weight == 0 {
+        return Vec::new();
+    }
+    weights
+        .iter()
+        .map(|(account, weight)| {
+            let share = total as u128 * *weight as u128 / total_weight;
+            (account.clone(), share as u64)
+        })
+        .filter(|(_, share)| *share > 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epochs_end_at_multiples_of_the_length() {
+        let policy = FeePolicy::Commons { epoch_length: 10 };
+        assert!(!policy.is_epoch_end(0));
+        assert!(!policy.is_epoch_end(9));
+        assert!(policy.is_epoch_end(10));
+        assert!(policy.is_epoch_end(20));
+        assert!(!FeePolicy::Commons { epoch_length: 0 }.is_epoch_end(10));
+        assert!(!FeePolicy::Burn.is_epoch_end(10));
+        assert!(!FeePolicy::Producer.is_epoch_end(10));
+    }
+
+    #[test]
+    fn test_proportional_shares_round_down() {
+        let mut weights = BTreeMap::new();
+        weights.insert("a".to_string(), 1);
+        weights.insert("b".to_string(), 2);
+        weights.insert("c".to_string(), 0);
+
+        assert_eq!(
+            proportional_shares(10, &weights),
+            vec![("a".to_string(), 3), ("b".to_string(), 6)]
+        );
+        assert_eq!(
+            proportional_shares(u64::MAX, &weights),
+            vec![
+                ("a".to_string(), u64::MAX / 3),
+                ("b".to_string(), u64::MAX / 3 * 2)
+            ]
+        );
+        assert!(proportional_shares(10, &BTreeMap::new()).is_empty());
+    }
+}
@@ -1,6 +1,11 @@
+use crate::blockchain::transaction::Transaction;
 use crate::token::expiration::Token;
+use crate::token::policy::ExpiryPolicy;
 use std::time::{SystemTime, UNIX_EPOCH}; // Added missing imports
 
+/// Default lifetime of freshly minted tokens, in seconds (30 days).
+pub const DEFAULT_TOKEN_LIFETIME: u64 = 30 * 24 * 60 * 60;
+
 /// Structure representing a token issuance event.
 #[derive(Debug, Clone)]
 pub struct Issuance {
@@ -26,6 +31,31 @@ impl Issuance {
             issuance_time: get_current_timestamp(),
         }
     }
+
+    /// Mints the tokens for an accepted work-claim transaction, with an expiration set by the
+    /// given expiry policy.
+    ///
+    /// The issuance time is the timestamp of the block that included the claim, so every node
+    /// derives the same expiration for the minted tokens.
+    ///
+    /// # Returns
+    /// * `None` if the transaction is not a work claim.
+    pub fn from_work_claim(
+        transaction: &Transaction,
+        block_timestamp: u64,
+        policy: &dyn ExpiryPolicy,
+    ) -> Option<Self> {
+        let claim = transaction.work_claim()?;
+        let token = Token {
+            amount: transaction.amount,
+            expiration_time: policy.expiration_time(block_timestamp, &claim.category),
+        };
+        Some(Self {
+            issued_tokens: vec![token],
+            issuer: transaction.to.to_string(),
+            issuance_time: block_timestamp,
+        })
+    }
 }
 
 fn get_current_timestamp() -> u64 {
@@ -38,6 +68,10 @@ fn get_current_timestamp() -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::address::{Address, Network};
+    use crate::token::policy::FixedTtl;
+    use ed25519_dalek::Keypair;
+    use rand::rngs::OsRng;
 
     #[test]
     fn test_single_token_issuance() {
@@ -57,4 +91,30 @@ mod tests {
         assert_eq!(issuance.issued_tokens.len(), 2);
         assert_eq!(issuance.issuer, "issuer_1");
     }
+
+    #[test]
+    fn test_issuance_from_work_claim() {
+        let worker = Keypair::generate(&mut OsRng);
+        let verifier = Keypair::generate(&mut OsRng);
+        let mut claim = Transaction::new_work_claim(
+            worker.public,
+            "work_hash".to_string(),
+            "Fixed the roof".to_string(),
+            "repair".to_string(),
+            40,
+        );
+        claim.attest(&verifier);
+        claim.sign(&worker);
+
+        let policy = FixedTtl { lifetime: 500 };
+        let issuance = Issuance::from_work_claim(&claim, 1_000, &policy).unwrap();
+        assert_eq!(issuance.issuer, claim.sender_address());
+        assert_eq!(issuance.issuance_time, 1_000);
+        assert_eq!(issuance.issued_tokens[0].amount, 40);
+        assert_eq!(issuance.issued_tokens[0].expiration_time, Some(1_500));
+
+        let receiver = Address::new(&verifier.public, Network::Main);
+        let transfer = Transaction::new(worker.public, receiver, 10, None);
+        assert!(Issuance::from_work_claim(&transfer, 1_000, &policy).is_none());
+    }
 }
@@ -0,0 +1,109 @@
+use crate::token::condition::Lock;
+use serde::{Deserialize, Serialize};
+
+/// A spendable lot of tokens held by a single owner.
+///
+/// Lots are created by work-claim mints and by transfers, and are consumed whole when spent.
+/// What a lot is currently worth is decided by the ledger's `ExpiryPolicy`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TokenLot {
+    /// Unique lot ID, in the form `<transaction id>:<output index>`.
+    pub id: String,
+    /// Address of the account that can spend this lot.
+    pub owner: String,
+    /// Number of tokens in the lot.
+    pub amount: u64,
+    /// Height of the block that originally minted these tokens.
+    pub mint_height: u64,
+    /// Timestamp of the block that originally minted these tokens.
+    pub mint_time: u64,
+    /// Timestamp after which the lot can no longer be spent.
+    pub expiration_time: Option<u64>,
+    /// Height at which `amount` was last valued: the mint height, or the height the lot was
+    /// split off by a transfer. Decaying expiry policies count from here.
+    #[serde(default)]
+    pub value_height: u64,
+    /// Spend condition the owner must meet to spend the lot, if it was paid in escrow.
+    #[serde(default)]
+    pub lock: Option<Lock>,
+    /// Height from which the lot can be spent, if it was minted for work that can still be
+    /// disputed. A lot whose mint is under dispute stays pending until the dispute is resolved.
+    #[serde(default)]
+    pub pending_until: Option<u64>,
+}
+
+impl TokenLot {
+    /// Builds the ID of the lot created by the given transaction output.
+    pub fn lot_id(transaction_id: &str, output_index: usize) -> String {
+        format!("{}:{}", transaction_id, output_index)
+    }
+
+    /// Returns the ID of the transaction that created the lot with the given ID.
+    pub fn source_transaction(lot_id: &str) -> &str {
+        lot_id
+            .rsplit_once(':')
+            .map_or(lot_id, |(transaction_id, _)| transaction_id)
+    }
+
+    /// Checks if the lot is still pending, and so cannot be spent, at the block `height`.
+    pub fn is_pending(&self, height: u64) -> bool {
+        self.pending_until.is_some_and(|until| height < until)
+    }
+
+    /// Checks if the lot has expired as of the given timestamp.
+    pub fn has_expired(&self, timestamp: u64) -> bool {
+        matches!(self.expiration_time, Some(expiration) if timestamp > expiration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lot_expiry_at_timestamp() {
+        let lot = TokenLot {
+            id: TokenLot::lot_id("tx", 0),
+            owner: "owner".to_string(),
+            amount: 10,
+            mint_height: 1,
+            mint_time: 100,
+            expiration_time: Some(200),
+            value_height: 1,
+            lock: None,
+            pending_until: None,
+        };
+
+        assert_eq!(lot.id, "tx:0");
+        assert!(!lot.has_expired(200));
+        assert!(lot.has_expired(201));
+
+        let perpetual = TokenLot {
+            expiration_time: None,
+            ..lot
+        };
+        assert!(!perpetual.has_expired(u64::MAX));
+    }
+
+    #[test]
+    fn test_pending_lot_is_spendable_once_window_closes() {
+        let lot = TokenLot {
+            id: TokenLot::lot_id("tx", 0),
+            owner: "owner".to_string(),
+            amount: 10,
+            mint_height: 1,
+            mint_time: 100,
+            expiration_time: None,
+            value_height: 1,
+            lock: None,
+            pending_until: Some(5),
+        };
+        assert!(lot.is_pending(4));
+        assert!(!lot.is_pending(5));
+        assert!(!TokenLot {
+            pending_until: None,
+            ..lot
+        }
+        .is_pending(0));
+    }
+}
@@ -1,8 +1,12 @@
+use crate::crypto::address::Address;
 use crate::token::expiration::Token;
 use std::collections::HashMap;
 
+/// In-memory token balances for off-chain bookkeeping.
+///
+/// On-chain balances are derived by the ledger from its token lots; see `Ledger::get_balance`.
 pub struct TokenManager {
-    pub balances: HashMap<String, Vec<Token>>, // Maps user IDs to their tokens
+    pub balances: HashMap<Address, Vec<Token>>, // Maps user addresses to their tokens
 }
 
 impl TokenManager {
@@ -14,46 +18,79 @@ impl TokenManager {
     }
 
     /// Adds tokens to a user's balance.
-    pub fn add_tokens(&mut self, user_id: &str, tokens: Vec<Token>) {
-        let user_balance = self
-            .balances
-            .entry(user_id.to_string())
-            .or_insert(Vec::new());
+    pub fn add_tokens(&mut self, user: &Address, tokens: Vec<Token>) {
+        let user_balance = self.balances.entry(*user).or_default();
         user_balance.extend(tokens);
     }
 
-    /// Transfers tokens between users.
-    pub fn transfer_tokens(&mut self, from_user: &str, to_user: &str, amount: u64) -> bool {
-        if let Some(from_balance) = self.balances.get_mut(from_user) {
-            let mut transferred_amount = 0;
-            let mut transferred_tokens = Vec::new();
-
-            // Transfer tokens until the requested amount is reached
-            from_balance.retain(|token| {
-                if transferred_amount < amount && !token.has_expired() {
-                    transferred_amount += token.amount;
-                    transferred_tokens.push(token.clone());
-                    false // Remove from `from_user`'s balance
-                } else {
-                    true
-                }
-            });
-
-            // If the total amount was transferred, update recipient's balance
-            if transferred_amount >= amount {
-                self.add_tokens(to_user, transferred_tokens);
-                return true;
+    /// Transfers exactly `amount` tokens between users, skipping tokens that have expired as of
+    /// `current_time`.
+    ///
+    /// Tokens closest to expiry are spent first. If a token is only partly needed it is split,
+    /// and the change stays with the sender under the original expiration time. The transfer is
+    /// all-or-nothing: if the sender cannot cover the amount, no balance is changed.
+    pub fn transfer_tokens(
+        &mut self,
+        from_user: &Address,
+        to_user: &Address,
+        amount: u64,
+        current_time: u64,
+    ) -> bool {
+        let Some(from_balance) = self.balances.get(from_user) else {
+            return false;
+        };
+
+        let available: u64 = from_balance
+            .iter()
+            .filter(|token| !token.has_expired(current_time))
+            .map(|token| token.amount)
+            .sum();
+        if available < amount {
+            return false;
+        }
+
+        // Spend the soonest-expiring tokens first; tokens that never expire go last
+        let mut spend_order: Vec<&Token> = from_balance
+            .iter()
+            .filter(|token| !token.has_expired(current_time))
+            .collect();
+        spend_order.sort_by_key(|token| (token.expiration_time.is_none(), token.expiration_time));
+
+        let mut remaining = amount;
+        let mut transferred_tokens = Vec::new();
+        let mut kept_tokens: Vec<Token> = from_balance
+            .iter()
+            .filter(|token| token.has_expired(current_time))
+            .cloned()
+            .collect();
+        for token in spend_order {
+            let spent = remaining.min(token.amount);
+            remaining -= spent;
+            if spent > 0 {
+                transferred_tokens.push(Token {
+                    amount: spent,
+                    expiration_time: token.expiration_time,
+                });
+            }
+            if token.amount > spent {
+                kept_tokens.push(Token {
+                    amount: token.amount - spent,
+                    expiration_time: token.expiration_time,
+                });
             }
         }
-        false
+
+        self.balances.insert(*from_user, kept_tokens);
+        self.add_tokens(to_user, transferred_tokens);
+        true
     }
 
-    /// Gets the user's total balance of valid (non-expired) tokens.
-    pub fn get_balance(&self, user_id: &str) -> u64 {
-        if let Some(tokens) = self.balances.get(user_id) {
+    /// Gets the user's total balance of tokens that are still valid at `current_time`.
+    pub fn get_balance(&self, user: &Address, current_time: u64) -> u64 {
+        if let Some(tokens) = self.balances.get(user) {
             tokens
                 .iter()
-                .filter(|token| !token.has_expired())
+                .filter(|token| !token.has_expired(current_time))
                 .map(|t| t.amount)
                 .sum()
         } else {
@@ -62,34 +99,146 @@ impl TokenManager {
     }
 }
 
+impl Default for TokenManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::address::Network;
+    use ed25519_dalek::Keypair;
+    use rand::rngs::OsRng;
+
+    fn address() -> Address {
+        Address::new(&Keypair::generate(&mut OsRng).public, Network::Main)
+    }
 
     #[test]
     fn test_add_and_get_balance() {
         let mut manager = TokenManager::new();
+        let user_1 = address();
         let tokens = vec![Token {
             amount: 100,
             expiration_time: None,
         }];
-        manager.add_tokens("user_1", tokens);
+        manager.add_tokens(&user_1, tokens);
 
-        assert_eq!(manager.get_balance("user_1"), 100);
+        assert_eq!(manager.get_balance(&user_1, 0), 100);
     }
 
     #[test]
     fn test_transfer_tokens() {
         let mut manager = TokenManager::new();
+        let (user_1, user_2) = (address(), address());
         let tokens = vec![Token {
             amount: 100,
             expiration_time: None,
         }];
-        manager.add_tokens("user_1", tokens);
+        manager.add_tokens(&user_1, tokens);
 
-        let success = manager.transfer_tokens("user_1", "user_2", 100);
+        let success = manager.transfer_tokens(&user_1, &user_2, 100, 0);
         assert!(success);
-        assert_eq!(manager.get_balance("user_2"), 100);
-        assert_eq!(manager.get_balance("user_1"), 0);
+        assert_eq!(manager.get_balance(&user_2, 0), 100);
+        assert_eq!(manager.get_balance(&user_1, 0), 0);
+    }
+
+    #[test]
+    fn test_partial_transfer_returns_change() {
+        let mut manager = TokenManager::new();
+        let (user_1, user_2) = (address(), address());
+        let tokens = vec![Token {
+            amount: 100,
+            expiration_time: Some(500),
+        }];
+        manager.add_tokens(&user_1, tokens);
+
+        assert!(manager.transfer_tokens(&user_1, &user_2, 50, 0));
+        assert_eq!(manager.get_balance(&user_1, 0), 50);
+        assert_eq!(manager.get_balance(&user_2, 0), 50);
+
+        // The change keeps the original expiration time
+        assert_eq!(manager.balances[&user_1][0].expiration_time, Some(500));
+        assert_eq!(manager.balances[&user_2][0].expiration_time, Some(500));
+    }
+
+    #[test]
+    fn test_transfer_spends_oldest_expiry_first() {
+        let mut manager = TokenManager::new();
+        let (user_1, user_2) = (address(), address());
+        let tokens = vec![
+            Token {
+                amount: 30,
+                expiration_time: None,
+            },
+            Token {
+                amount: 30,
+                expiration_time: Some(900),
+            },
+            Token {
+                amount: 30,
+                expiration_time: Some(300),
+            },
+        ];
+        manager.add_tokens(&user_1, tokens);
+
+        assert!(manager.transfer_tokens(&user_1, &user_2, 45, 0));
+
+        let received: Vec<(u64, Option<u64>)> = manager.balances[&user_2]
+            .iter()
+            .map(|token| (token.amount, token.expiration_time))
+            .collect();
+        assert_eq!(received, vec![(30, Some(300)), (15, Some(900))]);
+        assert_eq!(manager.get_balance(&user_1, 0), 45);
+    }
+
+    #[test]
+    fn test_transfer_with_mixed_expired_tokens() {
+        let mut manager = TokenManager::new();
+        let (user_1, user_2) = (address(), address());
+        let tokens = vec![
+            Token {
+                amount: 40,
+                expiration_time: Some(50), // Expired at time 100
+            },
+            Token {
+                amount: 40,
+                expiration_time: Some(200),
+            },
+        ];
+        manager.add_tokens(&user_1, tokens);
+
+        // Expired tokens cannot be used to cover a transfer
+        assert!(!manager.transfer_tokens(&user_1, &user_2, 60, 100));
+        assert!(manager.transfer_tokens(&user_1, &user_2, 30, 100));
+
+        assert_eq!(manager.get_balance(&user_2, 100), 30);
+        assert_eq!(manager.get_balance(&user_1, 100), 10);
+        assert_eq!(manager.balances[&user_2][0].expiration_time, Some(200));
+    }
+
+    #[test]
+    fn test_failed_transfer_leaves_balances_untouched() {
+        let mut manager = TokenManager::new();
+        let (user_1, user_2) = (address(), address());
+        let tokens = vec![
+            Token {
+                amount: 20,
+                expiration_time: Some(300),
+            },
+            Token {
+                amount: 20,
+                expiration_time: None,
+            },
+        ];
+        manager.add_tokens(&user_1, tokens);
+
+        assert!(!manager.transfer_tokens(&user_1, &user_2, 50, 0));
+        assert_eq!(manager.balances[&user_1].len(), 2);
+        assert_eq!(manager.get_balance(&user_1, 0), 40);
+        assert_eq!(manager.get_balance(&user_2, 0), 0);
+        assert!(!manager.transfer_tokens(&address(), &user_2, 1, 0));
     }
 }
@@ -1,7 +1,17 @@
+pub mod condition;
 pub mod expiration;
+pub mod fees;
 pub mod issuance;
+pub mod lot;
 pub mod management;
+pub mod policy;
+pub mod supply;
 
+pub use self::condition::{Lock, Requirement, SpendCondition, Timelock, Witness};
 pub use self::expiration::Token;
-pub use self::issuance::{batch_issue, issue_token, Issuance};
+pub use self::fees::FeePolicy;
+pub use self::issuance::Issuance;
+pub use self::lot::TokenLot;
 pub use self::management::TokenManager;
+pub use self::policy::{ExpiryPolicy, ExponentialDemurrage, FixedTtl, LinearDemurrage, TieredTtl};
+pub use self::supply::{EpochIssuance, IssuancePolicy, SupplySchedule, SupplyStats};
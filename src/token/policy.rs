@@ -0,0 +1,200 @@
+use crate::token::lot::TokenLot;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+/// Basis points in one whole (100%).
+pub const BASIS_POINTS: u64 = 10_000;
+
+/// Fixed-point scale used for deterministic exponential decay.
+const DECAY_SCALE: u128 = 1_000_000_000_000;
+
+/// Decides how long minted tokens last and how their value changes over time.
+///
+/// Policies must be deterministic: given the same inputs, every node has to compute the same
+/// expiration times and lot values, so implementations may only depend on block data.
+pub trait ExpiryPolicy: Debug + Send + Sync {
+    /// Returns when tokens minted at `mint_time` for work in `category` expire, if ever.
+    fn expiration_time(&self, mint_time: u64, category: &str) -> Option<u64>;
+
+    /// Returns what a lot is worth at the block `height`. Lots keep their full amount unless
+    /// the policy decays them.
+    fn lot_value(&self, lot: &TokenLot, _height: u64) -> u64 {
+        lot.amount
+    }
+}
+
+/// Tokens last a fixed number of seconds from the time they were minted.
+#[derive(Debug, Clone)]
+pub struct FixedTtl {
+    /// Lifetime of minted tokens, in seconds.
+    pub lifetime: u64,
+}
+
+impl ExpiryPolicy for FixedTtl {
+    fn expiration_time(&self, mint_time: u64, _category: &str) -> Option<u64> {
+        Some(mint_time.saturating_add(self.lifetime))
+    }
+}
+
+/// Tokens last a different number of seconds depending on the category of work that earned them.
+#[derive(Debug, Clone)]
+pub struct TieredTtl {
+    /// Lifetime for categories without an entry of their own, in seconds.
+    pub default_lifetime: u64,
+    /// Lifetime per work category, in seconds.
+    pub category_lifetimes: HashMap<String, u64>,
+}
+
+impl ExpiryPolicy for TieredTtl {
+    fn expiration_time(&self, mint_time: u64, category: &str) -> Option<u64> {
+        let lifetime = self
+            .category_lifetimes
+            .get(category)
+            .copied()
+            .unwrap_or(self.default_lifetime);
+        Some(mint_time.saturating_add(lifetime))
+    }
+}
+
+/// Tokens never expire outright, but lose a fixed share of their original value every block.
+#[derive(Debug, Clone)]
+pub struct LinearDemurrage {
+    /// Value lost per block, in basis points of the lot's amount.
+    pub rate_bps: u64,
+}
+
+impl ExpiryPolicy for LinearDemurrage {
+    fn expiration_time(&self, _mint_time: u64, _category: &str) -> Option<u64> {
+        None
+    }
+
+    fn lot_value(&self, lot: &TokenLot, height: u64) -> u64 {
+        let blocks = height.saturating_sub(lot.value_height) as u128;
+        let decay = (lot.amount as u128)
+            .saturating_mul(self.rate_bps as u128)
+            .saturating_mul(blocks)
+            / BASIS_POINTS as u128;
+        (lot.amount as u128).saturating_sub(decay) as u64
+    }
+}
+
+/// Tokens never expire outright, but lose a fixed share of their remaining value every block.
+#[derive(Debug, Clone)]
+pub struct ExponentialDemurrage {
+    /// Share of the remaining value lost per block, in basis points.
+    pub rate_bps: u64,
+}
+
+impl ExpiryPolicy for ExponentialDemurrage {
+    fn expiration_time(&self, _mint_time: u64, _category: &str) -> Option<u64> {
+        None
+    }
+
+    fn lot_value(&self, lot: &TokenLot, height: u64) -> u64 {
+        let blocks = height.saturating_sub(lot.value_height);
+        let retained_bps = BASIS_POINTS.saturating_sub(self.rate_bps) as u128;
+
+        // (retained / BASIS_POINTS) ^ blocks, by squaring in fixed point so every node rounds
+        // the same way
+        let mut factor = DECAY_SCALE;
+        let mut base = retained_bps * DECAY_SCALE / BASIS_POINTS as u128;
+        let mut exponent = blocks;
+        while exponent > 0 && factor > 0 {
+            if exponent & 1 == 1 {
+                factor = factor * base / DECAY_SCALE;
+            }
+            base = base * base / DECAY_SCALE;
+            exponent >>= 1;
+        }
+
+        (lot.amount as u128 * factor / DECAY_SCALE) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lot(amount: u64, value_height: u64) -> TokenLot {
+        TokenLot {
+            id: TokenLot::lot_id("tx", 0),
+            owner: "owner".to_string(),
+            amount,
+            mint_height: value_height,
+            mint_time: 1_000,
+            expiration_time: None,
+            value_height,
+            lock: None,
+            pending_until: None,
+        }
+    }
+
+    #[test]
+    fn test_fixed_ttl() {
+        let policy = FixedTtl { lifetime: 500 };
+
+        assert_eq!(policy.expiration_time(1_000, "any"), Some(1_500));
+        assert_eq!(policy.expiration_time(u64::MAX, "any"), Some(u64::MAX));
+        assert_eq!(policy.lot_value(&lot(100, 1), 1_000), 100);
+    }
+
+    #[test]
+    fn test_tiered_ttl() {
+        let mut category_lifetimes = HashMap::new();
+        category_lifetimes.insert("care".to_string(), 2_000);
+        category_lifetimes.insert("repair".to_string(), 300);
+        let policy = TieredTtl {
+            default_lifetime: 1_000,
+            category_lifetimes,
+        };
+
+        assert_eq!(policy.expiration_time(10, "care"), Some(2_010));
+        assert_eq!(policy.expiration_time(10, "repair"), Some(310));
+        assert_eq!(policy.expiration_time(10, "unlisted"), Some(1_010));
+        assert_eq!(policy.expiration_time(10, ""), Some(1_010));
+    }
+
+    #[test]
+    fn test_linear_demurrage() {
+        // 10% of the original amount per block
+        let policy = LinearDemurrage { rate_bps: 1_000 };
+        let minted = lot(1_000, 5);
+
+        assert_eq!(policy.expiration_time(10, "any"), None);
+        assert_eq!(policy.lot_value(&minted, 5), 1_000);
+        assert_eq!(policy.lot_value(&minted, 6), 900);
+        assert_eq!(policy.lot_value(&minted, 10), 500);
+        assert_eq!(policy.lot_value(&minted, 15), 0);
+        assert_eq!(policy.lot_value(&minted, 1_000), 0);
+
+        // Heights before the lot was valued do not add value
+        assert_eq!(policy.lot_value(&minted, 0), 1_000);
+
+        // Decay saturates rather than overflowing, however large the rate or the lot
+        let extreme = LinearDemurrage { rate_bps: u64::MAX };
+        assert_eq!(extreme.lot_value(&lot(u64::MAX, 0), u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_exponential_demurrage() {
+        // 10% of the remaining value per block
+        let policy = ExponentialDemurrage { rate_bps: 1_000 };
+        let minted = lot(1_000, 0);
+
+        assert_eq!(policy.expiration_time(10, "any"), None);
+        assert_eq!(policy.lot_value(&minted, 0), 1_000);
+        assert_eq!(policy.lot_value(&minted, 1), 900);
+        assert_eq!(policy.lot_value(&minted, 2), 810);
+        assert_eq!(policy.lot_value(&minted, 10), 348);
+        assert_eq!(policy.lot_value(&minted, 1_000), 0);
+
+        // Decay is a pure function of the inputs, so repeated evaluation always agrees
+        assert_eq!(
+            policy.lot_value(&minted, 12_345),
+            policy.lot_value(&minted, 12_345)
+        );
+
+        let no_decay = ExponentialDemurrage { rate_bps: 0 };
+        assert_eq!(no_decay.lot_value(&minted, 1_000_000), 1_000);
+    }
+}
@@ -0,0 +1,341 @@
+use crate::utils::encoding::CanonicalEncoder;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Default number of blocks in an issuance epoch.
+pub const DEFAULT_ISSUANCE_EPOCH_LENGTH: u64 = 100;
+
+/// How the cap on each epoch's issuance adapts to how tokens were used in the epoch before.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SupplySchedule {
+    /// Every epoch has the base cap.
+    #[default]
+    Fixed,
+    /// The cap grows by a share of the tokens that expired during the previous epoch, so new
+    /// work can replace the supply lost to expiry.
+    ExpiryLinked {
+        /// Share of the expired tokens added to the base cap, in basis points.
+        replacement_bps: u64,
+    },
+    /// The cap scales with how much of the circulating supply changed hands during the
+    /// previous epoch, relative to a target, between half and twice the base cap.
+    VelocityLinked {
+        /// Share of the circulating supply transferred per epoch at which the base cap
+        /// applies, in basis points.
+        target_velocity_bps: u64,
+    },
+}
+
+/// Limits on how many tokens can be minted for work in each epoch.
+///
+/// Nothing is limited unless a cap is set, as on a development network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IssuancePolicy {
+    /// Number of blocks in an epoch. Epochs start at heights that are multiples of it.
+    pub epoch_length: u64,
+    /// Base cap on the tokens minted across all workers in an epoch, before the schedule
+    /// adapts it. Unlimited if unset.
+    pub max_epoch_issuance: Option<u64>,
+    /// Cap on the tokens minted to any single worker in an epoch. Unlimited if unset.
+    pub max_worker_issuance: Option<u64>,
+    /// How the epoch cap adapts from one epoch to the next.
+    pub schedule: SupplySchedule,
+}
+
+impl Default for IssuancePolicy {
+    fn default() -> Self {
+        IssuancePolicy {
+            epoch_length: DEFAULT_ISSUANCE_EPOCH_LENGTH,
+            max_epoch_issuance: None,
+            max_worker_issuance: None,
+            schedule: SupplySchedule::default(),
+        }
+    }
+}
+
+impl IssuancePolicy {
+    /// Returns the epoch the block at `height` belongs to.
+    pub fn epoch(&self, height: u64) -> u64 {
+        height / self.epoch_length.max(1)
+    }
+
+    /// Returns the cap on the tokens minted across all workers during an epoch, given what
+    /// was measured over the epoch before it.
+    pub fn epoch_cap(&self, issuance: &EpochIssuance) -> Option<u64> {
+        let base = self.max_epoch_issuance?;
+        let cap = match self.schedule {
+            SupplySchedule::Fixed => base,
+            SupplySchedule::ExpiryLinked { replacement_bps } => {
+                base.saturating_add(bps_of(issuance.previous_expired, replacement_bps))
+            }
+            SupplySchedule::VelocityLinked {
+                target_velocity_bps,
+            } => match issuance.previous_velocity_bps {
+                Some(velocity) if target_velocity_bps > 0 => {
+                    let velocity = velocity.clamp(
+                        target_velocity_bps / 2,
+                        target_velocity_bps.saturating_mul(2),
+                    );
+                    (base as u128 * velocity as u128 / target_velocity_bps as u128)
+                        .min(u64::MAX as u128) as u64
+                }
+                _ => base,
+            },
+        };
+        Some(cap)
+    }
+}
+
+/// Returns `bps` basis points of `amount`, rounded down.
+fn bps_of(amount: u64, bps: u64) -> u64 {
+    (amount as u128 * bps as u128 / 10_000).min(u64::MAX as u128) as u64
+}
+
+/// Reasons tokens cannot be minted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssuanceError {
+    /// Minting would take the epoch's issuance over its cap.
+    EpochCapReached { cap: u64, requested: u64 },
+    /// Minting would take the worker's issuance this epoch over the per-worker cap.
+    WorkerCapReached {
+        worker: String,
+        cap: u64,
+        requested: u64,
+    },
+}
+
+impl fmt::Display for IssuanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IssuanceError::EpochCapReached { cap, requested } => write!(
+                f,
+                "minting would take this epoch's issuance to {}, over the cap of {}",
+                requested, cap
+            ),
+            IssuanceError::WorkerCapReached {
+                worker,
+                cap,
+                requested,
+            } => write!(
+                f,
+                "minting would take {}'s issuance this epoch to {}, over the cap of {}",
+                worker, requested, cap
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IssuanceError {}
+
+/// Tokens minted during the current epoch, and what the schedule measures to set the next
+/// epoch's cap.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct EpochIssuance {
+    /// The current epoch.
+    pub epoch: u64,
+    /// Tokens minted this epoch, including mints later reversed by disputes.
+    pub minted: u64,
+    /// Tokens minted to each worker this epoch, keyed by address.
+    pub minted_by_worker: BTreeMap<String, u64>,
+    /// Tokens lost to expiry or decay this epoch.
+    pub expired: u64,
+    /// Tokens that changed owner in transfers this epoch. Payments to the sender's own account
+    /// and change do not count, and payments into escrow only count once released.
+    pub transferred: u64,
+    /// Tokens lost to expiry or decay during the previous epoch.
+    pub previous_expired: u64,
+    /// Tokens transferred during the previous epoch as a share of the circulating supply at
+    /// its end, in basis points. Unset before the first epoch ends, or if nothing circulated.
+    pub previous_velocity_bps: Option<u64>,
+}
+
+impl EpochIssuance {
+    /// Starts the epoch the block at `height` belongs to, if it is not the current one,
+    /// carrying over what was measured during the current epoch.
+    ///
+    /// # Arguments
+    /// * `policy` - The issuance policy that decides the epochs.
+    /// * `height` - Height of the block being applied.
+    /// * `circulating` - Live value of every lot before the block is applied.
+    pub fn roll_over(&mut self, policy: &IssuancePolicy, height: u64, circulating: u64) {
+        let epoch = policy.epoch(height);
+        if epoch == self.epoch {
+            return;
+        }
+        let velocity = (circulating > 0).then(|| {
+            (self.transferred as u128 * 10_000 / circulating as u128).min(u64::MAX as u128) as u64
+        });
+        *self = EpochIssuance {
+            epoch,
+            previous_expired: self.expired,
+            previous_velocity_bps: velocity,
+            ..EpochIssuance::default()
+        };
+    }
+
+    /// Records tokens minted to a worker, unless they would exceed a cap.
+    ///
+    /// # Returns
+    /// * `Result<(), IssuanceError>` - Which cap the mint would exceed, if any.
+    pub fn issue(
+        &mut self,
+        policy: &IssuancePolicy,
+        worker: &str,
+        amount: u64,
+    ) -> Result<(), IssuanceError> {
+        let requested = self.minted.saturating_add(amount);
+        if let Some(cap) = policy.epoch_cap(self) {
+            if requested > cap {
+                return Err(IssuanceError::EpochCapReached { cap, requested });
+            }
+        }
+        let minted_by_worker = self.minted_by_worker.get(worker).copied().unwrap_or(0);
+        let worker_requested = minted_by_worker.saturating_add(amount);
+        if let Some(cap) = policy.max_worker_issuance {
+            if worker_requested > cap {
+                return Err(IssuanceError::WorkerCapReached {
+                    worker: worker.to_string(),
+                    cap,
+                    requested: worker_requested,
+                });
+            }
+        }
+        self.minted = requested;
+        self.minted_by_worker
+            .insert(worker.to_string(), worker_requested);
+        Ok(())
+    }
+
+    /// Appends the epoch's issuance to a canonical encoding.
+    pub fn encode(&self, encoder: &mut CanonicalEncoder) {
+        encoder
+            .u64(self.epoch)
+            .u64(self.minted)
+            .count(self.minted_by_worker.len());
+        for (worker, minted) in &self.minted_by_worker {
+            encoder.str(worker).u64(*minted);
+        }
+        encoder
+            .u64(self.expired)
+            .u64(self.transferred)
+            .u64(self.previous_expired)
+            .option_u64(self.previous_velocity_bps);
+    }
+}
+
+/// The token supply as of a block, as reported to clients.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SupplyStats {
+    /// Total number of tokens minted for work since genesis.
+    pub minted: u64,
+    /// Live value of every lot, including lots held in escrow, bonds and the fee pool.
+    pub circulating: u64,
+    /// Of the circulating supply, the live value of minted lots that cannot be spent until
+    /// their mint is final.
+    pub pending: u64,
+    /// Total number of tokens that have expired or decayed since genesis.
+    pub expired: u64,
+    /// Total number of tokens paid as fees and burned since genesis.
+    pub burned_fees: u64,
+    /// Total number of tokens slashed from bonds or forfeited by disputes since genesis.
+    pub slashed_bonds: u64,
+    /// Total number of tokens minted and then reversed by upheld disputes since genesis.
+    pub reversed: u64,
+    /// The current issuance epoch.
+    pub epoch: u64,
+    /// Tokens minted so far this epoch.
+    pub epoch_minted: u64,
+    /// Cap on the tokens minted this epoch, if any.
+    pub epoch_cap: Option<u64>,
+    /// Cap on the tokens minted to any single worker this epoch, if any.
+    pub worker_cap: Option<u64>,
+    /// Tokens lost to expiry or decay so far this epoch.
+    pub epoch_expired: u64,
+    /// Tokens that changed owner in transfers so far this epoch.
+    pub epoch_transferred: u64,
+    /// Velocity of the circulating supply during the previous epoch, in basis points.
+    pub previous_velocity_bps: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capped(schedule: SupplySchedule) -> IssuancePolicy {
+        IssuancePolicy {
+            epoch_length: 10,
+            max_epoch_issuance: Some(100),
+            max_worker_issuance: Some(40),
+            schedule,
+        }
+    }
+
+    #[test]
+    fn test_issuance_is_capped_per_epoch_and_worker() {
+        let policy = capped(SupplySchedule::Fixed);
+        let mut issuance = EpochIssuance::default();
+        assert_eq!(issuance.issue(&policy, "a", 40), Ok(()));
+        assert_eq!(
+            issuance.issue(&policy, "a", 1),
+            Err(IssuanceError::WorkerCapReached {
+                worker: "a".to_string(),
+                cap: 40,
+                requested: 41
+            })
+        );
+        assert_eq!(issuance.issue(&policy, "b", 40), Ok(()));
+        assert_eq!(
+            issuance.issue(&policy, "c", 21),
+            Err(IssuanceError::EpochCapReached {
+                cap: 100,
+                requested: 101
+            })
+        );
+        assert_eq!(issuance.minted, 80);
+
+        // A new epoch starts with nothing minted
+        issuance.roll_over(&policy, 9, 80);
+        assert_eq!(issuance.minted, 80);
+        issuance.roll_over(&policy, 10, 80);
+        assert_eq!(issuance.epoch, 1);
+        assert_eq!(issuance.issue(&policy, "a", 40), Ok(()));
+
+        let unlimited = IssuancePolicy::default();
+        assert_eq!(issuance.issue(&unlimited, "a", u64::MAX), Ok(()));
+    }
+
+    #[test]
+    fn test_expiry_linked_cap_replaces_expired_supply() {
+        let policy = capped(SupplySchedule::ExpiryLinked {
+            replacement_bps: 5_000,
+        });
+        let mut issuance = EpochIssuance::default();
+        assert_eq!(policy.epoch_cap(&issuance), Some(100));
+        issuance.expired = 60;
+        issuance.roll_over(&policy, 10, 1_000);
+        assert_eq!(issuance.expired, 0);
+        assert_eq!(policy.epoch_cap(&issuance), Some(130));
+    }
+
+    #[test]
+    fn test_velocity_linked_cap_is_bounded() {
+        let policy = capped(SupplySchedule::VelocityLinked {
+            target_velocity_bps: 1_000,
+        });
+        let mut issuance = EpochIssuance::default();
+        assert_eq!(policy.epoch_cap(&issuance), Some(100));
+
+        issuance.transferred = 150;
+        issuance.roll_over(&policy, 10, 1_000);
+        assert_eq!(issuance.previous_velocity_bps, Some(1_500));
+        assert_eq!(policy.epoch_cap(&issuance), Some(150));
+
+        issuance.transferred = 10_000;
+        issuance.roll_over(&policy, 20, 1_000);
+        assert_eq!(policy.epoch_cap(&issuance), Some(200));
+
+        issuance.roll_over(&policy, 30, 1_000);
+        assert_eq!(policy.epoch_cap(&issuance), Some(50));
+    }
+}
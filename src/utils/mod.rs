@@ -1,5 +1,7 @@
+pub mod encoding;
 pub mod serialization;
 
+pub use self::encoding::{CanonicalEncoder, ENCODING_VERSION};
 pub use self::serialization::{
     deserialize_from_binary, deserialize_from_json, load_from_file, save_to_file,
     serialize_to_binary, serialize_to_json,
@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod wallet;
 
-pub use self::wallet::{create_wallet, sign_transaction, Wallet};
+pub use self::wallet::Wallet;
@@ -1,8 +1,9 @@
+use crate::blockchain::multisig::Approval;
 use crate::blockchain::transaction::Transaction;
+use crate::crypto::address::{Address, Network};
 use crate::crypto::generate_keypair;
 use crate::crypto::keys::{load_keypair_from_private, save_keypair};
 use ed25519_dalek::{Keypair, Signature, Signer}; // Ensured correct imports
-use std::path::Path;
 
 pub struct Wallet {
     keypair: Keypair,
@@ -50,6 +51,18 @@ impl Wallet {
         self.keypair.sign(message.as_bytes())
     }
 
+    /// Approves a transaction from a multisig account the wallet's key is a member of.
+    ///
+    /// # Arguments
+    /// * `transaction` - The proposed transaction to approve.
+    ///
+    /// # Returns
+    /// The `Approval` to add to the transaction, or to send to a node collecting approvals
+    /// for it.
+    pub fn approve_transaction(&self, transaction: &Transaction) -> Approval {
+        Approval::sign(&transaction.calculate_hash(), &self.keypair)
+    }
+
     /// Gets the wallet's public key.
     ///
     /// # Returns
@@ -57,4 +70,21 @@ impl Wallet {
     pub fn get_public_key(&self) -> &ed25519_dalek::PublicKey {
         &self.keypair.public
     }
+
+    /// Gets the wallet's address on the given network, which is what others send tokens to.
+    ///
+    /// # Arguments
+    /// * `network` - The network the address is for.
+    ///
+    /// # Returns
+    /// The `Address` derived from the wallet's public key.
+    pub fn get_address(&self, network: Network) -> Address {
+        Address::new(&self.keypair.public, network)
+    }
+}
+
+impl Default for Wallet {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -1,35 +1,316 @@
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use crate::blockchain::block::Block;
-    use crate::blockchain::ledger::Ledger;
-    use crate::blockchain::merkle_tree::MerkleTree;
-    use crate::blockchain::transaction::Transaction;
-    use crate::crypto::hash::calculate_hash;
+    use core::blockchain::block::Block;
+    use core::blockchain::header::{AuthorityVote, BlockHeader};
+    use core::blockchain::ledger::Ledger;
+    use core::blockchain::merkle_tree::{MerkleTree, ProofStep, Side};
+    use core::blockchain::multisig::MultisigPolicy;
+    use core::blockchain::transaction::Transaction;
+    use core::config::{ExpiryPolicyKind, FeePolicyKind, SupplyScheduleKind, TokenSettings};
+    use core::consensus::dispute::{DisputeAction, DisputeError};
+    use core::consensus::engine::ConsensusEngine;
+    use core::consensus::proof_of_authority::ProofOfAuthority;
+    use core::consensus::proof_of_work::ProofOfWork;
+    use core::consensus::registry::{RegistryAction, RegistryError};
+    use core::consensus::validator::{ValidationError, Validator};
+    use core::crypto::address::{Address, AddressError, Network};
+    use core::crypto::hash::hash_bytes;
+    use core::task::marketplace::{
+        task_account, TaskAction, TaskError, TaskFilter, TaskPosting, TaskStatus,
+    };
+    use core::token::condition::{sign_release, Requirement, SpendCondition, Timelock, Witness};
+    use core::token::policy::LinearDemurrage;
+    use core::token::supply::IssuanceError;
+    use ed25519_dalek::{Keypair, PublicKey, SecretKey};
+    use rand::rngs::OsRng;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Creates a signed transfer from a freshly generated sender to an encoded address.
+    fn signed_transaction(to: &str, amount: u64) -> Transaction {
+        let sender = Keypair::generate(&mut OsRng);
+        let mut tx = Transaction::new(sender.public, to.parse().unwrap(), amount, None);
+        tx.sign(&sender);
+        tx
+    }
+
+    /// Creates a work claim attested by a single verifier and signed by the worker, using the
+    /// worker's `nonce`.
+    fn attested_work_claim(
+        worker: &Keypair,
+        nonce: u64,
+        work_hash: &str,
+        amount: u64,
+    ) -> Transaction {
+        attested_work_claim_in(worker, nonce, work_hash, "general", amount)
+    }
+
+    /// Creates an attested work claim for work in the given category.
+    fn attested_work_claim_in(
+        worker: &Keypair,
+        nonce: u64,
+        work_hash: &str,
+        category: &str,
+        amount: u64,
+    ) -> Transaction {
+        let verifier = Keypair::generate(&mut OsRng);
+        work_claim_attested_by(worker, nonce, work_hash, category, amount, &verifier)
+    }
+
+    /// Creates a work claim attested by the given verifier and signed by the worker.
+    fn work_claim_attested_by(
+        worker: &Keypair,
+        nonce: u64,
+        work_hash: &str,
+        category: &str,
+        amount: u64,
+        verifier: &Keypair,
+    ) -> Transaction {
+        let mut tx = Transaction::new_work_claim(
+            worker.public,
+            work_hash.to_string(),
+            "Completed task".to_string(),
+            category.to_string(),
+            amount,
+        )
+        .with_nonce(nonce);
+        tx.attest(verifier);
+        tx.sign(worker);
+        tx
+    }
+
+    /// Creates a registry action by `sender`, using the sender's `nonce`, signed by the sender.
+    fn signed_registry_action(
+        sender: &Keypair,
+        nonce: u64,
+        action: RegistryAction,
+        amount: u64,
+        inputs: Vec<String>,
+    ) -> Transaction {
+        let mut tx = Transaction::new_registry_action(sender.public, action, amount, inputs)
+            .with_nonce(nonce);
+        tx.sign(sender);
+        tx
+    }
+
+    /// Token settings enforcing the verifier registry, with the given governor and an optional
+    /// minimum bond.
+    fn registry_settings(governor: &Keypair, min_verifier_bond: Option<u64>) -> TokenSettings {
+        TokenSettings {
+            governors: vec![hex::encode(governor.public.as_bytes())],
+            min_verifier_bond,
+            ..TokenSettings::default()
+        }
+    }
+
+    /// Creates a dispute action by `sender`, using the sender's `nonce`, signed by the sender.
+    fn signed_dispute_action(
+        sender: &Keypair,
+        nonce: u64,
+        action: DisputeAction,
+        amount: u64,
+        inputs: Vec<String>,
+    ) -> Transaction {
+        let mut tx = Transaction::new_dispute_action(sender.public, action, amount, inputs)
+            .with_nonce(nonce);
+        tx.sign(sender);
+        tx
+    }
+
+    /// Token settings with the given governor on the dispute panel, holding work mints open to
+    /// dispute for `challenge_period` blocks.
+    fn dispute_settings(governor: &Keypair, challenge_period: u64) -> TokenSettings {
+        TokenSettings {
+            challenge_period,
+            ..registry_settings(governor, None)
+        }
+    }
+
+    /// Sets up a ledger whose governor has admitted a verifier and whose disputer has 20
+    /// spendable tokens, then mints 30 tokens to a worker for work the verifier attested.
+    ///
+    /// # Returns
+    /// * `(Ledger, Keypair, Keypair, Keypair, String, String)` - The ledger, the governor, the
+    ///   verifier, the disputer, the disputer's lot and the ID of the worker's mint.
+    fn disputable_mint() -> (Ledger, Keypair, Keypair, Keypair, String, String) {
+        let governor = Keypair::generate(&mut OsRng);
+        let mut ledger = Ledger::with_settings(&dispute_settings(&governor, 3));
+        let verifier = Keypair::generate(&mut OsRng);
+        let disputer = Keypair::generate(&mut OsRng);
+        let worker = Keypair::generate(&mut OsRng);
+
+        let admit = RegistryAction::Admit {
+            verifier: verifier.public,
+        };
+        let funding = work_claim_attested_by(&disputer, 0, "work_1", "general", 20, &verifier);
+        let disputer_lot = format!("{}:0", funding.id);
+        assert!(add_transactions(
+            &mut ledger,
+            vec![
+                signed_registry_action(&governor, 0, admit, 0, vec![]),
+                funding
+            ]
+        ));
+        for _ in 0..3 {
+            assert!(add_transactions(&mut ledger, vec![]));
+        }
+
+        let claim = work_claim_attested_by(&worker, 0, "work_2", "general", 30, &verifier);
+        let mint_id = claim.id.clone();
+        assert!(add_transactions(&mut ledger, vec![claim]));
+        (ledger, governor, verifier, disputer, disputer_lot, mint_id)
+    }
+
+    /// Creates a keypair from a fixed secret key, for tests that pin exact hashes.
+    fn fixed_keypair(seed: u8) -> Keypair {
+        let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        Keypair { secret, public }
+    }
+
+    /// Returns the encoded address of a named account, whose key is seeded by the name.
+    fn account(name: &str) -> String {
+        let mut seed = [0; 32];
+        seed[..name.len()].copy_from_slice(name.as_bytes());
+        let secret = SecretKey::from_bytes(&seed).unwrap();
+        Address::new(&PublicKey::from(&secret), Network::Main).to_string()
+    }
+
+    /// Token settings for a fixed lifetime, in seconds.
+    fn fixed_ttl_settings(token_lifetime: u64) -> TokenSettings {
+        TokenSettings {
+            token_lifetime,
+            ..TokenSettings::default()
+        }
+    }
+
+    /// Token settings capping every account's live balance, with an optional commons account.
+    fn holding_cap_settings(
+        max_account_balance: u64,
+        commons_account: Option<&str>,
+    ) -> TokenSettings {
+        TokenSettings {
+            max_account_balance: Some(max_account_balance),
+            commons_account: commons_account.map(str::to_string),
+            ..TokenSettings::default()
+        }
+    }
+
+    /// Token settings selecting a fee policy, with epochs of `fee_epoch_length` blocks.
+    fn fee_settings(fee_policy: FeePolicyKind, fee_epoch_length: u64) -> TokenSettings {
+        TokenSettings {
+            fee_policy,
+            fee_epoch_length,
+            ..TokenSettings::default()
+        }
+    }
+
+    /// Appends a block with the given transactions to the ledger.
+    fn add_transactions(ledger: &mut Ledger, transactions: Vec<Transaction>) -> bool {
+        add_transactions_after(ledger, 0, transactions)
+    }
+
+    /// Appends a block with the given transactions, `seconds` after the latest block.
+    fn add_transactions_after(
+        ledger: &mut Ledger,
+        seconds: u64,
+        transactions: Vec<Transaction>,
+    ) -> bool {
+        let latest_block = ledger.get_latest_block();
+        let mut block = Block::new(
+            latest_block.header.height + 1,
+            latest_block.hash.clone(),
+            transactions,
+            0,
+        );
+        block.header.timestamp =
+            latest_block.header.timestamp.max(block.header.timestamp) + seconds;
+        add_block(ledger, block)
+    }
+
+    /// Sets a block's target, commits its state root and mines it, then appends it to the
+    /// ledger. Blocks that cannot be applied are left for `Ledger::add_block` to reject.
+    fn add_block(ledger: &mut Ledger, mut block: Block) -> bool {
+        block.header.target = ledger.next_target();
+        block.hash = block.calculate_hash();
+        let _ = ledger.commit_state_root(&mut block);
+        ProofOfWork::default().mine_block(&mut block);
+        ledger.add_block(block)
+    }
+
+    /// Creates a transfer spending the given lots from `sender`, using the sender's `nonce`,
+    /// signed by the sender.
+    fn signed_transfer(
+        sender: &Keypair,
+        nonce: u64,
+        to: &str,
+        amount: u64,
+        inputs: Vec<String>,
+    ) -> Transaction {
+        let mut tx = Transaction::new_transfer(sender.public, to.parse().unwrap(), amount, inputs)
+            .with_nonce(nonce);
+        tx.sign(sender);
+        tx
+    }
+
+    /// Creates a task action by `sender`, using the sender's `nonce`, signed by the sender.
+    fn signed_task_action(sender: &Keypair, nonce: u64, action: TaskAction) -> Transaction {
+        let mut tx = Transaction::new_task_action(sender.public, action).with_nonce(nonce);
+        tx.sign(sender);
+        tx
+    }
+
+    /// Creates a posting of a task by `client`, putting up its reward from the given lots,
+    /// using the client's `nonce`, signed by the client.
+    fn signed_task_posting(
+        client: &Keypair,
+        nonce: u64,
+        posting: TaskPosting,
+        inputs: Vec<String>,
+    ) -> Transaction {
+        let mut tx =
+            Transaction::new_task_posting(client.public, posting, inputs).with_nonce(nonce);
+        tx.sign(client);
+        tx
+    }
+
+    /// Creates a task posting for `reward`, due `deadline`, attested by the given verifiers.
+    fn task_posting(
+        reward: u64,
+        deadline: u64,
+        verifiers: &[&Keypair],
+        required_attestations: u32,
+    ) -> TaskPosting {
+        TaskPosting {
+            title: "Translate the handbook".to_string(),
+            description: "Translate the volunteer handbook into Spanish".to_string(),
+            category: "translation".to_string(),
+            reward,
+            deadline,
+            verifiers: verifiers.iter().map(|verifier| verifier.public).collect(),
+            required_attestations,
+        }
+    }
 
     #[test]
     fn test_block_creation() {
         let transactions = vec![
-            Transaction::new("sender".to_string(), "receiver".to_string(), 50, None),
-            Transaction::new("sender2".to_string(), "receiver2".to_string(), 100, None),
+            signed_transaction(&account("receiver"), 50),
+            signed_transaction(&account("receiver2"), 100),
         ];
         let block = Block::new(1, "prev_hash".to_string(), transactions, 0);
 
         assert!(!block.hash.is_empty());
-        assert_eq!(block.index, 1);
-        assert_eq!(block.previous_hash, "prev_hash");
+        assert_eq!(block.header.height, 1);
+        assert_eq!(block.header.previous_hash, "prev_hash");
         assert_eq!(block.transactions.len(), 2);
         println!("Block Hash: {}", block.hash);
     }
 
     #[test]
     fn test_block_validation() {
-        let transactions = vec![Transaction::new(
-            "sender".to_string(),
-            "receiver".to_string(),
-            50,
-            None,
-        )];
+        let transactions = vec![signed_transaction(&account("receiver"), 50)];
         let block = Block::new(1, "prev_hash".to_string(), transactions, 0);
         assert!(block.validate());
     }
@@ -38,35 +319,27 @@ mod tests {
     fn test_ledger_initialization() {
         let ledger = Ledger::new();
         assert_eq!(ledger.chain.len(), 1);
-        assert_eq!(ledger.chain[0].index, 0);
+        assert_eq!(ledger.chain[0].header.height, 0);
     }
 
     #[test]
     fn test_add_block() {
         let mut ledger = Ledger::new();
-        let transactions = vec![Transaction::new(
-            "sender".to_string(),
-            "receiver".to_string(),
-            100,
-            None,
-        )];
+        let worker = Keypair::generate(&mut OsRng);
+        let transactions = vec![attested_work_claim(&worker, 0, "work_1", 100)];
         let new_block = Block::new(1, ledger.get_latest_block().hash.clone(), transactions, 0);
 
-        assert!(ledger.add_block(new_block));
+        assert!(add_block(&mut ledger, new_block));
         assert_eq!(ledger.chain.len(), 2);
     }
 
     #[test]
     fn test_validate_chain() {
         let mut ledger = Ledger::new();
-        let transactions = vec![Transaction::new(
-            "sender".to_string(),
-            "receiver".to_string(),
-            100,
-            None,
-        )];
+        let worker = Keypair::generate(&mut OsRng);
+        let transactions = vec![attested_work_claim(&worker, 0, "work_1", 100)];
         let new_block = Block::new(1, ledger.get_latest_block().hash.clone(), transactions, 0);
-        ledger.add_block(new_block);
+        assert!(add_block(&mut ledger, new_block));
 
         assert!(ledger.validate_chain());
     }
@@ -74,16 +347,11 @@ mod tests {
     #[test]
     fn test_invalid_block_addition() {
         let mut ledger = Ledger::new();
-        let transactions = vec![Transaction::new(
-            "sender".to_string(),
-            "receiver".to_string(),
-            100,
-            None,
-        )];
+        let transactions = vec![signed_transaction(&account("receiver"), 100)];
         let mut invalid_block = Block::new(1, "fake_hash".to_string(), transactions, 0);
         invalid_block.hash = invalid_block.calculate_hash();
 
-        assert!(!ledger.add_block(invalid_block));
+        assert!(!add_block(&mut ledger, invalid_block));
     }
 
     #[test]
@@ -104,13 +372,113 @@ mod tests {
         let transactions = vec!["tx1_hash".to_string(), "tx2_hash".to_string()];
 
         let merkle_tree = MerkleTree::new(transactions.clone());
-        let proof = vec![transactions[1].clone()];
+        let proof = merkle_tree.generate_proof(0).unwrap();
+        assert_eq!(
+            proof,
+            vec![ProofStep {
+                hash: merkle_tree.levels[0][1].clone(),
+                side: Side::Right,
+            }]
+        );
         let is_valid =
-            merkle_tree.verify_proof(&transactions[0], &proof, merkle_tree.root.as_ref().unwrap());
+            MerkleTree::verify_proof(&transactions[0], &proof, merkle_tree.root.as_ref().unwrap());
 
         assert!(is_valid);
     }
 
+    #[test]
+    fn test_merkle_proofs_for_every_leaf() {
+        for size in 1..=9 {
+            let transactions: Vec<String> = (0..size).map(|i| format!("tx{}_hash", i)).collect();
+            let merkle_tree = MerkleTree::new(transactions.clone());
+            let root = merkle_tree.root.clone().unwrap();
+
+            for (index, tx_hash) in transactions.iter().enumerate() {
+                let proof = merkle_tree.generate_proof(index).unwrap();
+                assert!(MerkleTree::verify_proof(tx_hash, &proof, &root));
+
+                // The proof only holds for its own transaction
+                let other = &transactions[(index + 1) % size];
+                if other != tx_hash {
+                    assert!(!MerkleTree::verify_proof(other, &proof, &root));
+                }
+            }
+            assert!(merkle_tree.generate_proof(size).is_none());
+        }
+    }
+
+    #[test]
+    fn test_merkle_proof_direction_matters() {
+        let transactions: Vec<String> = (0..4).map(|i| format!("tx{}_hash", i)).collect();
+        let merkle_tree = MerkleTree::new(transactions.clone());
+        let root = merkle_tree.root.clone().unwrap();
+
+        let mut proof = merkle_tree.generate_proof(2).unwrap();
+        assert!(MerkleTree::verify_proof(&transactions[2], &proof, &root));
+
+        proof[0].side = Side::Left;
+        assert!(!MerkleTree::verify_proof(&transactions[2], &proof, &root));
+    }
+
+    #[test]
+    fn test_merkle_root_resists_duplication_and_node_forgery() {
+        let transactions: Vec<String> = (0..3).map(|i| format!("tx{}_hash", i)).collect();
+        let root = MerkleTree::new(transactions.clone()).root.unwrap();
+
+        // Repeating the last transaction must not reproduce the root
+        let mut duplicated = transactions.clone();
+        duplicated.push(transactions[2].clone());
+        assert_ne!(MerkleTree::new(duplicated).root.unwrap(), root);
+
+        // An inner node presented as a leaf must not prove anything
+        let merkle_tree = MerkleTree::new(transactions);
+        let inner_node = merkle_tree.levels[1][0].clone();
+        let proof = vec![ProofStep {
+            hash: merkle_tree.levels[1][1].clone(),
+            side: Side::Right,
+        }];
+        assert!(!MerkleTree::verify_proof(&inner_node, &proof, &root));
+
+        // A single transaction's root is its leaf hash, not the transaction hash itself
+        let single = MerkleTree::new(vec!["tx0_hash".to_string()]);
+        assert_ne!(single.root.as_deref(), Some("tx0_hash"));
+    }
+
+    #[test]
+    fn test_block_merkle_proof_verifies_against_header() {
+        let worker = Keypair::generate(&mut OsRng);
+        let transactions = vec![
+            attested_work_claim(&worker, 0, "work_1", 10),
+            attested_work_claim(&worker, 1, "work_2", 20),
+            attested_work_claim(&worker, 2, "work_3", 30),
+        ];
+        let block = Block::new(1, "prev_hash".to_string(), transactions, 0);
+
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let proof = block.merkle_proof(index).unwrap();
+            assert!(MerkleTree::verify_proof(
+                &tx.calculate_signed_hash(),
+                &proof,
+                &block.header.merkle_root
+            ));
+        }
+        assert!(block.merkle_proof(3).is_none());
+    }
+
+    #[test]
+    fn test_merkle_root_commits_to_signatures() {
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 10);
+        let block = Block::new(1, "prev_hash".to_string(), vec![claim], 0);
+        assert!(block.validate());
+
+        // Swapping the signature keeps the transaction's ID but breaks the block
+        let mut resigned = block.clone();
+        resigned.transactions[0].sign(&Keypair::generate(&mut OsRng));
+        assert_eq!(resigned.transactions[0].id, block.transactions[0].id);
+        assert!(!resigned.validate());
+    }
+
     #[test]
     fn test_block_with_no_transactions() {
         let transactions: Vec<Transaction> = vec![];
@@ -119,8 +487,8 @@ mod tests {
         assert!(!block.hash.is_empty());
         assert_eq!(block.transactions.len(), 0);
         assert!(
-            block.merkle_root.is_empty()
-                || block.merkle_root == "some_expected_value_for_empty_root"
+            block.header.merkle_root.is_empty()
+                || block.header.merkle_root == "some_expected_value_for_empty_root"
         );
     }
 
@@ -129,24 +497,26 @@ mod tests {
         let ledger = Ledger::new();
         let genesis_block = &ledger.chain[0];
 
-        assert_eq!(genesis_block.index, 0);
-        assert_eq!(genesis_block.previous_hash, "0");
-        assert!(genesis_block.hash != "");
+        assert_eq!(genesis_block.header.height, 0);
+        assert_eq!(genesis_block.header.previous_hash, "0");
+        assert!(!genesis_block.hash.is_empty());
         assert_eq!(genesis_block.transactions.len(), 0);
     }
 
     #[test]
     fn test_invalid_transaction_in_block() {
+        // An unsigned transaction cannot be authorised by its sender
+        let sender = Keypair::generate(&mut OsRng);
         let invalid_transaction = Transaction::new(
-            "invalid_sender".to_string(),
-            "receiver".to_string(),
+            sender.public,
+            account("receiver").parse().unwrap(),
             50,
             None,
         );
         let block = Block::new(1, "prev_hash".to_string(), vec![invalid_transaction], 0);
 
         assert!(
-            !block.validate(),
+            !Validator::validate_block(&block),
             "Block with invalid transaction should not be valid"
         );
     }
@@ -155,7 +525,9 @@ mod tests {
     fn test_proof_of_work() {
         let mut block = Block::new(1, "prev_hash".to_string(), vec![], 0);
         let difficulty = 4; // Example difficulty level
-        block.mine(difficulty);
+        let proof_of_work = ProofOfWork::new(difficulty);
+        block.header.target = proof_of_work.initial_target();
+        proof_of_work.mine_block(&mut block);
 
         let leading_zeros = "0".repeat(difficulty);
         assert!(
@@ -167,21 +539,2550 @@ mod tests {
     #[test]
     fn test_adding_future_block() {
         let mut ledger = Ledger::new();
-        let transactions = vec![Transaction::new(
-            "sender".to_string(),
-            "receiver".to_string(),
-            100,
-            None,
-        )];
+        let transactions = vec![signed_transaction(&account("receiver"), 100)];
 
         // Simulate a future block with a timestamp much higher than the current time.
         let mut future_block =
-            Block::new(2, ledger.get_latest_block().hash.clone(), transactions, 0);
-        future_block.timestamp += 1000000; // Add an unrealistic timestamp
+            Block::new(1, ledger.get_latest_block().hash.clone(), transactions, 0);
+        future_block.header.timestamp += 1000000; // Add an unrealistic timestamp
 
         assert!(
-            !ledger.add_block(future_block),
+            !add_block(&mut ledger, future_block),
             "Should not allow adding a block with a future timestamp"
         );
     }
+
+    #[test]
+    fn test_work_claim_mints_tokens() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 75);
+        let worker_address = claim.sender_address();
+        let new_block = Block::new(1, ledger.get_latest_block().hash.clone(), vec![claim], 0);
+        let block_timestamp = new_block.header.timestamp;
+
+        assert!(add_block(&mut ledger, new_block));
+
+        let tokens = ledger.get_issued_tokens(&worker_address);
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].amount, 75);
+        assert_eq!(
+            tokens[0].expiration_time,
+            ledger
+                .state
+                .expiry_policy
+                .expiration_time(block_timestamp, "general")
+        );
+        assert!(ledger.is_work_claimed("work_1"));
+    }
+
+    #[test]
+    fn test_unattested_work_claim_rejected() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let mut claim = Transaction::new_work_claim(
+            worker.public,
+            "work_1".to_string(),
+            "Completed task".to_string(),
+            "general".to_string(),
+            75,
+        );
+        claim.sign(&worker);
+        let new_block = Block::new(1, ledger.get_latest_block().hash.clone(), vec![claim], 0);
+
+        assert!(!add_block(&mut ledger, new_block));
+        assert!(ledger.issuances.is_empty());
+    }
+
+    #[test]
+    fn test_self_attested_work_claim_rejected() {
+        let worker = Keypair::generate(&mut OsRng);
+        let mut claim = Transaction::new_work_claim(
+            worker.public,
+            "work_1".to_string(),
+            "Completed task".to_string(),
+            "general".to_string(),
+            75,
+        );
+        claim.attest(&worker);
+        claim.sign(&worker);
+
+        assert!(!Validator::validate_transaction(&claim));
+    }
+
+    #[test]
+    fn test_tampered_work_claim_amount_rejected() {
+        let worker = Keypair::generate(&mut OsRng);
+        let mut claim = attested_work_claim(&worker, 0, "work_1", 75);
+
+        // Re-signing by the worker cannot cover for the verifier attesting a smaller amount
+        claim.amount = 7_500;
+        claim.sign(&worker);
+
+        assert!(!Validator::validate_transaction(&claim));
+    }
+
+    #[test]
+    fn test_work_cannot_be_claimed_twice() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+
+        let first = attested_work_claim(&worker, 0, "work_1", 75);
+        let block = Block::new(1, ledger.get_latest_block().hash.clone(), vec![first], 0);
+        assert!(add_block(&mut ledger, block));
+
+        let second = attested_work_claim(&worker, 1, "work_1", 75);
+        let block = Block::new(2, ledger.get_latest_block().hash.clone(), vec![second], 0);
+        assert!(!add_block(&mut ledger, block));
+
+        let duplicate_in_block = vec![
+            attested_work_claim(&worker, 1, "work_2", 10),
+            attested_work_claim(&worker, 2, "work_2", 10),
+        ];
+        let block = Block::new(
+            2,
+            ledger.get_latest_block().hash.clone(),
+            duplicate_in_block,
+            0,
+        );
+        assert!(!add_block(&mut ledger, block));
+        assert_eq!(ledger.issuances.len(), 1);
+    }
+
+    #[test]
+    fn test_nonces_must_follow_in_order() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let address = Address::new(&worker.public, Network::Main).to_string();
+        assert_eq!(ledger.get_next_nonce(&address), 0);
+
+        // A nonce that skips ahead is rejected until the gap is filled
+        let skipped = attested_work_claim(&worker, 1, "work_2", 10);
+        assert_eq!(
+            ledger.validate_pending_transaction(&skipped),
+            Err(ValidationError::InvalidNonce {
+                expected: 0,
+                found: 1
+            })
+        );
+        assert!(!add_transactions(&mut ledger, vec![skipped.clone()]));
+
+        let first = attested_work_claim(&worker, 0, "work_1", 10);
+        assert!(add_transactions(&mut ledger, vec![first, skipped]));
+        assert_eq!(ledger.get_next_nonce(&address), 2);
+
+        // A used nonce cannot be replayed, even for a different claim
+        let reused = attested_work_claim(&worker, 1, "work_3", 10);
+        assert_eq!(
+            ledger.validate_pending_transaction(&reused),
+            Err(ValidationError::InvalidNonce {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert!(!add_transactions(&mut ledger, vec![reused]));
+
+        // Nonces are tracked per account
+        let other = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&other, 0, "work_4", 10);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+        assert_eq!(ledger.get_next_nonce(&address), 2);
+    }
+
+    #[test]
+    fn test_transactions_for_another_chain_rejected() {
+        let mut ledger = Ledger::new().with_chain_id(7);
+        let worker = Keypair::generate(&mut OsRng);
+
+        let claim = attested_work_claim(&worker, 0, "work_1", 10);
+        assert_eq!(
+            ledger.validate_pending_transaction(&claim),
+            Err(ValidationError::WrongChain {
+                expected: 7,
+                found: 1
+            })
+        );
+        assert!(!add_transactions(&mut ledger, vec![claim.clone()]));
+
+        // Changing the chain ID after signing breaks the signature
+        let mut replayed = claim.clone().with_chain_id(7);
+        replayed.signature = claim.signature.clone();
+        assert!(!Validator::validate_transaction(&replayed));
+
+        let verifier = Keypair::generate(&mut OsRng);
+        let mut claim = Transaction::new_work_claim(
+            worker.public,
+            "work_1".to_string(),
+            "Completed task".to_string(),
+            "general".to_string(),
+            10,
+        )
+        .with_chain_id(7);
+
+        // An attestation made for another chain does not carry over
+        let mut reattested = claim.clone().with_chain_id(1);
+        reattested.attest(&verifier);
+        let mut reattested = reattested.with_chain_id(7);
+        reattested.sign(&worker);
+        assert!(!Validator::validate_transaction(&reattested));
+
+        claim.attest(&verifier);
+        claim.sign(&worker);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+    }
+
+    #[test]
+    fn test_recipients_must_be_valid_addresses_for_the_chain() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let lot_id = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        // Malformed addresses never reach a transaction
+        let recipient = account("receiver");
+        let mut mistyped: Vec<char> = recipient.chars().collect();
+        mistyped[5] = if mistyped[5] == 'q' { 'p' } else { 'q' };
+        let mistyped: String = mistyped.into_iter().collect();
+        assert_eq!(
+            mistyped.parse::<Address>(),
+            Err(AddressError::InvalidChecksum)
+        );
+        assert!("receiver".parse::<Address>().is_err());
+
+        // A test network address cannot receive tokens on the main network
+        let test_address = Address::new(&Keypair::generate(&mut OsRng).public, Network::Test);
+        let mut transfer =
+            Transaction::new_transfer(worker.public, test_address, 60, vec![lot_id.clone()])
+                .with_nonce(1);
+        transfer.sign(&worker);
+        assert!(!Validator::validate_transaction(&transfer));
+        assert!(!add_transactions(&mut ledger, vec![transfer]));
+
+        let transfer = signed_transfer(&worker, 1, &recipient, 60, vec![lot_id]);
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+        assert_eq!(ledger.get_balance(&recipient), 60);
+    }
+
+    #[test]
+    fn test_multisig_account_spends_with_threshold_of_approvals() {
+        let mut ledger = Ledger::new();
+        let members: Vec<Keypair> = (0..3).map(|_| Keypair::generate(&mut OsRng)).collect();
+        let policy = MultisigPolicy::new(2, members.iter().map(|member| member.public).collect());
+        let treasury = policy.address(Network::Main).to_string();
+
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 100);
+        let funding = signed_transfer(&worker, 1, &treasury, 100, vec![format!("{}:0", claim.id)]);
+        let treasury_lot = format!("{}:0", funding.id);
+        assert!(add_transactions(&mut ledger, vec![claim, funding]));
+        assert_eq!(ledger.get_balance(&treasury), 100);
+
+        let mut payment = Transaction::new_transfer(
+            members[0].public,
+            account("receiver").parse().unwrap(),
+            60,
+            vec![treasury_lot],
+        )
+        .with_multisig(policy.clone());
+        assert_eq!(payment.sender_address(), treasury);
+
+        // One approval is not enough, and neither is a member's plain signature
+        assert!(payment.approve(&members[0]));
+        assert_eq!(payment.approvals_needed(), Some(1));
+        assert!(!Validator::validate_transaction(&payment));
+        let mut signed = payment.clone();
+        signed.sign(&members[1]);
+        assert!(!Validator::validate_transaction(&signed));
+        assert!(!payment.approve(&Keypair::generate(&mut OsRng)));
+        assert!(!add_transactions(&mut ledger, vec![payment.clone()]));
+
+        // Approving twice does not count twice
+        assert!(payment.approve(&members[0]));
+        assert_eq!(payment.approvals_needed(), Some(1));
+
+        assert!(payment.approve(&members[2]));
+        assert_eq!(payment.approvals_needed(), Some(0));
+        assert!(Validator::validate_transaction(&payment));
+
+        // Approvals are for the exact transaction, so it cannot be changed afterwards
+        let mut altered = payment.clone();
+        altered.amount = 100;
+        altered.id = altered.calculate_hash();
+        assert!(!Validator::validate_transaction(&altered));
+
+        assert!(add_transactions(&mut ledger, vec![payment]));
+        assert_eq!(ledger.get_balance(&account("receiver")), 60);
+        assert_eq!(ledger.get_balance(&treasury), 40);
+        assert_eq!(ledger.get_next_nonce(&treasury), 1);
+    }
+
+    #[test]
+    fn test_multisig_transactions_must_come_from_members() {
+        let members: Vec<Keypair> = (0..2).map(|_| Keypair::generate(&mut OsRng)).collect();
+        let policy = MultisigPolicy::new(1, members.iter().map(|member| member.public).collect());
+        let recipient: Address = account("receiver").parse().unwrap();
+
+        let outsider = Keypair::generate(&mut OsRng);
+        let mut proposal =
+            Transaction::new_transfer(outsider.public, recipient, 10, vec!["abc:0".to_string()])
+                .with_multisig(policy.clone());
+        assert!(proposal.approve(&members[0]));
+        assert!(!Validator::validate_transaction(&proposal));
+
+        // A policy that can never be met is rejected outright
+        let unmet = MultisigPolicy::new(3, policy.members.clone());
+        let mut proposal =
+            Transaction::new_transfer(members[0].public, recipient, 10, vec!["abc:0".to_string()])
+                .with_multisig(unmet);
+        proposal.approve(&members[0]);
+        proposal.approve(&members[1]);
+        assert!(!proposal.validate_contents());
+        assert!(!Validator::validate_transaction(&proposal));
+    }
+
+    #[test]
+    fn test_escrow_releases_to_worker_on_verifier_signature() {
+        let mut ledger = Ledger::new();
+        let client = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&client, 0, "work_1", 100);
+        let client_address = claim.sender_address();
+        let client_lot = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        // The client locks payment for a job until the verifier signs it off
+        let worker = Keypair::generate(&mut OsRng);
+        let worker_address = Address::new(&worker.public, Network::Main);
+        let verifier = Keypair::generate(&mut OsRng);
+        let condition = SpendCondition::new(vec![Requirement::VerifierSignature(verifier.public)])
+            .with_refund_after(Timelock::Height(10));
+        let mut escrow =
+            Transaction::new_transfer(client.public, worker_address, 60, vec![client_lot])
+                .with_nonce(1)
+                .with_condition(condition);
+        escrow.sign(&client);
+        let escrow_lot = format!("{}:0", escrow.id);
+        assert!(add_transactions(&mut ledger, vec![escrow]));
+
+        // Escrowed tokens are held for the worker but not yet part of their balance
+        let worker_address = worker_address.to_string();
+        assert_eq!(ledger.get_balance(&worker_address), 0);
+        assert_eq!(ledger.get_lots(&worker_address).len(), 1);
+        assert_eq!(ledger.get_balance(&client_address), 40);
+
+        // Neither the worker without a release, nor the client before the deadline, can spend
+        let unreleased = signed_transfer(&worker, 0, &worker_address, 60, vec![escrow_lot.clone()]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&unreleased),
+            Err(ValidationError::ConditionNotMet(escrow_lot.clone()))
+        );
+        let early_refund =
+            signed_transfer(&client, 2, &client_address, 60, vec![escrow_lot.clone()]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&early_refund),
+            Err(ValidationError::ConditionNotMet(escrow_lot.clone()))
+        );
+        let stranger = Keypair::generate(&mut OsRng);
+        let theft = signed_transfer(
+            &stranger,
+            0,
+            &account("receiver"),
+            60,
+            vec![escrow_lot.clone()],
+        );
+        assert_eq!(
+            ledger.validate_pending_transaction(&theft),
+            Err(ValidationError::UnownedInput(escrow_lot.clone()))
+        );
+
+        // A release signed by anyone but the verifier does not count
+        let forged =
+            Witness::new(escrow_lot.clone()).with_release(sign_release(&escrow_lot, &stranger));
+        let mut forged_release = Transaction::new_transfer(
+            worker.public,
+            worker_address.parse().unwrap(),
+            60,
+            vec![escrow_lot.clone()],
+        )
+        .with_witness(forged);
+        forged_release.sign(&worker);
+        assert!(!add_transactions(&mut ledger, vec![forged_release]));
+
+        let witness =
+            Witness::new(escrow_lot.clone()).with_release(sign_release(&escrow_lot, &verifier));
+        let mut release = Transaction::new_transfer(
+            worker.public,
+            worker_address.parse().unwrap(),
+            60,
+            vec![escrow_lot],
+        )
+        .with_witness(witness);
+        release.sign(&worker);
+        assert!(add_transactions(&mut ledger, vec![release]));
+        assert_eq!(ledger.get_balance(&worker_address), 60);
+        assert!(ledger
+            .get_lots(&worker_address)
+            .iter()
+            .all(|lot| lot.lock.is_none()));
+    }
+
+    #[test]
+    fn test_escrow_refunds_payer_after_deadline() {
+        let mut ledger = Ledger::new();
+        let client = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&client, 0, "work_1", 100);
+        let client_address = claim.sender_address();
+        let client_lot = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        // Payment released by revealing a secret, but not before a set time
+        let worker = Keypair::generate(&mut OsRng);
+        let unlock_time = ledger.get_latest_block().header.timestamp + 1_000;
+        let condition = SpendCondition::new(vec![
+            Requirement::HashLock(hash_bytes(b"delivery code")),
+            Requirement::After(Timelock::Time(unlock_time)),
+        ])
+        .with_refund_after(Timelock::Height(4));
+        let mut escrow = Transaction::new_transfer(
+            client.public,
+            Address::new(&worker.public, Network::Main),
+            100,
+            vec![client_lot],
+        )
+        .with_nonce(1)
+        .with_condition(condition);
+        escrow.sign(&client);
+        let escrow_lot = format!("{}:0", escrow.id);
+        assert!(add_transactions(&mut ledger, vec![escrow]));
+        assert_eq!(ledger.get_latest_block().header.height, 2);
+
+        // The secret alone is not enough before the unlock time
+        let witness = Witness::new(escrow_lot.clone()).with_preimage(b"delivery code".to_vec());
+        let mut early = Transaction::new_transfer(
+            worker.public,
+            account("receiver").parse().unwrap(),
+            100,
+            vec![escrow_lot.clone()],
+        )
+        .with_witness(witness);
+        early.sign(&worker);
+        assert_eq!(
+            ledger.validate_pending_transaction(&early),
+            Err(ValidationError::ConditionNotMet(escrow_lot.clone()))
+        );
+
+        // Nobody claims it, so at height 4 the client takes it back
+        let refund = signed_transfer(&client, 2, &client_address, 100, vec![escrow_lot.clone()]);
+        assert!(!add_transactions(&mut ledger, vec![refund.clone()]));
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(add_transactions(&mut ledger, vec![refund]));
+        assert_eq!(ledger.get_balance(&client_address), 100);
+        assert!(ledger
+            .get_lots(&Address::new(&worker.public, Network::Main).to_string())
+            .is_empty());
+    }
+
+    #[test]
+    fn test_task_lifecycle_pays_escrowed_reward_to_worker() {
+        let mut ledger = Ledger::new();
+        let client = Keypair::generate(&mut OsRng);
+        let worker = Keypair::generate(&mut OsRng);
+        let (verifier_a, verifier_b) =
+            (Keypair::generate(&mut OsRng), Keypair::generate(&mut OsRng));
+        let funding = attested_work_claim(&client, 0, "work_1", 100);
+        let lot = format!("{}:0", funding.id);
+        assert!(add_transactions(&mut ledger, vec![funding]));
+        let deadline = ledger.next_block_time() + 1_000;
+
+        // The client puts up the reward when posting the task
+        let posting = task_posting(80, deadline, &[&verifier_a, &verifier_b], 2);
+        let unfunded = signed_task_posting(&client, 1, posting.clone(), vec![]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&unfunded),
+            Err(ValidationError::NoInputs)
+        );
+        let post = signed_task_posting(&client, 1, posting, vec![lot]);
+        let task_id = post.id.clone();
+        let client_address = post.sender_address();
+        assert!(add_transactions(&mut ledger, vec![post]));
+        let task = ledger.get_task(&task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Open);
+        assert_eq!(task.client, client_address);
+        assert_eq!(ledger.get_balance(&client_address), 20);
+        assert_eq!(ledger.get_balance(&task_account(&task_id)), 80);
+
+        let claim = signed_task_action(
+            &worker,
+            0,
+            TaskAction::Claim {
+                task_id: task_id.clone(),
+            },
+        );
+        let worker_address = claim.sender_address();
+        assert!(add_transactions(&mut ledger, vec![claim]));
+        let deliver = signed_task_action(
+            &worker,
+            1,
+            TaskAction::Deliver {
+                task_id: task_id.clone(),
+                delivery_hash: hash_bytes(b"handbook-es.pdf"),
+            },
+        );
+        assert!(add_transactions(&mut ledger, vec![deliver]));
+        assert_eq!(ledger.get_balance(&worker_address), 0);
+
+        // The reward is only paid once both verifiers have attested
+        let attest = TaskAction::Attest {
+            task_id: task_id.clone(),
+        };
+        let first = signed_task_action(&verifier_a, 0, attest.clone());
+        assert!(add_transactions(&mut ledger, vec![first]));
+        assert_eq!(
+            ledger.get_task(&task_id).unwrap().status,
+            TaskStatus::Delivered
+        );
+        assert_eq!(ledger.get_balance(&worker_address), 0);
+        let second = signed_task_action(&verifier_b, 0, attest);
+        assert!(add_transactions(&mut ledger, vec![second]));
+
+        let task = ledger.get_task(&task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Completed);
+        assert_eq!(task.worker.as_deref(), Some(worker_address.as_str()));
+        assert_eq!(task.attestations.len(), 2);
+        assert_eq!(ledger.get_balance(&worker_address), 80);
+        assert_eq!(ledger.get_balance(&client_address), 20);
+        assert_eq!(ledger.get_balance(&task_account(&task_id)), 0);
+        assert_eq!(ledger.get_supply_stats().minted, 100);
+
+        let completed = TaskFilter {
+            status: Some(TaskStatus::Completed),
+            worker: Some(worker_address.clone()),
+            ..TaskFilter::default()
+        };
+        assert_eq!(ledger.get_tasks(&completed).len(), 1);
+        let open = TaskFilter {
+            status: Some(TaskStatus::Open),
+            ..TaskFilter::default()
+        };
+        assert!(ledger.get_tasks(&open).is_empty());
+
+        // The marketplace is rebuilt from the chain along with everything else
+        assert!(ledger.rollback_to(3));
+        let task = ledger.get_task(&task_id).unwrap();
+        assert_eq!(task.status, TaskStatus::Claimed);
+        assert_eq!(task.worker.as_deref(), Some(worker_address.as_str()));
+        assert_eq!(ledger.get_balance(&worker_address), 0);
+    }
+
+    #[test]
+    fn test_task_actions_must_follow_the_lifecycle() {
+        let mut ledger = Ledger::new();
+        let client = Keypair::generate(&mut OsRng);
+        let worker = Keypair::generate(&mut OsRng);
+        let verifier = Keypair::generate(&mut OsRng);
+        let now = ledger.next_block_time();
+
+        // Postings must fall due in the future, and clients cannot verify their own tasks
+        let overdue =
+            signed_task_posting(&client, 0, task_posting(10, now, &[&verifier], 1), vec![]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&overdue),
+            Err(ValidationError::InvalidTask(TaskError::InvalidPosting))
+        );
+        let self_verified = signed_task_posting(
+            &client,
+            0,
+            task_posting(10, now + 100, &[&client], 1),
+            vec![],
+        );
+        assert_eq!(
+            ledger.validate_pending_transaction(&self_verified),
+            Err(ValidationError::InvalidTask(TaskError::InvalidPosting))
+        );
+        let unknown = signed_task_action(
+            &worker,
+            0,
+            TaskAction::Claim {
+                task_id: "missing".to_string(),
+            },
+        );
+        assert_eq!(
+            ledger.validate_pending_transaction(&unknown),
+            Err(ValidationError::InvalidTask(TaskError::UnknownTask(
+                "missing".to_string()
+            )))
+        );
+
+        let funding = attested_work_claim(&client, 0, "work_1", 10);
+        let lot = format!("{}:0", funding.id);
+        let post = signed_task_posting(
+            &client,
+            1,
+            task_posting(10, now + 100, &[&verifier], 1),
+            vec![lot],
+        );
+        let task_id = post.id.clone();
+        let client_address = post.sender_address();
+        assert!(add_transactions(&mut ledger, vec![funding, post]));
+        let claim = TaskAction::Claim {
+            task_id: task_id.clone(),
+        };
+        let attest = TaskAction::Attest {
+            task_id: task_id.clone(),
+        };
+
+        // Nothing can be attested before it is delivered, and verifiers cannot take the work
+        assert_eq!(
+            ledger.validate_pending_transaction(&signed_task_action(&verifier, 0, attest.clone())),
+            Err(ValidationError::InvalidTask(TaskError::WrongStatus {
+                task_id: task_id.clone(),
+                status: TaskStatus::Open
+            }))
+        );
+        assert_eq!(
+            ledger.validate_pending_transaction(&signed_task_action(&verifier, 0, claim.clone())),
+            Err(ValidationError::InvalidTask(TaskError::NotPermitted(
+                task_id.clone()
+            )))
+        );
+
+        // Past the deadline, the task can no longer be claimed
+        let late_claim = signed_task_action(&worker, 0, claim);
+        assert!(!add_transactions_after(
+            &mut ledger,
+            200,
+            vec![late_claim.clone()]
+        ));
+        assert!(add_transactions_after(&mut ledger, 200, vec![]));
+        assert_eq!(
+            ledger.validate_pending_transaction(&late_claim),
+            Err(ValidationError::InvalidTask(TaskError::WrongStatus {
+                task_id: task_id.clone(),
+                status: TaskStatus::Expired
+            }))
+        );
+        let expired = TaskFilter {
+            status: Some(TaskStatus::Expired),
+            ..TaskFilter::default()
+        };
+        assert_eq!(ledger.get_tasks(&expired).len(), 1);
+
+        // Only the client can take back the reward of an expired task, and only once
+        let refund = TaskAction::Refund {
+            task_id: task_id.clone(),
+        };
+        assert_eq!(
+            ledger.validate_pending_transaction(&signed_task_action(&worker, 0, refund.clone())),
+            Err(ValidationError::InvalidTask(TaskError::NotPermitted(
+                task_id.clone()
+            )))
+        );
+        assert_eq!(ledger.get_balance(&client_address), 0);
+        assert!(add_transactions(
+            &mut ledger,
+            vec![signed_task_action(&client, 2, refund.clone())]
+        ));
+        assert_eq!(ledger.get_balance(&client_address), 10);
+        assert_eq!(ledger.get_balance(&task_account(&task_id)), 0);
+        assert_eq!(
+            ledger.validate_pending_transaction(&signed_task_action(&client, 3, refund)),
+            Err(ValidationError::InvalidTask(TaskError::WrongStatus {
+                task_id: task_id.clone(),
+                status: TaskStatus::Refunded
+            }))
+        );
+    }
+
+    #[test]
+    fn test_task_actions_spend_lots_only_to_pay_fees() {
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = TaskAction::Claim {
+            task_id: "task".to_string(),
+        };
+        assert!(signed_task_action(&worker, 0, claim.clone()).validate());
+
+        let mut paid = Transaction::new_task_action(worker.public, claim.clone()).with_fee(1);
+        paid.inputs = vec!["lot:0".to_string()];
+        paid.id = paid.calculate_hash();
+        paid.sign(&worker);
+        assert!(paid.validate());
+
+        let mut spending = Transaction::new_task_action(worker.public, claim);
+        spending.inputs = vec!["lot:0".to_string()];
+        spending.id = spending.calculate_hash();
+        spending.sign(&worker);
+        assert!(!spending.validate());
+    }
+
+    #[test]
+    fn test_registry_only_accepts_work_from_admitted_verifiers() {
+        let governor = Keypair::generate(&mut OsRng);
+        let mut ledger = Ledger::with_settings(&registry_settings(&governor, None));
+        let worker = Keypair::generate(&mut OsRng);
+        let verifier = Keypair::generate(&mut OsRng);
+        let verifier_key = hex::encode(verifier.public.as_bytes());
+
+        let claim = work_claim_attested_by(&worker, 0, "work_1", "general", 100, &verifier);
+        assert_eq!(
+            ledger.validate_pending_transaction(&claim),
+            Err(ValidationError::UnregisteredVerifier(verifier_key.clone()))
+        );
+
+        // Only governors may admit verifiers
+        let admit = RegistryAction::Admit {
+            verifier: verifier.public,
+        };
+        let self_admitted = signed_registry_action(&verifier, 0, admit.clone(), 0, vec![]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&self_admitted),
+            Err(ValidationError::InvalidRegistryAction(
+                RegistryError::NotGovernor(verifier_key.clone())
+            ))
+        );
+        let admitted = signed_registry_action(&governor, 0, admit, 0, vec![]);
+        assert!(add_transactions(&mut ledger, vec![admitted]));
+        assert!(add_transactions(&mut ledger, vec![claim.clone()]));
+        assert_eq!(ledger.get_balance(&claim.sender_address()), 100);
+        let status = ledger.get_verifier(&verifier_key).unwrap();
+        assert!(status.active);
+        assert_eq!(status.record.reputation, 1);
+        assert_eq!(status.record.accepted_attestations, 1);
+
+        // Once removed, the verifier's attestations are refused again
+        let remove = RegistryAction::Remove {
+            verifier: verifier.public,
+        };
+        assert!(add_transactions(
+            &mut ledger,
+            vec![signed_registry_action(&governor, 1, remove, 0, vec![])]
+        ));
+        let next_claim = work_claim_attested_by(&worker, 1, "work_2", "general", 100, &verifier);
+        assert_eq!(
+            ledger.validate_pending_transaction(&next_claim),
+            Err(ValidationError::UnregisteredVerifier(verifier_key.clone()))
+        );
+        assert!(!ledger.get_verifier(&verifier_key).unwrap().active);
+    }
+
+    #[test]
+    fn test_registry_only_assigns_tasks_to_admitted_verifiers() {
+        let governor = Keypair::generate(&mut OsRng);
+        let mut ledger = Ledger::with_settings(&registry_settings(&governor, None));
+        let client = Keypair::generate(&mut OsRng);
+        let verifier = Keypair::generate(&mut OsRng);
+        let verifier_key = hex::encode(verifier.public.as_bytes());
+
+        // The client's funds are attested by an admitted verifier
+        let admit = RegistryAction::Admit {
+            verifier: verifier.public,
+        };
+        let admitted = signed_registry_action(&governor, 0, admit, 0, vec![]);
+        assert!(add_transactions(&mut ledger, vec![admitted]));
+        let funding = work_claim_attested_by(&client, 0, "work_1", "general", 100, &verifier);
+        let lot = format!("{}:0", funding.id);
+        assert!(add_transactions(&mut ledger, vec![funding]));
+
+        let deadline = ledger.next_block_time() + 100;
+        let stranger = Keypair::generate(&mut OsRng);
+        let unvetted = signed_task_posting(
+            &client,
+            1,
+            task_posting(10, deadline, &[&verifier, &stranger], 1),
+            vec![lot.clone()],
+        );
+        assert_eq!(
+            ledger.validate_pending_transaction(&unvetted),
+            Err(ValidationError::UnregisteredVerifier(hex::encode(
+                stranger.public.as_bytes()
+            )))
+        );
+        let vetted = signed_task_posting(
+            &client,
+            1,
+            task_posting(10, deadline, &[&verifier], 1),
+            vec![lot],
+        );
+        assert!(add_transactions(&mut ledger, vec![vetted]));
+        assert!(ledger.get_verifier(&verifier_key).unwrap().active);
+    }
+
+    #[test]
+    fn test_bonded_verifier_is_slashed_for_upheld_dispute() {
+        let governor = Keypair::generate(&mut OsRng);
+        let settings = TokenSettings {
+            min_verifier_bond: Some(50),
+            ..dispute_settings(&governor, 2)
+        };
+        let mut ledger = Ledger::with_settings(&settings);
+        let sponsor = Keypair::generate(&mut OsRng);
+        let verifier = Keypair::generate(&mut OsRng);
+        let verifier_key = hex::encode(verifier.public.as_bytes());
+        let worker = Keypair::generate(&mut OsRng);
+        let disputer = Keypair::generate(&mut OsRng);
+
+        // An admitted sponsor attests the work that earns the new verifier its bond, and the
+        // disputer theirs
+        let admit = RegistryAction::Admit {
+            verifier: sponsor.public,
+        };
+        let earned = work_claim_attested_by(&verifier, 0, "work_1", "general", 100, &sponsor);
+        let verifier_address = earned.sender_address();
+        let earned_lot = format!("{}:0", earned.id);
+        let funding = work_claim_attested_by(&disputer, 0, "work_2", "general", 10, &sponsor);
+        let disputer_lot = format!("{}:0", funding.id);
+        assert!(add_transactions(
+            &mut ledger,
+            vec![
+                signed_registry_action(&governor, 0, admit, 0, vec![]),
+                earned,
+                funding
+            ]
+        ));
+        for _ in 0..2 {
+            assert!(add_transactions(&mut ledger, vec![]));
+        }
+
+        // A bond covering the minimum registers the verifier, and the rest comes back as change
+        let bond = signed_registry_action(&verifier, 1, RegistryAction::Bond, 60, vec![earned_lot]);
+        let change_lot = format!("{}:1", bond.id);
+        assert!(add_transactions(&mut ledger, vec![bond]));
+        assert_eq!(ledger.get_balance(&verifier_address), 40);
+        let status = ledger.get_verifier(&verifier_key).unwrap();
+        assert_eq!(status.bond, 60);
+        assert!(status.active);
+
+        let claim = work_claim_attested_by(&worker, 0, "work_3", "general", 10, &verifier);
+        let mint_id = claim.id.clone();
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        // Upholding a dispute costs reputation and half the bond, which leaves it below the
+        // minimum
+        let file = DisputeAction::File {
+            mint_id: mint_id.clone(),
+            evidence: "work_3 was never done".to_string(),
+        };
+        let uphold = DisputeAction::Vote {
+            mint_id,
+            uphold: true,
+        };
+        assert!(add_transactions(
+            &mut ledger,
+            vec![
+                signed_dispute_action(&disputer, 1, file, 10, vec![disputer_lot]),
+                signed_dispute_action(&governor, 1, uphold, 0, vec![])
+            ]
+        ));
+        let status = ledger.get_verifier(&verifier_key).unwrap();
+        assert_eq!(status.record.reputation, 1 - 10);
+        assert_eq!(status.record.upheld_disputes, 1);
+        assert_eq!(status.bond, 30);
+        assert!(!status.active);
+        assert_eq!(ledger.state.slashed_bonds, 30);
+        let refused = work_claim_attested_by(&worker, 1, "work_4", "general", 10, &verifier);
+        assert_eq!(
+            ledger.validate_pending_transaction(&refused),
+            Err(ValidationError::UnregisteredVerifier(verifier_key.clone()))
+        );
+
+        // The verifier takes back what is left of the bond
+        let unbond = signed_registry_action(&verifier, 2, RegistryAction::Unbond, 0, vec![]);
+        assert!(add_transactions(&mut ledger, vec![unbond]));
+        assert_eq!(ledger.get_balance(&verifier_address), 70);
+        assert_eq!(ledger.get_verifier(&verifier_key).unwrap().bond, 0);
+        assert!(ledger.state.get_lot(&change_lot).is_some());
+    }
+
+    #[test]
+    fn test_verifier_cannot_unbond_while_attested_mints_can_be_disputed() {
+        let governor = Keypair::generate(&mut OsRng);
+        let settings = TokenSettings {
+            min_verifier_bond: Some(50),
+            ..dispute_settings(&governor, 2)
+        };
+        let mut ledger = Ledger::with_settings(&settings);
+        let sponsor = Keypair::generate(&mut OsRng);
+        let verifier = Keypair::generate(&mut OsRng);
+        let verifier_key = hex::encode(verifier.public.as_bytes());
+        let worker = Keypair::generate(&mut OsRng);
+
+        let admit = RegistryAction::Admit {
+            verifier: sponsor.public,
+        };
+        let earned = work_claim_attested_by(&verifier, 0, "work_1", "general", 100, &sponsor);
+        let earned_lot = format!("{}:0", earned.id);
+        assert!(add_transactions(
+            &mut ledger,
+            vec![
+                signed_registry_action(&governor, 0, admit, 0, vec![]),
+                earned
+            ]
+        ));
+        for _ in 0..2 {
+            assert!(add_transactions(&mut ledger, vec![]));
+        }
+        let bond = signed_registry_action(&verifier, 1, RegistryAction::Bond, 60, vec![earned_lot]);
+        assert!(add_transactions(&mut ledger, vec![bond]));
+
+        // The bond stays while the mint the verifier attested can still be disputed
+        let claim = work_claim_attested_by(&worker, 0, "work_2", "general", 10, &verifier);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+        let unbond = signed_registry_action(&verifier, 2, RegistryAction::Unbond, 0, vec![]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&unbond),
+            Err(ValidationError::InvalidRegistryAction(
+                RegistryError::BondAtStake(verifier_key.clone())
+            ))
+        );
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(add_transactions(&mut ledger, vec![unbond]));
+        assert_eq!(ledger.get_verifier(&verifier_key).unwrap().bond, 0);
+    }
+
+    #[test]
+    fn test_minted_lots_are_pending_until_challenge_window_closes() {
+        let governor = Keypair::generate(&mut OsRng);
+        let mut ledger = Ledger::with_settings(&dispute_settings(&governor, 2));
+        let verifier = Keypair::generate(&mut OsRng);
+        let worker = Keypair::generate(&mut OsRng);
+        let admit = RegistryAction::Admit {
+            verifier: verifier.public,
+        };
+        let claim = work_claim_attested_by(&worker, 0, "work_1", "general", 20, &verifier);
+        let worker_address = claim.sender_address();
+        let mint_id = claim.id.clone();
+        let lot = format!("{}:0", claim.id);
+        assert!(add_transactions(
+            &mut ledger,
+            vec![
+                signed_registry_action(&governor, 0, admit, 0, vec![]),
+                claim
+            ]
+        ));
+        assert_eq!(ledger.get_balance(&worker_address), 0);
+        assert_eq!(
+            ledger
+                .state
+                .get_pending_balance(&worker_address, 1, ledger.next_block_time()),
+            20
+        );
+        assert_eq!(ledger.get_pending_mint(&mint_id).unwrap().window_closes, 3);
+
+        let transfer = signed_transfer(&worker, 1, &account("receiver"), 5, vec![lot.clone()]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&transfer),
+            Err(ValidationError::PendingInput(lot.clone()))
+        );
+
+        // Once the window closes the mint is final and can no longer be disputed
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(ledger.get_pending_mint(&mint_id).is_none());
+        assert_eq!(ledger.get_balance(&worker_address), 20);
+        let late = DisputeAction::File {
+            mint_id: mint_id.clone(),
+            evidence: "work_1 was never done".to_string(),
+        };
+        let late = signed_dispute_action(&worker, 1, late, 10, vec![lot]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&late),
+            Err(ValidationError::InvalidDispute(DisputeError::UnknownMint(
+                mint_id
+            )))
+        );
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+    }
+
+    #[test]
+    fn test_upheld_dispute_reverses_mint() {
+        let (mut ledger, governor, verifier, disputer, disputer_lot, mint_id) = disputable_mint();
+        let verifier_key = hex::encode(verifier.public.as_bytes());
+        let worker_address = ledger.get_pending_mint(&mint_id).unwrap().worker.clone();
+        let disputer_address = Address::new(&disputer.public, Network::Main).to_string();
+
+        let file = DisputeAction::File {
+            mint_id: mint_id.clone(),
+            evidence: "work_2 was never done".to_string(),
+        };
+        let low = signed_dispute_action(&disputer, 1, file.clone(), 5, vec![disputer_lot.clone()]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&low),
+            Err(ValidationError::InvalidDispute(DisputeError::BondTooLow {
+                required: 10,
+                found: 5
+            }))
+        );
+        let filed = signed_dispute_action(&disputer, 1, file.clone(), 10, vec![disputer_lot]);
+        assert!(add_transactions(&mut ledger, vec![filed]));
+        assert_eq!(ledger.get_balance(&disputer_address), 10);
+        let again = signed_dispute_action(&governor, 1, file, 10, vec![]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&again),
+            Err(ValidationError::InvalidDispute(
+                DisputeError::AlreadyDisputed(mint_id.clone())
+            ))
+        );
+
+        // The disputed lots stay pending after the window closes, until the panel decides
+        for _ in 0..3 {
+            assert!(add_transactions(&mut ledger, vec![]));
+        }
+        assert!(ledger.get_pending_mint(&mint_id).is_some());
+        assert_eq!(ledger.get_balance(&worker_address), 0);
+
+        let outsider = DisputeAction::Vote {
+            mint_id: mint_id.clone(),
+            uphold: true,
+        };
+        let outsider = signed_dispute_action(&verifier, 0, outsider, 0, vec![]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&outsider),
+            Err(ValidationError::InvalidDispute(DisputeError::NotPanelist(
+                verifier_key.clone()
+            )))
+        );
+        let uphold = DisputeAction::Vote {
+            mint_id: mint_id.clone(),
+            uphold: true,
+        };
+        assert!(add_transactions(
+            &mut ledger,
+            vec![signed_dispute_action(&governor, 1, uphold, 0, vec![])]
+        ));
+
+        assert!(ledger.get_pending_mint(&mint_id).is_none());
+        assert!(ledger.get_lots(&worker_address).is_empty());
+        assert_eq!(ledger.state.reversed_supply, 30);
+        assert_eq!(ledger.get_balance(&disputer_address), 20);
+        let record = ledger.get_verifier(&verifier_key).unwrap().record;
+        assert_eq!(record.reputation, 2 - 10);
+        assert_eq!(record.upheld_disputes, 1);
+    }
+
+    #[test]
+    fn test_dismissed_dispute_forfeits_bond() {
+        let (mut ledger, governor, _, disputer, disputer_lot, mint_id) = disputable_mint();
+        let worker_address = ledger.get_pending_mint(&mint_id).unwrap().worker.clone();
+        let disputer_address = Address::new(&disputer.public, Network::Main).to_string();
+
+        let file = DisputeAction::File {
+            mint_id: mint_id.clone(),
+            evidence: "work_2 was never done".to_string(),
+        };
+        let filed = signed_dispute_action(&disputer, 1, file, 10, vec![disputer_lot]);
+        let dismiss = DisputeAction::Vote {
+            mint_id: mint_id.clone(),
+            uphold: false,
+        };
+        let dismiss = signed_dispute_action(&governor, 1, dismiss, 0, vec![]);
+        assert!(add_transactions(&mut ledger, vec![filed]));
+        assert!(add_transactions(&mut ledger, vec![dismiss]));
+
+        // The mint is final at once, and the disputer's bond is burned
+        assert!(ledger.get_pending_mint(&mint_id).is_none());
+        assert_eq!(ledger.get_balance(&worker_address), 30);
+        assert_eq!(ledger.get_balance(&disputer_address), 10);
+        assert_eq!(ledger.state.slashed_bonds, 10);
+        assert_eq!(ledger.state.reversed_supply, 0);
+    }
+
+    #[test]
+    fn test_undecided_dispute_is_dismissed_when_voting_closes() {
+        let governor = Keypair::generate(&mut OsRng);
+        let settings = TokenSettings {
+            dispute_voting_period: 2,
+            ..dispute_settings(&governor, 3)
+        };
+        let mut ledger = Ledger::with_settings(&settings);
+        let verifier = Keypair::generate(&mut OsRng);
+        let disputer = Keypair::generate(&mut OsRng);
+        let worker = Keypair::generate(&mut OsRng);
+        let admit = RegistryAction::Admit {
+            verifier: verifier.public,
+        };
+        let funding = work_claim_attested_by(&disputer, 0, "work_1", "general", 20, &verifier);
+        let disputer_lot = format!("{}:0", funding.id);
+        assert!(add_transactions(
+            &mut ledger,
+            vec![
+                signed_registry_action(&governor, 0, admit, 0, vec![]),
+                funding
+            ]
+        ));
+        for _ in 0..3 {
+            assert!(add_transactions(&mut ledger, vec![]));
+        }
+        let claim = work_claim_attested_by(&worker, 0, "work_2", "general", 30, &verifier);
+        let mint_id = claim.id.clone();
+        let worker_address = claim.sender_address();
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        let file = DisputeAction::File {
+            mint_id: mint_id.clone(),
+            evidence: "work_2 was never done".to_string(),
+        };
+        let filed = signed_dispute_action(&disputer, 1, file, 10, vec![disputer_lot]);
+        assert!(add_transactions(&mut ledger, vec![filed]));
+        let closes = ledger
+            .get_pending_mint(&mint_id)
+            .and_then(|mint| mint.dispute.as_ref())
+            .map(|dispute| dispute.voting_closes);
+        assert_eq!(closes, Some(8));
+
+        // Votes after the panel's deadline are refused, and the dispute is dismissed
+        assert!(add_transactions(&mut ledger, vec![]));
+        let uphold = DisputeAction::Vote {
+            mint_id: mint_id.clone(),
+            uphold: true,
+        };
+        let late = signed_dispute_action(&governor, 1, uphold, 0, vec![]);
+        assert_eq!(
+            ledger.validate_pending_transaction(&late),
+            Err(ValidationError::InvalidDispute(DisputeError::VotingClosed(
+                mint_id.clone()
+            )))
+        );
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(ledger.get_pending_mint(&mint_id).is_none());
+        assert_eq!(ledger.get_balance(&worker_address), 30);
+        assert_eq!(ledger.state.slashed_bonds, 10);
+        assert_eq!(ledger.state.reversed_supply, 0);
+    }
+
+    #[test]
+    fn test_issuance_is_capped_per_epoch_and_worker() {
+        let settings = TokenSettings {
+            issuance_epoch_length: 5,
+            max_epoch_issuance: Some(100),
+            max_worker_issuance: Some(60),
+            ..TokenSettings::default()
+        };
+        let mut ledger = Ledger::with_settings(&settings);
+        let first = Keypair::generate(&mut OsRng);
+        let second = Keypair::generate(&mut OsRng);
+        let third = Keypair::generate(&mut OsRng);
+        let first_claim = attested_work_claim(&first, 0, "work_1", 60);
+        let first_address = first_claim.sender_address();
+        assert!(add_transactions(&mut ledger, vec![first_claim]));
+
+        let over_worker_cap = attested_work_claim(&first, 1, "work_2", 1);
+        assert_eq!(
+            ledger.validate_pending_transaction(&over_worker_cap),
+            Err(ValidationError::IssuanceCapExceeded(
+                IssuanceError::WorkerCapReached {
+                    worker: first_address,
+                    cap: 60,
+                    requested: 61
+                }
+            ))
+        );
+        assert!(add_transactions(
+            &mut ledger,
+            vec![attested_work_claim(&second, 0, "work_3", 40)]
+        ));
+        let over_epoch_cap = attested_work_claim(&third, 0, "work_4", 1);
+        assert_eq!(
+            ledger.validate_pending_transaction(&over_epoch_cap),
+            Err(ValidationError::IssuanceCapExceeded(
+                IssuanceError::EpochCapReached {
+                    cap: 100,
+                    requested: 101
+                }
+            ))
+        );
+        assert!(!add_transactions(&mut ledger, vec![over_epoch_cap.clone()]));
+
+        // The caps start over with the next epoch, at height 5
+        for _ in 0..2 {
+            assert!(add_transactions(&mut ledger, vec![]));
+        }
+        assert!(add_transactions(&mut ledger, vec![over_epoch_cap]));
+        let stats = ledger.get_supply_stats();
+        assert_eq!(stats.minted, 101);
+        assert_eq!(stats.circulating, 101);
+        assert_eq!(stats.epoch, 1);
+        assert_eq!(stats.epoch_minted, 1);
+        assert_eq!(stats.epoch_cap, Some(100));
+        assert_eq!(stats.worker_cap, Some(60));
+    }
+
+    #[test]
+    fn test_expiry_linked_schedule_replaces_expired_supply() {
+        let settings = TokenSettings {
+            token_lifetime: 10,
+            issuance_epoch_length: 3,
+            max_epoch_issuance: Some(50),
+            supply_schedule: SupplyScheduleKind::ExpiryLinked,
+            schedule_rate_bps: 10_000,
+            ..TokenSettings::default()
+        };
+        let mut ledger = Ledger::with_settings(&settings);
+        let worker = Keypair::generate(&mut OsRng);
+        assert!(add_transactions(
+            &mut ledger,
+            vec![attested_work_claim(&worker, 0, "work_1", 50)]
+        ));
+        assert!(add_transactions_after(&mut ledger, 20, vec![]));
+        let stats = ledger.get_supply_stats();
+        assert_eq!(stats.circulating, 0);
+        assert_eq!(stats.epoch_expired, 50);
+        assert_eq!(stats.epoch_cap, Some(50));
+
+        // The next epoch's cap makes up for the tokens that expired
+        assert!(add_transactions(
+            &mut ledger,
+            vec![attested_work_claim(&worker, 1, "work_2", 100)]
+        ));
+        let stats = ledger.get_supply_stats();
+        assert_eq!(stats.epoch, 1);
+        assert_eq!(stats.epoch_cap, Some(100));
+        assert_eq!(stats.minted, 150);
+    }
+
+    #[test]
+    fn test_balance_derived_from_lots() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let worker_address = claim.sender_address();
+        assert!(add_transactions(&mut ledger, vec![claim.clone()]));
+
+        let lots = ledger.get_lots(&worker_address);
+        assert_eq!(lots.len(), 1);
+        assert_eq!(lots[0].id, format!("{}:0", claim.id));
+        assert_eq!(lots[0].amount, 60);
+        assert_eq!(lots[0].mint_height, 1);
+        assert_eq!(ledger.get_balance(&worker_address), 60);
+    }
+
+    #[test]
+    fn test_transfer_moves_lots() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let worker_address = claim.sender_address();
+        let lot_id = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        let transfer = signed_transfer(&worker, 1, &account("receiver"), 60, vec![lot_id.clone()]);
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+
+        assert_eq!(ledger.get_balance(&worker_address), 0);
+        assert_eq!(ledger.get_balance(&account("receiver")), 60);
+        let received = ledger.get_lots(&account("receiver"));
+        assert_eq!(
+            received[0].mint_height, 1,
+            "Transfers keep the original mint details"
+        );
+        assert!(ledger.state.get_lot(&lot_id).is_none());
+    }
+
+    #[test]
+    fn test_rollback_rebuilds_state_and_stops_at_finalized_block() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let worker_address = claim.sender_address();
+        let lot_id = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+        let state_root = ledger.state.state_root();
+
+        let transfer = signed_transfer(&worker, 1, &account("receiver"), 60, vec![lot_id]);
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+        assert_eq!(ledger.get_balance(&account("receiver")), 60);
+
+        // Rolling back undoes the transfer but keeps the claim
+        assert!(ledger.rollback_to(1));
+        assert_eq!(ledger.chain.len(), 2);
+        assert_eq!(ledger.state.state_root(), state_root);
+        assert_eq!(ledger.get_balance(&worker_address), 60);
+        assert_eq!(ledger.get_balance(&account("receiver")), 0);
+        assert_eq!(ledger.get_issued_tokens(&worker_address).len(), 1);
+
+        // Once the claim's block is final, it can no longer be rolled back
+        let hash = ledger.get_latest_block().hash.clone();
+        assert!(ledger.finalize_block(&hash));
+        assert!(!ledger.rollback_to(0));
+        assert!(ledger.is_work_claimed("work_1"));
+        assert!(!ledger.finalize_block("unknown"));
+    }
+
+    #[test]
+    fn test_heavier_branch_replaces_main_chain() {
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let worker_address = claim.sender_address();
+
+        // Two nodes build competing branches from the same genesis block
+        let mut ledger = Ledger::new();
+        let mut rival = Ledger::new();
+        assert!(add_transactions(&mut ledger, vec![claim.clone()]));
+        let kept_claim = attested_work_claim(&worker, 0, "work_2", 30);
+        assert!(add_transactions(&mut rival, vec![kept_claim]));
+        assert!(add_transactions_after(&mut rival, 1, vec![]));
+        let main_tip = ledger.get_latest_block().hash.clone();
+
+        // A branch with no more work than the main chain is only stored
+        assert!(ledger.add_block(rival.chain[1].clone()));
+        assert_eq!(ledger.get_latest_block().hash, main_tip);
+        assert_eq!(ledger.side_blocks.len(), 1);
+        assert!(!ledger.add_block(rival.chain[1].clone()));
+
+        // Once it has more, the ledger switches to it and rebuilds its state
+        assert!(ledger.add_block(rival.chain[2].clone()));
+        assert_eq!(
+            ledger.get_latest_block().hash,
+            rival.get_latest_block().hash
+        );
+        assert_eq!(ledger.state.state_root(), rival.state.state_root());
+        assert_eq!(ledger.get_balance(&worker_address), 30);
+        assert!(!ledger.is_work_claimed("work_1"));
+        assert!(ledger.side_blocks.contains_key(&main_tip));
+        assert!(ledger.validate_chain());
+
+        // The claim from the dropped block goes back to the mempool
+        let orphaned = ledger.take_orphaned_transactions();
+        assert_eq!(orphaned.len(), 1);
+        assert_eq!(orphaned[0].id, claim.id);
+        assert!(ledger.take_orphaned_transactions().is_empty());
+    }
+
+    #[test]
+    fn test_reorg_never_rolls_back_finalized_block() {
+        let mut ledger = Ledger::new();
+        let mut rival = Ledger::new();
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(add_transactions_after(&mut rival, 1, vec![]));
+        assert!(add_transactions_after(&mut rival, 1, vec![]));
+
+        let main_tip = ledger.get_latest_block().hash.clone();
+        assert!(ledger.finalize_block(&main_tip));
+
+        // Blocks branching off before the finalized block are not even stored
+        assert!(!ledger.add_block(rival.chain[1].clone()));
+        assert!(!ledger.add_block(rival.chain[2].clone()));
+        assert_eq!(ledger.get_latest_block().hash, main_tip);
+        assert!(ledger.side_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_finalizing_a_side_block_reorganizes_onto_its_branch() {
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let mut ledger = Ledger::new();
+        let mut rival = Ledger::new();
+        assert!(add_transactions(&mut ledger, vec![claim.clone()]));
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(add_transactions_after(&mut rival, 1, vec![]));
+        let main_chain = ledger.chain.clone();
+
+        // The rival's block has less work than the main chain, so it is only stored
+        let finalized = rival.get_latest_block().hash.clone();
+        assert!(ledger.add_block(rival.chain[1].clone()));
+        assert_eq!(ledger.get_latest_block().hash, main_chain[2].hash);
+
+        // Once the validators finalize it, the ledger switches to its branch regardless
+        assert!(ledger.finalize_block(&finalized));
+        assert_eq!(ledger.get_latest_block().hash, finalized);
+        assert_eq!(ledger.finalized_height, 1);
+        assert_eq!(ledger.state.state_root(), rival.state.state_root());
+        assert!(!ledger.is_work_claimed("work_1"));
+        assert!(ledger.validate_chain());
+        let orphaned = ledger.take_orphaned_transactions();
+        assert_eq!(orphaned.len(), 1);
+        assert_eq!(orphaned[0].id, claim.id);
+
+        // The dropped branch does not contain the finalized block, so it is never switched
+        // back to, however much work it has
+        assert!(ledger.side_blocks.is_empty());
+        assert!(!ledger.add_block(main_chain[1].clone()));
+        assert!(!ledger.add_block(main_chain[2].clone()));
+        assert_eq!(ledger.get_latest_block().hash, finalized);
+        assert!(!ledger.finalize_block(&main_chain[2].hash));
+    }
+
+    #[test]
+    fn test_side_block_conflicting_with_finalized_block_cannot_be_finalized() {
+        let mut ledger = Ledger::new();
+        let mut rival = Ledger::new();
+        assert!(add_transactions_after(&mut rival, 1, vec![]));
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(ledger.add_block(rival.chain[1].clone()));
+        assert!(add_transactions(&mut ledger, vec![]));
+
+        // Finalizing the main chain drops the side block, so finalizing it again fails
+        let main_tip = ledger.get_latest_block().hash.clone();
+        assert!(ledger.finalize_block(&main_tip));
+        assert!(!ledger.finalize_block(&rival.chain[1].hash));
+        assert_eq!(ledger.get_latest_block().hash, main_tip);
+        assert_eq!(ledger.finalized_height, 2);
+    }
+
+    #[test]
+    fn test_invalid_heavier_branch_keeps_current_chain() {
+        let mut ledger = Ledger::new();
+        let mut rival = Ledger::new();
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(add_transactions_after(&mut rival, 1, vec![]));
+
+        // The rival's second block commits to the wrong state root
+        let parent = rival.get_latest_block().clone();
+        let mut invalid = Block::new(2, parent.hash.clone(), vec![], 0);
+        invalid.header.timestamp = parent.header.timestamp;
+        invalid.hash = invalid.calculate_hash();
+
+        let main_tip = ledger.get_latest_block().hash.clone();
+        let state_root = ledger.state.state_root();
+        assert!(ledger.add_block(parent.clone()));
+        assert!(!ledger.add_block(invalid.clone()));
+        assert_eq!(ledger.get_latest_block().hash, main_tip);
+        assert_eq!(ledger.state.state_root(), state_root);
+        assert!(ledger.take_orphaned_transactions().is_empty());
+
+        // The valid part of the branch is kept, the invalid block is not
+        assert!(ledger.side_blocks.contains_key(&parent.hash));
+        assert!(!ledger.side_blocks.contains_key(&invalid.hash));
+    }
+
+    #[test]
+    fn test_side_blocks_must_follow_the_retarget_rule() {
+        let mut ledger = Ledger::new();
+        let mut rival = Ledger::new();
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(add_transactions_after(&mut rival, 1, vec![]));
+
+        // A side block claiming a harder target than its branch allows is not stored, so its
+        // claimed work can never force a reorg
+        let mut forged = rival.chain[1].clone();
+        forged.header.target = [0; 32];
+        forged.hash = forged.calculate_hash();
+        assert!(!ledger.add_block(forged));
+        assert!(ledger.side_blocks.is_empty());
+
+        // Side blocks at or below the finalized block are pruned
+        assert!(ledger.add_block(rival.chain[1].clone()));
+        assert_eq!(ledger.side_blocks.len(), 1);
+        assert!(add_transactions(&mut ledger, vec![]));
+        let tip = ledger.get_latest_block().hash.clone();
+        assert!(ledger.finalize_block(&tip));
+        assert!(ledger.side_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_block_with_unknown_parent_rejected() {
+        let mut ledger = Ledger::new();
+        let mut block = Block::new(1, "f".repeat(64), vec![], 0);
+        ledger.commit_state_root(&mut block).unwrap();
+        assert!(!ledger.add_block(block));
+        assert!(ledger.side_blocks.is_empty());
+    }
+
+    #[test]
+    fn test_double_spend_rejected() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let lot_id = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        // Two spends of the same lot in one block
+        let first = signed_transfer(&worker, 1, &account("receiver"), 60, vec![lot_id.clone()]);
+        let second = signed_transfer(
+            &worker,
+            2,
+            &account("other_receiver"),
+            60,
+            vec![lot_id.clone()],
+        );
+        assert!(!add_transactions(&mut ledger, vec![first.clone(), second]));
+        assert_eq!(ledger.chain.len(), 2);
+
+        // A spend of a lot consumed by an earlier block
+        assert!(add_transactions(&mut ledger, vec![first]));
+        let replay = signed_transfer(&worker, 2, &account("other_receiver"), 60, vec![lot_id]);
+        assert!(!add_transactions(&mut ledger, vec![replay]));
+        assert_eq!(ledger.get_balance(&account("receiver")), 60);
+        assert_eq!(ledger.get_balance(&account("other_receiver")), 0);
+    }
+
+    #[test]
+    fn test_spending_expired_lot_rejected() {
+        let mut ledger = Ledger::with_settings(&fixed_ttl_settings(5));
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let lot_id = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        let transfer = signed_transfer(&worker, 1, &account("receiver"), 60, vec![lot_id]);
+        let mut block = Block::new(2, ledger.get_latest_block().hash.clone(), vec![transfer], 0);
+        block.header.timestamp = ledger.get_latest_block().header.timestamp + 10;
+
+        assert!(!add_block(&mut ledger, block));
+        assert_eq!(ledger.get_balance(&account("receiver")), 0);
+    }
+
+    #[test]
+    fn test_spending_unowned_or_mismatched_lots_rejected() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let lot_id = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        let thief = Keypair::generate(&mut OsRng);
+        let theft = signed_transfer(&thief, 0, &account("receiver"), 60, vec![lot_id.clone()]);
+        assert!(!add_transactions(&mut ledger, vec![theft]));
+
+        let overspend = signed_transfer(&worker, 1, &account("receiver"), 61, vec![lot_id.clone()]);
+        assert!(!add_transactions(&mut ledger, vec![overspend]));
+
+        let unknown = signed_transfer(
+            &worker,
+            1,
+            &account("receiver"),
+            60,
+            vec!["missing:0".to_string()],
+        );
+        assert!(!add_transactions(&mut ledger, vec![unknown]));
+
+        let no_inputs = signed_transfer(&worker, 1, &account("receiver"), 60, vec![]);
+        assert!(!add_transactions(&mut ledger, vec![no_inputs]));
+    }
+
+    #[test]
+    fn test_expiry_sweep_records_expired_supply() {
+        let mut ledger = Ledger::with_settings(&fixed_ttl_settings(5));
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let worker_address = claim.sender_address();
+        assert!(add_transactions(&mut ledger, vec![claim]));
+        assert_eq!(ledger.get_expired_supply(), 0);
+
+        // A later block sweeps the worker's lot, even though it does not touch it
+        let other_worker = Keypair::generate(&mut OsRng);
+        let other_claim = attested_work_claim(&other_worker, 0, "work_2", 25);
+        let mut block = Block::new(
+            2,
+            ledger.get_latest_block().hash.clone(),
+            vec![other_claim],
+            0,
+        );
+        block.header.timestamp = ledger.get_latest_block().header.timestamp + 10;
+        assert!(add_block(&mut ledger, block));
+
+        assert!(ledger.get_lots(&worker_address).is_empty());
+        assert_eq!(ledger.get_balance(&worker_address), 0);
+        assert_eq!(ledger.get_expired_supply(), 60);
+        assert_eq!(ledger.get_expired_supply_in_block(1), 0);
+        assert_eq!(ledger.get_expired_supply_in_block(2), 60);
+        assert_eq!(ledger.get_expired_supply_for_account(&worker_address), 60);
+    }
+
+    #[test]
+    fn test_replaying_chain_gives_same_state() {
+        let settings = fixed_ttl_settings(5);
+        let mut ledger = Ledger::with_settings(&settings);
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 60);
+        let worker_address = claim.sender_address();
+        let lot_id = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        let transfer = signed_transfer(&worker, 1, &account("receiver"), 60, vec![lot_id]);
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+
+        let mut sweep_block = Block::new(3, ledger.get_latest_block().hash.clone(), vec![], 0);
+        sweep_block.header.timestamp = ledger.get_latest_block().header.timestamp + 10;
+        assert!(add_block(&mut ledger, sweep_block));
+
+        // Another node replaying the same blocks later must reach the same balances
+        let mut replica = Ledger::with_settings(&settings);
+        for block in ledger.chain.iter().skip(1) {
+            assert!(replica.add_block(block.clone()));
+        }
+
+        for account in [worker_address.as_str(), "receiver"] {
+            assert_eq!(replica.get_balance(account), ledger.get_balance(account));
+            assert_eq!(
+                replica.get_expired_supply_for_account(account),
+                ledger.get_expired_supply_for_account(account)
+            );
+        }
+        assert_eq!(replica.get_expired_supply(), 60);
+        assert_eq!(
+            replica.get_expired_supply_for_account(&account("receiver")),
+            60
+        );
+    }
+
+    #[test]
+    fn test_block_timestamp_cannot_go_backwards() {
+        let mut ledger = Ledger::new();
+        assert!(add_transactions(&mut ledger, vec![]));
+
+        let mut block = Block::new(2, ledger.get_latest_block().hash.clone(), vec![], 0);
+        block.header.timestamp = ledger.get_latest_block().header.timestamp - 1;
+
+        assert!(!add_block(&mut ledger, block));
+    }
+
+    #[test]
+    fn test_partial_transfer_creates_change_lot() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 100);
+        let worker_address = claim.sender_address();
+        let lot_id = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+        let expiration_time = ledger.state.get_lot(&lot_id).unwrap().expiration_time;
+
+        let transfer = signed_transfer(&worker, 1, &account("receiver"), 50, vec![lot_id]);
+        let transfer_id = transfer.id.clone();
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+
+        assert_eq!(ledger.get_balance(&account("receiver")), 50);
+        assert_eq!(ledger.get_balance(&worker_address), 50);
+
+        let change = ledger.state.get_lot(&format!("{}:1", transfer_id)).unwrap();
+        assert_eq!(change.owner, worker_address);
+        assert_eq!(change.amount, 50);
+        assert_eq!(change.expiration_time, expiration_time);
+    }
+
+    #[test]
+    fn test_transfer_spends_soonest_expiring_lots_first() {
+        let mut ledger = Ledger::with_settings(&fixed_ttl_settings(100));
+        let worker = Keypair::generate(&mut OsRng);
+        let older = attested_work_claim(&worker, 0, "work_1", 30);
+        let newer = attested_work_claim(&worker, 1, "work_2", 50);
+        let worker_address = older.sender_address();
+        let older_lot = format!("{}:0", older.id);
+        let newer_lot = format!("{}:0", newer.id);
+        assert!(add_transactions(&mut ledger, vec![older]));
+        assert!(add_transactions_after(&mut ledger, 20, vec![newer]));
+        let older_expiry = ledger.state.get_lot(&older_lot).unwrap().expiration_time;
+        let newer_expiry = ledger.state.get_lot(&newer_lot).unwrap().expiration_time;
+
+        // The inputs are listed newest first, but the older lot is still spent first
+        let transfer = signed_transfer(
+            &worker,
+            2,
+            &account("receiver"),
+            40,
+            vec![newer_lot, older_lot],
+        );
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+
+        let received: Vec<(u64, Option<u64>)> = ledger
+            .get_lots(&account("receiver"))
+            .iter()
+            .map(|lot| (lot.amount, lot.expiration_time))
+            .collect();
+        assert_eq!(received, vec![(30, older_expiry), (10, newer_expiry)]);
+
+        let change = ledger.get_lots(&worker_address);
+        assert_eq!(change.len(), 1);
+        assert_eq!(change[0].amount, 40);
+        assert_eq!(change[0].expiration_time, newer_expiry);
+    }
+
+    #[test]
+    fn test_transfer_with_mixed_expired_and_unexpired_lots() {
+        let mut ledger = Ledger::with_settings(&fixed_ttl_settings(100));
+        let worker = Keypair::generate(&mut OsRng);
+        let expiring = attested_work_claim(&worker, 0, "work_1", 30);
+        let fresh = attested_work_claim(&worker, 1, "work_2", 50);
+        let worker_address = expiring.sender_address();
+        let expiring_lot = format!("{}:0", expiring.id);
+        let fresh_lot = format!("{}:0", fresh.id);
+        assert!(add_transactions(&mut ledger, vec![expiring]));
+        assert!(add_transactions_after(&mut ledger, 50, vec![fresh]));
+
+        // Move past the first lot's expiry, but not the second's
+        assert!(add_transactions_after(&mut ledger, 70, vec![]));
+        assert_eq!(ledger.get_balance(&worker_address), 50);
+        assert_eq!(ledger.get_expired_supply_for_account(&worker_address), 30);
+
+        let mixed = signed_transfer(
+            &worker,
+            2,
+            &account("receiver"),
+            60,
+            vec![expiring_lot, fresh_lot.clone()],
+        );
+        assert!(!add_transactions(&mut ledger, vec![mixed]));
+        assert_eq!(ledger.get_balance(&worker_address), 50);
+
+        let height = ledger.get_latest_block().header.height + 1;
+        let timestamp = ledger.get_latest_block().header.timestamp;
+        assert_eq!(
+            ledger
+                .state
+                .select_lots(&worker_address, 60, height, timestamp),
+            None
+        );
+        let inputs = ledger
+            .state
+            .select_lots(&worker_address, 20, height, timestamp)
+            .unwrap();
+        assert_eq!(inputs, vec![fresh_lot]);
+
+        let transfer = signed_transfer(&worker, 2, &account("receiver"), 20, inputs);
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+        assert_eq!(ledger.get_balance(&account("receiver")), 20);
+        assert_eq!(ledger.get_balance(&worker_address), 30);
+    }
+
+    #[test]
+    fn test_tiered_policy_sets_expiry_by_category() {
+        let mut category_lifetimes = HashMap::new();
+        category_lifetimes.insert("care".to_string(), 1_000);
+        let settings = TokenSettings {
+            token_lifetime: 100,
+            expiry_policy: ExpiryPolicyKind::Tiered,
+            category_lifetimes,
+            ..TokenSettings::default()
+        };
+        let mut ledger = Ledger::with_settings(&settings);
+        let worker = Keypair::generate(&mut OsRng);
+        let care = attested_work_claim_in(&worker, 0, "work_1", "care", 10);
+        let general = attested_work_claim_in(&worker, 1, "work_2", "general", 10);
+        let care_lot = format!("{}:0", care.id);
+        let general_lot = format!("{}:0", general.id);
+        assert!(add_transactions(&mut ledger, vec![care, general]));
+
+        let minted_at = ledger.get_latest_block().header.timestamp;
+        let care_expiry = ledger.state.get_lot(&care_lot).unwrap().expiration_time;
+        let general_expiry = ledger.state.get_lot(&general_lot).unwrap().expiration_time;
+        assert_eq!(care_expiry, Some(minted_at + 1_000));
+        assert_eq!(general_expiry, Some(minted_at + 100));
+    }
+
+    #[test]
+    fn test_demurrage_policy_decays_balances_per_block() {
+        // 10% of the original amount per block
+        let policy = Arc::new(LinearDemurrage { rate_bps: 1_000 });
+        let mut ledger = Ledger::with_expiry_policy(policy);
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 100);
+        let worker_address = claim.sender_address();
+        let lot_id = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+        assert_eq!(ledger.get_balance(&worker_address), 100);
+
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert_eq!(ledger.get_balance(&worker_address), 80);
+
+        // At height 4 the lot is worth 70: 50 is paid and 20 comes back as change
+        let transfer = signed_transfer(&worker, 1, &account("receiver"), 50, vec![lot_id]);
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+        assert_eq!(ledger.get_balance(&account("receiver")), 50);
+        assert_eq!(ledger.get_balance(&worker_address), 20);
+        assert_eq!(ledger.get_expired_supply_for_account(&worker_address), 30);
+
+        // Both new lots decay from the height they were created at
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert_eq!(ledger.get_balance(&account("receiver")), 45);
+        assert_eq!(ledger.get_balance(&worker_address), 18);
+
+        let overspend = signed_transfer(
+            &worker,
+            2,
+            &account("receiver"),
+            20,
+            vec![ledger.get_lots(&worker_address)[0].id.clone()],
+        );
+        assert!(!add_transactions(&mut ledger, vec![overspend]));
+    }
+
+    #[test]
+    fn test_holding_cap_rejects_mints_over_cap() {
+        let mut ledger = Ledger::with_settings(&holding_cap_settings(100, None));
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 80);
+        let worker_address = claim.sender_address();
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        let over_cap = attested_work_claim(&worker, 1, "work_2", 30);
+        assert_eq!(
+            ledger.validate_pending_transaction(&over_cap),
+            Err(ValidationError::HoldingCapExceeded {
+                account: worker_address.clone(),
+                balance: 110,
+                cap: 100,
+            })
+        );
+        assert!(!add_transactions(&mut ledger, vec![over_cap]));
+        assert_eq!(ledger.get_balance(&worker_address), 80);
+
+        // Up to the cap is still fine
+        let at_cap = attested_work_claim(&worker, 1, "work_3", 20);
+        assert_eq!(ledger.validate_pending_transaction(&at_cap), Ok(()));
+        assert!(add_transactions(&mut ledger, vec![at_cap]));
+        assert_eq!(ledger.get_balance(&worker_address), 100);
+    }
+
+    #[test]
+    fn test_holding_cap_counts_pending_mints() {
+        let governor = Keypair::generate(&mut OsRng);
+        let settings = TokenSettings {
+            max_account_balance: Some(100),
+            ..dispute_settings(&governor, 5)
+        };
+        let mut ledger = Ledger::with_settings(&settings);
+        let verifier = Keypair::generate(&mut OsRng);
+        let worker = Keypair::generate(&mut OsRng);
+        let admit = RegistryAction::Admit {
+            verifier: verifier.public,
+        };
+        let claim = work_claim_attested_by(&worker, 0, "work_1", "general", 80, &verifier);
+        let worker_address = claim.sender_address();
+        assert!(add_transactions(
+            &mut ledger,
+            vec![
+                signed_registry_action(&governor, 0, admit, 0, vec![]),
+                claim
+            ]
+        ));
+        assert_eq!(ledger.get_balance(&worker_address), 0);
+
+        // Minted lots count towards the cap while they are pending
+        let over_cap = work_claim_attested_by(&worker, 1, "work_2", "general", 30, &verifier);
+        assert_eq!(
+            ledger.validate_pending_transaction(&over_cap),
+            Err(ValidationError::HoldingCapExceeded {
+                account: worker_address,
+                balance: 110,
+                cap: 100,
+            })
+        );
+    }
+
+    #[test]
+    fn test_holding_cap_redirects_excess_to_commons() {
+        let mut ledger = Ledger::with_settings(&holding_cap_settings(100, Some("commons")));
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 150);
+        let worker_address = claim.sender_address();
+        assert!(add_transactions(&mut ledger, vec![claim]));
+        assert_eq!(ledger.get_balance(&worker_address), 100);
+        assert_eq!(ledger.get_balance("commons"), 50);
+
+        // A transfer to an account already at the cap goes entirely to the commons, while the
+        // sender's change is unaffected
+        let other_worker = Keypair::generate(&mut OsRng);
+        let other_claim = attested_work_claim(&other_worker, 0, "work_2", 90);
+        let other_address = other_claim.sender_address();
+        let other_lot = format!("{}:0", other_claim.id);
+        assert!(add_transactions(&mut ledger, vec![other_claim]));
+
+        let transfer = signed_transfer(&other_worker, 1, &worker_address, 60, vec![other_lot]);
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+        assert_eq!(ledger.get_balance(&worker_address), 100);
+        assert_eq!(ledger.get_balance(&other_address), 30);
+
+        // The commons account itself is exempt from the cap
+        assert_eq!(ledger.get_balance("commons"), 110);
+    }
+
+    #[test]
+    fn test_holding_cap_applies_to_change_released_from_escrow() {
+        let mut ledger = Ledger::with_settings(&holding_cap_settings(100, Some("commons")));
+        let client = Keypair::generate(&mut OsRng);
+        let client_claim = attested_work_claim(&client, 0, "work_1", 100);
+        let client_lot = format!("{}:0", client_claim.id);
+        let worker = Keypair::generate(&mut OsRng);
+        let worker_claim = attested_work_claim(&worker, 0, "work_2", 80);
+        let worker_address = worker_claim.sender_address();
+        assert!(add_transactions(
+            &mut ledger,
+            vec![client_claim, worker_claim]
+        ));
+
+        // Escrowed tokens do not count towards the worker's cap while they are held
+        let verifier = Keypair::generate(&mut OsRng);
+        let condition = SpendCondition::new(vec![Requirement::VerifierSignature(verifier.public)]);
+        let mut escrow = Transaction::new_transfer(
+            client.public,
+            worker_address.parse().unwrap(),
+            60,
+            vec![client_lot],
+        )
+        .with_nonce(1)
+        .with_condition(condition);
+        escrow.sign(&client);
+        let escrow_lot = format!("{}:0", escrow.id);
+        assert!(add_transactions(&mut ledger, vec![escrow]));
+        assert_eq!(ledger.get_balance(&worker_address), 80);
+
+        // Releasing the escrow to pay someone else returns the rest to the worker as change,
+        // which the cap limits like any other tokens the worker receives
+        let witness =
+            Witness::new(escrow_lot.clone()).with_release(sign_release(&escrow_lot, &verifier));
+        let mut release = Transaction::new_transfer(
+            worker.public,
+            account("receiver").parse().unwrap(),
+            10,
+            vec![escrow_lot],
+        )
+        .with_nonce(1)
+        .with_witness(witness);
+        release.sign(&worker);
+        assert!(add_transactions(&mut ledger, vec![release]));
+        assert_eq!(ledger.get_balance(&account("receiver")), 10);
+        assert_eq!(ledger.get_balance(&worker_address), 100);
+        assert_eq!(ledger.get_balance("commons"), 30);
+    }
+
+    #[test]
+    fn test_velocity_counts_only_value_that_changes_owner() {
+        let mut ledger = Ledger::new();
+        let client = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&client, 0, "work_1", 100);
+        let client_address = claim.sender_address();
+        let lot = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        // Paying yourself moves nothing, and change never counts
+        let to_self = signed_transfer(&client, 1, &client_address, 70, vec![lot]);
+        let self_lot = format!("{}:0", to_self.id);
+        let change_lot = format!("{}:1", to_self.id);
+        assert!(add_transactions(&mut ledger, vec![to_self]));
+        assert_eq!(ledger.get_supply_stats().epoch_transferred, 0);
+        let paid = signed_transfer(&client, 2, &account("receiver"), 20, vec![change_lot]);
+        assert!(add_transactions(&mut ledger, vec![paid]));
+        assert_eq!(ledger.get_supply_stats().epoch_transferred, 20);
+
+        // A payment into escrow only counts once the recipient releases it
+        let worker = Keypair::generate(&mut OsRng);
+        let worker_address = Address::new(&worker.public, Network::Main).to_string();
+        let verifier = Keypair::generate(&mut OsRng);
+        let condition = SpendCondition::new(vec![Requirement::VerifierSignature(verifier.public)]);
+        let mut escrow = Transaction::new_transfer(
+            client.public,
+            worker_address.parse().unwrap(),
+            50,
+            vec![self_lot],
+        )
+        .with_nonce(3)
+        .with_condition(condition);
+        escrow.sign(&client);
+        let escrow_lot = format!("{}:0", escrow.id);
+        assert!(add_transactions(&mut ledger, vec![escrow]));
+        assert_eq!(ledger.get_supply_stats().epoch_transferred, 20);
+
+        let witness =
+            Witness::new(escrow_lot.clone()).with_release(sign_release(&escrow_lot, &verifier));
+        let mut release = Transaction::new_transfer(
+            worker.public,
+            worker_address.parse().unwrap(),
+            50,
+            vec![escrow_lot],
+        )
+        .with_witness(witness);
+        release.sign(&worker);
+        assert!(add_transactions(&mut ledger, vec![release]));
+        assert_eq!(ledger.get_balance(&worker_address), 50);
+        assert_eq!(ledger.get_supply_stats().epoch_transferred, 70);
+    }
+
+    #[test]
+    fn test_fees_are_burned_by_default() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 50);
+        let worker_address = claim.sender_address();
+        let lot = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        // The inputs must cover the amount plus the fee
+        let mut too_costly = Transaction::new_transfer(
+            worker.public,
+            account("b").parse().unwrap(),
+            45,
+            vec![lot.clone()],
+        )
+        .with_nonce(1)
+        .with_fee(10);
+        too_costly.sign(&worker);
+        assert_eq!(
+            ledger.validate_pending_transaction(&too_costly),
+            Err(ValidationError::InsufficientFunds {
+                available: 50,
+                required: 55
+            })
+        );
+
+        let mut transfer =
+            Transaction::new_transfer(worker.public, account("b").parse().unwrap(), 30, vec![lot])
+                .with_nonce(1)
+                .with_fee(5);
+        transfer.sign(&worker);
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+        assert_eq!(ledger.get_balance(&account("b")), 30);
+        assert_eq!(ledger.get_balance(&worker_address), 15);
+        assert_eq!(ledger.get_burned_fees(), 5);
+        assert_eq!(ledger.get_fee_pool(), 0);
+    }
+
+    #[test]
+    fn test_every_transaction_pays_fees() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let address = Address::new(&worker.public, Network::Main).to_string();
+
+        // A work claim pays its fee out of the tokens it mints
+        let mut claim = Transaction::new_work_claim(
+            worker.public,
+            "work_1".to_string(),
+            "Completed task".to_string(),
+            "general".to_string(),
+            100,
+        )
+        .with_fee(5);
+        claim.attest(&Keypair::generate(&mut OsRng));
+        claim.sign(&worker);
+        let lot = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+        assert_eq!(ledger.get_balance(&address), 95);
+        assert_eq!(ledger.get_burned_fees(), 5);
+
+        // Other actions pay from the lots they spend, and get the rest back as change
+        let verifier = Keypair::generate(&mut OsRng);
+        let deadline = ledger.next_block_time() + 100;
+        let claim = TaskAction::Claim {
+            task_id: "task".to_string(),
+        };
+        let mut unpaid = Transaction::new_task_action(worker.public, claim)
+            .with_nonce(1)
+            .with_fee(3);
+        unpaid.sign(&worker);
+        assert_eq!(
+            ledger.validate_pending_transaction(&unpaid),
+            Err(ValidationError::NoInputs)
+        );
+        let posting = task_posting(10, deadline, &[&verifier], 1);
+        let mut post = Transaction::new_task_posting(worker.public, posting, vec![lot])
+            .with_nonce(1)
+            .with_fee(3);
+        post.sign(&worker);
+        assert!(add_transactions(&mut ledger, vec![post]));
+        assert_eq!(ledger.get_balance(&address), 82);
+        assert_eq!(ledger.get_burned_fees(), 8);
+
+        // A claim cannot pay its whole mint as a fee
+        let mut greedy = Transaction::new_work_claim(
+            worker.public,
+            "work_2".to_string(),
+            "Completed task".to_string(),
+            "general".to_string(),
+            5,
+        )
+        .with_nonce(2)
+        .with_fee(5);
+        greedy.attest(&verifier);
+        greedy.sign(&worker);
+        assert!(!Validator::validate_transaction(&greedy));
+    }
+
+    #[test]
+    fn test_fees_paid_to_block_beneficiary() {
+        let mut ledger = Ledger::with_settings(&fee_settings(FeePolicyKind::Producer, 0));
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 100);
+        let lot = format!("{}:0", claim.id);
+        assert!(add_transactions(&mut ledger, vec![claim]));
+
+        let mut first =
+            Transaction::new_transfer(worker.public, account("b").parse().unwrap(), 10, vec![lot])
+                .with_nonce(1)
+                .with_fee(4);
+        first.sign(&worker);
+        let change = format!("{}:1", first.id);
+        let mut second = Transaction::new_transfer(
+            worker.public,
+            account("c").parse().unwrap(),
+            10,
+            vec![change],
+        )
+        .with_nonce(2)
+        .with_fee(6);
+        second.sign(&worker);
+
+        let latest_block = ledger.get_latest_block();
+        let mut block = Block::new(
+            latest_block.header.height + 1,
+            latest_block.hash.clone(),
+            vec![first],
+            0,
+        );
+        block.header.beneficiary = account("producer").parse().ok();
+        assert!(add_block(&mut ledger, block));
+        assert_eq!(ledger.get_balance(&account("producer")), 4);
+        assert_eq!(ledger.get_fee_pool(), 0);
+
+        // A block without a beneficiary burns its fees
+        assert!(add_transactions(&mut ledger, vec![second]));
+        assert_eq!(ledger.get_balance(&account("producer")), 4);
+        assert_eq!(ledger.get_burned_fees(), 6);
+        assert_eq!(ledger.get_fee_pool(), 0);
+    }
+
+    #[test]
+    fn test_fee_pool_shared_among_epoch_workers() {
+        let mut ledger = Ledger::with_settings(&fee_settings(FeePolicyKind::Commons, 3));
+        let (a, b) = (Keypair::generate(&mut OsRng), Keypair::generate(&mut OsRng));
+        let claim_a = attested_work_claim(&a, 0, "work_a", 100);
+        let claim_b = attested_work_claim(&b, 0, "work_b", 200);
+        let (address_a, address_b) = (claim_a.sender_address(), claim_b.sender_address());
+        let lot = format!("{}:0", claim_a.id);
+        assert!(add_transactions(&mut ledger, vec![claim_a, claim_b]));
+
+        // Fees collect in the pool until the end of the epoch
+        let mut transfer =
+            Transaction::new_transfer(a.public, account("c").parse().unwrap(), 10, vec![lot])
+                .with_nonce(1)
+                .with_fee(31);
+        transfer.sign(&a);
+        assert!(add_transactions(&mut ledger, vec![transfer]));
+        assert_eq!(ledger.get_fee_pool(), 31);
+        assert_eq!(ledger.get_balance(&address_a), 59);
+
+        // At height 3 the pool is shared in proportion to what each worker minted, and the
+        // token left over by rounding stays for the next epoch
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert_eq!(ledger.get_balance(&address_a), 59 + 10);
+        assert_eq!(ledger.get_balance(&address_b), 200 + 20);
+        assert_eq!(ledger.get_fee_pool(), 1);
+        assert!(ledger.state.epoch_work.is_empty());
+
+        // With no work minted in the next epoch, the pool carries over
+        for _ in 0..3 {
+            assert!(add_transactions(&mut ledger, vec![]));
+        }
+        assert_eq!(ledger.get_fee_pool(), 1);
+        assert_eq!(ledger.get_burned_fees(), 0);
+    }
+
+    #[test]
+    fn test_transaction_encoding_golden_vector() {
+        let sender = fixed_keypair(1);
+        let recipient = Address::new(&fixed_keypair(4).public, Network::Main);
+        assert_eq!(
+            recipient.to_string(),
+            "wt1qe2f6c9c9rpc8r4nms0rl7rh7syyw3mz9xpt46aexs7fn8k76he7q6z2suy"
+        );
+        let mut tx =
+            Transaction::new_transfer(sender.public, recipient, 50, vec!["abc:0".to_string()])
+                .with_nonce(3)
+                .with_fee(2);
+        tx.timestamp = 1_700_000_000;
+        tx.id = tx.calculate_hash();
+
+        assert_eq!(
+            hex::encode(tx.canonical_bytes()),
+            concat!(
+                "01",                                                     // version
+                "00000017776f726b2d746f6b656e732f7472616e73616374696f6e", // domain
+                "00000020",                                               // sender key
+                "8a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c",
+                "00000001",           // chain ID
+                "0000000000000003",   // nonce
+                "0000003e",           // recipient
+                "7774317165326636633963397270633872346e6d7330726c3772683773797977336d7a3978707434366165787337666e386b373668653771367a32737579",
+                "0000000000000032",   // amount
+                "0000000000000002",   // fee
+                "000000006553f100",   // timestamp
+                "00",                 // no expiration
+                "0000000000000001",   // input count
+                "000000056162633a30", // input
+                "00",                 // transfer
+                "00",                 // single-key sender
+                "00",                 // no spend condition
+                "0000000000000000",   // witness count
+            )
+        );
+        assert_eq!(
+            tx.id,
+            "d36325ae46d2441b88d6cab0c7888fa32129a7e44c87331476d17ff5517316d3"
+        );
+    }
+
+    #[test]
+    fn test_work_claim_encoding_golden_vector() {
+        let worker = fixed_keypair(2);
+        let verifier = fixed_keypair(3);
+        let mut claim = Transaction::new_work_claim(
+            worker.public,
+            "work_1".to_string(),
+            "Completed task".to_string(),
+            "general".to_string(),
+            100,
+        );
+        claim.timestamp = 1_700_000_000;
+        assert!(claim.attest(&verifier));
+
+        // Ed25519 signatures are deterministic, so the attestation is pinned too
+        assert_eq!(
+            claim
+                .work_claim()
+                .unwrap()
+                .attestation_message(1, &worker.public, 100),
+            "7c2f36b662f128f0d818451cbd924f4cf0fc2431d97f563eb0ba67ee1ed420ae"
+        );
+        assert_eq!(
+            claim.id,
+            "01315b99f325adabdd7cfe0e5e64b3054290313db0f8869419efe8395a1e754e"
+        );
+    }
+
+    #[test]
+    fn test_block_header_encoding_golden_vector() {
+        let header = BlockHeader {
+            version: 1,
+            height: 1,
+            previous_hash: "0".repeat(64),
+            merkle_root: "ab".repeat(32),
+            timestamp: 1_700_000_000,
+            target: [0xff; 32],
+            nonce: 42,
+            extra_nonce: 7,
+            state_root: "cd".repeat(32),
+            vote: Some(AuthorityVote {
+                authority: "ef".repeat(32),
+                add: true,
+            }),
+            beneficiary: Some(Address::new(&fixed_keypair(5).public, Network::Main)),
+        };
+
+        assert_eq!(
+            hex::encode(header.canonical_bytes()),
+            [
+                "01",                                                       // encoding version
+                "00000018776f726b2d746f6b656e732f626c6f636b2d686561646572", // domain
+                "00000001",                                                 // header version
+                "0000000000000001",                                         // height
+                "00000040",                                                 // previous hash
+                &"30".repeat(64),
+                "00000040", // merkle root
+                &"6162".repeat(32),
+                "000000006553f100", // timestamp
+                "00000020",         // target
+                &"ff".repeat(32),
+                "000000000000002a", // nonce
+                "0000000000000007", // extra nonce
+                "00000040",         // state root
+                &"6364".repeat(32),
+                "01",       // vote present
+                "00000040", // voted authority
+                &"6566".repeat(32),
+                "01", // add
+                "01", // beneficiary present
+                "0000003e",
+                &hex::encode("wt1qdeapehffkzmcl5f67nz4nrl07nhj49ckdc72duhyl07vmqzst0csca4mz4"),
+            ]
+            .concat()
+        );
+        assert_eq!(
+            header.calculate_hash(),
+            "74ef422b7f91ba144d8861d44e00eff96bd2012ae9801a8a1c517ca288addadb"
+        );
+    }
+
+    #[test]
+    fn test_block_hash_separates_fields() {
+        // Index 1 with timestamp 23 must not collide with index 12 with timestamp 3
+        let mut block = Block::new(1, "prev".to_string(), vec![], 0);
+        block.header.timestamp = 23;
+        let mut other = block.clone();
+        other.header.height = 12;
+        other.header.timestamp = 3;
+        assert_ne!(block.calculate_hash(), other.calculate_hash());
+    }
+
+    #[test]
+    fn test_block_body_must_match_merkle_root() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 100);
+        let mut block = Block::new(1, ledger.get_latest_block().hash.clone(), vec![claim], 0);
+        ledger.commit_state_root(&mut block).unwrap();
+        assert!(block.validate());
+
+        // Swapping the body for different transactions leaves the header, and so the hash,
+        // unchanged, but no longer matches the merkle root
+        let mut swapped = block.clone();
+        swapped.transactions = vec![attested_work_claim(&worker, 0, "work_2", 100)];
+        assert_eq!(swapped.hash, swapped.calculate_hash());
+        assert!(!swapped.validate());
+        assert!(!ledger.add_block(swapped));
+
+        let mut emptied = block.clone();
+        emptied.transactions.clear();
+        assert!(!ledger.add_block(emptied));
+
+        assert!(ledger.add_block(block));
+    }
+
+    #[test]
+    fn test_stored_block_hash_is_checked() {
+        let mut ledger = Ledger::new();
+        let mut block = Block::new(1, ledger.get_latest_block().hash.clone(), vec![], 0);
+        ledger.commit_state_root(&mut block).unwrap();
+
+        let mut tampered = block.clone();
+        tampered.hash = "f".repeat(64);
+        assert!(!ledger.add_block(tampered));
+
+        // Changing the header without rehashing is caught too
+        let mut stale = block.clone();
+        stale.header.nonce += 1;
+        assert!(!ledger.add_block(stale));
+
+        assert!(ledger.add_block(block));
+    }
+
+    #[test]
+    fn test_block_state_root_must_match_resulting_state() {
+        let mut ledger = Ledger::new();
+        let worker = Keypair::generate(&mut OsRng);
+        let claim = attested_work_claim(&worker, 0, "work_1", 100);
+        let mut block = Block::new(1, ledger.get_latest_block().hash.clone(), vec![claim], 0);
+
+        // A block that does not commit to the resulting state is rejected
+        assert!(!ledger.add_block(block.clone()));
+
+        block.header.state_root = ledger.state.state_root();
+        block.hash = block.calculate_hash();
+        assert!(!ledger.add_block(block.clone()));
+
+        ledger.commit_state_root(&mut block).unwrap();
+        assert!(ledger.add_block(block));
+        assert_eq!(
+            ledger.get_latest_block().header.state_root,
+            ledger.state.state_root()
+        );
+    }
+
+    /// Proof-of-Work rules that retarget every three blocks, aiming for a minute per block.
+    fn fast_retargeting(difficulty: usize) -> ProofOfWork {
+        ProofOfWork {
+            retarget_interval: 3,
+            target_block_time: 60,
+            max_retarget_factor: 4,
+            ..ProofOfWork::new(difficulty)
+        }
+    }
+
+    #[test]
+    fn test_target_retargets_to_observed_block_times() {
+        let mut ledger = Ledger::new().with_consensus(Arc::new(fast_retargeting(2)));
+        let initial_target = ProofOfWork::difficulty_target(2);
+        assert_eq!(ledger.get_latest_block().header.target, initial_target);
+
+        // Heights 1 and 2 keep the initial target
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert_eq!(ledger.next_target(), {
+            // The first window starts at the genesis block, long ago, so mining gets as much
+            // easier as one retarget allows
+            let mut eased = initial_target;
+            eased[0] = 0x03;
+            eased[31] = 0xfc;
+            eased
+        });
+
+        // Blocks then arrive far faster than a minute apart, so the next retarget makes mining
+        // as much harder as it allows
+        for _ in 3..6 {
+            assert!(add_transactions(&mut ledger, vec![]));
+        }
+        assert_eq!(ledger.next_target(), initial_target);
+        assert!(add_transactions(&mut ledger, vec![]));
+        assert_eq!(ledger.get_latest_block().header.target, initial_target);
+        assert!(ledger.validate_chain());
+    }
+
+    #[test]
+    fn test_block_target_must_follow_retarget_rule() {
+        let mut ledger = Ledger::new().with_consensus(Arc::new(fast_retargeting(1)));
+        assert!(add_transactions(&mut ledger, vec![]));
+
+        // A block claiming an easier target than the rule allows is rejected, even if mined
+        let mut easy = Block::new(2, ledger.get_latest_block().hash.clone(), vec![], 0);
+        ledger.commit_state_root(&mut easy).unwrap();
+        easy.header.target = ProofOfWork::difficulty_target(0);
+        ProofOfWork::default().mine_block(&mut easy);
+        assert!(!ledger.add_block(easy));
+
+        // A block carrying the right target must also meet it
+        let mut unmined = Block::new(2, ledger.get_latest_block().hash.clone(), vec![], 0);
+        unmined.header.target = ledger.next_target();
+        ledger.commit_state_root(&mut unmined).unwrap();
+        while ProofOfWork::meets_target(&unmined.hash, &unmined.header.target) {
+            unmined.header.nonce += 1;
+            unmined.hash = unmined.calculate_hash();
+        }
+        assert!(!ledger.add_block(unmined.clone()));
+
+        ProofOfWork::default().mine_block(&mut unmined);
+        assert!(ledger.add_block(unmined));
+    }
+
+    /// Proof-of-Authority rules for the given authorities, in turn order.
+    fn authority_rules(authorities: &[&Keypair]) -> ProofOfAuthority {
+        ProofOfAuthority {
+            authorities: authorities
+                .iter()
+                .map(|authority| hex::encode(authority.public.as_bytes()))
+                .collect(),
+            block_period: 5,
+            proposer_timeout: 10,
+            max_clock_drift: 1,
+        }
+    }
+
+    /// Builds an empty block `seconds` after the latest block, carrying an optional vote, and
+    /// seals it with the given key.
+    fn sealed_block(
+        ledger: &Ledger,
+        sealer: &Keypair,
+        seconds: u64,
+        vote: Option<AuthorityVote>,
+    ) -> Block {
+        let latest_block = ledger.get_latest_block();
+        let mut block = Block::new(
+            latest_block.header.height + 1,
+            latest_block.hash.clone(),
+            vec![],
+            0,
+        );
+        block.header.timestamp = latest_block.header.timestamp + seconds;
+        block.header.vote = vote;
+        ledger.commit_state_root(&mut block).unwrap();
+        ProofOfAuthority::seal_block(&mut block, sealer);
+        block
+    }
+
+    #[test]
+    fn test_authorities_seal_blocks_in_turn() {
+        let (a, b, c) = (fixed_keypair(1), fixed_keypair(2), fixed_keypair(3));
+        let rules = authority_rules(&[&a, &b, &c]);
+        let mut ledger = Ledger::new().with_consensus(Arc::new(rules.clone()));
+
+        // Height 1 is b's turn
+        assert_eq!(
+            rules.in_turn_authority(&ledger.chain),
+            Some(hex::encode(b.public.as_bytes()))
+        );
+        assert!(ledger.add_block(sealed_block(&ledger, &b, 5, None)));
+
+        // Height 2 is c's turn, so a has to wait out c's timeout first
+        let early = sealed_block(&ledger, &a, 5, None);
+        assert_eq!(
+            rules.verify_seal(&ledger.chain, &early),
+            Err(ValidationError::OutOfTurn {
+                authority: hex::encode(a.public.as_bytes()),
+                earliest: ledger.get_latest_block().header.timestamp + 15,
+            })
+        );
+        assert!(!ledger.add_block(early));
+        assert!(ledger.add_block(sealed_block(&ledger, &a, 15, None)));
+
+        // Even the authority in turn must respect the block period
+        assert!(!ledger.add_block(sealed_block(&ledger, &a, 4, None)));
+        assert!(ledger.validate_chain());
+    }
+
+    #[test]
+    fn test_authorities_cannot_stamp_blocks_ahead_of_the_clock() {
+        let a = fixed_keypair(1);
+        let mut ledger = Ledger::new().with_consensus(Arc::new(authority_rules(&[&a])));
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        // A minute ahead is well within the proof-of-work bound, but not the authority's
+        assert!(!ledger.add_block(sealed_block(&ledger, &a, now + 60, None)));
+        assert!(ledger.add_block(sealed_block(&ledger, &a, now, None)));
+    }
+
+    #[test]
+    fn test_blocks_must_be_sealed_by_an_authority() {
+        let (a, outsider) = (fixed_keypair(1), fixed_keypair(9));
+        let rules = authority_rules(&[&a]);
+        let mut ledger = Ledger::new().with_consensus(Arc::new(rules.clone()));
+
+        let unsealed = Block::new(1, ledger.get_latest_block().hash.clone(), vec![], 0);
+        assert_eq!(
+            rules.verify_seal(&ledger.chain, &unsealed),
+            Err(ValidationError::MissingSeal)
+        );
+
+        let by_outsider = sealed_block(&ledger, &outsider, 5, None);
+        assert_eq!(
+            rules.verify_seal(&ledger.chain, &by_outsider),
+            Err(ValidationError::UnknownAuthority(hex::encode(
+                outsider.public.as_bytes()
+            )))
+        );
+        assert!(!ledger.add_block(by_outsider));
+
+        // Claiming to be an authority without its key does not work either
+        let mut forged = sealed_block(&ledger, &outsider, 5, None);
+        forged.seal.as_mut().unwrap().signer = a.public;
+        assert_eq!(
+            rules.verify_seal(&ledger.chain, &forged),
+            Err(ValidationError::InvalidSeal)
+        );
+        assert!(!ledger.add_block(forged));
+
+        assert!(ledger.add_block(sealed_block(&ledger, &a, 5, None)));
+    }
+
+    #[test]
+    fn test_authorities_vote_to_change_the_set() {
+        let (a, b, c, d) = (
+            fixed_keypair(1),
+            fixed_keypair(2),
+            fixed_keypair(3),
+            fixed_keypair(4),
+        );
+        let rules = authority_rules(&[&a, &b, &c]);
+        let mut ledger = Ledger::new().with_consensus(Arc::new(rules.clone()));
+        let add_d = AuthorityVote {
+            authority: hex::encode(d.public.as_bytes()),
+            add: true,
+        };
+
+        // d cannot seal until a majority of authorities vote it in
+        assert!(ledger.add_block(sealed_block(&ledger, &b, 5, Some(add_d.clone()))));
+        assert!(!ledger.add_block(sealed_block(&ledger, &d, 100, None)));
+        assert!(ledger.add_block(sealed_block(&ledger, &c, 5, Some(add_d.clone()))));
+        assert_eq!(
+            rules.authorities_at(&ledger.chain),
+            authority_rules(&[&a, &b, &c, &d]).authorities
+        );
+
+        // Height 3 is d's turn in the new set, and voting d in again changes nothing
+        assert!(!ledger.add_block(sealed_block(&ledger, &d, 5, Some(add_d))));
+        assert!(ledger.add_block(sealed_block(&ledger, &d, 5, None)));
+
+        // Removing an authority takes a majority of the set as well
+        let remove_a = AuthorityVote {
+            authority: hex::encode(a.public.as_bytes()),
+            add: false,
+        };
+        for voter in [&b, &c, &d] {
+            let block = sealed_block(&ledger, voter, 100, Some(remove_a.clone()));
+            assert!(ledger.add_block(block));
+        }
+        assert_eq!(
+            rules.authorities_at(&ledger.chain),
+            authority_rules(&[&b, &c, &d]).authorities
+        );
+        assert!(!ledger.add_block(sealed_block(&ledger, &a, 100, None)));
+        assert!(ledger.validate_chain());
+    }
 }
@@ -0,0 +1,356 @@
+#[cfg(test)]
+mod tests {
+    use core::blockchain::block::Block;
+    use core::blockchain::ledger::Ledger;
+    use core::consensus::engine::ConsensusEngine;
+    use core::consensus::finality::{FinalityEvent, FinalityGadget, FinalityVote, VoteStep};
+    use core::consensus::miner::Miner;
+    use core::consensus::proof_of_authority::ProofOfAuthority;
+    use core::network::message::{Message, MessageType};
+    use core::network::p2p::{MessageHandler, P2PNetwork};
+    use core::node::Node;
+    use ed25519_dalek::{Keypair, PublicKey, SecretKey};
+    use std::collections::VecDeque;
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    /// Creates a keypair from a fixed seed, so the authority order is the same every run.
+    fn fixed_keypair(seed: u8) -> Keypair {
+        let secret = SecretKey::from_bytes(&[seed; 32]).unwrap();
+        let public = PublicKey::from(&secret);
+        Keypair { secret, public }
+    }
+
+    /// One node of the simulated network, which is both an authority and a validator.
+    struct SimulatedNode {
+        ledger: Ledger,
+        gadget: FinalityGadget,
+        keypair: Keypair,
+        /// Offline nodes neither receive nor send messages.
+        online: bool,
+    }
+
+    impl SimulatedNode {
+        /// Handles a message from the network, returning the messages the node sends in reply.
+        fn handle(&mut self, message: &Message) -> Vec<Message> {
+            match message.message_type {
+                MessageType::Block => {
+                    let block: Block = serde_json::from_str(&message.payload).unwrap();
+                    let (height, hash) = (block.header.height, block.hash.clone());
+                    if !self.ledger.add_block(block) {
+                        return vec![];
+                    }
+                    self.gadget
+                        .prevote(height, 0, hash, &self.keypair)
+                        .map(|vote| Message::finality_vote(&vote))
+                        .into_iter()
+                        .collect()
+                }
+                MessageType::Prevote | MessageType::Precommit => {
+                    let vote = message.to_finality_vote().unwrap();
+                    match self.gadget.add_vote(vote) {
+                        Ok(Some(FinalityEvent::Prevoted {
+                            height,
+                            round,
+                            block_hash,
+                        })) => {
+                            // Only precommit a block this node has accepted
+                            if self.ledger.get_block_by_hash(&block_hash).is_none() {
+                                return vec![];
+                            }
+                            self.gadget
+                                .precommit(height, round, &self.keypair)
+                                .map(|vote| Message::finality_vote(&vote))
+                                .into_iter()
+                                .collect()
+                        }
+                        Ok(Some(FinalityEvent::Finalized { block_hash, .. })) => {
+                            assert!(self.ledger.finalize_block(&block_hash));
+                            vec![]
+                        }
+                        Ok(None) | Err(_) => vec![],
+                    }
+                }
+                _ => vec![],
+            }
+        }
+    }
+
+    /// An in-process network of nodes running Proof-of-Authority with a finality gadget on
+    /// top, which delivers every message to every online node, including its sender.
+    struct SimulatedNetwork {
+        nodes: Vec<SimulatedNode>,
+        rules: ProofOfAuthority,
+        queue: VecDeque<Message>,
+    }
+
+    impl SimulatedNetwork {
+        fn new(size: u8) -> Self {
+            let keypairs: Vec<Keypair> = (1..=size).map(fixed_keypair).collect();
+            let addresses: Vec<String> = keypairs
+                .iter()
+                .map(|keypair| hex::encode(keypair.public.as_bytes()))
+                .collect();
+            let rules = ProofOfAuthority::new(addresses.clone());
+
+            let nodes = keypairs
+                .into_iter()
+                .map(|keypair| SimulatedNode {
+                    ledger: Ledger::new().with_consensus(Arc::new(rules.clone())),
+                    gadget: FinalityGadget::new(addresses.clone()),
+                    keypair,
+                    online: true,
+                })
+                .collect();
+            SimulatedNetwork {
+                nodes,
+                rules,
+                queue: VecDeque::new(),
+            }
+        }
+
+        /// Has the first online authority in turn order seal the next block, as soon as its
+        /// slot allows, and broadcasts it.
+        fn propose(&mut self) {
+            let proposer = self
+                .nodes
+                .iter()
+                .filter(|node| node.online)
+                .min_by_key(|node| {
+                    self.rules.earliest_timestamp(
+                        &node.ledger.chain,
+                        &hex::encode(node.keypair.public.as_bytes()),
+                    )
+                })
+                .expect("An authority is online");
+
+            let latest_block = proposer.ledger.get_latest_block();
+            let mut block = Block::new(
+                latest_block.header.height + 1,
+                latest_block.hash.clone(),
+                vec![],
+                0,
+            );
+            block.header.timestamp = self
+                .rules
+                .earliest_timestamp(
+                    &proposer.ledger.chain,
+                    &hex::encode(proposer.keypair.public.as_bytes()),
+                )
+                .unwrap();
+            proposer.ledger.commit_state_root(&mut block).unwrap();
+            ProofOfAuthority::seal_block(&mut block, &proposer.keypair);
+
+            let payload = serde_json::to_string(&block).unwrap();
+            self.queue
+                .push_back(Message::new(MessageType::Block, payload));
+        }
+
+        /// Delivers messages until the network goes quiet.
+        fn run(&mut self) {
+            while let Some(message) = self.queue.pop_front() {
+                for node in self.nodes.iter_mut().filter(|node| node.online) {
+                    self.queue.extend(node.handle(&message));
+                }
+            }
+        }
+
+        /// Returns the nodes that are online.
+        fn online_nodes(&self) -> impl Iterator<Item = &SimulatedNode> {
+            self.nodes.iter().filter(|node| node.online)
+        }
+    }
+
+    /// Starts a node for each of `size` authorities, each a validator serving the others
+    /// over TCP on localhost and sending them its blocks and votes.
+    fn start_nodes(size: u8) -> Vec<Arc<Node>> {
+        let keypairs: Vec<Keypair> = (1..=size).map(fixed_keypair).collect();
+        let addresses: Vec<String> = keypairs
+            .iter()
+            .map(|keypair| hex::encode(keypair.public.as_bytes()))
+            .collect();
+        let rules = ProofOfAuthority {
+            block_period: 0,
+            ..ProofOfAuthority::new(addresses.clone())
+        };
+        let listeners: Vec<TcpListener> = (0..size)
+            .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let peer_addresses: Vec<String> = listeners
+            .iter()
+            .map(|listener| listener.local_addr().unwrap().to_string())
+            .collect();
+
+        keypairs
+            .into_iter()
+            .zip(listeners)
+            .enumerate()
+            .map(|(index, (keypair, listener))| {
+                let network = P2PNetwork::new();
+                for (peer, address) in peer_addresses.iter().enumerate() {
+                    if peer != index {
+                        network.connect_to_peer(address);
+                    }
+                }
+                let ledger = Ledger::new().with_consensus(Arc::new(rules.clone()));
+                let node = Arc::new(
+                    Node::new(Arc::new(Mutex::new(ledger)), Miner::default())
+                        .with_keypair(keypair)
+                        .with_finality(FinalityGadget::new(addresses.clone()))
+                        .with_network(network.clone()),
+                );
+                node.start_round_timer();
+                let handler: Arc<dyn MessageHandler> = node.clone();
+                thread::spawn(move || network.serve(listener, handler));
+                node
+            })
+            .collect()
+    }
+
+    /// Waits for every node's ledger to finalize the block at `height`, for a few seconds at
+    /// most.
+    fn wait_for_finality(nodes: &[Arc<Node>], height: u64) -> bool {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            let finalized = nodes
+                .iter()
+                .all(|node| node.ledger.lock().unwrap().finalized_height >= height);
+            if finalized {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    #[test]
+    fn test_validators_finalize_blocks_over_the_network() {
+        let nodes = start_nodes(4);
+
+        // Each block goes out to the peers, who prevote and precommit it over the network
+        for height in 1..=3 {
+            let proposer = &nodes[height as usize % nodes.len()];
+            let block = proposer.produce_block(vec![]).unwrap();
+            assert!(wait_for_finality(&nodes, height));
+            for node in &nodes {
+                let ledger = node.ledger.lock().unwrap();
+                assert_eq!(ledger.get_latest_block().hash, block.hash);
+                assert_eq!(ledger.finalized_height, height);
+            }
+        }
+    }
+
+    #[test]
+    fn test_honest_validators_finalize_every_block() {
+        let mut network = SimulatedNetwork::new(4);
+        for _ in 0..3 {
+            network.propose();
+            network.run();
+        }
+
+        let tip = network.nodes[0].ledger.get_latest_block().hash.clone();
+        for node in &network.nodes {
+            assert_eq!(node.ledger.chain.len(), 4);
+            assert_eq!(node.ledger.get_latest_block().hash, tip);
+            assert_eq!(node.ledger.finalized_height, 3);
+            assert_eq!(node.gadget.finalized_hash(), Some(tip.as_str()));
+        }
+    }
+
+    #[test]
+    fn test_finality_tolerates_an_offline_validator() {
+        let mut network = SimulatedNetwork::new(4);
+        network.nodes[3].online = false;
+
+        // The offline node's turn comes up, and the next authority takes over after a timeout
+        for _ in 0..4 {
+            network.propose();
+            network.run();
+        }
+
+        for node in network.online_nodes() {
+            assert_eq!(node.ledger.chain.len(), 5);
+            assert_eq!(node.ledger.finalized_height, 4);
+            assert!(node.ledger.validate_chain());
+        }
+        assert_eq!(network.nodes[3].ledger.finalized_height, 0);
+    }
+
+    #[test]
+    fn test_no_finality_without_a_quorum() {
+        let mut network = SimulatedNetwork::new(4);
+        network.nodes[2].online = false;
+        network.nodes[3].online = false;
+
+        network.propose();
+        network.run();
+
+        // Blocks are still produced, but two of four validators is not more than two thirds
+        for node in network.online_nodes() {
+            assert_eq!(node.ledger.chain.len(), 2);
+            assert_eq!(node.ledger.finalized_height, 0);
+            assert_eq!(node.gadget.finalized_height(), None);
+        }
+    }
+
+    #[test]
+    fn test_equivocating_validator_is_detected() {
+        let mut network = SimulatedNetwork::new(4);
+
+        // The first validator prevotes for a block that does not exist, then for the real one
+        let conflicting = FinalityVote::new(
+            VoteStep::Prevote,
+            1,
+            0,
+            "f".repeat(64),
+            &network.nodes[0].keypair,
+        );
+        network
+            .queue
+            .push_back(Message::finality_vote(&conflicting));
+        network.propose();
+        network.run();
+
+        for node in &network.nodes {
+            let equivocations = node.gadget.equivocations();
+            assert_eq!(equivocations.len(), 1);
+            assert_eq!(equivocations[0].first, conflicting);
+            assert_eq!(
+                equivocations[0].second.block_hash,
+                node.ledger.get_latest_block().hash
+            );
+
+            // The other three validators still make a quorum
+            assert_eq!(node.ledger.finalized_height, 1);
+        }
+    }
+
+    #[test]
+    fn test_ledger_refuses_to_roll_back_finalized_blocks() {
+        let mut network = SimulatedNetwork::new(4);
+        for _ in 0..2 {
+            network.propose();
+            network.run();
+        }
+
+        // Without a quorum, the third block is not finalized
+        network.nodes[2].online = false;
+        network.nodes[3].online = false;
+        network.propose();
+        network.run();
+
+        let ledger = &mut network.nodes[0].ledger;
+        assert_eq!(ledger.chain.len(), 4);
+        assert_eq!(ledger.finalized_height, 2);
+        assert!(ledger.is_finalized(2));
+        assert!(!ledger.is_finalized(3));
+
+        assert!(!ledger.rollback_to(1));
+        assert_eq!(ledger.chain.len(), 4);
+        assert!(ledger.rollback_to(2));
+        assert_eq!(ledger.chain.len(), 3);
+        assert!(ledger.validate_chain());
+    }
+}